@@ -0,0 +1,84 @@
+//! Resolving singletons, factories and arena values, run with `cargo bench`.
+
+use std::hint::black_box;
+use std::rc::Rc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use kamikaze_di::{Container, ContainerBuilder, Resolver};
+
+fn container() -> Container {
+    let mut builder = ContainerBuilder::new();
+    builder
+        .register::<Rc<String>>(Rc::new("shared".to_string()))
+        .unwrap();
+    builder.register_builder::<u64, _>(|_| 42).unwrap();
+    builder.register_factory::<i64, _>(|_| 42).unwrap();
+    let mut count = 0u32;
+    builder
+        .register_factory::<u32, _>(move |_| {
+            count += 1;
+            count
+        })
+        .unwrap();
+    builder.register_scoped::<i32, _>(|_| 42).unwrap();
+    builder.register::<u16>(8080).unwrap();
+    builder.register_in_arena::<u16>(8080).unwrap();
+
+    builder.build()
+}
+
+fn shared(c: &mut Criterion) {
+    let container = container();
+
+    c.bench_function("registered singleton", |b| {
+        b.iter(|| container.resolve::<Rc<String>>().unwrap())
+    });
+    c.bench_function("built singleton", |b| {
+        b.iter(|| container.resolve::<u64>().unwrap())
+    });
+    c.bench_function("registered value", |b| {
+        b.iter(|| container.resolve::<u16>().unwrap())
+    });
+}
+
+fn arena(c: &mut Criterion) {
+    let container = container();
+    let port = container.arena_handle::<u16>().unwrap();
+
+    c.bench_function("arena value", |b| {
+        b.iter(|| container.resolve_from_arena::<u16>().unwrap())
+    });
+    c.bench_function("arena value by handle", |b| {
+        b.iter(|| container.resolve_handle(black_box(port)).unwrap())
+    });
+}
+
+fn factories(c: &mut Criterion) {
+    let container = container();
+
+    c.bench_function("factory", |b| {
+        b.iter(|| container.resolve::<i64>().unwrap())
+    });
+    c.bench_function("factory with state", |b| {
+        b.iter(|| container.resolve::<u32>().unwrap())
+    });
+}
+
+fn scoped(c: &mut Criterion) {
+    let container = container();
+
+    c.bench_function("scoped, first in scope", |b| {
+        b.iter(|| {
+            let scope = container.enter_scope();
+            scope.resolve::<i32>().unwrap()
+        })
+    });
+
+    let scope = container.enter_scope();
+    c.bench_function("scoped, cached", |b| {
+        b.iter(|| scope.resolve::<i32>().unwrap())
+    });
+}
+
+criterion_group!(benches, shared, arena, factories, scoped);
+criterion_main!(benches);
@@ -0,0 +1,244 @@
+//! Injection into actix-web handlers, needs the `actix` feature.
+//!
+//! Containers can't be shared between threads, so each worker builds its
+//! own, in the closure passed to `HttpServer::new()`, and adds it to the app
+//! with `App::app_data()`. Handlers then take [Inject<T>](struct.Inject.html)
+//! parameters, resolved from that container. To give every request a child
+//! container of its own, wrap the app in [per_request()](fn.per_request.html).
+
+use std::future::{ready, Ready};
+use std::ops::Deref;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::middleware::Next;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+
+use crate::{Container, Injector};
+
+/// Extracts T from the request's container.
+///
+/// The container is the request's child container if there is one, see
+/// [per_request()](fn.per_request.html), or the one added as app data
+/// otherwise. Failing to resolve T is an internal server error.
+///
+/// # Examples
+///
+/// ```
+/// # use actix_web::{test, web, App};
+/// # use kamikaze_di::actix::Inject;
+/// # use kamikaze_di::ContainerBuilder;
+/// # use std::rc::Rc;
+/// #
+/// trait UserRepo { fn name(&self, id: u32) -> String; }
+///
+/// struct InMemory;
+/// impl UserRepo for InMemory { fn name(&self, _: u32) -> String { "ana".to_string() } }
+///
+/// async fn user(repo: Inject<Rc<dyn UserRepo>>, id: web::Path<u32>) -> String {
+///     repo.name(*id)
+/// }
+///
+/// # actix_web::rt::System::new().block_on(async {
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<Rc<dyn UserRepo>>(Rc::new(InMemory)).unwrap();
+///
+/// let app = test::init_service(
+///     App::new()
+///         .app_data(builder.build())
+///         .route("/users/{id}", web::get().to(user)),
+/// )
+/// .await;
+///
+/// let body = test::call_and_read_body(&app, test::TestRequest::get().uri("/users/7").to_request()).await;
+/// assert_eq!("ana", body);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct Inject<T>(pub T);
+
+impl<T> Inject<T> {
+    /// Returns the resolved item.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Inject<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: 'static> FromRequest for Inject<T>
+where
+    Container: Injector<T>,
+{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let child = request.extensions().get::<Container>().cloned();
+        let container = match child.as_ref().or_else(|| request.app_data()) {
+            Some(container) => container,
+            None => {
+                return ready(Err(ErrorInternalServerError(
+                    "No container was added to the app",
+                )))
+            }
+        };
+
+        let result = container.inject().map(Inject).map_err(|error| {
+            warn!("could not inject {}: {}", std::any::type_name::<T>(), error);
+
+            ErrorInternalServerError(error.to_string())
+        });
+
+        ready(result)
+    }
+}
+
+/// Middleware giving every request a child container.
+///
+/// The child is a [fork()](../struct.Container.html#method.fork) of the
+/// app's container, so replacing things in it, like the current user, only
+/// affects the request. Middleware further in finds it in the request's
+/// extensions, as a [MutableContainer](../struct.MutableContainer.html) for
+/// replacing things, and as a Container for resolving them. Use with
+/// `actix_web::middleware::from_fn()`. Builders
+/// can't be forked, so resolve what they build before serving requests,
+/// see [warm_up()](../struct.Container.html#method.warm_up).
+///
+/// # Examples
+///
+/// ```
+/// # use actix_web::dev::ServiceRequest;
+/// # use actix_web::middleware::{from_fn, Next};
+/// # use actix_web::{test, web, App, HttpMessage};
+/// # use kamikaze_di::actix::{per_request, Inject};
+/// # use kamikaze_di::{ContainerBuilder, MutableContainer};
+/// #
+/// # actix_web::rt::System::new().block_on(async {
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<u32>(0).unwrap();
+///
+/// let app = test::init_service(
+///     App::new()
+///         .app_data(builder.build())
+///         .route("/", web::get().to(|id: Inject<u32>| async move { id.to_string() }))
+///         .wrap(from_fn(|request: ServiceRequest, next: Next<_>| async move {
+///             let child = request.extensions().get::<MutableContainer>().cloned().unwrap();
+///             child.replace::<u32>(7).unwrap();
+///
+///             next.call(request).await
+///         }))
+///         .wrap(from_fn(per_request)),
+/// )
+/// .await;
+///
+/// let body = test::call_and_read_body(&app, test::TestRequest::get().to_request()).await;
+/// assert_eq!("7", body);
+/// # });
+/// ```
+pub async fn per_request<B: MessageBody>(
+    request: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let child = match request.app_data::<Container>() {
+        Some(container) => container.fork_mutable().map_err(|error| {
+            warn!("could not create request container: {}", error);
+
+            ErrorInternalServerError(error.to_string())
+        })?,
+        None => {
+            return Err(ErrorInternalServerError(
+                "No container was added to the app",
+            ))
+        }
+    };
+
+    request.extensions_mut().insert(child.container().clone());
+    request.extensions_mut().insert(child);
+
+    next.call(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, MutableContainer, Resolver};
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App};
+
+    async fn answer(answer: Inject<u32>) -> String {
+        answer.to_string()
+    }
+
+    #[actix_web::test]
+    async fn missing_dependencies_are_server_errors() {
+        let app = test::init_service(
+            App::new()
+                .app_data(ContainerBuilder::new().build())
+                .route("/", web::get().to(answer)),
+        )
+        .await;
+
+        let response = test::call_service(&app, test::TestRequest::get().to_request()).await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+    }
+
+    #[actix_web::test]
+    async fn apps_need_a_container() {
+        let app = test::init_service(
+            App::new()
+                .route("/", web::get().to(answer))
+                .wrap(from_fn(per_request)),
+        )
+        .await;
+
+        let error = test::try_call_service(&app, test::TestRequest::get().to_request())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error.as_response_error().status_code()
+        );
+    }
+
+    #[actix_web::test]
+    async fn request_containers_are_kept_apart() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+
+        let container = builder.build();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(container.clone())
+                .route("/", web::get().to(answer))
+                .wrap(from_fn(
+                    |request: ServiceRequest, next: Next<_>| async move {
+                        let child = request
+                            .extensions()
+                            .get::<MutableContainer>()
+                            .cloned()
+                            .unwrap();
+                        child.replace::<u32>(1).unwrap();
+
+                        next.call(request).await
+                    },
+                ))
+                .wrap(from_fn(per_request)),
+        )
+        .await;
+
+        let body = test::call_and_read_body(&app, test::TestRequest::get().to_request()).await;
+
+        assert_eq!("1", body);
+        assert_eq!(42, container.resolve::<u32>().unwrap());
+    }
+}
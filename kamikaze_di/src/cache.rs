@@ -0,0 +1,481 @@
+//! Cache abstraction with swappable backends.
+//!
+//! Services depend on `Rc<dyn Cache<K, V>>`, the backend is chosen when
+//! wiring the container via [CacheModule](struct.CacheModule.html). The
+//! redis backend needs the `redis` feature.
+
+use alloc::collections::BTreeMap;
+use core::cell::{Cell, RefCell};
+
+use crate::collections::HashMap;
+use alloc::rc::Rc;
+use core::fmt;
+use core::hash::Hash;
+use core::marker::PhantomData;
+
+use crate::{ContainerBuilder, Module, Result};
+
+/// Key-value cache.
+pub trait Cache<K, V> {
+    /// Returns the cached value, if there is one.
+    fn get(&self, key: &K) -> Option<V>;
+
+    /// Caches a value, replacing any previous one.
+    fn put(&self, key: K, value: V);
+
+    /// Removes a value from the cache.
+    fn remove(&self, key: &K);
+}
+
+/// In-memory cache that evicts the least recently used entries.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::cache::{Cache, LruCache};
+/// #
+/// let cache = LruCache::new(2);
+/// cache.put("a", 1);
+/// cache.put("b", 2);
+///
+/// // "a" becomes the most recently used entry
+/// assert_eq!(Some(1), cache.get(&"a"));
+///
+/// cache.put("c", 3);
+/// assert_eq!(None, cache.get(&"b"));
+/// assert_eq!(Some(1), cache.get(&"a"));
+/// ```
+pub struct LruCache<K, V> {
+    capacity: usize,
+    // values, with when they were last used
+    entries: RefCell<HashMap<K, (V, u64)>>,
+    // keys by when they were last used, least recently used first
+    usage: RefCell<BTreeMap<u64, K>>,
+    clock: Cell<u64>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    /// Creates a cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> LruCache<K, V> {
+        LruCache {
+            capacity,
+            entries: Default::default(),
+            usage: Default::default(),
+            clock: Cell::new(0),
+        }
+    }
+
+    /// Marks the key as used now, forgetting when it was used before.
+    fn touch(&self, key: &K, last_used: Option<u64>) -> u64 {
+        let now = self.clock.get();
+        self.clock.set(now + 1);
+
+        let mut usage = self.usage.borrow_mut();
+        if let Some(last_used) = last_used {
+            usage.remove(&last_used);
+        }
+        usage.insert(now, key.clone());
+
+        now
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> for LruCache<K, V> {
+    fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.borrow_mut();
+        let (value, last_used) = entries.get_mut(key)?;
+        *last_used = self.touch(key, Some(*last_used));
+
+        Some(value.clone())
+    }
+
+    fn put(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        let last_used = entries.get(&key).map(|(_, last_used)| *last_used);
+        let now = self.touch(&key, last_used);
+        entries.insert(key, (value, now));
+
+        if entries.len() > self.capacity {
+            let evicted = self.usage.borrow_mut().pop_first();
+
+            if let Some((_, evicted)) = evicted {
+                entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn remove(&self, key: &K) {
+        if let Some((_, last_used)) = self.entries.borrow_mut().remove(key) {
+            self.usage.borrow_mut().remove(&last_used);
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for LruCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LruCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.entries.borrow().len())
+            .finish()
+    }
+}
+
+/// Cache that never stores anything.
+///
+/// Useful for tests, or to turn caching off.
+pub struct NoopCache<K, V> {
+    _marker: PhantomData<fn(K, V)>,
+}
+
+impl<K, V> NoopCache<K, V> {
+    /// Constructor.
+    pub fn new() -> NoopCache<K, V> {
+        NoopCache {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Default for NoopCache<K, V> {
+    fn default() -> NoopCache<K, V> {
+        NoopCache::new()
+    }
+}
+
+impl<K, V> Cache<K, V> for NoopCache<K, V> {
+    fn get(&self, _: &K) -> Option<V> {
+        None
+    }
+
+    fn put(&self, _: K, _: V) {}
+
+    fn remove(&self, _: &K) {}
+}
+
+impl<K, V> Clone for NoopCache<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for NoopCache<K, V> {}
+
+impl<K, V> fmt::Debug for NoopCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NoopCache").finish()
+    }
+}
+
+/// Cache kept in redis, needs the `redis` feature.
+///
+/// Keys and values are stored as redis encodes them, see redis'
+/// `ToRedisArgs` and `FromRedisValue`. The connection is opened when the
+/// cache is first used. Redis being unreachable is not an error, lookups
+/// miss and writes are dropped, and the next use connects again.
+#[cfg(feature = "redis")]
+pub struct RedisCache<K, V> {
+    client: redis::Client,
+    connection: RefCell<Option<redis::Connection>>,
+    _marker: PhantomData<fn(K, V)>,
+}
+
+#[cfg(feature = "redis")]
+impl<K, V> RedisCache<K, V>
+where
+    K: redis::ToRedisArgs,
+    V: redis::ToRedisArgs + redis::FromRedisValue,
+{
+    /// Creates a cache for the redis server at `url`, like
+    /// `redis://127.0.0.1/`.
+    ///
+    /// Fails if the url is not a redis url, the server is not contacted.
+    pub fn open(url: &str) -> Result<RedisCache<K, V>> {
+        let client = redis::Client::open(url)
+            .map_err(|error| format!("Invalid redis url {}: {}", url, error))?;
+
+        Ok(RedisCache::new(client))
+    }
+
+    /// Creates a cache using the client's server.
+    pub fn new(client: redis::Client) -> RedisCache<K, V> {
+        RedisCache {
+            client,
+            connection: RefCell::new(None),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Runs the command, connecting first if needed.
+    ///
+    /// Connections are dropped after errors, so the next command reconnects.
+    fn command<T, F>(&self, command: F) -> Option<T>
+    where
+        F: FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    {
+        let mut connection = self.connection.borrow_mut();
+
+        if connection.is_none() {
+            match self.client.get_connection() {
+                Ok(opened) => *connection = Some(opened),
+                Err(error) => {
+                    warn!("could not connect to redis: {}", error);
+                    return None;
+                }
+            }
+        }
+
+        let result = command(connection.as_mut()?);
+
+        match result {
+            Ok(result) => Some(result),
+            Err(error) => {
+                warn!("redis cache command failed: {}", error);
+                *connection = None;
+
+                None
+            }
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+impl<K, V> Cache<K, V> for RedisCache<K, V>
+where
+    K: redis::ToRedisArgs,
+    V: redis::ToRedisArgs + redis::FromRedisValue,
+{
+    fn get(&self, key: &K) -> Option<V> {
+        use redis::Commands;
+
+        self.command(|connection| connection.get::<_, Option<V>>(key))
+            .flatten()
+    }
+
+    fn put(&self, key: K, value: V) {
+        use redis::Commands;
+
+        self.command(|connection| connection.set::<_, _, ()>(key, value));
+    }
+
+    fn remove(&self, key: &K) {
+        use redis::Commands;
+
+        self.command(|connection| connection.del::<_, ()>(key));
+    }
+}
+
+#[cfg(feature = "redis")]
+impl<K, V> fmt::Debug for RedisCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RedisCache")
+            .field("server", &self.client.get_connection_info().addr)
+            .finish()
+    }
+}
+
+/// Registers an `Rc<dyn Cache<K, V>>` with the chosen backend.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use kamikaze_di::cache::{Cache, CacheModule};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// builder.add_module(CacheModule::<String, u32>::lru(100))?;
+///
+/// let container = builder.build();
+///
+/// let cache = container.resolve::<Rc<dyn Cache<String, u32>>>()?;
+/// cache.put("answer".to_string(), 42);
+///
+/// let same_cache = container.resolve::<Rc<dyn Cache<String, u32>>>()?;
+/// assert_eq!(Some(42), same_cache.get(&"answer".to_string()));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct CacheModule<K, V> {
+    backend: Backend,
+    open: Opener<K, V>,
+}
+
+/// Creates the cache for a backend.
+type Opener<K, V> = fn(&Backend) -> Result<Rc<dyn Cache<K, V>>>;
+
+#[derive(Clone, Debug)]
+enum Backend {
+    Lru(usize),
+    Noop,
+    #[cfg(feature = "redis")]
+    Redis(String),
+}
+
+impl<K, V> CacheModule<K, V>
+where
+    K: Eq + Hash + Clone + 'static,
+    V: Clone + 'static,
+{
+    /// Uses an [LruCache](struct.LruCache.html) as backend.
+    pub fn lru(capacity: usize) -> CacheModule<K, V> {
+        CacheModule {
+            backend: Backend::Lru(capacity),
+            open: open_in_memory,
+        }
+    }
+
+    /// Uses a [NoopCache](struct.NoopCache.html) as backend.
+    pub fn noop() -> CacheModule<K, V> {
+        CacheModule {
+            backend: Backend::Noop,
+            open: open_in_memory,
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+impl<K, V> CacheModule<K, V>
+where
+    K: redis::ToRedisArgs + 'static,
+    V: redis::ToRedisArgs + redis::FromRedisValue + 'static,
+{
+    /// Uses a [RedisCache](struct.RedisCache.html) as backend, needs the
+    /// `redis` feature.
+    ///
+    /// Registering the module fails if the url is not a redis url.
+    pub fn redis(url: &str) -> CacheModule<K, V> {
+        CacheModule {
+            backend: Backend::Redis(url.to_string()),
+            open: open_redis,
+        }
+    }
+}
+
+fn open_in_memory<K, V>(backend: &Backend) -> Result<Rc<dyn Cache<K, V>>>
+where
+    K: Eq + Hash + Clone + 'static,
+    V: Clone + 'static,
+{
+    match backend {
+        Backend::Lru(capacity) => Ok(Rc::new(LruCache::new(*capacity))),
+        Backend::Noop => Ok(Rc::new(NoopCache::new())),
+        #[cfg(feature = "redis")]
+        Backend::Redis(_) => unreachable!("redis backends open redis caches"),
+    }
+}
+
+#[cfg(feature = "redis")]
+fn open_redis<K, V>(backend: &Backend) -> Result<Rc<dyn Cache<K, V>>>
+where
+    K: redis::ToRedisArgs + 'static,
+    V: redis::ToRedisArgs + redis::FromRedisValue + 'static,
+{
+    match backend {
+        Backend::Redis(url) => Ok(Rc::new(RedisCache::open(url)?)),
+        _ => unreachable!("in-memory backends open in-memory caches"),
+    }
+}
+
+impl<K: 'static, V: 'static> Module for CacheModule<K, V> {
+    fn register(&self, builder: &mut ContainerBuilder) -> Result<()> {
+        let cache = (self.open)(&self.backend)?;
+
+        builder.register(cache)
+    }
+}
+
+impl<K, V> fmt::Debug for CacheModule<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CacheModule")
+            .field("backend", &self.backend)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_cache_evicts_least_recently_used() {
+        let cache = LruCache::new(2);
+        cache.put(1, "one");
+        cache.put(2, "two");
+        cache.put(1, "uno");
+        cache.put(3, "three");
+
+        assert_eq!(Some("uno"), cache.get(&1));
+        assert_eq!(None, cache.get(&2));
+        assert_eq!(Some("three"), cache.get(&3));
+    }
+
+    #[test]
+    fn lru_cache_forgets_removed_entries() {
+        let cache = LruCache::new(1);
+        cache.put(1, "one");
+        cache.remove(&1);
+        cache.put(2, "two");
+
+        assert_eq!(None, cache.get(&1));
+        assert_eq!(Some("two"), cache.get(&2));
+    }
+
+    #[test]
+    fn lru_cache_keeps_recently_read_entries() {
+        let cache = LruCache::new(3);
+        for key in 0..3 {
+            cache.put(key, key * 10);
+        }
+
+        // reading 0 and 1 leaves 2 as the least recently used
+        cache.get(&0);
+        cache.get(&1);
+        cache.put(3, 30);
+        cache.put(4, 40);
+
+        assert_eq!(None, cache.get(&2));
+        assert_eq!(None, cache.get(&0));
+        assert_eq!(Some(10), cache.get(&1));
+        assert_eq!(Some(30), cache.get(&3));
+        assert_eq!(Some(40), cache.get(&4));
+    }
+
+    #[test]
+    fn noop_cache_stores_nothing() {
+        let cache = NoopCache::new();
+        cache.put(1, "one");
+
+        assert_eq!(None, cache.get(&1));
+    }
+
+    #[test]
+    #[cfg(feature = "redis")]
+    fn unreachable_redis_misses() {
+        // nothing listens on port 1
+        let cache = RedisCache::<String, u32>::open("redis://127.0.0.1:1/").unwrap();
+        cache.put("answer".to_string(), 42);
+
+        assert_eq!(None, cache.get(&"answer".to_string()));
+        assert!(RedisCache::<String, u32>::open("not a url").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "redis")]
+    fn redis_modules_need_a_redis_url() {
+        let mut builder = ContainerBuilder::new();
+
+        assert!(builder
+            .add_module(CacheModule::<String, u32>::redis("not a url"))
+            .is_err());
+        assert!(builder
+            .add_module(CacheModule::<String, u32>::redis("redis://127.0.0.1/"))
+            .is_ok());
+    }
+}
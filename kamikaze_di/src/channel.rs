@@ -0,0 +1,100 @@
+//! Typed channels shared through the container.
+//!
+//! Producers depend on `SyncSender<T>`, consumers on `Rc<Receiver<T>>`, and
+//! [ChannelModule](struct.ChannelModule.html) makes sure both ends belong to
+//! the same channel.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::mpsc::sync_channel;
+
+use crate::{ContainerBuilder, Module, Result};
+
+/// Registers both ends of a bounded channel of T.
+///
+/// Once the channel holds `capacity` messages, senders block until the
+/// consumer catches up, so producers can't outrun it.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use kamikaze_di::channel::ChannelModule;
+/// # use std::rc::Rc;
+/// # use std::sync::mpsc::{Receiver, SyncSender};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Job(u32);
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.add_module(ChannelModule::<Job>::bounded(10))?;
+///
+/// let container = builder.build();
+///
+/// let sender = container.resolve::<SyncSender<Job>>()?;
+/// sender.send(Job(42)).unwrap();
+///
+/// let receiver = container.resolve::<Rc<Receiver<Job>>>()?;
+/// assert_eq!(42, receiver.recv().unwrap().0);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChannelModule<T> {
+    capacity: usize,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> ChannelModule<T> {
+    /// Uses a channel holding at most `capacity` messages.
+    ///
+    /// With a capacity of 0, every send blocks until the message is received.
+    pub fn bounded(capacity: usize) -> ChannelModule<T> {
+        ChannelModule {
+            capacity,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> Module for ChannelModule<T> {
+    fn register(&self, builder: &mut ContainerBuilder) -> Result<()> {
+        let (sender, receiver) = sync_channel::<T>(self.capacity);
+
+        builder.register(sender)?;
+        builder.register(Rc::new(receiver))
+    }
+}
+
+impl<T> fmt::Debug for ChannelModule<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChannelModule")
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+    use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
+
+    #[test]
+    fn senders_block_at_capacity() {
+        let mut builder = ContainerBuilder::new();
+        builder.add_module(ChannelModule::<u8>::bounded(1)).unwrap();
+
+        let container = builder.build();
+        let sender = container.resolve::<SyncSender<u8>>().unwrap();
+
+        sender.try_send(1).unwrap();
+        assert_eq!(Err(TrySendError::Full(2)), sender.try_send(2));
+
+        let receiver = container.resolve::<Rc<Receiver<u8>>>().unwrap();
+        assert_eq!(Ok(1), receiver.recv());
+        assert!(sender.try_send(2).is_ok());
+    }
+}
@@ -0,0 +1,178 @@
+//! Timing, on targets that have a clock.
+//!
+//! There's no clock without std, and std's clock panics on
+//! wasm32-unknown-unknown, so timings are left out there.
+
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod imp {
+    use core::cmp::Ordering;
+    use core::task::Waker;
+    use core::time::Duration;
+    use std::collections::BinaryHeap;
+    use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+    use std::sync::{Mutex, PoisonError};
+    use std::time::Instant;
+
+    /// Measures how long something took.
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) struct Stopwatch(Instant);
+
+    impl Stopwatch {
+        pub fn start() -> Stopwatch {
+            Stopwatch(Instant::now())
+        }
+
+        /// Time since the stopwatch was started.
+        pub fn elapsed(&self) -> Option<Duration> {
+            Some(self.0.elapsed())
+        }
+    }
+
+    /// Timer thread, started the first time it's needed.
+    static TIMER: Mutex<Option<Sender<Timer>>> = Mutex::new(None);
+
+    /// A task to wake up at some point.
+    struct Timer {
+        at: Instant,
+        waker: Waker,
+    }
+
+    // ordered the other way around, so the heap has the earliest on top
+    impl Ord for Timer {
+        fn cmp(&self, other: &Timer) -> Ordering {
+            other.at.cmp(&self.at)
+        }
+    }
+
+    impl PartialOrd for Timer {
+        fn partial_cmp(&self, other: &Timer) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl PartialEq for Timer {
+        fn eq(&self, other: &Timer) -> bool {
+            self.at == other.at
+        }
+    }
+
+    impl Eq for Timer {}
+
+    /// Wakes the task once the time is up.
+    ///
+    /// Tasks are woken by a single timer thread shared by the process,
+    /// which sleeps until the earliest one is due.
+    pub(crate) fn wake_after(duration: Duration, waker: Waker) {
+        let mut timer = Timer {
+            at: Instant::now() + duration,
+            waker,
+        };
+
+        let mut sender = TIMER.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(running) = sender.as_ref() {
+            match running.send(timer) {
+                Ok(()) => return,
+                // a waker panicked and took the thread down, start another one
+                Err(error) => timer = error.0,
+            }
+        }
+
+        let (running, timers) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("kamikaze_di timer".to_string())
+            .spawn(move || run(timers))
+            .expect("could not start the timer thread");
+
+        running.send(timer).expect("timer thread is gone");
+        *sender = Some(running);
+    }
+
+    fn run(new_timers: Receiver<Timer>) {
+        let mut timers = BinaryHeap::new();
+
+        loop {
+            let now = Instant::now();
+            while timers.peek().is_some_and(|timer: &Timer| timer.at <= now) {
+                timers.pop().unwrap().waker.wake();
+            }
+
+            let received = match timers.peek() {
+                Some(timer) => match new_timers.recv_timeout(timer.at - now) {
+                    Ok(timer) => Some(timer),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+                None => match new_timers.recv() {
+                    Ok(timer) => Some(timer),
+                    Err(_) => return,
+                },
+            };
+
+            timers.extend(received);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::Arc;
+        use std::task::Wake;
+
+        struct Record(&'static str, Arc<Mutex<Vec<&'static str>>>);
+
+        impl Wake for Record {
+            fn wake(self: Arc<Self>) {
+                self.1.lock().unwrap().push(self.0);
+            }
+        }
+
+        #[test]
+        fn tasks_are_woken_in_order() {
+            let woken = Arc::new(Mutex::new(Vec::new()));
+
+            for (name, millis) in &[("late", 60), ("early", 20)] {
+                let waker = Waker::from(Arc::new(Record(name, woken.clone())));
+                wake_after(Duration::from_millis(*millis), waker);
+            }
+
+            std::thread::sleep(Duration::from_millis(40));
+            assert_eq!(vec!["early"], *woken.lock().unwrap());
+
+            std::thread::sleep(Duration::from_millis(60));
+            assert_eq!(vec!["early", "late"], *woken.lock().unwrap());
+        }
+    }
+}
+
+#[cfg(not(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+)))]
+mod imp {
+    use core::task::Waker;
+    use core::time::Duration;
+
+    /// Stands in for a stopwatch where there's no clock.
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) struct Stopwatch;
+
+    impl Stopwatch {
+        pub fn start() -> Stopwatch {
+            Stopwatch
+        }
+
+        /// Always None, there's no clock to ask.
+        pub fn elapsed(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    /// Never wakes the task, the time is never up without a clock.
+    pub(crate) fn wake_after(_: Duration, _: Waker) {}
+}
+
+pub(crate) use imp::{wake_after, Stopwatch};
@@ -0,0 +1,58 @@
+//! Hash maps from std, or from hashbrown without std.
+
+use core::any::TypeId;
+use core::hash::{BuildHasherDefault, Hasher};
+
+#[cfg(feature = "std")]
+pub use std::collections::{HashMap, HashSet};
+
+#[cfg(not(feature = "std"))]
+pub use hashbrown::{HashMap, HashSet};
+
+/// Map keyed by type, without hashing the already hashed TypeIds again.
+pub type TypeIdMap<V> = HashMap<TypeId, V, BuildHasherDefault<TypeIdHasher>>;
+
+/// Set of types, see TypeIdMap.
+pub type TypeIdSet = HashSet<TypeId, BuildHasherDefault<TypeIdHasher>>;
+
+/// Keeps the bits a TypeId hashes itself as.
+///
+/// TypeIds are hashes of their type already, and only write a single u64.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TypeIdHasher {
+    hash: u64,
+}
+
+impl Hasher for TypeIdHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        // not used by TypeId, but keep other input working
+        for &byte in bytes {
+            self.hash = self.hash.rotate_left(8) ^ u64::from(byte);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.hash = self.hash.rotate_left(32) ^ n;
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypeIdMap;
+    use core::any::TypeId;
+
+    #[test]
+    fn types_are_told_apart() {
+        let mut map = TypeIdMap::default();
+        map.insert(TypeId::of::<u8>(), "u8");
+        map.insert(TypeId::of::<i8>(), "i8");
+        map.insert(TypeId::of::<(u8, i8)>(), "(u8, i8)");
+
+        assert_eq!(3, map.len());
+        assert_eq!(Some(&"i8"), map.get(&TypeId::of::<i8>()));
+    }
+}
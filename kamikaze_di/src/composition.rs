@@ -0,0 +1,83 @@
+/// Wires the container in one place, and checks the wiring in debug builds.
+///
+/// The wiring block gets a `ContainerBuilder` under the given name, and can
+/// use `?`. The macro evaluates to a `Result<Container>`.
+///
+/// In debug builds, the built container goes through
+/// [composition_report()](struct.Container.html#method.composition_report):
+/// `roots` lists the types the application resolves directly, so they
+/// don't count as unused, and `forbid` lists module pairs that must not
+/// depend on each other. Any problem fails with a single report listing
+/// all of them, as [ErrorKind::Composition](enum.ErrorKind.html#variant.Composition).
+/// Release builds skip the checks.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate kamikaze_di;
+/// # use kamikaze_di::Resolver;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let container = composition_root!(|builder| {
+///     builder.register::<i32>(42)?;
+///     builder.register_builder::<i64, _>(|container| {
+///         container.resolve::<i32>().unwrap().into()
+///     })?;
+///     builder.declare_dependencies::<i64, (i32,)>();
+/// }, roots: [i64], forbid: ["my_app::db" => "my_app::web"])?;
+///
+/// assert_eq!(42, container.resolve::<i64>()?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Wiring problems are reported together:
+///
+/// ```
+/// # #[macro_use] extern crate kamikaze_di;
+/// #
+/// let result = composition_root!(|builder| {
+///     builder.register::<u8>(1)?;
+///     builder.register_builder::<i64, _>(|_| 42)?;
+///     builder.declare_dependencies::<i64, (i32,)>();
+/// }, roots: [i64]);
+///
+/// # if cfg!(debug_assertions) {
+/// let report = result.unwrap_err().to_string();
+///
+/// assert!(report.contains("i64 depends on i32, which is not registered"));
+/// assert!(report.contains("u8 is registered, but nothing depends on it"));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! composition_root {
+    (
+        |$builder:ident| $wiring:block
+        $(, roots: [$($root:ty),* $(,)?])?
+        $(, forbid: [$($from:expr => $to:expr),* $(,)?])?
+        $(,)?
+    ) => {{
+        let wire = || -> $crate::Result<$crate::Container> {
+            #[allow(unused_mut)]
+            let mut $builder = $crate::ContainerBuilder::new();
+            $wiring
+            let container = $builder.build();
+
+            if cfg!(debug_assertions) {
+                let roots = [$($($crate::Dependency::of::<$root>()),*)?];
+                let forbidden: &[(&str, &str)] = &[$($(($from, $to)),*)?];
+
+                let report = container.composition_report(&roots, forbidden);
+                if !report.is_empty() {
+                    return Err($crate::ErrorKind::Composition(report).into());
+                }
+            }
+
+            Ok(container)
+        };
+
+        wire()
+    }};
+}
@@ -0,0 +1,407 @@
+//! Typed configuration, needs the `config` feature.
+//!
+//! Configuration is loaded once into a [ConfigSource](struct.ConfigSource.html),
+//! from TOML or environment variables. Config structs are then registered as
+//! sections via [ConfigModule](struct.ConfigModule.html), and resolved as `Rc<T>`.
+//! Sections are deserialized when the module is added, so configuration
+//! errors show up while wiring the container.
+//!
+//! Sections can also check their values, see
+//! [validate()](trait.ConfigSection.html#method.validate). Sections failing
+//! their checks are still registered, every failure is reported together by
+//! [Container::validate()](../struct.Container.html#method.validate) and
+//! [Container::warm_up()](../struct.Container.html#method.warm_up).
+//! `#[derive(ConfigSection)]` from kamikaze_di_derive implements both from
+//! attributes.
+
+use std::fmt;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use toml::{Table, Value};
+
+use crate::container::validation::InvalidValues;
+use crate::{ContainerBuilder, Module, Result};
+
+/// A config struct, read from its own section.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::config::ConfigSection;
+/// # use serde::Deserialize;
+/// #
+/// #[derive(Deserialize)]
+/// struct DatabaseConfig { url: String }
+///
+/// impl ConfigSection for DatabaseConfig {}
+///
+/// #[derive(Deserialize)]
+/// struct Cluster { nodes: Vec<String> }
+///
+/// impl ConfigSection for Cluster {
+///     fn section() -> String { "search.cluster".to_string() }
+/// }
+///
+/// assert_eq!("database", DatabaseConfig::section());
+/// ```
+pub trait ConfigSection: DeserializeOwned + 'static {
+    /// Path of the section holding the configuration, dot separated.
+    ///
+    /// Defaults to the type name in snake case, without a `Config` suffix,
+    /// so `DatabaseConfig` is read from `[database]`.
+    fn section() -> String {
+        section_name(std::any::type_name::<Self>())
+    }
+
+    /// Checks the values, returning what's wrong with them.
+    ///
+    /// Nothing is checked by default.
+    fn validate(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Configuration values, grouped in sections.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::config::ConfigSource;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let source = ConfigSource::from_toml(r#"
+///     [database]
+///     url = "postgres://localhost"
+///     pool_size = 4
+/// "#)?;
+///
+/// assert_eq!(4, source.get::<u32>("database.pool_size")?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigSource {
+    values: Table,
+}
+
+impl ConfigSource {
+    /// Creates an empty source.
+    pub fn new() -> ConfigSource {
+        Default::default()
+    }
+
+    /// Parses a TOML document.
+    pub fn from_toml(toml: &str) -> Result<ConfigSource> {
+        let values = toml
+            .parse::<Table>()
+            .map_err(|error| format!("Invalid configuration: {}", error))?;
+
+        Ok(ConfigSource { values })
+    }
+
+    /// Reads environment variables starting with a prefix.
+    ///
+    /// `APP_DATABASE__POOL_SIZE=4` is read as `pool_size = 4` in the
+    /// `[database]` section, for the `APP` prefix. Values are parsed as TOML
+    /// values where possible, so numbers and booleans keep their types, and
+    /// are kept as strings otherwise.
+    pub fn from_env(prefix: &str) -> ConfigSource {
+        ConfigSource::from_vars(prefix, std::env::vars())
+    }
+
+    fn from_vars<I>(prefix: &str, vars: I) -> ConfigSource
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let prefix = format!("{}_", prefix);
+        let mut source = ConfigSource::new();
+
+        for (name, raw) in vars {
+            let path = match name.strip_prefix(&prefix) {
+                Some(path) => path.to_lowercase(),
+                None => continue,
+            };
+
+            let keys: Vec<_> = path.split("__").collect();
+            insert_path(&mut source.values, &keys, parse_env_value(raw));
+        }
+
+        source
+    }
+
+    /// Merges two sources, values from the other one win.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::config::ConfigSource;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let defaults = ConfigSource::from_toml("[http]\nport = 80\nhost = \"localhost\"")?;
+    /// let local = ConfigSource::from_toml("[http]\nport = 8080")?;
+    ///
+    /// let source = defaults.merge(local);
+    ///
+    /// assert_eq!(8080, source.get::<u16>("http.port")?);
+    /// assert_eq!("localhost", source.get::<String>("http.host")?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge(mut self, other: ConfigSource) -> ConfigSource {
+        merge_tables(&mut self.values, other.values);
+
+        self
+    }
+
+    /// Deserializes the value at a dot separated path.
+    ///
+    /// Missing sections are read as empty tables, so structs whose fields
+    /// all have defaults can still be read.
+    pub fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let mut value = Value::Table(self.values.clone());
+
+        for key in path.split('.').filter(|key| !key.is_empty()) {
+            value = match value {
+                Value::Table(mut table) => table
+                    .remove(key)
+                    .unwrap_or_else(|| Value::Table(Table::new())),
+                _ => return Err(format!("Invalid configuration path: {}", path).into()),
+            };
+        }
+
+        value
+            .try_into()
+            .map_err(|error| format!("Invalid configuration [{}]: {}", path, error).into())
+    }
+}
+
+/// Registers config sections as `Rc<T>`, and the source as `Rc<ConfigSource>`.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use kamikaze_di::config::{ConfigModule, ConfigSection, ConfigSource};
+/// # use serde::Deserialize;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Deserialize)]
+/// struct AppConfig { name: String }
+///
+/// impl ConfigSection for AppConfig {}
+///
+/// let source = ConfigSource::from_toml("[app]\nname = \"shop\"")?;
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.add_module(ConfigModule::new(source).section::<AppConfig>())?;
+///
+/// let container = builder.build();
+///
+/// assert_eq!("shop", container.resolve::<Rc<AppConfig>>()?.name);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct ConfigModule {
+    source: ConfigSource,
+    sections: Vec<Box<SectionRegistration>>,
+}
+
+type SectionRegistration = dyn Fn(&ConfigSource, &mut ContainerBuilder) -> Result<()>;
+
+impl ConfigModule {
+    /// Uses configuration from a source.
+    pub fn new(source: ConfigSource) -> ConfigModule {
+        ConfigModule {
+            source,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Registers a config section.
+    pub fn section<T: ConfigSection>(mut self) -> ConfigModule {
+        self.sections.push(Box::new(|source, builder| {
+            let config: T = source.get(&T::section())?;
+            let problems = config.validate();
+
+            builder.register(Rc::new(config))?;
+
+            if problems.is_empty() {
+                return Ok(());
+            }
+
+            builder.add_metadata::<Rc<T>, _>(InvalidValues(problems))
+        }));
+
+        self
+    }
+}
+
+impl Module for ConfigModule {
+    fn register(&self, builder: &mut ContainerBuilder) -> Result<()> {
+        for section in &self.sections {
+            section(&self.source, builder)?;
+        }
+
+        builder.register(Rc::new(self.source.clone()))
+    }
+}
+
+impl fmt::Debug for ConfigModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConfigModule")
+            .field("source", &self.source)
+            .field("sections", &self.sections.len())
+            .finish()
+    }
+}
+
+fn parse_env_value(raw: String) -> Value {
+    format!("value = {}", raw)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or(Value::String(raw))
+}
+
+fn insert_path(table: &mut Table, keys: &[&str], value: Value) {
+    match keys {
+        [] => {}
+        [key] => {
+            table.insert(key.to_string(), value);
+        }
+        [section, rest @ ..] => {
+            let section = table
+                .entry(section.to_string())
+                .or_insert_with(|| Value::Table(Table::new()));
+
+            // a value is already there, keep it
+            if let Value::Table(section) = section {
+                insert_path(section, rest, value);
+            }
+        }
+    }
+}
+
+fn merge_tables(into: &mut Table, from: Table) {
+    for (key, value) in from {
+        match (into.get_mut(&key), value) {
+            (Some(Value::Table(into)), Value::Table(from)) => merge_tables(into, from),
+            (_, value) => {
+                into.insert(key, value);
+            }
+        }
+    }
+}
+
+fn section_name(type_name: &str) -> String {
+    let name = type_name.split('<').next().unwrap_or(type_name);
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let name = match name.strip_suffix("Config") {
+        Some(stripped) if !stripped.is_empty() => stripped,
+        _ => name,
+    };
+
+    let mut section = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            section.push('_');
+        }
+
+        section.extend(c.to_lowercase());
+    }
+
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_names_come_from_type_names() {
+        assert_eq!("database", section_name("app::DatabaseConfig"));
+        assert_eq!("http_server", section_name("app::HttpServer<u8>"));
+        assert_eq!("config", section_name("Config"));
+    }
+
+    #[test]
+    fn env_vars_are_read_into_sections() {
+        let vars = vec![
+            ("APP_DATABASE__POOL_SIZE".to_string(), "4".to_string()),
+            ("APP_DATABASE__URL".to_string(), "postgres://db".to_string()),
+            ("APP_DEBUG".to_string(), "true".to_string()),
+            ("OTHER_DEBUG".to_string(), "false".to_string()),
+        ];
+
+        let source = ConfigSource::from_vars("APP", vars);
+
+        assert_eq!(4, source.get::<i64>("database.pool_size").unwrap());
+        assert_eq!(
+            "postgres://db",
+            source.get::<String>("database.url").unwrap()
+        );
+        assert!(source.get::<bool>("debug").unwrap());
+    }
+
+    #[test]
+    fn sections_failing_their_checks_are_reported() {
+        #[derive(serde::Deserialize)]
+        struct PoolConfig {
+            size: u32,
+        }
+        impl ConfigSection for PoolConfig {
+            fn validate(&self) -> Vec<String> {
+                match self.size {
+                    0 => vec!["size can't be 0".to_string()],
+                    _ => Vec::new(),
+                }
+            }
+        }
+
+        let source = ConfigSource::from_toml("[pool]\nsize = 0").unwrap();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .add_module(ConfigModule::new(source).section::<PoolConfig>())
+            .unwrap();
+
+        let container = builder.build();
+        assert_eq!(
+            "Container validation failed:\n  alloc::rc::Rc<kamikaze_di::config::tests::sections_failing_their_checks_are_reported::PoolConfig> is invalid: size can't be 0",
+            container.validate().unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Warm up failed:\n  alloc::rc::Rc<kamikaze_di::config::tests::sections_failing_their_checks_are_reported::PoolConfig>: size can't be 0",
+            container.warm_up().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn invalid_sections_fail_when_the_module_is_added() {
+        #[derive(serde::Deserialize)]
+        struct PortConfig {
+            #[allow(dead_code)]
+            port: u16,
+        }
+        impl ConfigSection for PortConfig {}
+
+        let source = ConfigSource::from_toml("[port]\nport = \"http\"").unwrap();
+
+        let mut builder = ContainerBuilder::new();
+        let error = builder
+            .add_module(ConfigModule::new(source).section::<PortConfig>())
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Invalid configuration [port]"));
+    }
+}
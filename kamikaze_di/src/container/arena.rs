@@ -0,0 +1,202 @@
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+use crate::Result;
+
+/// Small shared values, kept in one slab instead of an Rc each.
+///
+/// The slab doesn't change once the container is built, so values are
+/// read without borrowing the resolver map, and copied out instead of
+/// cloning an Rc.
+#[derive(Default)]
+pub(super) struct Arena {
+    slots: Vec<Slot>,
+    indices: TypeIdMap<usize>,
+}
+
+struct Slot {
+    type_name: &'static str,
+    item: Box<dyn Any>,
+}
+
+impl Arena {
+    pub fn insert<T: Copy + 'static>(&mut self, item: T) -> Result<()> {
+        if self.indices.contains_key(&TypeId::of::<T>()) {
+            return Err(format!("{} is already in the arena", core::any::type_name::<T>()).into());
+        }
+
+        self.push(
+            Box::new(item),
+            core::any::type_name::<T>(),
+            TypeId::of::<T>(),
+        );
+
+        Ok(())
+    }
+
+    /// Adds their values, keeping ours for types in both unless told otherwise.
+    pub fn merge(&mut self, other: Arena, keep_theirs: bool) {
+        let mut theirs: Vec<_> = other.indices.into_iter().collect();
+        // keep the slab in the order they inserted
+        theirs.sort_by_key(|(_, index)| *index);
+
+        let mut slots: Vec<_> = other.slots.into_iter().map(Some).collect();
+
+        for (type_id, index) in theirs {
+            let slot = slots[index].take().expect("arena slots are unique");
+
+            match self.indices.get(&type_id) {
+                Some(&ours) if keep_theirs => self.slots[ours] = slot,
+                Some(_) => {}
+                None => self.push(slot.item, slot.type_name, type_id),
+            }
+        }
+    }
+
+    pub fn handle<T: Copy + 'static>(&self) -> Result<ArenaHandle<T>> {
+        self.indices
+            .get(&TypeId::of::<T>())
+            .map(|&index| ArenaHandle {
+                index,
+                item: PhantomData,
+            })
+            .ok_or_else(|| format!("{} is not in the arena", core::any::type_name::<T>()).into())
+    }
+
+    pub fn get<T: Copy + 'static>(&self, handle: ArenaHandle<T>) -> Result<T> {
+        self.slots
+            .get(handle.index)
+            .and_then(|slot| slot.item.downcast_ref::<T>())
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "the arena handle for {} belongs to another container",
+                    core::any::type_name::<T>()
+                )
+                .into()
+            })
+    }
+
+    fn push(&mut self, item: Box<dyn Any>, type_name: &'static str, type_id: TypeId) {
+        self.indices.insert(type_id, self.slots.len());
+        self.slots.push(Slot { type_name, item });
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.slots.iter().map(|slot| slot.type_name))
+            .finish()
+    }
+}
+
+/// Points at a value in the container's arena.
+///
+/// Get one once via [arena_handle()](struct.Container.html#method.arena_handle),
+/// then read the value via [resolve_handle()](struct.Container.html#method.resolve_handle)
+/// in hot loops, which skips looking T up. Handles only work with the
+/// container they came from, and its scopes.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::ContainerBuilder;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Clone, Copy)]
+/// struct Timeout(u32);
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_in_arena::<Timeout>(Timeout(30))?;
+/// builder.register_in_arena::<u16>(8080)?;
+///
+/// let container = builder.build();
+/// let timeout = container.arena_handle::<Timeout>()?;
+///
+/// let mut total = 0;
+/// for _ in 0..100 {
+///     total += container.resolve_handle(timeout)?.0;
+/// }
+///
+/// assert_eq!(3000, total);
+/// assert_eq!(8080, container.resolve_from_arena::<u16>()?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct ArenaHandle<T> {
+    index: usize,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for ArenaHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArenaHandle<T> {}
+
+impl<T> fmt::Debug for ArenaHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArenaHandle")
+            .field("type", &core::any::type_name::<T>())
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, MergePolicy};
+
+    #[test]
+    fn values_are_kept_apart_from_registrations() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_in_arena::<u8>(1).unwrap();
+        builder.register::<u8>(2).unwrap();
+
+        assert!(builder.register_in_arena::<u8>(3).is_err());
+
+        let container = builder.build();
+
+        assert_eq!(1, container.resolve_from_arena::<u8>().unwrap());
+        assert_eq!(2, crate::Resolver::resolve::<u8>(&container).unwrap());
+        assert!(container.resolve_from_arena::<u16>().is_err());
+    }
+
+    #[test]
+    fn merged_arenas_follow_the_policy() {
+        let mut ours = ContainerBuilder::new();
+        ours.register_in_arena::<u8>(1).unwrap();
+
+        let mut theirs = ContainerBuilder::new();
+        theirs.register_in_arena::<u16>(2).unwrap();
+        theirs.register_in_arena::<u8>(3).unwrap();
+
+        ours.merge(theirs, MergePolicy::KeepLast).unwrap();
+        let container = ours.build();
+
+        assert_eq!(3, container.resolve_from_arena::<u8>().unwrap());
+        assert_eq!(2, container.resolve_from_arena::<u16>().unwrap());
+    }
+
+    #[test]
+    fn handles_only_work_with_their_container() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_in_arena::<u16>(0).unwrap();
+        builder.register_in_arena::<u8>(1).unwrap();
+        let handle = builder.build().arena_handle::<u8>().unwrap();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_in_arena::<u8>(1).unwrap();
+        let other = builder.build();
+
+        assert!(other.resolve_handle(handle).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use super::Container;
+use crate::Result;
+
+/// Types built from dependencies and arguments given when resolving them.
+///
+/// The classic factory with runtime data: a report generator needs the
+/// database from the container, and the id of the report from the caller.
+/// Resolve them via
+/// [resolve_assisted()](struct.Container.html#method.resolve_assisted).
+///
+/// The `#[inject]` attribute of kamikaze_di_derive implements this for
+/// constructors marked with `#[assisted(...)]`, naming the parameters
+/// the caller gives.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Assisted, Container, ContainerBuilder, Resolver, Result};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Db;
+///
+/// struct ReportGenerator {
+///     db: Rc<Db>,
+///     report_id: u64,
+/// }
+///
+/// impl Assisted for ReportGenerator {
+///     type Args = (u64,);
+///
+///     fn resolve_assisted(container: &Container, (report_id,): (u64,)) -> Result<Self> {
+///         Ok(ReportGenerator { db: container.resolve()?, report_id })
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<Rc<Db>>(Rc::new(Db))?;
+///
+/// let container = builder.build();
+/// let generator = container.resolve_assisted::<ReportGenerator>((42,))?;
+///
+/// assert_eq!(42, generator.report_id);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Assisted: Sized {
+    /// What the caller gives, usually a tuple.
+    type Args;
+
+    /// Resolves the dependencies, and builds the item with the arguments.
+    fn resolve_assisted(container: &Container, args: Self::Args) -> Result<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+
+    #[derive(Debug)]
+    struct Greeting(String);
+
+    impl Assisted for Greeting {
+        type Args = (&'static str, u8);
+
+        fn resolve_assisted(container: &Container, (name, times): Self::Args) -> Result<Self> {
+            let greeting: String = container.resolve()?;
+
+            Ok(Greeting(
+                format!("{} {}", greeting, name).repeat(times.into()),
+            ))
+        }
+    }
+
+    #[test]
+    fn arguments_are_mixed_with_dependencies() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<String>("hi".to_string()).unwrap();
+
+        let container = builder.build();
+        let greeting = container.resolve_assisted::<Greeting>(("bob!", 2)).unwrap();
+
+        assert_eq!("hi bob!hi bob!", greeting.0);
+    }
+
+    #[test]
+    fn missing_dependencies_are_errors() {
+        let container = ContainerBuilder::new().build();
+
+        let error = container
+            .resolve_assisted::<Greeting>(("bob", 1))
+            .unwrap_err();
+        // named along with the path that led to it
+        assert!(error.to_string().ends_with(
+            "Greeting -> alloc::string::String: Type not registered: alloc::string::String"
+        ));
+    }
+}
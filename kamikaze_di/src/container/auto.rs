@@ -0,0 +1,99 @@
+use core::fmt;
+
+use super::builder::ContainerBuilder;
+use crate::Result;
+
+/// Registers services with a builder, collected from every crate at link time.
+///
+/// Submit them via [submit_registration!](macro.submit_registration.html)
+/// next to the services they register, and apply them all via
+/// [with_auto_registrations()](struct.ContainerBuilder.html#method.with_auto_registrations),
+/// so there's no central file that has to know every service. They're
+/// applied in no particular order, so they shouldn't depend on each other.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{submit_registration, ContainerBuilder, Resolver};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Clone)]
+/// struct Mailer {
+///     host: &'static str,
+/// }
+///
+/// submit_registration!(|builder| builder.register::<Mailer>(Mailer { host: "smtp.local" }));
+///
+/// let container = ContainerBuilder::with_auto_registrations()?.build();
+///
+/// assert_eq!("smtp.local", container.resolve::<Mailer>()?.host);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct AutoRegistration {
+    register: fn(&mut ContainerBuilder) -> Result<()>,
+}
+
+impl AutoRegistration {
+    /// Wraps a registration function, see
+    /// [submit_registration!](macro.submit_registration.html).
+    pub const fn new(register: fn(&mut ContainerBuilder) -> Result<()>) -> AutoRegistration {
+        AutoRegistration { register }
+    }
+
+    /// Every registration submitted by the crates linked in.
+    pub(super) fn all() -> impl Iterator<Item = &'static AutoRegistration> {
+        inventory::iter::<AutoRegistration>.into_iter()
+    }
+
+    pub(super) fn apply(&self, builder: &mut ContainerBuilder) -> Result<()> {
+        (self.register)(builder)
+    }
+}
+
+impl fmt::Debug for AutoRegistration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AutoRegistration").finish()
+    }
+}
+
+inventory::collect!(AutoRegistration);
+
+/// Submits a registration, applied by
+/// [with_auto_registrations()](struct.ContainerBuilder.html#method.with_auto_registrations).
+///
+/// Takes a `fn(&mut ContainerBuilder) -> Result<()>`, or a closure that
+/// doesn't capture anything. Can be used in any crate linked into the
+/// application, see [AutoRegistration](struct.AutoRegistration.html).
+#[macro_export]
+macro_rules! submit_registration {
+    ($register:expr) => {
+        $crate::__inventory::submit! {
+            $crate::AutoRegistration::new($register)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, Resolver};
+
+    #[derive(Clone)]
+    struct Submitted(u8);
+
+    submit_registration!(|builder| builder.register::<Submitted>(Submitted(42)));
+
+    #[test]
+    fn submitted_registrations_are_applied() {
+        let container = ContainerBuilder::with_auto_registrations().unwrap().build();
+
+        assert_eq!(42, container.resolve::<Submitted>().unwrap().0);
+        assert!(ContainerBuilder::new()
+            .build()
+            .resolve::<Submitted>()
+            .is_err());
+    }
+}
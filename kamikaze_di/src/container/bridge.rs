@@ -0,0 +1,178 @@
+use alloc::rc::Rc;
+use core::any::Any;
+use core::fmt;
+
+use super::fallback::Fallback;
+use super::interceptor::Resolution;
+use super::Container;
+use crate::error::ErrorKind;
+use crate::prelude::*;
+use crate::Result;
+
+/// Another container, asked for the types this one doesn't have.
+///
+/// Meant for migrating between containers one type at a time: types not
+/// registered here are delegated to the other container, which can ask
+/// this one for what it needs in turn. See
+/// [bridge()](struct.MutableContainer.html#method.bridge).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, ExternalResolver, Resolution, Resolver, Result};
+/// # use std::any::{Any, TypeId};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct LegacyRegistry;
+///
+/// impl ExternalResolver for LegacyRegistry {
+///     fn name(&self) -> &str {
+///         "the legacy registry"
+///     }
+///
+///     fn resolve_external(
+///         &self,
+///         resolution: &Resolution,
+///         container: &Container,
+///     ) -> Result<Option<Box<dyn Any>>> {
+///         if resolution.type_id == TypeId::of::<String>() {
+///             let port: u16 = container.resolve()?;
+///
+///             return Ok(Some(Box::new(format!("localhost:{}", port))));
+///         }
+///
+///         Ok(None)
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<u16>(8080)?;
+///
+/// let container = builder.build_mutable();
+/// container.bridge(Rc::new(LegacyRegistry));
+///
+/// assert_eq!("localhost:8080", container.resolve::<String>()?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait ExternalResolver {
+    /// Names the other container in errors.
+    fn name(&self) -> &str {
+        "the external container"
+    }
+
+    /// Returns an item, boxed as the type being resolved, or None if the
+    /// other container doesn't have the type either.
+    fn resolve_external(
+        &self,
+        resolution: &Resolution,
+        container: &Container,
+    ) -> Result<Option<Box<dyn Any>>>;
+}
+
+/// Delegates unregistered types to an external resolver, as a fallback.
+pub(super) struct Bridge(pub Rc<dyn ExternalResolver>);
+
+impl Fallback for Bridge {
+    fn resolve(
+        &self,
+        resolution: &Resolution,
+        container: &Container,
+    ) -> Result<Option<Box<dyn Any>>> {
+        debug!("resolving via bridged container");
+
+        match self.0.resolve_external(resolution, container) {
+            // the other container asked for the type right back
+            Err(error) if bounced(resolution, error.kind()) => Err(format!(
+                "{} bounced between the container and {}, neither of them has it",
+                resolution.type_name,
+                self.0.name()
+            )
+            .into()),
+            result => result,
+        }
+    }
+}
+
+fn bounced(resolution: &Resolution, error: &ErrorKind) -> bool {
+    match error {
+        ErrorKind::CycleDetected(cycle) => cycle.path == [resolution.type_name; 2],
+        _ => false,
+    }
+}
+
+impl fmt::Debug for Bridge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Bridge").field(&self.0.name()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+    use core::any::TypeId;
+
+    /// Has u64, built from the container's u8, and asks the container right
+    /// back for u16, like a registry bridged the other way would.
+    struct Registry;
+
+    impl ExternalResolver for Registry {
+        fn name(&self) -> &str {
+            "the registry"
+        }
+
+        fn resolve_external(
+            &self,
+            resolution: &Resolution,
+            container: &Container,
+        ) -> Result<Option<Box<dyn Any>>> {
+            if resolution.type_id == TypeId::of::<u64>() {
+                let item: u8 = container.resolve()?;
+
+                return Ok(Some(Box::new(u64::from(item))));
+            }
+
+            if resolution.type_id == TypeId::of::<u16>() {
+                return Ok(Some(Box::new(container.resolve::<u16>()?)));
+            }
+
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn bridged_containers_resolve_through_each_other() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(7).unwrap();
+        builder
+            .register_try_factory::<u32, _>(|container| Ok(container.resolve::<u64>()? as u32 + 1))
+            .unwrap();
+
+        let container = builder.build();
+        container.bridge(Rc::new(Registry));
+
+        assert_eq!(8, container.resolve::<u32>().unwrap());
+        assert!(container
+            .resolve::<i8>()
+            .err()
+            .unwrap()
+            .to_string()
+            .ends_with("Type not registered: i8"));
+    }
+
+    #[test]
+    fn loops_between_containers_are_stopped() {
+        let container = ContainerBuilder::new().build();
+        container.bridge(Rc::new(Registry));
+
+        let error = container.resolve::<u16>().err().unwrap();
+
+        assert!(error.to_string().ends_with(
+            "u16 bounced between the container and the registry, neither of them has it"
+        ));
+    }
+}
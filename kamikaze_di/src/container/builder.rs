@@ -1,12 +1,48 @@
-use std::any::{Any, TypeId};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use alloc::collections::BTreeMap;
+use alloc::rc::{Rc, Weak};
+use core::any::{Any, TypeId};
+use core::cell::RefCell;
+use core::convert::TryFrom;
+use core::fmt;
+use core::future::Future;
+use core::hash::Hash;
+use core::marker::PhantomData;
 
-use super::cycle::CycleStopper;
-use super::injector::Inject;
-use crate::Result;
+use super::arena::Arena;
+#[cfg(feature = "auto-register")]
+use super::auto::AutoRegistration;
+use super::erased::{ErasedBuilder, ErasedFactory};
+use super::events::EventLog;
+use super::generic::GenericFactory;
+use super::initialize::{Initialize, Initializer};
+use super::injector::{Inject, InjectAsRc};
+use super::leaks::{self, LeakCheck};
+use super::lifecycle::{Lifecycle, Managed};
+use super::marked::Marked;
+use super::memo::MemoCounters;
+use super::metadata::Metadata;
+use super::middleware::Pipelines;
+use super::module::Module;
+use super::mutable::MutableContainer;
+use super::null::NullObject;
+use super::observer::{Observed, Observer, Observers};
+use super::pending::Pending;
+#[cfg(feature = "std")]
+use super::per_thread::PerThread;
+use super::pool::Pool;
+use super::scope::Scope;
+use super::stats::StatsRecorder;
+use super::tags::Tags;
+use super::trace;
+use super::validation::{self, CompositionReport, Declaration, Dependencies, Dependency};
+use crate::collections::{HashMap, TypeIdMap, TypeIdSet};
+use crate::{Error, ErrorKind, Result};
 
-use super::{Container, Resolver};
+use super::{
+    unshare, Collection, Container, FactoryWithArgs, Inner, Keyed, Registration, Resolver,
+    ResolverType, Teardown, Transformer, Warmer,
+};
+use crate::prelude::*;
 
 /// Dependency container builder.
 ///
@@ -19,6 +55,8 @@ use super::{Container, Resolver};
 /// [register_factory()](struct.ContainerBuilder.html#method.register_factory) and
 /// [register_automatic_factory()](struct.ContainerBuilder.html#method.register_automatic_factory) functions.
 ///
+/// You can register dependencies that are shared for the lifetime of a scope
+/// with the [register_scoped()](struct.ContainerBuilder.html#method.register_scoped) function.
 ///
 /// # Examples
 ///
@@ -41,38 +79,159 @@ use super::{Container, Resolver};
 /// # }
 /// ```
 ///
-/// Circular dependencies will cause continer.resolve() to panic:
-/// ```should_panic
-/// # use kamikaze_di::{Container, ContainerBuilder, Resolver};
+/// Circular dependencies make container.resolve() fail:
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, ErrorKind, Resolver};
 /// #
 /// # fn main() -> Result<(), String> {
 /// #
 /// let mut builder = ContainerBuilder::new();
 ///
-/// builder.register_factory::<i32, _>(|container| {
-///     use std::convert::TryInto;
+/// builder.register_try_factory::<i32, _>(|container| {
+///     let base: i64 = container.resolve()?;
+///     Ok(base as i32 - 1)
+/// })?;
 ///
-///     let base: i64 = container.resolve().unwrap();
-///     let base: i32 = base.try_into().unwrap();
-///     base - 1
-/// });
-///
-/// builder.register_factory::<i64, _>(|container| {
-///     let base: i32 = container.resolve().unwrap();
-///     let base: i64 = base.into();
-///     base - 1
-/// });
+/// builder.register_try_factory::<i64, _>(|container| {
+///     let base: i32 = container.resolve()?;
+///     Ok(i64::from(base) - 1)
+/// })?;
 ///
 /// let container = builder.build();
 ///
-/// let forty_one: i64 = container.resolve()?;
+/// let error = container.resolve::<i64>().err().unwrap();
+/// assert!(matches!(error.kind(), ErrorKind::CycleDetected(_)));
+/// assert_eq!("Circular dependency detected: i64 -> i32 -> i64", error.to_string());
 /// #
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Default, Debug)]
+#[derive(Default)]
 pub struct ContainerBuilder {
-    resolvers: HashMap<TypeId, Resolver>,
+    resolvers: TypeIdMap<Resolver>,
+    transformers: TypeIdMap<Box<dyn Any>>,
+    declarations: TypeIdMap<Declaration>,
+    teardowns: TypeIdMap<Teardown>,
+    initializers: TypeIdMap<Initializer>,
+    lifecycles: TypeIdMap<Managed>,
+    pending: TypeIdMap<Rc<dyn Any>>,
+    warmers: TypeIdMap<Warmer>,
+    /// Startup priorities, see register_eager_with_priority().
+    priorities: TypeIdMap<i32>,
+    constructed: Vec<TypeId>,
+    memo_counters: TypeIdMap<Rc<MemoCounters>>,
+    collections: TypeIdMap<Collection>,
+    keyed: TypeIdMap<Keyed>,
+    /// Names of types with keyed items, which aren't registrations.
+    keyed_names: TypeIdMap<&'static str>,
+    named: HashMap<String, Rc<dyn Any>>,
+    registrations: TypeIdMap<Registration>,
+    deprecations: TypeIdMap<&'static str>,
+    /// Shared Rcs to look for leaks in, see watch_for_leaks().
+    leak_checks: TypeIdMap<LeakCheck>,
+    carry_overs: TypeIdSet,
+    /// Generic factories, see register_generic().
+    generics: TypeIdSet,
+    tags: Tags,
+    metadata: Metadata,
+    /// Small values, see register_in_arena().
+    arena: Arena,
+    pipelines: Pipelines,
+    /// Null objects that haven't been replaced yet.
+    nulls: TypeIdSet,
+    arg_factories: TypeIdMap<Rc<RefCell<Box<dyn Any>>>>,
+    event_capacity: usize,
+    record_stats: bool,
+    async_timeout: Option<core::time::Duration>,
+    default_fallback: DefaultFallback,
+    /// See freeze_on_first_resolve().
+    freeze_on_first_resolve: bool,
+    /// See on_duplicate().
+    duplicate_policy: DuplicatePolicy,
+    profile: Option<String>,
+    observers: Observers,
+}
+
+/// What to do with types two builders both register, see
+/// [merge()](struct.ContainerBuilder.html#method.merge).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum MergePolicy {
+    /// Fail, listing every conflict.
+    Error,
+    /// Keep the registration of the builder merged into.
+    KeepFirst,
+    /// Keep the registration of the builder being merged.
+    KeepLast,
+}
+
+/// What to do when a type is registered again, see
+/// [on_duplicate()](struct.ContainerBuilder.html#method.on_duplicate).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum DuplicatePolicy {
+    /// Fail, the type keeps its first registration.
+    #[default]
+    Error,
+    /// Keep the first registration, dropping the new one.
+    Ignore,
+    /// Replace the registration with the new one.
+    Replace,
+    /// Keep the first registration, and add every item registered for the
+    /// type to its collection, see
+    /// [resolve_all()](struct.Container.html#method.resolve_all). Only items
+    /// can be collected, not factories or builders.
+    Collect,
+}
+
+/// What to do with unregistered types that implement `Default`, see
+/// [fall_back_to_default()](struct.ContainerBuilder.html#method.fall_back_to_default).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum DefaultFallback {
+    /// Fail, like for any type that isn't registered.
+    #[default]
+    Off,
+    /// Create a new default item every time.
+    Create,
+    /// Create a default item once, and share it from then on.
+    Share,
+}
+
+/// Options for registering T, combined before it's registered.
+///
+/// Returned from [define()](struct.ContainerBuilder.html#method.define).
+/// Nothing is registered until
+/// [from_factory()](struct.Definition.html#method.from_factory) is called.
+#[must_use = "nothing is registered until from_factory() is called"]
+pub struct Definition<'a, T> {
+    builder: &'a mut ContainerBuilder,
+    scope: Scope,
+    /// Named scope level, see in_scope().
+    level: Option<&'static str>,
+    lazy: bool,
+    priority: Option<i32>,
+    /// Applied once T is registered, like tags and teardowns.
+    options: Vec<Box<DefinitionOption>>,
+    item: PhantomData<fn() -> T>,
+}
+
+type DefinitionOption = dyn FnOnce(&mut ContainerBuilder) -> Result<()>;
+
+impl fmt::Debug for ContainerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let registrations: BTreeMap<_, _> = self
+            .registrations
+            .iter()
+            .filter_map(|(type_id, registration)| {
+                let resolver_type = ResolverType::from(self.resolvers.get(type_id)?);
+
+                Some((registration.type_name, resolver_type))
+            })
+            .collect();
+
+        f.debug_struct("ContainerBuilder")
+            .field("registrations", &registrations)
+            .field("profile", &self.profile)
+            .finish()
+    }
 }
 
 impl ContainerBuilder {
@@ -81,13 +240,123 @@ impl ContainerBuilder {
         Default::default()
     }
 
+    /// Creates a builder with an active profile, like "dev" or "prod".
+    ///
+    /// See [register_for_profile()](struct.ContainerBuilder.html#method.register_for_profile).
+    pub fn with_profile(profile: &str) -> ContainerBuilder {
+        ContainerBuilder {
+            profile: Some(profile.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a builder with every registration submitted at link time.
+    ///
+    /// See [submit_registration!](macro.submit_registration.html). Returns
+    /// the first error a registration fails with.
+    #[cfg(feature = "auto-register")]
+    pub fn with_auto_registrations() -> Result<ContainerBuilder> {
+        debug!("applying auto registrations");
+
+        let mut builder = ContainerBuilder::new();
+
+        for registration in AutoRegistration::all() {
+            registration.apply(&mut builder)?;
+        }
+
+        Ok(builder)
+    }
+
+    /// The active profile, if there is one.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
     /// Creates a Container from the builder.
-    pub fn build(self) -> Container {
+    pub fn build(mut self) -> Container {
         debug!("builder consumed");
-        Container {
-            resolvers: RefCell::new(self.resolvers),
-            cycle_stopper: CycleStopper::default(),
-        }
+
+        // middleware wraps everything else, decorators included
+        let pipelines = core::mem::take(&mut self.pipelines);
+        pipelines.apply(&mut self);
+
+        let mut inner = Inner::default();
+        inner.resolvers = RefCell::new(self.resolvers);
+        inner.transformers = Rc::new(self.transformers);
+        inner.declarations = self.declarations;
+        inner.teardowns = RefCell::new(self.teardowns);
+        inner.initializers = Rc::new(self.initializers);
+        inner.lifecycles = self.lifecycles;
+        inner.pending = RefCell::new(self.pending);
+        inner.warmers = self.warmers;
+        inner.priorities = self.priorities;
+        inner.constructed = RefCell::new(self.constructed);
+        inner.memo_counters = self.memo_counters;
+        inner.collections = Rc::new(self.collections);
+        inner.keyed = Rc::new(self.keyed);
+        inner.named = Rc::new(self.named);
+        inner.registrations = RefCell::new(self.registrations);
+        inner.deprecations = self.deprecations;
+        inner.leak_checks = self.leak_checks;
+        inner.carry_overs = self.carry_overs;
+        inner.generics = Rc::new(self.generics);
+        inner.tags = Rc::new(self.tags);
+        inner.metadata = Rc::new(self.metadata);
+        inner.arena = Rc::new(self.arena);
+        inner.arg_factories = self.arg_factories;
+        inner.events = EventLog::with_capacity(self.event_capacity);
+        inner.stats = StatsRecorder::new(self.record_stats);
+        inner.async_timeout = self.async_timeout;
+        inner.default_fallback = self.default_fallback;
+        inner.strict.set(self.freeze_on_first_resolve);
+        inner.observers = RefCell::new(self.observers);
+
+        Container::from_inner(inner)
+    }
+
+    /// Creates a container whose registrations can still change.
+    ///
+    /// See [MutableContainer](struct.MutableContainer.html), containers
+    /// from [build()](struct.ContainerBuilder.html#method.build) can't change.
+    pub fn build_mutable(self) -> MutableContainer {
+        MutableContainer::new(self.build())
+    }
+
+    /// Creates a Container, failing if declared dependencies are missing or circular.
+    ///
+    /// Runs [validate()](struct.Container.html#method.validate) before
+    /// anything is resolved, so wiring mistakes show up at startup instead
+    /// of on the first request that needs the broken type. Only dependencies
+    /// declared via
+    /// [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies),
+    /// [register_builder_with_deps()](struct.ContainerBuilder.html#method.register_builder_with_deps)
+    /// and [register_factory_with_deps()](struct.ContainerBuilder.html#method.register_factory_with_deps)
+    /// are checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, ErrorKind};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder_with_deps::<i32, (i64,), _>(|_| 42)?;
+    /// builder.register_builder_with_deps::<i64, (i32,), _>(|_| 42)?;
+    ///
+    /// match builder.try_build().unwrap_err().kind() {
+    ///     ErrorKind::Validation(report) => assert_eq!(vec![vec!["i32", "i64", "i32"]], report.cycles),
+    ///     _ => unreachable!(),
+    /// }
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_build(self) -> Result<Container> {
+        let container = self.build();
+        container.validate()?;
+
+        Ok(container)
     }
 
     /// Registeres a dependency directly.
@@ -102,11 +371,12 @@ impl ContainerBuilder {
     ///
     /// assert!(result.is_ok());
     /// ```
+    #[track_caller]
     pub fn register<T: 'static>(&mut self, item: T) -> Result<()> {
         debug!("registering type");
 
         // shared resolvers hold Box<Any>
-        let resolver = Resolver::Shared(Box::new(item));
+        let resolver = Resolver::Shared(Rc::new(item));
 
         self.insert::<T>(resolver)
     }
@@ -144,6 +414,7 @@ impl ContainerBuilder {
     /// # Ok(())
     /// # }
     /// ```
+    #[track_caller]
     pub fn register_factory<T, F>(&mut self, factory: F) -> Result<()>
     where
         F: (FnMut(&Container) -> T) + 'static,
@@ -151,17 +422,213 @@ impl ContainerBuilder {
     {
         debug!("registering factory");
 
-        // We use double boxes so we can downcast to the inner box type.
-        // you can only downcast to Sized types, that's why we need an inner box
-        // see call_factory() for use.
-        let boxed: Box<dyn (FnMut(&Container) -> T) + 'static> = Box::new(factory);
-        let boxed: Box<dyn Any> = Box::new(boxed);
-        let resolver = Resolver::Factory(RefCell::new(boxed));
+        let factory = ErasedFactory::new(factory);
+        let resolver = Resolver::Factory(Rc::new(RefCell::new(factory)));
 
         self.insert::<T>(resolver)
     }
 
-    /// Every time a dependency is resolved, a new item will be created.
+    /// Registers a factory that can fail.
+    ///
+    /// Resolving T returns the factory's error, saying which type it was
+    /// creating, so failures deep down the dependency tree show the whole
+    /// path to the broken dependency.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver, Result};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Connection;
+    /// struct Users { connection: Rc<Connection> }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_try_factory::<Rc<Connection>, _>(|_| Err("host unreachable".into()))?;
+    /// builder.register_try_factory::<Rc<Users>, _>(|container| {
+    ///     let connection = container.resolve::<Rc<Connection>>()?;
+    ///     Ok(Rc::new(Users { connection }))
+    /// })?;
+    ///
+    /// let container = builder.build();
+    /// let error = container.resolve::<Rc<Users>>().err().unwrap();
+    ///
+    /// assert!(error.to_string().ends_with("Connection>: host unreachable"));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_try_factory<T, F>(&mut self, mut factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> Result<T>) + 'static,
+        T: 'static,
+    {
+        debug!("registering fallible factory");
+
+        let factory = ErasedFactory::fallible(move |container| {
+            factory(container).map_err(|error| creation_failed::<T>(&error))
+        });
+        let resolver = Resolver::Factory(Rc::new(RefCell::new(factory)));
+
+        self.insert::<T>(resolver)
+    }
+
+    /// Registers a factory that takes arguments from the caller.
+    ///
+    /// The factory gets the container, for its dependencies, and whatever
+    /// the caller passes to
+    /// [resolve_with()](struct.Container.html#method.resolve_with), like a
+    /// tenant or request id. Types registered this way can't be resolved
+    /// without arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_factory_with_args::<String, (&str, u32), _>(|_, (name, id)| {
+    ///     format!("{}#{}", name, id)
+    /// })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!("job#7", container.resolve_with::<String, (&str, u32)>(("job", 7))?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_factory_with_args<T, A, F>(&mut self, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container, A) -> T) + 'static,
+        T: 'static,
+        A: 'static,
+    {
+        debug!("registering factory with arguments");
+
+        if !self.make_room::<T>()? {
+            return Ok(());
+        }
+
+        // Double boxed, you can only downcast to Sized types. Only
+        // resolve_with() calls these, so they aren't worth an ErasedFactory.
+        let boxed: Box<FactoryWithArgs<T, A>> = Box::new(factory);
+        let boxed: Box<dyn Any> = Box::new(boxed);
+
+        let type_id = TypeId::of::<T>();
+        self.arg_factories
+            .insert(type_id, Rc::new(RefCell::new(boxed)));
+        self.registrations.insert(type_id, Registration::of::<T>());
+
+        Ok(())
+    }
+
+    /// Registers a factory that creates one item per thread.
+    ///
+    /// The item is kept in thread local storage and cloned on every resolve.
+    /// Every registration has its own item, so separately built containers
+    /// never share it, even when wired by the same code, while forks of a
+    /// container do. Different threads never share it, and the item is
+    /// dropped together with the registration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{Container, ContainerBuilder, Resolver};
+    /// # use std::thread::{self, ThreadId};
+    /// #
+    /// fn wire() -> Container {
+    ///     let mut builder = ContainerBuilder::new();
+    ///     builder
+    ///         .register_thread_local::<ThreadId, _>(|_| thread::current().id())
+    ///         .unwrap();
+    ///
+    ///     builder.build()
+    /// }
+    ///
+    /// let worker = thread::spawn(|| wire().resolve::<ThreadId>().unwrap());
+    ///
+    /// assert_eq!(thread::current().id(), wire().resolve::<ThreadId>().unwrap());
+    /// assert_eq!(worker.thread().id(), worker.join().unwrap());
+    /// ```
+    #[cfg(feature = "std")]
+    #[track_caller]
+    pub fn register_thread_local<T, F>(&mut self, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: Clone + 'static,
+    {
+        debug!("registering thread local factory");
+
+        let mut per_thread = PerThread::new(factory);
+
+        self.register_factory(move |container| per_thread.get_or_create(container))
+    }
+
+    /// Registers the null object for T, as an `Rc<T>`.
+    ///
+    /// Registering a real `Rc<T>` afterwards replaces the null object
+    /// instead of failing, and the null object is skipped if a real one was
+    /// registered first. That way optional integrations can always be
+    /// resolved, and profiles that enable them just register the real thing.
+    /// See [NullObject](trait.NullObject.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, NullObject, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Analytics { fn enabled(&self) -> bool; }
+    ///
+    /// struct NullAnalytics;
+    /// impl Analytics for NullAnalytics { fn enabled(&self) -> bool { false } }
+    /// impl NullObject for dyn Analytics {
+    ///     fn null() -> Rc<dyn Analytics> { Rc::new(NullAnalytics) }
+    /// }
+    ///
+    /// struct Segment;
+    /// impl Analytics for Segment { fn enabled(&self) -> bool { true } }
+    ///
+    /// let mut builder = ContainerBuilder::with_profile("prod");
+    /// builder.register_null::<dyn Analytics>()?;
+    /// builder.register_for_profile("prod", |builder| {
+    ///     builder.register::<Rc<dyn Analytics>>(Rc::new(Segment))
+    /// })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert!(container.resolve::<Rc<dyn Analytics>>()?.enabled());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_null<T: NullObject + ?Sized + 'static>(&mut self) -> Result<()> {
+        debug!("registering null object");
+
+        if self.has::<Rc<T>>() {
+            return Ok(());
+        }
+
+        self.register::<Rc<T>>(T::null())?;
+        self.nulls.insert(TypeId::of::<Rc<T>>());
+
+        Ok(())
+    }
+
+    /// Registers a factory for a type that implements [Inject](trait.Inject.html).
+    ///
+    /// Every time a dependency is resolved, a new item will be created,
+    /// with its own dependencies resolved from the container.
     ///
     /// # Examples
     ///
@@ -190,11 +657,86 @@ impl ContainerBuilder {
     /// # Ok(())
     /// # }
     /// ```
+    #[track_caller]
     pub fn register_automatic_factory<T: Inject + 'static>(&mut self) -> Result<()> {
         debug!("registering auto factory");
         self.register_factory(auto_factory::<T>)
     }
 
+    /// Registers a type that implements [Inject](trait.Inject.html).
+    ///
+    /// The item is created when first needed, and then shared, just like
+    /// when it's resolved automatically. Without the `specialization`
+    /// feature, this is how Inject types are made available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{Container, ContainerBuilder, Resolver, Inject, Result};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Clone)]
+    /// struct Port(u16);
+    /// impl Inject for Port {
+    ///     fn resolve(container: &Container) -> Result<Self> {
+    ///         Ok(Port(container.resolve::<u16>()? + 1))
+    ///     }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u16>(8079)?;
+    /// builder.register_automatic::<Port>()?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(8080, container.resolve::<Port>()?.0);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_automatic<T: Inject + 'static>(&mut self) -> Result<()> {
+        debug!("registering automatic builder");
+        self.register_builder(auto_factory::<T>)
+    }
+
+    /// Registers `Rc<T>` for a type that implements [InjectAsRc](trait.InjectAsRc.html).
+    ///
+    /// The item is created when first needed, and then shared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{Container, ContainerBuilder, Resolver, InjectAsRc, Result};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Pool { size: usize }
+    /// impl InjectAsRc for Pool {
+    ///     fn resolve(container: &Container) -> Result<Self> {
+    ///         Ok(Pool { size: container.resolve()? })
+    ///     }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<usize>(4)?;
+    /// builder.register_automatic_as_rc::<Pool>()?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(4, container.resolve::<Rc<Pool>>()?.size);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_automatic_as_rc<T: InjectAsRc + 'static>(&mut self) -> Result<()> {
+        debug!("registering automatic Rc builder");
+        self.register_builder(|container| Rc::new(T::resolve(container).unwrap()))
+    }
+
     /// Registers a builder.
     ///
     /// The dependency is created only when needed and after that
@@ -234,6 +776,7 @@ impl ContainerBuilder {
     /// # Ok(())
     /// # }
     /// ```
+    #[track_caller]
     pub fn register_builder<T, B>(&mut self, builder: B) -> Result<()>
     where
         B: (FnOnce(&Container) -> T) + 'static,
@@ -241,48 +784,3031 @@ impl ContainerBuilder {
     {
         debug!("registering buiilder");
 
-        // We use double boxes so we can downcast to the inner box type.
-        // you can only downcast to Sized types, that's why we need an inner box
-        // see consume_builder() for use.
-        let boxed: Box<dyn (FnOnce(&Container) -> T) + 'static> = Box::new(builder);
-        let boxed: Box<dyn Any> = Box::new(boxed);
-        let resolver = Resolver::Builder(boxed);
+        let resolver = Resolver::Builder(ErasedBuilder::new(builder));
 
-        self.insert::<T>(resolver)
+        self.insert::<T>(resolver)?;
+        self.warmers.insert(TypeId::of::<T>(), Container::warm::<T>);
+
+        Ok(())
     }
 
-    /// Returns true if a dependency is registered.
+    /// Registers a builder that can fail.
+    ///
+    /// Like [register_try_factory()](struct.ContainerBuilder.html#method.register_try_factory),
+    /// the error is returned when resolving T. A builder that failed runs
+    /// again the next time T is resolved, so a database that wasn't up yet
+    /// can still be connected to later. Once it succeeds, it's kept for
+    /// [reset()](struct.MutableContainer.html#method.reset).
     ///
     /// # Examples
     ///
     /// ```
-    /// # use kamikaze_di::{Container, ContainerBuilder, Resolver};
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
     /// #
+    /// let database_up = Rc::new(Cell::new(false));
+    /// let up = database_up.clone();
+    ///
     /// let mut builder = ContainerBuilder::new();
-    /// builder.register::<i16>(43);
+    /// builder.register_try_builder::<u32, _>(move |_| {
+    ///     if up.get() { Ok(42) } else { Err("database is down".into()) }
+    /// })?;
     ///
-    /// assert!(builder.has::<i16>());
-    /// assert!(!builder.has::<i32>());
+    /// let container = builder.build();
+    /// assert_eq!(
+    ///     "Could not create u32: database is down",
+    ///     container.resolve::<u32>().err().unwrap().to_string()
+    /// );
+    ///
+    /// database_up.set(true);
+    /// assert_eq!(42, container.resolve::<u32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
     /// ```
-    pub fn has<T: 'static>(&self) -> bool {
-        let type_id = TypeId::of::<T>();
-
-        self.resolvers.contains_key(&type_id)
-    }
-
-    fn insert<T: 'static>(&mut self, resolver: Resolver) -> Result<()> {
-        debug!("inserting new object");
-
-        let type_id = TypeId::of::<T>();
+    #[track_caller]
+    pub fn register_try_builder<T, B>(&mut self, mut builder: B) -> Result<()>
+    where
+        B: (FnMut(&Container) -> Result<T>) + 'static,
+        T: 'static,
+    {
+        debug!("registering fallible builder");
 
-        if self.has::<T>() {
-            return Err(format!("Container already has {:?}", type_id).into());
-        }
+        let builder = ErasedBuilder::fallible(move |container| {
+            builder(container).map_err(|error| creation_failed::<T>(&error))
+        });
 
-        self.resolvers.insert(type_id, resolver);
+        self.insert::<T>(Resolver::Builder(builder))?;
+        self.warmers.insert(TypeId::of::<T>(), Container::warm::<T>);
 
         Ok(())
     }
+
+    /// Registers a builder, with its place in the startup order.
+    ///
+    /// [warm_up()](struct.Container.html#method.warm_up) and
+    /// [start_all()](struct.Container.html#method.start_all) handle lower
+    /// priorities first, builders registered without one have a priority
+    /// of 0. Declared dependencies still come before their dependents.
+    /// See [startup_order()](struct.Container.html#method.startup_order).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Cache;
+    /// struct Repositories;
+    /// struct HttpServer;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_eager_with_priority::<HttpServer, _>(30, |_| HttpServer)?;
+    /// builder.register_eager_with_priority::<Cache, _>(10, |_| Cache)?;
+    /// builder.register_eager_with_priority::<Repositories, _>(20, |_| Repositories)?;
+    ///
+    /// let container = builder.build();
+    /// let order = container.startup_order();
+    ///
+    /// assert!(order[0].ends_with("Cache"));
+    /// assert!(order[1].ends_with("Repositories"));
+    /// assert!(order[2].ends_with("HttpServer"));
+    ///
+    /// container.warm_up()?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_eager_with_priority<T, B>(&mut self, priority: i32, builder: B) -> Result<()>
+    where
+        B: (FnOnce(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        self.register_builder::<T, B>(builder)?;
+        self.priorities.insert(TypeId::of::<T>(), priority);
+
+        Ok(())
+    }
+
+    /// Registers a builder that can run again, after a reset.
+    ///
+    /// Works like [register_builder()](struct.ContainerBuilder.html#method.register_builder),
+    /// but the builder is kept once it has built the item, so
+    /// [reset()](struct.MutableContainer.html#method.reset) can have it build a
+    /// new one.
+    #[track_caller]
+    pub fn register_rebuildable<T, B>(&mut self, builder: B) -> Result<()>
+    where
+        B: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering rebuildable builder");
+
+        let resolver = Resolver::Builder(ErasedBuilder::rebuildable(builder));
+
+        self.insert::<T>(resolver)?;
+        self.warmers.insert(TypeId::of::<T>(), Container::warm::<T>);
+
+        Ok(())
+    }
+
+    /// Registers a factory whose item is shared while it's in use, as an `Rc<T>`.
+    ///
+    /// The container only keeps a `Weak<T>`: resolving hands out the item
+    /// as long as someone holds on to it, once everyone dropped it the next
+    /// resolution creates a new one. Handy for large resources that are
+    /// only needed now and then, like parsed rule sets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_weak_cached::<Vec<String>, _>(|_| vec!["deny *".to_string()])?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let rules = container.resolve::<Rc<Vec<String>>>()?;
+    /// assert!(Rc::ptr_eq(&rules, &container.resolve::<Rc<Vec<String>>>()?));
+    ///
+    /// drop(rules);
+    ///
+    /// // rebuilt, and not kept by the container
+    /// assert_eq!(1, Rc::strong_count(&container.resolve::<Rc<Vec<String>>>()?));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_weak_cached<T, F>(&mut self, mut factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering weak cached factory");
+
+        let mut cached = Weak::new();
+
+        self.register_factory::<Rc<T>, _>(move |container| {
+            if let Some(item) = cached.upgrade() {
+                container.hit(TypeId::of::<Rc<T>>());
+
+                return item;
+            }
+
+            let item = Rc::new(factory(container));
+            cached = Rc::downgrade(&item);
+
+            item
+        })
+    }
+
+    /// Registers a pool of up to `size` reusable items of T.
+    ///
+    /// Items are created by the factory as they're needed, and borrowed via
+    /// [resolve_pooled()](struct.Container.html#method.resolve_pooled) until
+    /// the handle is dropped, see [PooledHandle](struct.PooledHandle.html).
+    /// Pools are kept apart from other registrations of T.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_pool::<Vec<u8>, _>(4, |_| Vec::with_capacity(4096))?;
+    ///
+    /// let container = builder.build();
+    /// let buffer = container.resolve_pooled::<Vec<u8>>()?;
+    ///
+    /// assert_eq!(4096, buffer.capacity());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_pool<T, F>(&mut self, size: usize, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering pool");
+
+        if size == 0 {
+            return Err(
+                format!("the pool of {} can't be empty", core::any::type_name::<T>()).into(),
+            );
+        }
+
+        self.register::<Rc<Pool<T>>>(Rc::new(Pool::new(size, factory)))
+    }
+
+    /// Registers a builder along with the types it depends on.
+    ///
+    /// Same as [register_builder()](struct.ContainerBuilder.html#method.register_builder)
+    /// followed by [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies),
+    /// so [try_build()](struct.ContainerBuilder.html#method.try_build) can
+    /// check the builder's dependencies. Dependencies are listed as a tuple.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i16>(41)?;
+    /// builder.register_builder_with_deps::<i32, (i16,), _>(|container| {
+    ///     let base: i16 = container.resolve().unwrap();
+    ///     i32::from(base) + 1
+    /// })?;
+    ///
+    /// let container = builder.try_build()?;
+    ///
+    /// assert_eq!(42, container.resolve::<i32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_builder_with_deps<T, D, B>(&mut self, builder: B) -> Result<()>
+    where
+        B: (FnOnce(&Container) -> T) + 'static,
+        T: 'static,
+        D: Dependencies,
+    {
+        self.register_builder::<T, B>(builder)?;
+        self.declare_dependencies::<T, D>();
+
+        Ok(())
+    }
+
+    /// Registers a factory along with the types it depends on.
+    ///
+    /// Same as [register_factory()](struct.ContainerBuilder.html#method.register_factory)
+    /// followed by [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies),
+    /// see [register_builder_with_deps()](struct.ContainerBuilder.html#method.register_builder_with_deps).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, ErrorKind};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_factory_with_deps::<i32, (i16,), _>(|_| 42)?;
+    ///
+    /// match builder.try_build().unwrap_err().kind() {
+    ///     ErrorKind::Validation(report) => assert_eq!(vec![("i32", "i16")], report.missing),
+    ///     _ => unreachable!(),
+    /// }
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_factory_with_deps<T, D, F>(&mut self, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+        D: Dependencies,
+    {
+        self.register_factory::<T, F>(factory)?;
+        self.declare_dependencies::<T, D>();
+
+        Ok(())
+    }
+
+    /// Registers T's default, built the first time T is resolved.
+    ///
+    /// Saves writing a builder for value objects like empty registries or
+    /// zeroed counters. Use [register_defaults!](macro.register_defaults.html)
+    /// to register several types at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// # use std::cell::Cell;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Default)]
+    /// struct Counters { requests: Cell<u64> }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_default::<Rc<Counters>>()?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// container.resolve::<Rc<Counters>>()?.requests.set(1);
+    /// assert_eq!(1, container.resolve::<Rc<Counters>>()?.requests.get());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_default<T: Default + 'static>(&mut self) -> Result<()> {
+        debug!("registering default");
+
+        self.register_builder::<T, _>(|_| T::default())
+    }
+
+    /// Registers an `Rc<T>` that is already shared with other code.
+    ///
+    /// The container keeps the pointer it's given, so resolving `Rc<T>`
+    /// hands out the same item the rest of the program holds. Works for
+    /// trait objects too. Resolve it as `Rc<T>`, or via
+    /// [resolve_trait()](struct.Container.html#method.resolve_trait).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let requests = Rc::new(Cell::new(0));
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_rc::<Cell<u32>>(requests.clone())?;
+    ///
+    /// let container = builder.build();
+    /// container.resolve::<Rc<Cell<u32>>>()?.set(1);
+    ///
+    /// assert_eq!(1, requests.get());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_rc<T: ?Sized + 'static>(&mut self, item: Rc<T>) -> Result<()> {
+        debug!("registering shared pointer");
+
+        self.register::<Rc<T>>(item)?;
+        self.watch_for_leaks::<T>();
+
+        Ok(())
+    }
+
+    /// Registers a boxed trait object as a shared `Rc<dyn Trait>`.
+    ///
+    /// Saves the double indirection of registering `Box<dyn Trait>` and
+    /// resolving `Rc<Box<dyn Trait>>`. Resolve it via
+    /// [resolve_trait()](struct.Container.html#method.resolve_trait), or as
+    /// `Rc<dyn Trait>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Greeter { fn greet(&self) -> String; }
+    ///
+    /// struct English;
+    /// impl Greeter for English {
+    ///     fn greet(&self) -> String { "hello".to_string() }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_boxed_trait::<dyn Greeter>(Box::new(English))?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!("hello", container.resolve_trait::<dyn Greeter>()?.greet());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_boxed_trait<T: ?Sized + 'static>(&mut self, item: Box<T>) -> Result<()> {
+        debug!("registering boxed trait object");
+
+        self.register::<Rc<T>>(Rc::from(item))
+    }
+
+    /// Registers a mutable service, shared as `Rc<RefCell<T>>`.
+    ///
+    /// Saves wrapping services that need interior mutability by hand.
+    /// Resolve it via [resolve_mut()](struct.Container.html#method.resolve_mut),
+    /// or as `Rc<RefCell<T>>`, which is also how fields of derived types get it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_mut::<Vec<String>>(Vec::new())?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// container.resolve_mut::<Vec<String>>()?.borrow_mut().push("started".to_string());
+    ///
+    /// assert_eq!(1, container.resolve_mut::<Vec<String>>()?.borrow().len());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_mut<T: 'static>(&mut self, item: T) -> Result<()> {
+        debug!("registering mutable service");
+
+        self.register::<Rc<RefCell<T>>>(Rc::new(RefCell::new(item)))
+    }
+
+    /// Stores a small value in the container's arena.
+    ///
+    /// For graphs with many tiny values, like ports and timeouts, arena
+    /// values skip the Rc each registered item gets, and are copied out
+    /// instead. They're kept apart from registrations, resolve them via
+    /// [resolve_from_arena()](struct.Container.html#method.resolve_from_arena)
+    /// or through an [ArenaHandle](struct.ArenaHandle.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_in_arena::<u16>(8080)?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(8080, container.resolve_from_arena::<u16>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_in_arena<T: Copy + 'static>(&mut self, item: T) -> Result<()> {
+        debug!("storing value in arena");
+
+        self.arena.insert(item)
+    }
+
+    /// Registers a factory for a whole generic family, like `Repository<T>`.
+    ///
+    /// Items are built on demand, see [GenericFactory](trait.GenericFactory.html).
+    pub fn register_generic<F: GenericFactory>(&mut self) -> Result<()> {
+        debug!("registering generic factory");
+
+        if !self.generics.insert(TypeId::of::<F>()) {
+            return Err(format!(
+                "Generic factory {} is already registered",
+                core::any::type_name::<F>()
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Registers a future that produces T.
+    ///
+    /// Handy for resources whose construction started before the container
+    /// was built. The future is awaited the first time T is resolved via
+    /// [resolve_async()](struct.Container.html#method.resolve_async), and
+    /// the item is shared after that, like a registered one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::future::Future;
+    /// # use std::pin::{pin, Pin};
+    /// # use std::task::{Context, Poll, Waker};
+    /// #
+    /// # fn block_on<F: Future>(future: F) -> F::Output {
+    /// #     let mut future = pin!(future);
+    /// #     let mut cx = Context::from_waker(Waker::noop());
+    /// #     loop {
+    /// #         if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+    /// #             return output;
+    /// #         }
+    /// #     }
+    /// # }
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let address: Pin<Box<dyn Future<Output = String>>> =
+    ///     Box::pin(async { "10.0.0.1".to_string() });
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_pending(address)?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert!(container.resolve::<String>().is_err());
+    /// assert_eq!("10.0.0.1", block_on(container.resolve_async::<String>())?);
+    /// assert_eq!("10.0.0.1", container.resolve::<String>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_pending<T, F>(&mut self, future: F) -> Result<()>
+    where
+        F: Future<Output = T> + 'static,
+        T: 'static,
+    {
+        debug!("registering pending item");
+
+        let type_id = TypeId::of::<T>();
+
+        if !self.make_room::<T>()? {
+            return Ok(());
+        }
+
+        self.pending.insert(type_id, Rc::new(Pending::new(future)));
+        self.registrations.insert(type_id, Registration::of::<T>());
+
+        Ok(())
+    }
+
+    /// Registers a scoped factory.
+    ///
+    /// The dependency is created once per scope, and shared within
+    /// that scope. Resolving it outside of a scope is an error.
+    /// See [enter_scope()](struct.Container.html#method.enter_scope).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_scoped::<Rc<String>, _>(|_| Rc::new("request".to_string()))?;
+    ///
+    /// let container = builder.build();
+    /// assert!(container.resolve::<Rc<String>>().is_err());
+    ///
+    /// let scope = container.enter_scope();
+    /// let first = scope.resolve::<Rc<String>>()?;
+    /// let second = scope.resolve::<Rc<String>>()?;
+    ///
+    /// assert!(Rc::ptr_eq(&first, &second));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_scoped<T, F>(&mut self, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering scoped factory");
+
+        let factory = ErasedFactory::new(factory);
+        let resolver = Resolver::Scoped(Rc::new(RefCell::new(factory)), None);
+
+        self.insert::<T>(resolver)
+    }
+
+    /// Registers a factory scoped to a named level, like `"session"`.
+    ///
+    /// The dependency is created once per scope at that level, and shared
+    /// by every scope nested inside it. Resolution walks outward from the
+    /// innermost scope to the closest one at the level, and fails if there
+    /// is none. Request scopes are at the `"request"` level.
+    /// See [enter_named_scope()](struct.Container.html#method.enter_named_scope).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Cart;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_in_scope::<Rc<Cart>, _>("session", |_| Rc::new(Cart))?;
+    ///
+    /// let container = builder.build();
+    /// assert!(container.enter_scope().resolve::<Rc<Cart>>().is_err());
+    ///
+    /// let session = container.enter_named_scope("session");
+    /// let cart = session.resolve::<Rc<Cart>>()?;
+    ///
+    /// let request = session.enter_named_scope("request");
+    /// assert!(Rc::ptr_eq(&cart, &request.resolve::<Rc<Cart>>()?));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_in_scope<T, F>(&mut self, level: &'static str, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering factory scoped to {}", level);
+
+        let factory = ErasedFactory::new(factory);
+        let resolver = Resolver::Scoped(Rc::new(RefCell::new(factory)), Some(level));
+
+        self.insert::<T>(resolver)
+    }
+
+    /// Registers a factory that falls back to another one when it fails.
+    ///
+    /// The primary factory is tried every time the dependency is resolved,
+    /// and the fallback is used whenever it returns an error, like when a
+    /// remote service is down. Switching to the fallback and back is logged,
+    /// and observers are told about every fallback as
+    /// [Observed::FellBack](enum.Observed.html#variant.FellBack).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Search { fn backend(&self) -> &str; }
+    ///
+    /// struct ClusterSearch;
+    /// impl Search for ClusterSearch { fn backend(&self) -> &str { "cluster" } }
+    ///
+    /// struct LocalIndex;
+    /// impl Search for LocalIndex { fn backend(&self) -> &str { "local" } }
+    ///
+    /// let cluster_up = Rc::new(Cell::new(true));
+    /// let health = cluster_up.clone();
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_with_fallback::<Rc<dyn Search>, _, _>(
+    ///     move |_| {
+    ///         if health.get() {
+    ///             Ok(Rc::new(ClusterSearch) as Rc<dyn Search>)
+    ///         } else {
+    ///             Err("cluster is down".into())
+    ///         }
+    ///     },
+    ///     |_| Rc::new(LocalIndex),
+    /// )?;
+    ///
+    /// let container = builder.build();
+    /// assert_eq!("cluster", container.resolve::<Rc<dyn Search>>()?.backend());
+    ///
+    /// cluster_up.set(false);
+    /// assert_eq!("local", container.resolve::<Rc<dyn Search>>()?.backend());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_with_fallback<T, P, F>(&mut self, mut primary: P, mut fallback: F) -> Result<()>
+    where
+        P: (FnMut(&Container) -> Result<T>) + 'static,
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering factory with fallback");
+
+        let mut degraded = false;
+
+        self.register_factory(move |container| match primary(container) {
+            Ok(item) => {
+                if degraded {
+                    info!("{} recovered", core::any::type_name::<T>());
+                    degraded = false;
+                }
+
+                item
+            }
+            Err(error) => {
+                if !degraded {
+                    warn!(
+                        "{} failed, switching to fallback: {}",
+                        core::any::type_name::<T>(),
+                        error
+                    );
+                    degraded = true;
+                }
+
+                container.notify(&Observed::FellBack {
+                    type_name: core::any::type_name::<T>(),
+                    error: &error,
+                });
+
+                fallback(container)
+            }
+        })
+    }
+
+    /// Registers a scoped factory whose items are reused across scopes with the same key.
+    ///
+    /// Each scope still gets its item once, but instead of calling the factory
+    /// for every new scope, the key function is called and items are reused
+    /// for scopes that produce the same key, like scopes for the same tenant.
+    /// Memoized items are kept for the lifetime of the container.
+    ///
+    /// Hit rates are available via
+    /// [memo_stats()](struct.Container.html#method.memo_stats).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Clone)]
+    /// struct TenantId(u32);
+    ///
+    /// struct Parser { tenant: u32 }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_scoped_memoized::<Rc<Parser>, _, _, _>(
+    ///     |container| container.resolve::<TenantId>().map(|tenant| tenant.0).unwrap_or(0),
+    ///     |container| {
+    ///         let tenant = container.resolve::<TenantId>().unwrap().0;
+    ///         Rc::new(Parser { tenant })
+    ///     },
+    /// )?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// for tenant in &[1, 2, 1, 1] {
+    ///     let scope = container.enter_scope_with(TenantId(*tenant));
+    ///     assert_eq!(*tenant, scope.resolve::<Rc<Parser>>()?.tenant);
+    /// }
+    ///
+    /// let stats = container.memo_stats::<Rc<Parser>>().unwrap();
+    /// assert_eq!(2, stats.hits);
+    /// assert_eq!(2, stats.misses);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_scoped_memoized<T, K, KF, F>(&mut self, key: KF, mut factory: F) -> Result<()>
+    where
+        KF: Fn(&Container) -> K + 'static,
+        F: (FnMut(&Container) -> T) + 'static,
+        K: Eq + Hash + 'static,
+        T: Clone + 'static,
+    {
+        debug!("registering memoized scoped factory");
+
+        let counters = Rc::new(MemoCounters::default());
+        let factory_counters = counters.clone();
+        let mut memo: HashMap<K, T> = HashMap::new();
+
+        self.register_scoped(move |container| {
+            let key = key(container);
+
+            if let Some(item) = memo.get(&key) {
+                factory_counters.hit();
+                return item.clone();
+            }
+
+            factory_counters.miss();
+            let item = factory(container);
+            memo.insert(key, item.clone());
+
+            item
+        })?;
+
+        self.memo_counters.insert(TypeId::of::<T>(), counters);
+
+        Ok(())
+    }
+
+    /// Registers a factory with an explicit scope.
+    ///
+    /// Transient dependencies behave like
+    /// [register_factory()](struct.ContainerBuilder.html#method.register_factory),
+    /// singletons like
+    /// [register_builder()](struct.ContainerBuilder.html#method.register_builder)
+    /// and scoped ones like
+    /// [register_scoped()](struct.ContainerBuilder.html#method.register_scoped).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver, Scope};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    ///
+    /// let mut i = 0;
+    /// builder.register_with_scope::<i32, _>(Scope::Singleton, move |_| {
+    ///     i += 1;
+    ///     i
+    /// })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(1, container.resolve::<i32>()?);
+    /// assert_eq!(1, container.resolve::<i32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_with_scope<T, F>(&mut self, scope: Scope, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        match scope {
+            Scope::Transient => self.register_factory(factory),
+            Scope::Singleton => self.register_builder(factory),
+            Scope::Scoped => self.register_scoped(factory),
+        }
+    }
+
+    /// Starts defining a registration of T, with options chained on.
+    ///
+    /// Lifetimes, tags, startup options and teardowns combine freely, T is
+    /// registered once [from_factory()](struct.Definition.html#method.from_factory)
+    /// is called. T is a singleton unless told otherwise. See
+    /// [Definition](struct.Definition.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Connection { closed: Rc<Cell<bool>> }
+    ///
+    /// let closed = Rc::new(Cell::new(false));
+    /// let flag = closed.clone();
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder
+    ///     .define::<Rc<Connection>>()
+    ///     .singleton()
+    ///     .lazy()
+    ///     .tagged("storage")
+    ///     .teardown(|connection| connection.closed.set(true))
+    ///     .from_factory(move |_| Rc::new(Connection { closed: flag.clone() }))?;
+    ///
+    /// let container = builder.build();
+    /// container.warm_up()?;
+    ///
+    /// // lazy, warming up skipped it
+    /// assert!(container.registrations().all(|registration| !registration.built));
+    ///
+    /// assert_eq!(1, container.resolve_tagged("storage")?.len());
+    ///
+    /// drop(container);
+    /// assert!(closed.get());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn define<T: 'static>(&mut self) -> Definition<'_, T> {
+        Definition {
+            builder: self,
+            scope: Scope::Singleton,
+            level: None,
+            lazy: false,
+            priority: None,
+            options: Vec::new(),
+            item: PhantomData,
+        }
+    }
+
+    /// Adds a transformer for items created by factories and builders.
+    ///
+    /// Transformers run after the item is created and before it is cached
+    /// or returned, which makes them a good place for validation. Errors
+    /// are returned from resolve(). Multiple transformers for the same
+    /// type run in the order they were added.
+    ///
+    /// Items registered directly via
+    /// [register()](struct.ContainerBuilder.html#method.register) are not
+    /// transformed. If a transformer fails for an item created by a
+    /// builder, the builder is not run again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_factory::<i32, _>(|_| 42)?;
+    ///
+    /// builder.add_transformer::<i32, _>(|port| match port {
+    ///     0 => Err("port can't be 0".into()),
+    ///     port => Ok(port),
+    /// });
+    /// builder.add_transformer::<i32, _>(|port| Ok(port + 1));
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(43, container.resolve::<i32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_transformer<T, F>(&mut self, transformer: F)
+    where
+        F: Fn(T) -> Result<T> + 'static,
+        T: 'static,
+    {
+        debug!("adding transformer");
+
+        let type_id = TypeId::of::<T>();
+
+        // Transformers are double boxed so we can downcast them, most
+        // types have none, so they aren't worth an ErasedFactory.
+        let boxed: Box<Transformer<T>> = match self.transformers.remove(&type_id) {
+            Some(previous) => {
+                let previous = previous
+                    .downcast::<Box<Transformer<T>>>()
+                    .expect("could not downcast transformer");
+
+                Box::new(move |item| transformer(previous(item)?))
+            }
+            None => Box::new(transformer),
+        };
+
+        self.transformers.insert(type_id, Box::new(boxed));
+    }
+
+    /// Makes `Rc<To>` resolve to the item registered as `Rc<From>`.
+    ///
+    /// One registration can then be looked up as its concrete type and as
+    /// the traits it implements, and both lookups share the same item. The
+    /// closure turns one Rc into the other, `|item| item` does for traits.
+    /// Aliases follow their target, so aliasing a factory gets a new item
+    /// every time. `Rc<To>` is declared to depend on `Rc<From>`, for
+    /// [validate()](struct.Container.html#method.validate).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::RefCell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Cache { fn put(&self, key: &str); }
+    ///
+    /// #[derive(Default)]
+    /// struct RedisCache { keys: RefCell<Vec<String>> }
+    /// impl Cache for RedisCache { fn put(&self, key: &str) { self.keys.borrow_mut().push(key.to_string()) } }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<RedisCache>>(Rc::new(RedisCache::default()))?;
+    /// builder.alias::<RedisCache, dyn Cache, _>(|cache| cache)?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// container.resolve::<Rc<dyn Cache>>()?.put("user:1");
+    ///
+    /// assert_eq!(vec!["user:1"], *container.resolve::<Rc<RedisCache>>()?.keys.borrow());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn alias<From, To, F>(&mut self, coerce: F) -> Result<()>
+    where
+        From: ?Sized + 'static,
+        To: ?Sized + 'static,
+        F: Fn(Rc<From>) -> Rc<To> + 'static,
+    {
+        debug!("registering alias");
+
+        let factory = ErasedFactory::fallible(move |container| {
+            container.get::<Rc<From>>().map(&coerce).map_err(|error| {
+                format!(
+                    "Could not resolve {}, an alias of {}: {}",
+                    core::any::type_name::<Rc<To>>(),
+                    core::any::type_name::<Rc<From>>(),
+                    error
+                )
+                .into()
+            })
+        });
+
+        self.insert::<Rc<To>>(Resolver::Factory(Rc::new(RefCell::new(factory))))?;
+        self.declare_dependencies::<Rc<To>, (Rc<From>,)>();
+
+        Ok(())
+    }
+
+    /// Makes `Rc<To>` resolve to the item registered as `Rc<From>`, for
+    /// types that coerce, needs the `unsize` feature.
+    ///
+    /// Like [alias()](struct.ContainerBuilder.html#method.alias) with
+    /// `|item| item`, which is all it takes when From implements the trait
+    /// To stands for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Source { fn read(&self) -> &str; }
+    ///
+    /// struct File;
+    /// impl Source for File { fn read(&self) -> &str { "contents" } }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<File>>(Rc::new(File))?;
+    /// builder.register_coercion::<File, dyn Source>()?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!("contents", container.resolve::<Rc<dyn Source>>()?.read());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unsize")]
+    #[track_caller]
+    pub fn register_coercion<From, To>(&mut self) -> Result<()>
+    where
+        From: core::marker::Unsize<To> + ?Sized + 'static,
+        To: ?Sized + 'static,
+    {
+        self.alias::<From, To, _>(|item| item)
+    }
+
+    /// Makes Target resolve to the registered Source, converted via `From`.
+    ///
+    /// Bridges small mismatches, like a newtype around a registered value
+    /// or a wider number, without registering the value twice. The
+    /// conversion runs every time Target is resolved, Source decides what's
+    /// shared. Target is declared to depend on Source, for
+    /// [validate()](struct.Container.html#method.validate).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Clone)]
+    /// struct Port(u16);
+    ///
+    /// impl From<Port> for u32 {
+    ///     fn from(port: Port) -> u32 { port.0.into() }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Port>(Port(8080))?;
+    /// builder.register_conversion::<Port, u32>()?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(8080, container.resolve::<u32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_conversion<Source, Target>(&mut self) -> Result<()>
+    where
+        Source: Clone + 'static,
+        Target: From<Source> + 'static,
+    {
+        debug!("registering conversion");
+
+        let factory =
+            ErasedFactory::fallible(|container| Ok(Target::from(container.get::<Source>()?)));
+
+        self.insert::<Target>(Resolver::Factory(Rc::new(RefCell::new(factory))))?;
+        self.declare_dependencies::<Target, (Source,)>();
+
+        Ok(())
+    }
+
+    /// Makes Target resolve to the registered Source, converted via `TryFrom`.
+    ///
+    /// Like [register_conversion()](struct.ContainerBuilder.html#method.register_conversion),
+    /// resolving Target fails if the conversion does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i64>(-1)?;
+    /// builder.register_try_conversion::<i64, u32>()?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(
+    ///     "Could not convert i64 into u32: out of range integral type conversion attempted",
+    ///     container.resolve::<u32>().err().unwrap().to_string()
+    /// );
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_try_conversion<Source, Target>(&mut self) -> Result<()>
+    where
+        Source: Clone + 'static,
+        Target: TryFrom<Source> + 'static,
+        Target::Error: fmt::Display,
+    {
+        debug!("registering fallible conversion");
+
+        let factory = ErasedFactory::fallible(|container| {
+            Target::try_from(container.get::<Source>()?).map_err(|error| {
+                format!(
+                    "Could not convert {} into {}: {}",
+                    core::any::type_name::<Source>(),
+                    core::any::type_name::<Target>(),
+                    error
+                )
+                .into()
+            })
+        });
+
+        self.insert::<Target>(Resolver::Factory(Rc::new(RefCell::new(factory))))?;
+        self.declare_dependencies::<Target, (Source,)>();
+
+        Ok(())
+    }
+
+    /// Adds an item to the collection of T.
+    ///
+    /// Can be called any number of times, collections are kept apart from
+    /// regular registrations of T. Resolve every item added, in order, via
+    /// [resolve_all()](struct.Container.html#method.resolve_all). Items
+    /// added here have priority 0, see
+    /// [register_in_collection_with_priority()](struct.ContainerBuilder.html#method.register_in_collection_with_priority).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// trait EventHandler { fn name(&self) -> &str; }
+    ///
+    /// struct Audit;
+    /// impl EventHandler for Audit { fn name(&self) -> &str { "audit" } }
+    ///
+    /// struct Mailer;
+    /// impl EventHandler for Mailer { fn name(&self) -> &str { "mailer" } }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_in_collection::<dyn EventHandler>(Rc::new(Audit));
+    /// builder.register_in_collection::<dyn EventHandler>(Rc::new(Mailer));
+    ///
+    /// let container = builder.build();
+    ///
+    /// let names: Vec<_> = container
+    ///     .resolve_all::<dyn EventHandler>()
+    ///     .iter()
+    ///     .map(|handler| handler.name().to_string())
+    ///     .collect();
+    ///
+    /// assert_eq!(vec!["audit", "mailer"], names);
+    /// ```
+    pub fn register_in_collection<T: ?Sized + 'static>(&mut self, item: Rc<T>) {
+        self.register_in_collection_with_priority(item, 0);
+    }
+
+    /// Adds an item to the collection of T, with a priority.
+    ///
+    /// [resolve_all()](struct.Container.html#method.resolve_all) returns
+    /// items with higher priorities first, which matters for things like
+    /// middleware. Items with the same priority keep the order they were
+    /// added in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_in_collection_with_priority::<str>(Rc::from("logging"), -10);
+    /// builder.register_in_collection::<str>(Rc::from("routing"));
+    /// builder.register_in_collection_with_priority::<str>(Rc::from("auth"), 10);
+    /// builder.register_in_collection::<str>(Rc::from("handler"));
+    ///
+    /// let container = builder.build();
+    /// let middleware: Vec<_> = container.resolve_all::<str>().iter().map(|name| name.to_string()).collect();
+    ///
+    /// assert_eq!(vec!["auth", "routing", "handler", "logging"], middleware);
+    /// ```
+    pub fn register_in_collection_with_priority<T: ?Sized + 'static>(
+        &mut self,
+        item: Rc<T>,
+        priority: i32,
+    ) {
+        debug!("adding item to collection");
+
+        let items = self.collections.entry(TypeId::of::<T>()).or_default();
+
+        add_to_collection(items, priority, Box::new(item));
+    }
+
+    /// Registers one of several implementations of T, under a key.
+    ///
+    /// Keyed items are kept apart from regular registrations of T. Pick one
+    /// at runtime via
+    /// [resolve_keyed()](struct.Container.html#method.resolve_keyed).
+    /// Returns an error if the key is taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait PaymentGateway { fn charge(&self, cents: u32) -> String; }
+    ///
+    /// struct Stripe;
+    /// impl PaymentGateway for Stripe {
+    ///     fn charge(&self, cents: u32) -> String { format!("stripe: {}", cents) }
+    /// }
+    ///
+    /// struct Paypal;
+    /// impl PaymentGateway for Paypal {
+    ///     fn charge(&self, cents: u32) -> String { format!("paypal: {}", cents) }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_keyed::<dyn PaymentGateway>("stripe", Rc::new(Stripe))?;
+    /// builder.register_keyed::<dyn PaymentGateway>("paypal", Rc::new(Paypal))?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let gateway = container.resolve_keyed::<dyn PaymentGateway>("stripe")?;
+    ///
+    /// assert_eq!("stripe: 100", gateway.charge(100));
+    /// assert_eq!(vec!["paypal", "stripe"], container.keys::<dyn PaymentGateway>());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_keyed<T: ?Sized + 'static>(&mut self, key: &str, item: Rc<T>) -> Result<()> {
+        debug!("registering keyed item");
+
+        let type_id = TypeId::of::<T>();
+        self.keyed_names
+            .insert(type_id, core::any::type_name::<T>());
+
+        let items = self.keyed.entry(type_id).or_default();
+
+        if items.contains_key(key) {
+            return Err(format!(
+                "Container already has {} for key \"{}\"",
+                core::any::type_name::<T>(),
+                key
+            )
+            .into());
+        }
+
+        items.insert(key.to_string(), Box::new(item));
+
+        Ok(())
+    }
+
+    /// Registers an item of T under the marker type K.
+    ///
+    /// Like [register_keyed()](struct.ContainerBuilder.html#method.register_keyed),
+    /// but the key is a type, usually zero-sized, so typos don't compile.
+    /// Pick one via [resolve_for()](struct.Container.html#method.resolve_for).
+    /// Returns an error if T is already registered for K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct DbPool { url: &'static str }
+    ///
+    /// struct Primary;
+    /// struct Replica;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_for::<Primary, Rc<DbPool>>(Rc::new(DbPool { url: "db-1" }))?;
+    /// builder.register_for::<Replica, Rc<DbPool>>(Rc::new(DbPool { url: "db-2" }))?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!("db-2", container.resolve_for::<Replica, Rc<DbPool>>()?.url);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_for<K: 'static, T: Clone + 'static>(&mut self, item: T) -> Result<()> {
+        debug!("registering item for marker type");
+
+        self.register::<Marked<K, T>>(Marked::new(item))
+    }
+
+    /// Registers an item under a name, for lookups at runtime.
+    ///
+    /// Named items are kept apart from every other registration, and don't
+    /// need their type to be known where they're looked up, which suits
+    /// scripting and plugins. See
+    /// [resolve_named()](struct.Container.html#method.resolve_named).
+    /// Returns an error if the name is taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Mailer { host: &'static str }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_named("mailer", Mailer { host: "smtp.example.com" })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let mailer = container.resolve_named("mailer")?;
+    /// assert_eq!("smtp.example.com", mailer.downcast_ref::<Mailer>().unwrap().host);
+    /// assert!(container.resolve_named("queue").is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_named<T: 'static>(&mut self, name: &str, item: T) -> Result<()> {
+        debug!("registering named item");
+
+        if self.named.contains_key(name) {
+            return Err(format!("Container already has an item named \"{}\"", name).into());
+        }
+
+        self.named.insert(name.to_string(), Rc::new(item));
+
+        Ok(())
+    }
+
+    /// Declares the dependencies of a registered type.
+    ///
+    /// The container can't look inside factories and builders, declared
+    /// dependencies let [validate()](struct.Container.html#method.validate)
+    /// find missing registrations and cycles before anything is resolved.
+    /// Dependencies are listed as a tuple. Declaring again adds to the
+    /// existing declarations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i16>(41)?;
+    /// builder.register_builder::<i32, _>(|container| {
+    ///     let base: i16 = container.resolve().unwrap();
+    ///     i32::from(base) + 1
+    /// })?;
+    /// builder.declare_dependencies::<i32, (i16,)>();
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert!(container.validate().is_ok());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn declare_dependencies<T: 'static, D: Dependencies>(&mut self) {
+        debug!("declaring dependencies");
+
+        self.declarations
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Declaration {
+                type_name: core::any::type_name::<T>(),
+                dependencies: Vec::new(),
+            })
+            .dependencies
+            .extend(D::dependencies());
+    }
+
+    /// Marks T as deprecated.
+    ///
+    /// Declared dependencies on T show up as warnings in wiring reports,
+    /// see [validate()](struct.ContainerBuilder.html#method.validate).
+    /// Resolving T still works.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Dependencies};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i32>(42)?;
+    /// builder.register_builder::<i64, _>(|_| 42)?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    /// builder.deprecate::<i32>("use i64 instead");
+    ///
+    /// let report = builder.validate(&<(i64,)>::dependencies(), &[]);
+    ///
+    /// assert!(!report.has_errors());
+    /// assert_eq!("use i64 instead", report.deprecated[0].note);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deprecate<T: 'static>(&mut self, note: &'static str) {
+        debug!("deprecating type");
+
+        self.deprecations.insert(TypeId::of::<T>(), note);
+    }
+
+    /// Has [report_leaks()](struct.Container.html#method.report_leaks) check
+    /// the shared `Rc<U>` for references outside the container.
+    ///
+    /// The container can't look inside every registered type, so only the
+    /// `Rc`s it's told about are checked. Those registered via
+    /// [register_rc()](struct.ContainerBuilder.html#method.register_rc)
+    /// are watched already.
+    pub fn watch_for_leaks<U: ?Sized + 'static>(&mut self) {
+        debug!("watching shared pointer for leaks");
+
+        self.leak_checks
+            .insert(TypeId::of::<Rc<U>>(), leaks::strong_count_of::<U>);
+    }
+
+    /// Tags T, so it's resolved along with every other type carrying the tag.
+    ///
+    /// See [resolve_tagged()](struct.Container.html#method.resolve_tagged).
+    /// T can carry several tags, but each tag only once. Tagged types are
+    /// resolved in the order they were tagged.
+    pub fn tag<T: Clone + 'static>(&mut self, tag: &str) -> Result<()> {
+        debug!("tagging type");
+
+        self.tags.add::<T>(tag)
+    }
+
+    /// Tags T, and tells how to resolve it as `Rc<U>`.
+    ///
+    /// Lets services of different types be resolved as one trait, like event
+    /// subscribers or command handlers, via
+    /// [resolve_tagged_as()](struct.Container.html#method.resolve_tagged_as).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Subscriber { fn on_signup(&self, user: &str) -> String; }
+    ///
+    /// struct Mailer;
+    /// impl Subscriber for Mailer {
+    ///     fn on_signup(&self, user: &str) -> String { format!("welcome mail to {}", user) }
+    /// }
+    ///
+    /// struct Stats;
+    /// impl Subscriber for Stats {
+    ///     fn on_signup(&self, _: &str) -> String { "signups += 1".to_string() }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<Mailer>>(Rc::new(Mailer))?;
+    /// builder.register::<Rc<Stats>>(Rc::new(Stats))?;
+    /// builder.tag_as::<Rc<Mailer>, dyn Subscriber, _>("signup", |mailer| mailer)?;
+    /// builder.tag_as::<Rc<Stats>, dyn Subscriber, _>("signup", |stats| stats)?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let handled: Vec<_> = container
+    ///     .resolve_tagged_as::<dyn Subscriber>("signup")?
+    ///     .iter()
+    ///     .map(|subscriber| subscriber.on_signup("ana"))
+    ///     .collect();
+    ///
+    /// assert_eq!(vec!["welcome mail to ana", "signups += 1"], handled);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tag_as<T, U, F>(&mut self, tag: &str, cast: F) -> Result<()>
+    where
+        T: Clone + 'static,
+        U: ?Sized + 'static,
+        F: Fn(T) -> Rc<U> + 'static,
+    {
+        debug!("tagging type with a cast");
+
+        self.tags.add_as::<T, U, F>(tag, cast)
+    }
+
+    /// Attaches metadata to T, to be looked up by its type.
+    ///
+    /// Lets frameworks built on the container describe registrations, like
+    /// the route a handler serves, and discover them later via
+    /// [carrying_metadata()](struct.Container.html#method.carrying_metadata)
+    /// or [metadata_of()](struct.Container.html#method.metadata_of). T can
+    /// carry any number of kinds of metadata, but each kind only once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Route { path: &'static str }
+    ///
+    /// #[derive(Clone)]
+    /// struct UsersHandler;
+    /// #[derive(Clone)]
+    /// struct OrdersHandler;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<UsersHandler>(UsersHandler)?;
+    /// builder.add_metadata::<UsersHandler, _>(Route { path: "/users" })?;
+    /// builder.register::<OrdersHandler>(OrdersHandler)?;
+    /// builder.add_metadata::<OrdersHandler, _>(Route { path: "/orders" })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let paths: Vec<_> = container
+    ///     .carrying_metadata::<Route>()
+    ///     .iter()
+    ///     .map(|(_, route)| route.path)
+    ///     .collect();
+    ///
+    /// assert_eq!(vec!["/users", "/orders"], paths);
+    /// assert_eq!("/orders", container.metadata_of::<OrdersHandler, Route>().unwrap().path);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_metadata<T: 'static, M: 'static>(&mut self, metadata: M) -> Result<()> {
+        debug!("attaching metadata");
+
+        self.metadata.add::<T, M>(metadata)
+    }
+
+    /// Sets what happens when a type is registered again.
+    ///
+    /// Strict applications want duplicates to fail, which is the default,
+    /// while plugin hosts may rather keep the first registration, let the
+    /// last one win, or collect every item. See
+    /// [DuplicatePolicy](enum.DuplicatePolicy.html), and
+    /// [with_duplicate_policy()](struct.ContainerBuilder.html#method.with_duplicate_policy)
+    /// for a policy that only applies to some registrations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, DuplicatePolicy, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.on_duplicate(DuplicatePolicy::Collect);
+    ///
+    /// builder.register::<&'static str>("markdown")?;
+    /// builder.register::<&'static str>("spellcheck")?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!("markdown", container.resolve::<&str>()?);
+    /// assert_eq!(
+    ///     vec!["markdown", "spellcheck"],
+    ///     container.resolve_all::<&str>().iter().map(|plugin| **plugin).collect::<Vec<_>>()
+    /// );
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_duplicate(&mut self, policy: DuplicatePolicy) {
+        self.duplicate_policy = policy;
+    }
+
+    /// Registers with a duplicate policy that only applies inside the closure.
+    ///
+    /// See [on_duplicate()](struct.ContainerBuilder.html#method.on_duplicate).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, DuplicatePolicy, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(1)?;
+    ///
+    /// builder.with_duplicate_policy(DuplicatePolicy::Replace, |builder| {
+    ///     builder.register::<u32>(2)
+    /// })?;
+    /// assert!(builder.register::<u32>(3).is_err());
+    ///
+    /// assert_eq!(2, builder.build().resolve::<u32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_duplicate_policy<F>(&mut self, policy: DuplicatePolicy, register: F) -> Result<()>
+    where
+        F: FnOnce(&mut ContainerBuilder) -> Result<()>,
+    {
+        let previous = core::mem::replace(&mut self.duplicate_policy, policy);
+        let result = register(self);
+        self.duplicate_policy = previous;
+
+        result
+    }
+
+    /// Has the container remember its most recent events.
+    ///
+    /// Keeps up to `capacity` events, dropping the oldest ones, see
+    /// [recent_events()](struct.Container.html#method.recent_events).
+    /// Cheap enough to leave on in production, it's off by default.
+    pub fn record_events(&mut self, capacity: usize) {
+        self.event_capacity = capacity;
+    }
+
+    /// Has the container keep resolution statistics.
+    ///
+    /// See [stats()](struct.Container.html#method.stats). Timing every
+    /// construction has a cost, so it's off by default.
+    pub fn record_stats(&mut self) {
+        self.record_stats = true;
+    }
+
+    /// Has [resolve_async()](struct.Container.html#method.resolve_async) give up after a timeout.
+    ///
+    /// Pending items that depend on flaky networks would otherwise keep
+    /// startup waiting forever. See
+    /// [resolve_async_timeout()](struct.Container.html#method.resolve_async_timeout).
+    pub fn async_timeout(&mut self, timeout: core::time::Duration) {
+        self.async_timeout = Some(timeout);
+    }
+
+    /// Subscribes an observer to registrations made from now on, and to
+    /// what the built container does.
+    ///
+    /// See [Observer](trait.Observer.html).
+    pub fn subscribe(&mut self, observer: Rc<dyn Observer>) {
+        self.observers.subscribe(observer);
+    }
+
+    /// Lets the container create unregistered types that implement `Default`.
+    ///
+    /// Applies to [resolve_or_default()](struct.Container.html#method.resolve_or_default),
+    /// so trivial values like empty registries don't need registering. It's
+    /// off by default, so missing registrations aren't hidden.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, DefaultFallback};
+    /// # use std::rc::Rc;
+    /// # use std::cell::RefCell;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Default)]
+    /// struct Registry(RefCell<Vec<&'static str>>);
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.fall_back_to_default(DefaultFallback::Share);
+    ///
+    /// let container = builder.build();
+    ///
+    /// let registry = container.resolve_or_default::<Rc<Registry>>()?;
+    /// registry.0.borrow_mut().push("users");
+    ///
+    /// let again = container.resolve_or_default::<Rc<Registry>>()?;
+    /// assert_eq!(vec!["users"], *again.0.borrow());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fall_back_to_default(&mut self, fallback: DefaultFallback) {
+        self.default_fallback = fallback;
+    }
+
+    /// Freezes the container the first time something is resolved.
+    ///
+    /// Once frozen, registrations can't change anymore:
+    /// [replace()](struct.MutableContainer.html#method.replace), [swap()](struct.MutableContainer.html#method.swap),
+    /// [take()](struct.MutableContainer.html#method.take) and [remove()](struct.MutableContainer.html#method.remove)
+    /// return an error, which catches wiring that only happens after the
+    /// application started. [reopen()](struct.MutableContainer.html#method.reopen)
+    /// lifts the freeze, for the few places that change wiring on purpose.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(42)?;
+    /// builder.freeze_on_first_resolve();
+    ///
+    /// let container = builder.build_mutable();
+    /// container.replace::<u32>(1)?;
+    ///
+    /// assert_eq!(1, container.resolve::<u32>()?);
+    /// assert!(container.replace::<u32>(2).is_err());
+    ///
+    /// container.reopen();
+    /// container.replace::<u32>(2)?;
+    /// assert_eq!(2, container.resolve::<u32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn freeze_on_first_resolve(&mut self) {
+        self.freeze_on_first_resolve = true;
+    }
+
+    /// Marks T to be carried over when the container is rebuilt.
+    ///
+    /// Once built, the item is shared with the next generation instead of
+    /// being built again, see
+    /// [rebuild_with()](struct.Container.html#method.rebuild_with).
+    pub fn carry_over<T: 'static>(&mut self) {
+        debug!("marking type to carry over");
+
+        self.carry_overs.insert(TypeId::of::<T>());
+    }
+
+    /// Checks the wiring without building the container.
+    ///
+    /// Runs the same checks as
+    /// [composition_report()](struct.Container.html#method.composition_report),
+    /// so nothing gets built. Meant for a `--validate-wiring` style flag
+    /// that lets a deploy pipeline check the wiring without starting the
+    /// application. With the `serde` feature, the report can be serialized
+    /// to any format serde supports.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Dependencies};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u8>(1)?;
+    /// builder.register_builder::<i64, _>(|_| 42)?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    ///
+    /// let report = builder.validate(&<(i64,)>::dependencies(), &[]);
+    ///
+    /// assert!(report.has_errors());
+    /// assert_eq!(vec![("i64", "i32")], report.validation.missing);
+    /// assert_eq!(vec!["u8"], report.unused);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate(&self, roots: &[Dependency], forbidden: &[(&str, &str)]) -> CompositionReport {
+        debug!("validating builder");
+
+        let registered = self
+            .registrations
+            .iter()
+            .map(|(type_id, registration)| (type_id, registration.type_name));
+
+        validation::composition_report(
+            &self.declarations,
+            |type_id| self.resolvers.contains_key(&type_id) || self.pending.contains_key(&type_id),
+            registered,
+            &self.deprecations,
+            roots,
+            forbidden,
+        )
+    }
+
+    /// Has the container initialize T once it builds it.
+    ///
+    /// Needed for [Initialize](trait.Initialize.html) types without the
+    /// `specialization` feature, which finds them on its own. Only items the
+    /// container builds are initialized, not the ones registered already built.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{Container, ContainerBuilder, Initialize, Resolver};
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Cache { warm: Cell<bool> }
+    ///
+    /// impl Initialize for Cache {
+    ///     fn initialize(&self, _: &Container) { self.warm.set(true) }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder::<Rc<Cache>, _>(|_| Rc::new(Cache { warm: Cell::new(false) }))?;
+    /// builder.register_initializer::<Rc<Cache>>()?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert!(container.resolve::<Rc<Cache>>()?.warm.get());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_initializer<T: Initialize + 'static>(&mut self) -> Result<()> {
+        debug!("registering initializer");
+
+        let type_id = TypeId::of::<T>();
+
+        if self.initializers.contains_key(&type_id) {
+            return Err(format!(
+                "Container already has an initializer for {}",
+                core::any::type_name::<T>()
+            )
+            .into());
+        }
+
+        self.initializers.insert(type_id, Initializer::of::<T>());
+
+        Ok(())
+    }
+
+    /// Gives a shared dependency a lifecycle.
+    ///
+    /// T is started by [start_all()](struct.Container.html#method.start_all)
+    /// and stopped by [stop_all()](struct.Container.html#method.stop_all),
+    /// see [Lifecycle](trait.Lifecycle.html) for an example.
+    pub fn register_lifecycle<T: Lifecycle + Clone + 'static>(&mut self) -> Result<()> {
+        self.add_lifecycle::<T>(Managed::of::<T>())
+    }
+
+    /// Gives a shared dependency a lifecycle, with a timeout for each step.
+    ///
+    /// Services are started and stopped on the calling thread, so a slow
+    /// one isn't interrupted. Taking longer than the timeout is reported as
+    /// a failure once it's done, like any other.
+    #[cfg(feature = "std")]
+    pub fn register_lifecycle_with_timeout<T: Lifecycle + Clone + 'static>(
+        &mut self,
+        timeout: core::time::Duration,
+    ) -> Result<()> {
+        self.add_lifecycle::<T>(Managed::with_timeout::<T>(timeout))
+    }
+
+    fn add_lifecycle<T: 'static>(&mut self, managed: Managed) -> Result<()> {
+        debug!("registering lifecycle");
+
+        let type_id = TypeId::of::<T>();
+
+        if self.lifecycles.contains_key(&type_id) {
+            return Err(format!(
+                "Container already has a lifecycle for {}",
+                core::any::type_name::<T>()
+            )
+            .into());
+        }
+
+        self.lifecycles.insert(type_id, managed);
+
+        Ok(())
+    }
+
+    /// Registers a teardown callback for a shared dependency.
+    ///
+    /// The callback gets the container's own copy of the dependency when
+    /// the container is shut down or dropped, see
+    /// [shutdown()](struct.Container.html#method.shutdown). It is only called
+    /// if the dependency was created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Connection { closed: Cell<bool> }
+    ///
+    /// let connection = Rc::new(Connection { closed: Cell::new(false) });
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<Connection>>(connection.clone())?;
+    /// builder.register_teardown::<Rc<Connection>, _>(|connection| connection.closed.set(true))?;
+    ///
+    /// drop(builder.build());
+    ///
+    /// assert!(connection.closed.get());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_teardown<T, F>(&mut self, teardown: F) -> Result<()>
+    where
+        F: FnOnce(T) + 'static,
+        T: 'static,
+    {
+        debug!("registering teardown");
+
+        let type_id = TypeId::of::<T>();
+
+        if self.teardowns.contains_key(&type_id) {
+            return Err(format!(
+                "Container already has a teardown for {}",
+                core::any::type_name::<T>()
+            )
+            .into());
+        }
+
+        let teardown = Teardown(Box::new(move |item: Rc<dyn Any>| {
+            let item = item
+                .downcast::<T>()
+                .expect("could not downcast item to tear down");
+
+            match Rc::try_unwrap(item) {
+                Ok(item) => teardown(item),
+                Err(_) => warn!(
+                    "{} is still shared with a fork, skipping its teardown",
+                    core::any::type_name::<T>()
+                ),
+            }
+        }));
+
+        self.teardowns.insert(type_id, teardown);
+
+        Ok(())
+    }
+
+    /// Wraps a registered dependency with a decorator.
+    ///
+    /// The decorator gets the item the original registration produced and
+    /// returns the item that will be resolved instead. Decorating the same
+    /// type more than once stacks the decorators, the last one added is the
+    /// outermost. Factories stay factories and builders stay builders;
+    /// items registered directly are decorated when first resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Mailer { fn send(&self, to: &str) -> String; }
+    ///
+    /// struct SmtpMailer;
+    /// impl Mailer for SmtpMailer {
+    ///     fn send(&self, to: &str) -> String { format!("sent to {}", to) }
+    /// }
+    ///
+    /// struct LoggingMailer { inner: Rc<dyn Mailer> }
+    /// impl Mailer for LoggingMailer {
+    ///     fn send(&self, to: &str) -> String { format!("[logged] {}", self.inner.send(to)) }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<dyn Mailer>>(Rc::new(SmtpMailer))?;
+    /// builder.decorate::<Rc<dyn Mailer>, _>(|inner, _| Rc::new(LoggingMailer { inner }))?;
+    ///
+    /// let container = builder.build();
+    /// let mailer = container.resolve::<Rc<dyn Mailer>>()?;
+    ///
+    /// assert_eq!("[logged] sent to fabian", mailer.send("fabian"));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decorate<T, F>(&mut self, decorator: F) -> Result<()>
+    where
+        F: Fn(T, &Container) -> T + 'static,
+        T: 'static,
+    {
+        debug!("decorating registration");
+
+        let type_id = TypeId::of::<T>();
+
+        let resolver = match self.resolvers.remove(&type_id) {
+            Some(Resolver::Shared(item)) => {
+                let item = item
+                    .downcast::<T>()
+                    .expect("could not downcast shared object");
+                let item = unshare(item);
+                self.constructed
+                    .retain(|constructed| *constructed != type_id);
+
+                let builder = ErasedBuilder::new(move |container| decorator(item, container));
+                self.warmers.insert(type_id, Container::warm::<T>);
+                Resolver::Builder(builder)
+            }
+            Some(Resolver::Builder(original)) => {
+                let mut original = Some(original);
+
+                Resolver::Builder(ErasedBuilder::fallible(move |container| {
+                    let builder = original.take().expect("builder already ran");
+                    let (item, retry) = builder.call::<T>(container);
+                    original = retry;
+
+                    item.map(|item| decorator(item, container))
+                }))
+            }
+            Some(Resolver::Factory(cell)) => {
+                let factory = decorate_factory(unshare(cell), decorator);
+                Resolver::Factory(Rc::new(RefCell::new(factory)))
+            }
+            Some(Resolver::Scoped(cell, level)) => {
+                let factory = decorate_factory(unshare(cell), decorator);
+                Resolver::Scoped(Rc::new(RefCell::new(factory)), level)
+            }
+            None => {
+                return Err(format!(
+                    "Can't decorate {}, it's not registered",
+                    core::any::type_name::<T>()
+                )
+                .into());
+            }
+        };
+
+        self.insert::<T>(resolver)
+    }
+
+    /// Adds a middleware to the pipeline wrapping T.
+    ///
+    /// Like [decorate()](struct.ContainerBuilder.html#method.decorate), but
+    /// the wrappers are put together when the container is built, ordered by
+    /// priority: the highest priority is outermost, so it sees calls first.
+    /// Wrappers with the same priority wrap in the order they were added.
+    /// Middleware wraps whatever decorators T has, and T has to be registered
+    /// before any middleware is added for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait HttpClient { fn get(&self, url: &str) -> String; }
+    ///
+    /// struct Client;
+    /// impl HttpClient for Client {
+    ///     fn get(&self, url: &str) -> String { format!("GET {}", url) }
+    /// }
+    ///
+    /// struct Wrapped(&'static str, Rc<dyn HttpClient>);
+    /// impl HttpClient for Wrapped {
+    ///     fn get(&self, url: &str) -> String { format!("{}: {}", self.0, self.1.get(url)) }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<dyn HttpClient>>(Rc::new(Client))?;
+    /// builder.add_middleware::<Rc<dyn HttpClient>, _>(10, |inner, _| Rc::new(Wrapped("retry", inner)))?;
+    /// builder.add_middleware::<Rc<dyn HttpClient>, _>(30, |inner, _| Rc::new(Wrapped("log", inner)))?;
+    /// builder.add_middleware::<Rc<dyn HttpClient>, _>(20, |inner, _| Rc::new(Wrapped("metrics", inner)))?;
+    ///
+    /// let client = builder.build().resolve::<Rc<dyn HttpClient>>()?;
+    ///
+    /// assert_eq!("log: metrics: retry: GET /users", client.get("/users"));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_middleware<T, F>(&mut self, priority: i32, wrapper: F) -> Result<()>
+    where
+        F: Fn(T, &Container) -> T + 'static,
+        T: 'static,
+    {
+        debug!("adding middleware");
+
+        let type_id = TypeId::of::<T>();
+
+        if !self.resolvers.contains_key(&type_id) {
+            return Err(format!(
+                "Can't add middleware for {}, it's not registered",
+                core::any::type_name::<T>()
+            )
+            .into());
+        }
+
+        self.pipelines.add(
+            type_id,
+            priority,
+            Box::new(move |builder| builder.decorate::<T, F>(wrapper)),
+        );
+
+        Ok(())
+    }
+
+    /// Checks the arguments and results of a factory, in debug builds.
+    ///
+    /// Meant to catch services misusing each other during development:
+    /// `pre` checks the arguments passed to
+    /// [resolve_with()](struct.Container.html#method.resolve_with), `post`
+    /// checks the item the factory returns, and a broken contract panics,
+    /// like `debug_assert!()` does. In release builds the checks are dropped
+    /// without being called. Only works for types registered via
+    /// [register_factory_with_args()](struct.ContainerBuilder.html#method.register_factory_with_args).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Clone)]
+    /// struct Page { size: usize }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_factory_with_args::<Page, usize, _>(|_, size| Page { size })?;
+    /// builder.with_contract::<Page, usize, _, _>(
+    ///     |size| if *size > 0 { Ok(()) } else { Err("pages can't be empty".into()) },
+    ///     |page| if page.size <= 100 { Ok(()) } else { Err("pages are too large".into()) },
+    /// )?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(20, container.resolve_with::<Page, usize>(20)?.size);
+    /// # if cfg!(debug_assertions) {
+    /// let misuse = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    ///     container.resolve_with::<Page, usize>(0)
+    /// }));
+    /// assert!(misuse.is_err());
+    /// # }
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_contract<T, A, P, Q>(&mut self, pre: P, post: Q) -> Result<()>
+    where
+        P: Fn(&A) -> Result<()> + 'static,
+        Q: Fn(&T) -> Result<()> + 'static,
+        T: 'static,
+        A: 'static,
+    {
+        let cell = match self.arg_factories.get(&TypeId::of::<T>()) {
+            Some(cell) => cell,
+            None => {
+                return Err(format!(
+                    "Can't check the contract of {}, it has no factory with arguments",
+                    core::any::type_name::<T>()
+                )
+                .into());
+            }
+        };
+
+        if !cell.borrow().is::<Box<FactoryWithArgs<T, A>>>() {
+            return Err(format!(
+                "{} takes other arguments than {}",
+                core::any::type_name::<T>(),
+                core::any::type_name::<A>()
+            )
+            .into());
+        }
+
+        if cfg!(debug_assertions) {
+            debug!("checking contract");
+
+            let mut boxed = cell.borrow_mut();
+            let taken = core::mem::replace(&mut *boxed, Box::new(()));
+            let mut original = taken
+                .downcast::<Box<FactoryWithArgs<T, A>>>()
+                .expect("could not downcast factory");
+
+            let factory: Box<FactoryWithArgs<T, A>> = Box::new(move |container, args| {
+                if let Err(error) = pre(&args) {
+                    panic!(
+                        "{} was resolved with arguments breaking its contract: {}",
+                        core::any::type_name::<T>(),
+                        error
+                    );
+                }
+
+                let item = original(container, args);
+
+                if let Err(error) = post(&item) {
+                    panic!(
+                        "{} was built breaking its contract: {}",
+                        core::any::type_name::<T>(),
+                        error
+                    );
+                }
+
+                item
+            });
+
+            *boxed = Box::new(factory);
+        }
+
+        Ok(())
+    }
+
+    /// Runs registrations only if a condition holds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let verbose = false;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_if(verbose, |builder| builder.register::<u8>(3))?;
+    ///
+    /// assert!(!builder.has::<u8>());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_if<F>(&mut self, condition: bool, register: F) -> Result<()>
+    where
+        F: FnOnce(&mut ContainerBuilder) -> Result<()>,
+    {
+        if condition {
+            register(self)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs registrations only if a profile is active.
+    ///
+    /// Profiles are chosen when creating the builder, see
+    /// [with_profile()](struct.ContainerBuilder.html#method.with_profile).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Mailer { fn send(&self) -> &str; }
+    ///
+    /// struct Smtp;
+    /// impl Mailer for Smtp { fn send(&self) -> &str { "sent" } }
+    ///
+    /// struct Log;
+    /// impl Mailer for Log { fn send(&self) -> &str { "logged" } }
+    ///
+    /// let mut builder = ContainerBuilder::with_profile("dev");
+    /// builder.register_for_profile("prod", |builder| {
+    ///     builder.register::<Rc<dyn Mailer>>(Rc::new(Smtp))
+    /// })?;
+    /// builder.register_for_profile("dev", |builder| {
+    ///     builder.register::<Rc<dyn Mailer>>(Rc::new(Log))
+    /// })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!("logged", container.resolve::<Rc<dyn Mailer>>()?.send());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_for_profile<F>(&mut self, profile: &str, register: F) -> Result<()>
+    where
+        F: FnOnce(&mut ContainerBuilder) -> Result<()>,
+    {
+        let active = self.profile() == Some(profile);
+
+        self.register_if(active, register)
+    }
+
+    /// Adds all registrations of a module.
+    ///
+    /// Stops at the first registration that fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Module, Result};
+    /// #
+    /// struct Answer;
+    ///
+    /// impl Module for Answer {
+    ///     fn register(&self, builder: &mut ContainerBuilder) -> Result<()> {
+    ///         builder.register::<u32>(42)
+    ///     }
+    /// }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    ///
+    /// assert!(builder.add_module(Answer).is_ok());
+    /// assert!(builder.has::<u32>());
+    ///
+    /// // modules can't register the same dependency twice
+    /// assert!(builder.add_module(Answer).is_err());
+    /// ```
+    pub fn add_module<M: Module>(&mut self, module: M) -> Result<()> {
+        debug!("adding module");
+
+        module.register(self)
+    }
+
+    /// Registers everything in the closure, or nothing if any of it fails.
+    ///
+    /// The closure registers into a builder of its own, with this builder's
+    /// profile, which is then merged in with
+    /// [MergePolicy::Error](enum.MergePolicy.html#variant.Error). If the
+    /// closure returns an error, or registers a type this builder already
+    /// has, none of its registrations are kept, so a module can't end up
+    /// half registered. Registrations in the closure can't see this
+    /// builder's, things like decorating an existing type go outside.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(1)?;
+    ///
+    /// let result = builder.transaction(|tx| {
+    ///     tx.register::<u64>(2)?;
+    ///     tx.register::<u32>(3)
+    /// });
+    ///
+    /// assert!(result.is_err());
+    /// assert!(!builder.has::<u64>());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transaction<F>(&mut self, registrations: F) -> Result<()>
+    where
+        F: FnOnce(&mut ContainerBuilder) -> Result<()>,
+    {
+        debug!("registering in a transaction");
+
+        let mut tx = ContainerBuilder {
+            profile: self.profile.clone(),
+            ..Default::default()
+        };
+
+        registrations(&mut tx)?;
+
+        self.merge(tx, MergePolicy::Error)
+    }
+
+    /// Adds everything registered in another builder.
+    ///
+    /// Lets crates each wire their part into a builder of their own, and
+    /// the application combine them. Types registered in both builders are
+    /// conflicts, and so are items of a type under the same key or name, and
+    /// transformers, teardowns or deprecations of the same type. The policy
+    /// decides what happens with those: [MergePolicy::Error](enum.MergePolicy.html#variant.Error)
+    /// fails without changing anything and lists every conflict, the other
+    /// two keep one side's. Null objects never conflict, they give way to
+    /// real registrations. Collections are combined, keeping priorities,
+    /// and so are declared dependencies. The profile stays this builder's.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, MergePolicy, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut billing = ContainerBuilder::new();
+    /// billing.register::<u32>(1)?;
+    /// billing.register::<&'static str>("billing")?;
+    ///
+    /// let mut shipping = ContainerBuilder::new();
+    /// shipping.register::<u64>(2)?;
+    /// shipping.register::<&'static str>("shipping")?;
+    ///
+    /// let mut app = ContainerBuilder::new();
+    /// app.merge(billing, MergePolicy::Error)?;
+    /// app.merge(shipping, MergePolicy::KeepLast)?;
+    ///
+    /// let container = app.build();
+    /// assert_eq!(1, container.resolve::<u32>()?);
+    /// assert_eq!(2, container.resolve::<u64>()?);
+    /// assert_eq!("shipping", container.resolve::<&str>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge(&mut self, mut other: ContainerBuilder, policy: MergePolicy) -> Result<()> {
+        debug!("merging builders");
+
+        let type_name = |type_id: &TypeId, other: &ContainerBuilder| {
+            self.registrations
+                .get(type_id)
+                .or_else(|| other.registrations.get(type_id))
+                .map(|registration| registration.type_name)
+                .or_else(|| other.keyed_names.get(type_id).cloned())
+                .unwrap_or("<unknown>")
+        };
+
+        // registrations where a null object gives way are not conflicts
+        let mut conflicts: Vec<String> = other
+            .registered()
+            .filter(|type_id| self.registered().any(|ours| ours == *type_id))
+            .filter(|type_id| !self.nulls.contains(type_id) && !other.nulls.contains(type_id))
+            .map(|type_id| type_name(&type_id, &other).to_string())
+            .collect();
+
+        for (what, type_ids) in [
+            (
+                "transformer",
+                clashes_of(&self.transformers, &other.transformers),
+            ),
+            ("teardown", clashes_of(&self.teardowns, &other.teardowns)),
+            (
+                "deprecation",
+                clashes_of(&self.deprecations, &other.deprecations),
+            ),
+        ] {
+            conflicts.extend(
+                type_ids
+                    .iter()
+                    .map(|type_id| format!("{} of {}", what, type_name(type_id, &other))),
+            );
+        }
+
+        conflicts.extend(
+            other
+                .named
+                .keys()
+                .filter(|name| self.named.contains_key(*name))
+                .map(|name| format!("item named \"{}\"", name)),
+        );
+
+        for (type_id, theirs) in &other.keyed {
+            if let Some(ours) = self.keyed.get(type_id) {
+                conflicts.extend(
+                    theirs
+                        .keys()
+                        .filter(|key| ours.contains_key(*key))
+                        .map(|key| format!("{} for key \"{}\"", type_name(type_id, &other), key)),
+                );
+            }
+        }
+
+        if policy == MergePolicy::Error && !conflicts.is_empty() {
+            conflicts.sort();
+
+            return Err(
+                format!("Can't merge builders, both have: {}", conflicts.join(", ")).into(),
+            );
+        }
+
+        let keep_theirs = policy == MergePolicy::KeepLast;
+
+        for type_id in other.registered().collect::<Vec<_>>() {
+            let ours = self.registered().any(|ours| ours == type_id);
+            let take = !ours
+                || self.nulls.contains(&type_id)
+                || (keep_theirs && !other.nulls.contains(&type_id));
+
+            if take {
+                self.forget(type_id);
+                self.move_registration(type_id, &mut other);
+            }
+        }
+
+        merge_map(&mut self.transformers, other.transformers, keep_theirs);
+        merge_map(&mut self.teardowns, other.teardowns, keep_theirs);
+        merge_map(&mut self.deprecations, other.deprecations, keep_theirs);
+        merge_map(&mut self.initializers, other.initializers, false);
+        merge_map(&mut self.lifecycles, other.lifecycles, false);
+
+        for (type_id, declaration) in other.declarations {
+            match self.declarations.get_mut(&type_id) {
+                Some(ours) => ours.dependencies.extend(declaration.dependencies),
+                None => {
+                    self.declarations.insert(type_id, declaration);
+                }
+            }
+        }
+
+        for (type_id, items) in other.collections {
+            let ours = self.collections.entry(type_id).or_default();
+            for (priority, item) in items {
+                add_to_collection(ours, priority, item);
+            }
+        }
+
+        for (type_id, items) in other.keyed {
+            let ours = self.keyed.entry(type_id).or_default();
+            for (key, item) in items {
+                if keep_theirs || !ours.contains_key(&key) {
+                    ours.insert(key, item);
+                }
+            }
+        }
+
+        for (name, item) in other.named {
+            if keep_theirs || !self.named.contains_key(&name) {
+                self.named.insert(name, item);
+            }
+        }
+
+        self.keyed_names.extend(other.keyed_names);
+        self.carry_overs.extend(other.carry_overs);
+        self.leak_checks.extend(other.leak_checks);
+        self.priorities.extend(other.priorities);
+        self.generics.extend(other.generics);
+        self.tags.merge(other.tags);
+        self.metadata.merge(other.metadata);
+        self.arena.merge(other.arena, keep_theirs);
+        self.pipelines.merge(other.pipelines);
+        self.event_capacity = self.event_capacity.max(other.event_capacity);
+        self.record_stats |= other.record_stats;
+        self.async_timeout = self.async_timeout.or(other.async_timeout);
+        self.freeze_on_first_resolve |= other.freeze_on_first_resolve;
+        self.observers.merge(other.observers);
+
+        if self.default_fallback == DefaultFallback::Off {
+            self.default_fallback = other.default_fallback;
+        }
+
+        Ok(())
+    }
+
+    /// Types registered in any way.
+    fn registered(&self) -> impl Iterator<Item = TypeId> + '_ {
+        self.resolvers
+            .keys()
+            .chain(self.pending.keys())
+            .chain(self.arg_factories.keys())
+            .cloned()
+    }
+
+    /// Removes the registration of a type, but not its other settings.
+    fn forget(&mut self, type_id: TypeId) {
+        self.resolvers.remove(&type_id);
+        self.pending.remove(&type_id);
+        self.arg_factories.remove(&type_id);
+        self.warmers.remove(&type_id);
+        self.memo_counters.remove(&type_id);
+        self.registrations.remove(&type_id);
+        self.nulls.remove(&type_id);
+        self.constructed
+            .retain(|constructed| *constructed != type_id);
+    }
+
+    /// Moves the registration of a type over from another builder.
+    fn move_registration(&mut self, type_id: TypeId, other: &mut ContainerBuilder) {
+        if let Some(resolver) = other.resolvers.remove(&type_id) {
+            self.resolvers.insert(type_id, resolver);
+        }
+        if let Some(pending) = other.pending.remove(&type_id) {
+            self.pending.insert(type_id, pending);
+        }
+        if let Some(factory) = other.arg_factories.remove(&type_id) {
+            self.arg_factories.insert(type_id, factory);
+        }
+        if let Some(warmer) = other.warmers.remove(&type_id) {
+            self.warmers.insert(type_id, warmer);
+        }
+        if let Some(counters) = other.memo_counters.remove(&type_id) {
+            self.memo_counters.insert(type_id, counters);
+        }
+        if let Some(registration) = other.registrations.remove(&type_id) {
+            self.registrations.insert(type_id, registration);
+        }
+        if other.nulls.remove(&type_id) {
+            self.nulls.insert(type_id);
+        }
+        if other.constructed.contains(&type_id) {
+            self.constructed.push(type_id);
+        }
+    }
+
+    /// Returns true if a dependency is registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{Container, ContainerBuilder, Resolver};
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i16>(43);
+    ///
+    /// assert!(builder.has::<i16>());
+    /// assert!(!builder.has::<i32>());
+    /// ```
+    pub fn has<T: 'static>(&self) -> bool {
+        let type_id = TypeId::of::<T>();
+
+        self.resolvers.contains_key(&type_id)
+            || self.pending.contains_key(&type_id)
+            || self.arg_factories.contains_key(&type_id)
+    }
+
+    #[track_caller]
+    fn insert<T: 'static>(&mut self, resolver: Resolver) -> Result<()> {
+        debug!("inserting new object");
+
+        let type_id = TypeId::of::<T>();
+
+        let collecting = self.duplicate_policy == DuplicatePolicy::Collect;
+        if collecting && self.has::<T>() && !self.nulls.contains(&type_id) {
+            return self.collect::<T>(resolver);
+        }
+
+        if !self.make_room::<T>()? {
+            return Ok(());
+        }
+
+        if let Resolver::Shared(_) = resolver {
+            self.constructed.push(type_id);
+        }
+
+        let kind = ResolverType::from(&resolver);
+        trace::registered::<T>(kind);
+        self.resolvers.insert(type_id, resolver);
+        self.registrations.insert(type_id, Registration::of::<T>());
+
+        if !self.observers.is_empty() {
+            self.observers.notify(&Observed::Registered {
+                type_name: core::any::type_name::<T>(),
+                kind,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Makes room for registering T, returns false if T should be skipped.
+    ///
+    /// Null objects always give way, other registrations follow the
+    /// duplicate policy.
+    fn make_room<T: 'static>(&mut self) -> Result<bool> {
+        let type_id = TypeId::of::<T>();
+
+        if self.nulls.remove(&type_id) {
+            debug!("replacing null object");
+
+            self.resolvers.remove(&type_id);
+            self.constructed
+                .retain(|constructed| *constructed != type_id);
+
+            return Ok(true);
+        }
+
+        if !self.has::<T>() {
+            return Ok(true);
+        }
+
+        match self.duplicate_policy {
+            DuplicatePolicy::Ignore => {
+                debug!("ignoring duplicate registration");
+                Ok(false)
+            }
+            DuplicatePolicy::Replace => {
+                debug!("replacing registration");
+                self.forget(type_id);
+                Ok(true)
+            }
+            DuplicatePolicy::Error | DuplicatePolicy::Collect => {
+                Err(format!("Container already has {}", core::any::type_name::<T>()).into())
+            }
+        }
+    }
+
+    /// Adds a duplicate item of T to its collection, and the first one too.
+    fn collect<T: 'static>(&mut self, resolver: Resolver) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+
+        let (first, item) = match (self.resolvers.get(&type_id), resolver) {
+            (Some(Resolver::Shared(first)), Resolver::Shared(item)) => (first.clone(), item),
+            _ => {
+                return Err(format!(
+                    "Can't collect {}, only registered items can be collected",
+                    core::any::type_name::<T>()
+                )
+                .into());
+            }
+        };
+
+        let downcast = |item: Rc<dyn Any>| {
+            item.downcast::<T>()
+                .expect("could not downcast shared item")
+        };
+        let (first, item) = (downcast(first), downcast(item));
+
+        let items = self.collections.entry(type_id).or_default();
+        let first_collected = items
+            .iter()
+            .filter_map(|(_, collected)| collected.downcast_ref::<Rc<T>>())
+            .any(|collected| Rc::ptr_eq(collected, &first));
+
+        if !first_collected {
+            add_to_collection(items, 0, Box::new(first));
+        }
+        add_to_collection(items, 0, Box::new(item));
+
+        Ok(())
+    }
+}
+
+impl<T: 'static> Definition<'_, T> {
+    /// Shares one item for the lifetime of the container, the default.
+    pub fn singleton(mut self) -> Self {
+        self.scope = Scope::Singleton;
+        self.level = None;
+
+        self
+    }
+
+    /// Creates a new item every time T is resolved.
+    pub fn transient(mut self) -> Self {
+        self.scope = Scope::Transient;
+        self.level = None;
+
+        self
+    }
+
+    /// Creates one item per scope, see
+    /// [register_scoped()](struct.ContainerBuilder.html#method.register_scoped).
+    pub fn scoped(mut self) -> Self {
+        self.scope = Scope::Scoped;
+        self.level = None;
+
+        self
+    }
+
+    /// Creates one item per scope of a level, see
+    /// [register_in_scope()](struct.ContainerBuilder.html#method.register_in_scope).
+    pub fn in_scope(mut self, level: &'static str) -> Self {
+        self.scope = Scope::Scoped;
+        self.level = Some(level);
+
+        self
+    }
+
+    /// Builds the singleton the first time it's resolved, and not before.
+    ///
+    /// [warm_up()](struct.Container.html#method.warm_up) skips it.
+    pub fn lazy(mut self) -> Self {
+        self.lazy = true;
+
+        self
+    }
+
+    /// Sets when the singleton is built while warming up, see
+    /// [register_eager_with_priority()](struct.ContainerBuilder.html#method.register_eager_with_priority).
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = Some(priority);
+
+        self
+    }
+
+    /// Runs a teardown when the container shuts down, see
+    /// [register_teardown()](struct.ContainerBuilder.html#method.register_teardown).
+    pub fn teardown<F>(mut self, teardown: F) -> Self
+    where
+        F: FnOnce(T) + 'static,
+    {
+        self.options.push(Box::new(move |builder| {
+            builder.register_teardown::<T, F>(teardown)
+        }));
+
+        self
+    }
+
+    /// Registers T with the options chosen, created by the factory.
+    ///
+    /// Fails without registering anything if T is registered already, if
+    /// an option fails, like a second teardown for T, or if the options
+    /// don't fit together: only singletons can be lazy or have a priority.
+    #[track_caller]
+    pub fn from_factory<F>(self, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+    {
+        debug!("registering definition");
+
+        let type_id = TypeId::of::<T>();
+        let Definition {
+            builder,
+            scope,
+            level,
+            lazy,
+            priority,
+            options,
+            ..
+        } = self;
+
+        if scope != Scope::Singleton && (lazy || priority.is_some()) {
+            return Err(format!(
+                "{} is {:?}, only singletons can be lazy or have a priority",
+                core::any::type_name::<T>(),
+                scope
+            )
+            .into());
+        }
+
+        match level {
+            Some(level) => builder.register_in_scope::<T, F>(level, factory)?,
+            None => builder.register_with_scope::<T, F>(scope, factory)?,
+        }
+
+        if lazy {
+            builder.warmers.remove(&type_id);
+        }
+        if let Some(priority) = priority {
+            builder.priorities.insert(type_id, priority);
+        }
+
+        // all or nothing, T is registered without its options otherwise
+        let applied = builder
+            .transaction(|builder| options.into_iter().try_for_each(|option| option(builder)));
+
+        if applied.is_err() {
+            builder.forget(type_id);
+            builder.priorities.remove(&type_id);
+        }
+
+        applied
+    }
+}
+
+impl<T: Clone + 'static> Definition<'_, T> {
+    /// Tags T, see [tag()](struct.ContainerBuilder.html#method.tag).
+    pub fn tagged(mut self, tag: &str) -> Self {
+        let tag = tag.to_string();
+
+        self.options
+            .push(Box::new(move |builder| builder.tag::<T>(&tag)));
+
+        self
+    }
+}
+
+impl<T> fmt::Debug for Definition<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Definition")
+            .field("type_name", &core::any::type_name::<T>())
+            .field("scope", &self.scope)
+            .field("level", &self.level)
+            .field("lazy", &self.lazy)
+            .field("priority", &self.priority)
+            .field("options", &self.options.len())
+            .finish()
+    }
+}
+
+fn decorate_factory<T, F>(cell: RefCell<ErasedFactory>, decorator: F) -> ErasedFactory
+where
+    F: Fn(T, &Container) -> T + 'static,
+    T: 'static,
+{
+    let mut original = cell.into_inner();
+
+    ErasedFactory::fallible(move |container| {
+        original
+            .call::<T>(container)
+            .map(|item| decorator(item, container))
+    })
+}
+
+/// Types both maps have entries for.
+fn clashes_of<V, W>(ours: &TypeIdMap<V>, theirs: &TypeIdMap<W>) -> Vec<TypeId> {
+    theirs
+        .keys()
+        .filter(|type_id| ours.contains_key(*type_id))
+        .cloned()
+        .collect()
+}
+
+/// Adds another builder's entries, replacing ours only if asked to.
+fn merge_map<V>(ours: &mut TypeIdMap<V>, theirs: TypeIdMap<V>, replace: bool) {
+    for (type_id, value) in theirs {
+        if replace || !ours.contains_key(&type_id) {
+            ours.insert(type_id, value);
+        }
+    }
+}
+
+/// Adds a boxed Rc<T> to a collection, keeping it sorted by priority.
+fn add_to_collection(items: &mut Collection, priority: i32, item: Box<dyn Any>) {
+    // after everything with the same priority, so ties keep their order
+    let position = items.partition_point(|(other, _)| *other >= priority);
+    items.insert(position, (priority, item));
+}
+
+/// Registers the defaults of several types, see
+/// [register_default()](struct.ContainerBuilder.html#method.register_default).
+///
+/// Evaluates to a `Result<()>`, stopping at the first type that can't be
+/// registered.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate kamikaze_di;
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// register_defaults!(builder, u32, String, Vec<u8>)?;
+///
+/// // u32 is registered already
+/// assert!(register_defaults!(builder, u64, u32).is_err());
+///
+/// let container = builder.build();
+///
+/// assert_eq!(0, container.resolve::<u32>()?);
+/// assert!(container.resolve::<Vec<u8>>()?.is_empty());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! register_defaults {
+    ($builder:expr, $($type:ty),+ $(,)?) => {{
+        let builder: &mut $crate::ContainerBuilder = &mut $builder;
+
+        (|| -> $crate::Result<()> {
+            $(builder.register_default::<$type>()?;)+
+
+            Ok(())
+        })()
+    }};
+}
+
+/// Adds the type being created to a factory's or builder's error.
+fn creation_failed<T>(error: &Error) -> Error {
+    // the path already says what was being created
+    if !error.path().is_empty() || matches!(error.kind(), ErrorKind::CycleDetected(_)) {
+        return error.clone();
+    }
+
+    format!(
+        "Could not create {}: {}",
+        core::any::type_name::<T>(),
+        error
+    )
+    .into()
 }
 
 fn auto_factory<T: Inject>(container: &Container) -> T {
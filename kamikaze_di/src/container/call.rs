@@ -0,0 +1,159 @@
+use super::injector::Injector;
+use super::Container;
+use crate::prelude::*;
+use crate::Result;
+
+/// Functions and closures whose arguments can be injected.
+///
+/// Implemented for everything callable once with up to eight arguments,
+/// as long as the container can inject each of them.
+///
+/// See [call()](struct.Container.html#method.call).
+pub trait Callable<Args> {
+    /// What the function returns.
+    type Output;
+
+    /// Injects the arguments and calls the function.
+    fn call_with(self, container: &Container) -> Result<Self::Output>;
+}
+
+/// Tuples whose items can all be injected, see
+/// [resolve_tuple()](struct.Container.html#method.resolve_tuple).
+///
+/// Implemented for tuples of up to eight items.
+pub trait InjectAll: Sized {
+    /// Injects every item, failing with all the items that couldn't be.
+    fn inject_all(container: &Container) -> Result<Self>;
+}
+
+macro_rules! callable {
+    ($($arg:ident),*) => {
+        impl<$($arg),*> InjectAll for ($($arg,)*)
+        where
+            Container: $(Injector<$arg> +)*,
+        {
+            // items are named after their types
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn inject_all(container: &Container) -> Result<Self> {
+                $(let $arg = argument::<$arg>(container);)*
+
+                let mut failures: Vec<String> = Vec::new();
+                $(
+                    if let Err(error) = &$arg {
+                        failures.push(error.to_string());
+                    }
+                )*
+
+                if !failures.is_empty() {
+                    return Err(failures.join("; ").into());
+                }
+
+                Ok(($($arg?,)*))
+            }
+        }
+
+        impl<F, R, $($arg),*> Callable<($($arg,)*)> for F
+        where
+            F: FnOnce($($arg),*) -> R,
+            Container: $(Injector<$arg> +)*,
+        {
+            type Output = R;
+
+            #[allow(non_snake_case)]
+            fn call_with(self, container: &Container) -> Result<R> {
+                let ($($arg,)*) = <($($arg,)*)>::inject_all(container)?;
+
+                Ok(self($($arg),*))
+            }
+        }
+    };
+}
+
+callable!();
+callable!(A);
+callable!(A, B);
+callable!(A, B, C);
+callable!(A, B, C, D);
+callable!(A, B, C, D, E);
+callable!(A, B, C, D, E, G);
+callable!(A, B, C, D, E, G, H);
+callable!(A, B, C, D, E, G, H, I);
+
+fn argument<T>(container: &Container) -> Result<T>
+where
+    Container: Injector<T>,
+{
+    container.inject().map_err(|error| {
+        format!(
+            "could not inject argument {}: {}",
+            core::any::type_name::<T>(),
+            error
+        )
+        .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, Result};
+    use std::rc::Rc;
+
+    #[test]
+    fn functions_get_their_arguments_injected() {
+        fn describe(name: String, age: u8, city: Rc<str>) -> String {
+            format!("{} ({}) from {}", name, age, city)
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<String>("Ana".to_string()).unwrap();
+        builder.register::<u8>(30).unwrap();
+        builder.register::<Rc<str>>("Cluj".into()).unwrap();
+
+        let container = builder.build();
+
+        assert_eq!("Ana (30) from Cluj", container.call(describe).unwrap());
+        assert_eq!(42, container.call(|| 42).unwrap());
+    }
+
+    #[test]
+    fn missing_arguments_are_errors() {
+        let container = ContainerBuilder::new().build();
+        let mut called = false;
+
+        let result = container.call(|_: u8| called = true);
+
+        assert!(!called);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("could not inject argument u8: "));
+    }
+
+    #[test]
+    fn every_missing_argument_is_reported() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u16>(1).unwrap();
+
+        let container = builder.build();
+
+        let error = container.resolve_tuple::<(u8, u16, u32)>().unwrap_err();
+        assert_eq!(
+            "could not inject argument u8: Type not registered: u8; \
+             could not inject argument u32: Type not registered: u32",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn results_are_returned_as_they_are() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(0).unwrap();
+
+        let container = builder.build();
+
+        let result: Result<Result<u8>> =
+            container.call(|divisor: u8| 10u8.checked_div(divisor).ok_or_else(|| "zero".into()));
+
+        assert_eq!("zero", result.unwrap().unwrap_err().to_string());
+    }
+}
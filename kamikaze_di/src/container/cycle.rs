@@ -1,37 +1,118 @@
-use std::any::TypeId;
-use std::cell::RefCell;
-use std::collections::HashSet;
+use core::any::TypeId;
+use core::cell::{Cell, RefCell};
+
+use crate::error::CycleDetected;
+use crate::prelude::*;
+use crate::{Error, Result};
 
 #[derive(Default, Debug)]
 pub struct CycleStopper {
-    tracked: RefCell<HashSet<TypeId>>,
+    /// Types being resolved, outermost first.
+    tracked: RefCell<Vec<(TypeId, &'static str)>>,
+    /// How many resolutions can be nested, see Container::set_max_depth().
+    max_depth: Cell<Option<usize>>,
 }
 
 /// We use this mechanism to avoid circular dependencies
 impl CycleStopper {
-    pub fn track(&self, type_id: TypeId) -> CycleGuard<'_> {
-        let mut tracked = self.tracked.borrow_mut();
+    pub fn track<T: ?Sized + 'static>(&self) -> Result<CycleGuard<'_>> {
+        let type_id = TypeId::of::<T>();
+
+        if self.is_tracked(type_id) {
+            return Err(self.cycle::<T>().into());
+        }
 
-        if tracked.contains(&type_id) {
-            // admitedly, this ends up being a very unuseful error message
-            panic!(
-                "Circular dependency detected when resolving {:#?}.\nResole history is:\n{:#?}",
-                type_id, tracked
-            );
+        if let Some(max_depth) = self.max_depth.get() {
+            if self.tracked.borrow().len() >= max_depth {
+                return Err(self.too_deep::<T>(max_depth));
+            }
         }
 
-        tracked.insert(type_id);
+        self.tracked
+            .borrow_mut()
+            .push((type_id, core::any::type_name::<T>()));
 
-        CycleGuard {
+        Ok(CycleGuard {
             guarded_type: type_id,
-            stopper: &self,
-        }
+            stopper: self,
+        })
+    }
+
+    /// The cycle resolving T again would close.
+    pub fn cycle<T: ?Sized + 'static>(&self) -> CycleDetected {
+        let tracked = self.tracked.borrow();
+        let type_id = TypeId::of::<T>();
+
+        // types resolved before T first came up aren't part of the cycle
+        let start = tracked
+            .iter()
+            .position(|(tracked, _)| *tracked == type_id)
+            .unwrap_or(0);
+
+        let mut path: Vec<_> = tracked[start..]
+            .iter()
+            .map(|(_, type_name)| *type_name)
+            .collect();
+        path.push(core::any::type_name::<T>());
+
+        CycleDetected { path }
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth.get()
+    }
+
+    pub fn set_max_depth(&self, max_depth: Option<usize>) {
+        self.max_depth.set(max_depth);
+    }
+
+    /// Resolving T would nest deeper than allowed.
+    fn too_deep<T: ?Sized + 'static>(&self, max_depth: usize) -> Error {
+        let error: Error =
+            format!("Resolution nested deeper than the limit of {}", max_depth).into();
+
+        error.resolving(|| {
+            let mut path: Vec<_> = self
+                .tracked
+                .borrow()
+                .iter()
+                .map(|(_, type_name)| *type_name)
+                .collect();
+            path.push(core::any::type_name::<T>());
+
+            path
+        })
+    }
+
+    /// Returns true if nothing is being resolved.
+    pub fn is_idle(&self) -> bool {
+        self.tracked.borrow().is_empty()
+    }
+
+    /// Adds the types being resolved to the error.
+    pub fn add_path(&self, error: Error) -> Error {
+        error.resolving(|| {
+            self.tracked
+                .borrow()
+                .iter()
+                .map(|(_, type_name)| *type_name)
+                .collect()
+        })
+    }
+
+    fn is_tracked(&self, type_id: TypeId) -> bool {
+        self.tracked
+            .borrow()
+            .iter()
+            .any(|(tracked, _)| *tracked == type_id)
     }
 
     fn untrack(&self, type_id: TypeId) {
         let mut tracked = self.tracked.borrow_mut();
 
-        tracked.remove(&type_id);
+        if let Some(position) = tracked.iter().rposition(|(tracked, _)| *tracked == type_id) {
+            tracked.remove(position);
+        }
     }
 }
 
@@ -54,21 +135,40 @@ mod tests {
     fn allows_new_types() {
         let stopper: CycleStopper = Default::default();
 
-        stopper.track(TypeId::of::<i32>());
-        stopper.track(TypeId::of::<u32>());
+        let _i32 = stopper.track::<i32>().unwrap();
+        let _u32 = stopper.track::<u32>().unwrap();
+    }
+
+    #[test]
+    fn reports_tracked_types() {
+        let stopper: CycleStopper = Default::default();
+
+        let _u8 = stopper.track::<u8>().unwrap();
+        let _i32 = stopper.track::<i32>().unwrap();
+        let _u32 = stopper.track::<u32>().unwrap();
+
+        assert_eq!(
+            CycleDetected {
+                path: vec!["i32", "u32", "i32"]
+            },
+            stopper.cycle::<i32>()
+        );
+        assert!(stopper.track::<i32>().is_err());
     }
 
     #[test]
-    #[should_panic]
-    fn panics_on_tracked_types() {
+    fn nesting_is_limited() {
         let stopper: CycleStopper = Default::default();
+        stopper.set_max_depth(Some(2));
 
-        let _ = {
-            let guard = stopper.track(TypeId::of::<i32>());
-            let _ = stopper.track(TypeId::of::<i32>());
+        let _u8 = stopper.track::<u8>().unwrap();
+        let _i32 = stopper.track::<i32>().unwrap();
 
-            guard
-        };
+        let error = stopper.track::<u32>().err().unwrap();
+        assert_eq!(vec!["u8", "i32", "u32"], error.path());
+        assert!(error
+            .to_string()
+            .ends_with("Resolution nested deeper than the limit of 2"));
     }
 
     #[test]
@@ -76,8 +176,8 @@ mod tests {
         let stopper: CycleStopper = Default::default();
 
         {
-            stopper.track(TypeId::of::<i32>());
+            let _guard = stopper.track::<i32>().unwrap();
         } // the CycleGuard created by .track() goes out of scope
-        stopper.track(TypeId::of::<i32>());
+        stopper.track::<i32>().unwrap();
     }
 }
@@ -0,0 +1,246 @@
+use alloc::rc::Rc;
+use core::cell::{OnceCell, RefCell};
+use core::fmt;
+use core::ops::Deref;
+
+use super::injector::{Inject, Injector};
+use super::Container;
+use crate::prelude::*;
+use crate::Result;
+
+/// A dependency that is filled in once the item asking for it exists.
+///
+/// Deferred handles let two services refer to each other: A gets a handle
+/// to B instead of B itself, so building A doesn't need B yet. When the
+/// resolution that built A is done, the container resolves B, which can
+/// now use A, and fills the handle in. Until then, the handle is empty.
+///
+/// Get them via [resolve_deferred()](struct.Container.html#method.resolve_deferred),
+/// or via the [Injector](trait.Injector.html) trait, as fields of types that
+/// derive `Inject` or `InjectAsRc`. Both sides should be shared, so they
+/// end up with each other and not with new items.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Deferred, Resolver};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Parent {
+///     child: Deferred<Rc<Child>>,
+/// }
+///
+/// struct Child {
+///     parent: Rc<Parent>,
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_try_builder::<Rc<Parent>, _>(|container| {
+///     Ok(Rc::new(Parent { child: container.resolve_deferred()? }))
+/// })?;
+/// builder.register_try_builder::<Rc<Child>, _>(|container| {
+///     Ok(Rc::new(Child { parent: container.resolve()? }))
+/// })?;
+///
+/// let container = builder.build();
+/// let parent = container.resolve::<Rc<Parent>>()?;
+///
+/// assert!(Rc::ptr_eq(&parent, &parent.child.get()?.parent));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Shared items that point at each other are never dropped, since each
+/// keeps the other alive. That's fine for services that live as long as
+/// the application, otherwise one side should hold a `Weak` instead.
+pub struct Deferred<T> {
+    cell: Rc<OnceCell<T>>,
+}
+
+impl<T> Deferred<T> {
+    /// Returns the dependency.
+    ///
+    /// Fails if it wasn't filled in yet, which is the case while the item
+    /// holding the handle is still being built.
+    pub fn get(&self) -> Result<&T> {
+        self.cell.get().ok_or_else(|| {
+            format!(
+                "{} is deferred, it can't be used before it's filled in",
+                core::any::type_name::<T>()
+            )
+            .into()
+        })
+    }
+
+    /// Returns true if the dependency was filled in.
+    pub fn is_filled(&self) -> bool {
+        self.cell.get().is_some()
+    }
+}
+
+impl<T> Deref for Deferred<T> {
+    type Target = T;
+
+    /// # Panics
+    ///
+    /// Panics if the dependency wasn't filled in yet, use
+    /// [get()](struct.Deferred.html#method.get) to handle the error instead.
+    fn deref(&self) -> &T {
+        match self.get() {
+            Ok(item) => item,
+            Err(error) => panic!("{}", error),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Inject for Deferred<T> {
+    fn resolve(container: &Container) -> Result<Self> {
+        container.resolve_deferred()
+    }
+}
+
+impl<T> Clone for Deferred<T> {
+    fn clone(&self) -> Self {
+        Deferred {
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Deferred<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Deferred")
+            .field("item", &self.cell.get())
+            .finish()
+    }
+}
+
+/// Fills a handle in, resolving its dependency.
+type Fill = Box<dyn FnOnce(&Container) -> Result<()>>;
+
+/// Handles waiting to be filled in, see Container::resolve_deferred().
+#[derive(Default)]
+pub(super) struct Deferrals {
+    fills: RefCell<Vec<Fill>>,
+}
+
+impl Deferrals {
+    pub fn defer<T: Clone + 'static>(&self) -> Deferred<T> {
+        let cell: Rc<OnceCell<T>> = Default::default();
+        let handle = cell.clone();
+
+        self.fills.borrow_mut().push(Box::new(move |container| {
+            let item = Injector::<T>::inject(container)?;
+            // handles filled in some other way keep what they have
+            let _ = handle.set(item);
+
+            Ok(())
+        }));
+
+        Deferred { cell }
+    }
+
+    /// Fills in every waiting handle, in the order they were handed out.
+    pub fn fill(&self, container: &Container) -> Result<()> {
+        loop {
+            // not borrowed while filling, dependencies may defer too
+            let fills: Vec<_> = self.fills.borrow_mut().drain(..).collect();
+            if fills.is_empty() {
+                return Ok(());
+            }
+
+            debug!("filling {} deferred dependencies", fills.len());
+
+            for fill in fills {
+                fill(container)?;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Deferrals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Deferrals")
+            .field("waiting", &self.fills.borrow().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Deferred;
+    use crate::{ContainerBuilder, Resolver};
+    use std::rc::Rc;
+
+    struct Left {
+        right: Deferred<Rc<Right>>,
+    }
+
+    struct Right {
+        left: Rc<Left>,
+    }
+
+    fn builder() -> ContainerBuilder {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_try_builder::<Rc<Left>, _>(|container| {
+                let right: Deferred<Rc<Right>> = container.resolve_deferred()?;
+                assert!(right.get().is_err());
+
+                Ok(Rc::new(Left { right }))
+            })
+            .unwrap();
+        builder
+            .register_try_builder::<Rc<Right>, _>(|container| {
+                Ok(Rc::new(Right {
+                    left: container.resolve()?,
+                }))
+            })
+            .unwrap();
+
+        builder
+    }
+
+    #[test]
+    fn handles_are_filled_in_once_resolving_is_done() {
+        let container = builder().build();
+
+        let right = container.resolve::<Rc<Right>>().unwrap();
+        let left = container.resolve::<Rc<Left>>().unwrap();
+
+        assert!(left.right.is_filled());
+        assert!(Rc::ptr_eq(&right, &left.right));
+        assert!(Rc::ptr_eq(&left, &right.left));
+    }
+
+    #[test]
+    fn warming_up_fills_handles_in_too() {
+        let container = builder().build();
+
+        container.warm_up().unwrap();
+
+        let left = container.resolve::<Rc<Left>>().unwrap();
+        assert!(left.right.is_filled());
+    }
+
+    #[test]
+    fn failing_fills_fail_the_resolution() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_try_builder::<Rc<Left>, _>(|container| {
+                Ok(Rc::new(Left {
+                    right: container.resolve_deferred()?,
+                }))
+            })
+            .unwrap();
+
+        let container = builder.build();
+        let error = container.resolve::<Rc<Left>>().err().unwrap();
+
+        assert!(error.to_string().contains("Type not registered"));
+        assert!(error.to_string().ends_with("Right>"));
+    }
+}
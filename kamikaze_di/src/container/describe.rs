@@ -0,0 +1,114 @@
+use core::fmt::Write;
+
+use super::scope::Scope;
+use super::RegistrationInfo;
+use crate::prelude::*;
+
+/// A registration, with what's known about its surroundings.
+pub(super) struct Described<'a> {
+    pub info: RegistrationInfo,
+    pub scope: Scope,
+    /// Tags carried by the type, sorted.
+    pub tags: Vec<&'a str>,
+    /// Declared dependencies, in declaration order.
+    pub dependencies: Vec<&'static str>,
+}
+
+/// Renders registrations as a JSON document, keeping their order.
+pub(super) fn to_json(described: &[Described]) -> String {
+    let mut json = String::from("{\"registrations\":[");
+
+    for (index, registration) in described.iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+
+        let location = registration.info.registered_at;
+
+        let _ = write!(
+            json,
+            "{{\"type_name\":{},\"kind\":{},\"scope\":{},\"built\":{},\"tags\":{},\"dependencies\":{},\"registered_at\":{{\"file\":{},\"line\":{},\"column\":{}}}}}",
+            string(registration.info.type_name),
+            string(&format!("{:?}", registration.info.resolver_type)),
+            string(&format!("{:?}", registration.scope)),
+            registration.info.built,
+            array(&registration.tags),
+            array(&registration.dependencies),
+            string(location.file()),
+            location.line(),
+            location.column(),
+        );
+    }
+
+    json.push_str("]}");
+
+    json
+}
+
+fn array(items: &[&str]) -> String {
+    let items: Vec<_> = items.iter().map(|item| string(item)).collect();
+
+    format!("[{}]", items.join(","))
+}
+
+fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResolverType;
+    use core::panic::Location;
+
+    #[test]
+    fn renders_json() {
+        let described = vec![Described {
+            info: RegistrationInfo {
+                type_name: "app::Repository<\"users\">",
+                resolver_type: ResolverType::Builder,
+                built: false,
+                registered_at: Location::caller(),
+            },
+            scope: Scope::Singleton,
+            tags: vec!["repositories"],
+            dependencies: vec!["app::Db", "u8"],
+        }];
+
+        let location = Location::caller();
+        let json = to_json(&described);
+
+        assert!(json.starts_with(
+            "{\"registrations\":[{\"type_name\":\"app::Repository<\\\"users\\\">\",\
+             \"kind\":\"Builder\",\"scope\":\"Singleton\",\"built\":false,\
+             \"tags\":[\"repositories\"],\"dependencies\":[\"app::Db\",\"u8\"],\
+             \"registered_at\":{\"file\":"
+        ));
+        assert!(json.contains(location.file()));
+        assert!(json.ends_with("}}]}"));
+    }
+
+    #[test]
+    fn control_characters_are_escaped() {
+        assert_eq!("\"a\\tb\\u0001\"", string("a\tb\u{1}"));
+    }
+}
@@ -0,0 +1,238 @@
+use core::any::Any;
+
+use super::Container;
+use crate::prelude::*;
+use crate::Result;
+
+/// A factory whose item type is erased.
+///
+/// Closures can't be downcast to `dyn FnMut`, which is why they used to be
+/// boxed twice, a `Box<dyn FnMut>` inside a `Box<dyn Any>`. Instead, the
+/// closure is boxed once as a `dyn MakeInto`, which writes the item into a
+/// slot of the caller's choosing, and only the slot is downcast.
+pub(super) struct ErasedFactory(Box<dyn MakeInto>);
+
+/// Makes an item, and puts it into `slot`, an `Option<Result<T>>`.
+trait MakeInto {
+    fn make_into(&mut self, container: &Container, slot: &mut dyn Any);
+}
+
+impl<T, F> MakeInto for F
+where
+    F: (FnMut(&Container) -> T) + 'static,
+    T: 'static,
+{
+    fn make_into(&mut self, container: &Container, slot: &mut dyn Any) {
+        let slot = slot
+            .downcast_mut::<Option<Result<T>>>()
+            .expect("could not downcast factory");
+
+        *slot = Some(Ok(self(container)));
+    }
+}
+
+/// A factory or builder that can fail.
+struct Fallible<F>(F);
+
+impl<T, F> MakeInto for Fallible<F>
+where
+    F: (FnMut(&Container) -> Result<T>) + 'static,
+    T: 'static,
+{
+    fn make_into(&mut self, container: &Container, slot: &mut dyn Any) {
+        let slot = slot
+            .downcast_mut::<Option<Result<T>>>()
+            .expect("could not downcast factory");
+
+        *slot = Some((self.0)(container));
+    }
+}
+
+impl ErasedFactory {
+    pub fn new<T, F>(factory: F) -> ErasedFactory
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        ErasedFactory(Box::new(factory))
+    }
+
+    pub fn fallible<T, F>(factory: F) -> ErasedFactory
+    where
+        F: (FnMut(&Container) -> Result<T>) + 'static,
+        T: 'static,
+    {
+        ErasedFactory(Box::new(Fallible(factory)))
+    }
+
+    pub fn call<T: 'static>(&mut self, container: &Container) -> Result<T> {
+        let mut slot: Option<Result<T>> = None;
+        self.0.make_into(container, &mut slot);
+
+        slot.expect("factory did not make an item")
+    }
+}
+
+impl core::fmt::Debug for ErasedFactory {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("ErasedFactory")
+    }
+}
+
+/// A builder whose item type is erased, see ErasedFactory.
+pub(super) struct ErasedBuilder(Box<dyn BuildInto>);
+
+/// Builds an item, and puts it into `slot`, an `Option<Result<T>>`.
+///
+/// Builders that can run again return themselves, so they can be tried
+/// again after failing, or rebuild their item after a reset.
+trait BuildInto {
+    fn build_into(
+        self: Box<Self>,
+        container: &Container,
+        slot: &mut dyn Any,
+    ) -> Option<Box<dyn BuildInto>>;
+}
+
+impl<T, B> BuildInto for B
+where
+    B: FnOnce(&Container) -> T + 'static,
+    T: 'static,
+{
+    fn build_into(
+        self: Box<Self>,
+        container: &Container,
+        slot: &mut dyn Any,
+    ) -> Option<Box<dyn BuildInto>> {
+        let slot = slot
+            .downcast_mut::<Option<Result<T>>>()
+            .expect("could not downcast builder");
+
+        *slot = Some(Ok(self(container)));
+
+        None
+    }
+}
+
+impl<T, B> BuildInto for Fallible<B>
+where
+    B: (FnMut(&Container) -> Result<T>) + 'static,
+    T: 'static,
+{
+    fn build_into(
+        mut self: Box<Self>,
+        container: &Container,
+        slot: &mut dyn Any,
+    ) -> Option<Box<dyn BuildInto>> {
+        let slot = slot
+            .downcast_mut::<Option<Result<T>>>()
+            .expect("could not downcast builder");
+
+        *slot = Some((self.0)(container));
+
+        Some(self)
+    }
+}
+
+impl ErasedBuilder {
+    pub fn new<T, B>(builder: B) -> ErasedBuilder
+    where
+        B: FnOnce(&Container) -> T + 'static,
+        T: 'static,
+    {
+        ErasedBuilder(Box::new(builder))
+    }
+
+    pub fn fallible<T, B>(builder: B) -> ErasedBuilder
+    where
+        B: (FnMut(&Container) -> Result<T>) + 'static,
+        T: 'static,
+    {
+        ErasedBuilder(Box::new(Fallible(builder)))
+    }
+
+    pub fn rebuildable<T, B>(mut builder: B) -> ErasedBuilder
+    where
+        B: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        ErasedBuilder::fallible(move |container| Ok(builder(container)))
+    }
+
+    /// Builds the item, and returns the builder too if it can run again.
+    pub fn call<T: 'static>(self, container: &Container) -> (Result<T>, Option<ErasedBuilder>) {
+        let mut slot: Option<Result<T>> = None;
+        let retry = self.0.build_into(container, &mut slot);
+
+        (
+            slot.expect("builder did not build an item"),
+            retry.map(ErasedBuilder),
+        )
+    }
+}
+
+impl core::fmt::Debug for ErasedBuilder {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("ErasedBuilder")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factories_keep_their_state() {
+        let container = Container::new();
+
+        let mut count = 0;
+        let mut factory = ErasedFactory::new(move |_| {
+            count += 1;
+            count
+        });
+
+        assert_eq!(Ok(1), factory.call::<i32>(&container));
+        assert_eq!(Ok(2), factory.call::<i32>(&container));
+    }
+
+    #[test]
+    #[should_panic(expected = "could not downcast factory")]
+    fn factories_only_make_their_type() {
+        let mut factory = ErasedFactory::new(|_| 42i32);
+
+        factory.call::<u32>(&Container::new()).unwrap();
+    }
+
+    #[test]
+    fn builders_are_called_once() {
+        let name = "built".to_string();
+        let builder = ErasedBuilder::new(move |_| name);
+
+        assert_eq!(Ok("built".to_string()), builder.call(&Container::new()).0);
+    }
+
+    #[test]
+    fn failed_builders_can_be_tried_again() {
+        let container = Container::new();
+
+        let mut attempts = 0;
+        let builder = ErasedBuilder::fallible(move |_| {
+            attempts += 1;
+            if attempts < 2 {
+                Err("not yet".into())
+            } else {
+                Ok(attempts)
+            }
+        });
+
+        let (result, retry) = builder.call::<i32>(&container);
+        assert_eq!("not yet", result.unwrap_err().to_string());
+
+        let (result, retry) = retry.unwrap().call::<i32>(&container);
+        assert_eq!(Ok(2), result);
+
+        // kept for rebuilding after a reset
+        let (result, _) = retry.unwrap().call::<i32>(&container);
+        assert_eq!(Ok(3), result);
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::collections::VecDeque;
+use core::cell::RefCell;
+use core::fmt;
+
+use crate::prelude::*;
+use crate::Result;
+
+/// Something that happened in a container.
+///
+/// See [recent_events()](struct.Container.html#method.recent_events).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ContainerEvent {
+    /// A type was resolved.
+    Resolved(&'static str),
+    /// A type failed to resolve.
+    ResolveFailed {
+        /// Name of the type.
+        type_name: &'static str,
+        /// What went wrong.
+        error: String,
+    },
+    /// A frame of overrides was pushed.
+    OverridesPushed,
+    /// A frame of overrides was popped or dropped.
+    OverridesPopped,
+    /// A scope was entered, with its id.
+    ScopeEntered(usize),
+    /// A scope ended, with its id.
+    ScopeExited(usize),
+}
+
+impl fmt::Display for ContainerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContainerEvent::Resolved(type_name) => write!(f, "resolved {}", type_name),
+            ContainerEvent::ResolveFailed { type_name, error } => {
+                write!(f, "failed to resolve {}: {}", type_name, error)
+            }
+            ContainerEvent::OverridesPushed => write!(f, "pushed overrides"),
+            ContainerEvent::OverridesPopped => write!(f, "popped overrides"),
+            ContainerEvent::ScopeEntered(id) => write!(f, "entered scope {}", id),
+            ContainerEvent::ScopeExited(id) => write!(f, "exited scope {}", id),
+        }
+    }
+}
+
+/// Ring buffer of the most recent events, off unless given a capacity.
+#[derive(Debug, Default)]
+pub(super) struct EventLog {
+    capacity: usize,
+    events: RefCell<VecDeque<ContainerEvent>>,
+}
+
+impl EventLog {
+    pub fn with_capacity(capacity: usize) -> EventLog {
+        EventLog {
+            capacity,
+            events: RefCell::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn record(&self, event: ContainerEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut events = self.events.borrow_mut();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+
+        events.push_back(event);
+    }
+
+    pub fn record_resolution<T>(&self, result: &Result<T>) {
+        // skip formatting errors nobody will look at
+        if !self.is_enabled() {
+            return;
+        }
+
+        let type_name = core::any::type_name::<T>();
+
+        self.record(match result {
+            Ok(_) => ContainerEvent::Resolved(type_name),
+            Err(error) => ContainerEvent::ResolveFailed {
+                type_name,
+                error: error.to_string(),
+            },
+        });
+    }
+
+    /// Oldest first.
+    pub fn recent(&self) -> Vec<ContainerEvent> {
+        self.events.borrow().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_most_recent_events_are_kept() {
+        let log = EventLog::with_capacity(2);
+        for id in 0..3 {
+            log.record(ContainerEvent::ScopeEntered(id));
+        }
+
+        assert_eq!(
+            vec![
+                ContainerEvent::ScopeEntered(1),
+                ContainerEvent::ScopeEntered(2)
+            ],
+            log.recent()
+        );
+    }
+
+    #[test]
+    fn nothing_is_kept_by_default() {
+        let log = EventLog::default();
+        log.record(ContainerEvent::OverridesPushed);
+
+        assert!(log.recent().is_empty());
+    }
+}
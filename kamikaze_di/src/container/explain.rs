@@ -0,0 +1,223 @@
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::time::Duration;
+
+use super::ResolverType;
+use crate::clock::Stopwatch;
+use crate::prelude::*;
+use crate::Result;
+
+/// What handed out an item, see [TraceNode](struct.TraceNode.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ResolvedVia {
+    /// The registration, as it was when the resolution started.
+    Registration(ResolverType),
+    /// An override, see [override_scoped()](struct.MutableContainer.html#method.override_scoped).
+    Override,
+    /// An item seeded in the current scope.
+    ScopeSeed,
+    /// Nothing registered, like fallbacks or the container handle.
+    Unregistered,
+}
+
+impl fmt::Display for ResolvedVia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolvedVia::Registration(ResolverType::Factory) => write!(f, "factory"),
+            ResolvedVia::Registration(ResolverType::Builder) => write!(f, "builder"),
+            ResolvedVia::Registration(ResolverType::Shared) => write!(f, "shared item"),
+            ResolvedVia::Registration(ResolverType::Scoped) => write!(f, "scoped factory"),
+            ResolvedVia::Override => write!(f, "override"),
+            ResolvedVia::ScopeSeed => write!(f, "scope seed"),
+            ResolvedVia::Unregistered => write!(f, "unregistered"),
+        }
+    }
+}
+
+/// A type resolved while explaining, with what it resolved in turn.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TraceNode {
+    /// Name of the type.
+    pub type_name: &'static str,
+    /// What handed out the item.
+    pub via: ResolvedVia,
+    /// Whether the item already existed, shared or scoped.
+    pub cache_hit: bool,
+    /// Time the resolution took, including its dependencies.
+    ///
+    /// Always zero without the `std` feature, or on wasm32-unknown-unknown,
+    /// there is no clock otherwise.
+    pub took: Duration,
+    /// What went wrong, if the resolution failed.
+    pub error: Option<String>,
+    /// Types resolved while resolving this one, in order.
+    pub dependencies: Vec<TraceNode>,
+}
+
+impl TraceNode {
+    fn new(type_name: &'static str, via: ResolvedVia) -> TraceNode {
+        TraceNode {
+            type_name,
+            via,
+            cache_hit: false,
+            took: Duration::ZERO,
+            error: None,
+            dependencies: Vec::new(),
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write!(
+            f,
+            "\n{:indent$}{} via {}, ",
+            "",
+            self.type_name,
+            self.via,
+            indent = depth * 2
+        )?;
+
+        match (&self.error, self.cache_hit, self.via) {
+            (Some(error), _, _) => write!(f, "failed: {}", error)?,
+            (None, true, _) => write!(f, "cache hit")?,
+            (None, false, ResolvedVia::Registration(kind)) if kind != ResolverType::Shared => {
+                write!(f, "constructed in {:?}", self.took)?
+            }
+            (None, false, _) => write!(f, "resolved in {:?}", self.took)?,
+        }
+
+        for dependency in &self.dependencies {
+            dependency.write(f, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How a type was resolved, returned from [explain()](struct.Container.html#method.explain).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ResolutionTrace {
+    /// The type explained, with everything resolved for it.
+    pub root: TraceNode,
+}
+
+impl ResolutionTrace {
+    /// Names of the types that got a new item, in the order they finished.
+    ///
+    /// Factories show up every time they're explained, builders only the
+    /// first time.
+    pub fn constructions(&self) -> Vec<&'static str> {
+        let mut constructed = Vec::new();
+        collect_constructions(&self.root, &mut constructed);
+
+        constructed
+    }
+}
+
+fn collect_constructions(node: &TraceNode, constructed: &mut Vec<&'static str>) {
+    for dependency in &node.dependencies {
+        collect_constructions(dependency, constructed);
+    }
+
+    let constructing = match node.via {
+        ResolvedVia::Registration(kind) => kind != ResolverType::Shared,
+        _ => false,
+    };
+
+    if constructing && !node.cache_hit && node.error.is_none() {
+        constructed.push(node.type_name);
+    }
+}
+
+impl fmt::Display for ResolutionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Resolution of {}:", self.root.type_name)?;
+
+        self.root.write(f, 1)
+    }
+}
+
+/// Records resolutions as a tree, while a container is explaining.
+#[derive(Debug, Default)]
+pub(super) struct Explainer {
+    /// Nested explain() calls in progress.
+    active: Cell<usize>,
+    /// Resolutions in progress, the innermost last.
+    frames: RefCell<Vec<(TraceNode, Stopwatch)>>,
+    /// The outermost resolution, once it's done.
+    finished: RefCell<Option<TraceNode>>,
+}
+
+impl Explainer {
+    pub fn is_active(&self) -> bool {
+        self.active.get() > 0
+    }
+
+    pub fn start(&self) {
+        self.active.set(self.active.get() + 1);
+    }
+
+    /// Returns the trace of the resolution explained last.
+    pub fn finish(&self) -> TraceNode {
+        self.active.set(self.active.get() - 1);
+
+        // explained inside another explanation, it's one of its dependencies
+        let nested = self
+            .frames
+            .borrow()
+            .last()
+            .and_then(|(parent, _)| parent.dependencies.last().cloned());
+
+        nested
+            .or_else(|| self.finished.borrow_mut().take())
+            .expect("explained types resolve through get()")
+    }
+
+    pub fn enter(&self, type_name: &'static str, via: ResolvedVia) {
+        self.frames
+            .borrow_mut()
+            .push((TraceNode::new(type_name, via), Stopwatch::start()));
+    }
+
+    pub fn exit<T>(&self, result: &Result<T>, cache_hit: bool) {
+        let mut frames = self.frames.borrow_mut();
+        let (mut node, begun) = frames.pop().expect("resolutions exit after entering");
+
+        node.cache_hit = cache_hit && result.is_ok();
+        node.took = begun.elapsed().unwrap_or(Duration::ZERO);
+        node.error = result.as_ref().err().map(ToString::to_string);
+
+        match frames.last_mut() {
+            Some((parent, _)) => parent.dependencies.push(node),
+            None => *self.finished.borrow_mut() = Some(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_are_displayed_as_trees() {
+        let mut root = TraceNode::new(
+            "app::Service",
+            ResolvedVia::Registration(ResolverType::Factory),
+        );
+        root.dependencies.push(TraceNode {
+            cache_hit: true,
+            ..TraceNode::new("app::Db", ResolvedVia::Registration(ResolverType::Shared))
+        });
+        root.dependencies.push(TraceNode {
+            error: Some("Type not registered: app::Mailer".to_string()),
+            ..TraceNode::new("app::Mailer", ResolvedVia::Unregistered)
+        });
+
+        let trace = ResolutionTrace { root };
+
+        assert_eq!(
+            "Resolution of app::Service:\n  app::Service via factory, constructed in 0ns\n    app::Db via shared item, cache hit\n    app::Mailer via unregistered, failed: Type not registered: app::Mailer",
+            trace.to_string()
+        );
+        assert_eq!(vec!["app::Service"], trace.constructions());
+    }
+}
@@ -0,0 +1,173 @@
+use alloc::rc::Rc;
+use core::any::Any;
+use core::fmt;
+
+use super::interceptor::Resolution;
+use super::Container;
+use crate::prelude::*;
+use crate::Result;
+
+/// Resolves types the container has no registration for.
+///
+/// Handy for bridging to another container, handing out mocks in tests,
+/// or constructing types on the fly. Closures taking the resolution and
+/// the container are fallbacks too.
+///
+/// See [set_fallback()](struct.MutableContainer.html#method.set_fallback).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, Fallback, Resolution, Resolver, Result};
+/// # use std::any::{Any, TypeId};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Zeroes;
+///
+/// impl Fallback for Zeroes {
+///     fn resolve(&self, resolution: &Resolution, _: &Container) -> Result<Option<Box<dyn Any>>> {
+///         if resolution.type_id == TypeId::of::<u32>() {
+///             return Ok(Some(Box::new(0u32)));
+///         }
+///
+///         Ok(None)
+///     }
+/// }
+///
+/// let container = ContainerBuilder::new().build_mutable();
+/// container.set_fallback(Rc::new(Zeroes));
+///
+/// assert_eq!(0, container.resolve::<u32>()?);
+/// assert!(container.resolve::<u64>().is_err());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Fallback {
+    /// Returns an item, boxed as the type being resolved, or None to pass
+    /// the resolution on to the next fallback.
+    fn resolve(
+        &self,
+        resolution: &Resolution,
+        container: &Container,
+    ) -> Result<Option<Box<dyn Any>>>;
+}
+
+impl<F> Fallback for F
+where
+    F: Fn(&Resolution, &Container) -> Result<Option<Box<dyn Any>>>,
+{
+    fn resolve(
+        &self,
+        resolution: &Resolution,
+        container: &Container,
+    ) -> Result<Option<Box<dyn Any>>> {
+        self(resolution, container)
+    }
+}
+
+/// Fallbacks set on a container, tried in order.
+#[derive(Clone, Default)]
+pub(super) struct Fallbacks(pub Vec<Rc<dyn Fallback>>);
+
+impl Fallbacks {
+    /// Asks each fallback for T, until one of them has it.
+    pub fn resolve<T: 'static>(&self, container: &Container) -> Option<Result<T>> {
+        let resolution = Resolution::of::<T>();
+
+        for fallback in &self.0 {
+            let item = match fallback.resolve(&resolution, container) {
+                Ok(None) => continue,
+                Ok(Some(item)) => item,
+                Err(error) => return Some(Err(error)),
+            };
+
+            debug!("resolved via fallback");
+
+            return Some(item.downcast::<T>().map(|item| *item).map_err(|_| {
+                format!(
+                    "Fallback returned something other than {}",
+                    resolution.type_name
+                )
+                .into()
+            }));
+        }
+
+        None
+    }
+}
+
+impl fmt::Debug for Fallbacks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Fallbacks").field(&self.0.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+    use core::any::TypeId;
+
+    fn answer(resolution: &Resolution, _: &Container) -> Result<Option<Box<dyn Any>>> {
+        if resolution.type_id == TypeId::of::<u32>() {
+            return Ok(Some(Box::new(42u32)));
+        }
+
+        Ok(None)
+    }
+
+    #[test]
+    fn registrations_come_before_fallbacks() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(1).unwrap();
+
+        let container = builder.build();
+        container.set_fallback(Rc::new(answer));
+
+        assert_eq!(1, container.resolve::<u32>().unwrap());
+        assert!(container.resolve::<u64>().is_err());
+    }
+
+    #[test]
+    fn fallbacks_are_tried_in_order_and_can_resolve_dependencies() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(7).unwrap();
+
+        let container = builder.build();
+        container.set_fallback(Rc::new(answer));
+        container.set_fallback(Rc::new(
+            |resolution: &Resolution, container: &Container| -> Result<Option<Box<dyn Any>>> {
+                if resolution.type_id != TypeId::of::<String>() {
+                    return Ok(None);
+                }
+
+                let count: u32 = container.resolve()?;
+                let item: u8 = container.resolve()?;
+
+                Ok(Some(Box::new(format!("{} x {}", count, item))))
+            },
+        ));
+
+        assert_eq!("42 x 7", container.resolve::<String>().unwrap());
+        assert_eq!(42, container.fork().unwrap().resolve::<u32>().unwrap());
+    }
+
+    #[test]
+    fn items_of_the_wrong_type_are_errors() {
+        let container = ContainerBuilder::new().build();
+        container.set_fallback(Rc::new(
+            |_: &Resolution, _: &Container| -> Result<Option<Box<dyn Any>>> {
+                Ok(Some(Box::new("forty two")))
+            },
+        ));
+
+        let error = container.resolve::<u32>().unwrap_err();
+        assert_eq!(
+            "Fallback returned something other than u32",
+            error.to_string()
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use super::Container;
+use crate::Result;
+
+/// Fills in dependencies of an item that already exists.
+///
+/// For items the container can't build, like deserialized or FFI created
+/// ones. Usually derived with `#[derive(InjectFields)]` from
+/// kamikaze_di_derive, which fills every `Rc<T>` field, and every
+/// `Option<Rc<T>>` field with whatever is registered. Other fields are left
+/// alone.
+///
+/// See [inject_into()](struct.Container.html#method.inject_into).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, InjectFields, Resolver, Result};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Db;
+///
+/// struct Job {
+///     id: u32,
+///     db: Option<Rc<Db>>,
+/// }
+///
+/// impl InjectFields for Job {
+///     fn inject_fields(&mut self, container: &Container) -> Result<()> {
+///         self.db = Some(container.resolve()?);
+///
+///         Ok(())
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<Rc<Db>>(Rc::new(Db))?;
+///
+/// let container = builder.build();
+///
+/// let mut job = Job { id: 7, db: None };
+/// container.inject_into(&mut job)?;
+///
+/// assert!(job.db.is_some());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait InjectFields {
+    /// Sets the fields resolved from the container.
+    fn inject_fields(&mut self, container: &Container) -> Result<()>;
+}
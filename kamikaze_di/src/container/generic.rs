@@ -0,0 +1,125 @@
+use super::Container;
+use crate::Result;
+
+/// Builds every type of a generic family, like `Repository<T>` for any T.
+///
+/// Registered once via
+/// [register_generic()](struct.ContainerBuilder.html#method.register_generic),
+/// a family's types are built the first time they're resolved via
+/// [resolve_generic()](struct.Container.html#method.resolve_generic), and
+/// shared after that, so they can be resolved like registered ones too.
+/// Items are kept by their type, so `Output<T>` has to differ for every T.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, GenericFactory, Resolver, Result};
+/// # use std::marker::PhantomData;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Repository<T> {
+///     table: String,
+///     entity: PhantomData<T>,
+/// }
+///
+/// struct User;
+/// struct Order;
+///
+/// struct Repositories;
+///
+/// impl GenericFactory for Repositories {
+///     type Output<T: 'static> = Rc<Repository<T>>;
+///
+///     fn create<T: 'static>(container: &Container) -> Result<Rc<Repository<T>>> {
+///         let schema = container.resolve::<String>()?;
+///         let entity = std::any::type_name::<T>().rsplit("::").next().unwrap();
+///
+///         Ok(Rc::new(Repository {
+///             table: format!("{}.{}", schema, entity.to_lowercase()),
+///             entity: PhantomData,
+///         }))
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<String>("shop".to_string())?;
+/// builder.register_generic::<Repositories>()?;
+///
+/// let container = builder.build();
+///
+/// let users = container.resolve_generic::<Repositories, User>()?;
+/// let orders = container.resolve_generic::<Repositories, Order>()?;
+///
+/// assert_eq!("shop.user", users.table);
+/// assert_eq!("shop.order", orders.table);
+/// assert!(Rc::ptr_eq(&users, &container.resolve::<Rc<Repository<User>>>()?));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait GenericFactory: 'static {
+    /// The type built for T, like `Rc<Repository<T>>`.
+    type Output<T: 'static>: Clone + 'static;
+
+    /// Builds the item for T.
+    fn create<T: 'static>(container: &Container) -> Result<Self::Output<T>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GenericFactory;
+    use crate::{Container, ContainerBuilder, Resolver, Result};
+    use std::cell::Cell;
+    use std::marker::PhantomData;
+    use std::rc::Rc;
+
+    thread_local! {
+        static CREATED: Cell<u32> = const { Cell::new(0) };
+    }
+
+    type Name<T> = Rc<(&'static str, PhantomData<T>)>;
+
+    struct Named;
+
+    impl GenericFactory for Named {
+        type Output<T: 'static> = Name<T>;
+
+        fn create<T: 'static>(_: &Container) -> Result<Name<T>> {
+            CREATED.with(|created| created.set(created.get() + 1));
+
+            Ok(Rc::new((core::any::type_name::<T>(), PhantomData)))
+        }
+    }
+
+    #[test]
+    fn members_are_created_once() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_generic::<Named>().unwrap();
+
+        let container = builder.build();
+
+        assert_eq!("u8", container.resolve_generic::<Named, u8>().unwrap().0);
+        assert_eq!("u8", container.resolve_generic::<Named, u8>().unwrap().0);
+        assert_eq!(1, CREATED.with(Cell::get));
+
+        // only built once asked for
+        assert!(container.resolve::<Name<u16>>().is_err());
+        container.resolve_generic::<Named, u16>().unwrap();
+        assert_eq!("u16", container.resolve::<Name<u16>>().unwrap().0);
+        assert_eq!(2, CREATED.with(Cell::get));
+    }
+
+    #[test]
+    fn families_have_to_be_registered() {
+        let container = ContainerBuilder::new().build();
+
+        assert!(container
+            .resolve_generic::<Named, u8>()
+            .unwrap_err()
+            .to_string()
+            .contains("is not registered as a generic factory"));
+        assert!(container.resolve::<Name<u8>>().is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use alloc::collections::BTreeSet;
+use core::fmt::Write;
+
+use super::validation::Declaration;
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+
+/// Registered types and the dependencies declared between them.
+///
+/// Only dependencies declared via
+/// [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies)
+/// show up as edges.
+///
+/// See [dependency_graph()](struct.Container.html#method.dependency_graph).
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct DependencyGraph {
+    /// Type names, sorted.
+    pub nodes: Vec<&'static str>,
+    /// Pairs of (dependent, dependency) type names, sorted.
+    pub edges: Vec<(&'static str, &'static str)>,
+}
+
+impl DependencyGraph {
+    /// Renders the graph in the Graphviz DOT format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i32>(42)?;
+    /// builder.register_builder::<i64, _>(|_| 42)?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    ///
+    /// let dot = builder.build().dependency_graph().to_dot();
+    ///
+    /// assert!(dot.contains("\"i64\" -> \"i32\";"));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n");
+
+        for node in &self.nodes {
+            let _ = writeln!(dot, "    {};", quote(node));
+        }
+
+        for (dependent, dependency) in &self.edges {
+            let _ = writeln!(dot, "    {} -> {};", quote(dependent), quote(dependency));
+        }
+
+        dot.push('}');
+
+        dot
+    }
+}
+
+/// Collects registered types and declared dependencies into a graph.
+///
+/// Declared dependencies that aren't registered are still listed as nodes.
+pub(super) fn build<I>(declarations: &TypeIdMap<Declaration>, registered: I) -> DependencyGraph
+where
+    I: IntoIterator<Item = &'static str>,
+{
+    let mut nodes: BTreeSet<&'static str> = registered.into_iter().collect();
+    let mut edges = BTreeSet::new();
+
+    for declaration in declarations.values() {
+        nodes.insert(declaration.type_name);
+
+        for dependency in &declaration.dependencies {
+            nodes.insert(dependency.type_name());
+            edges.insert((declaration.type_name, dependency.type_name()));
+        }
+    }
+
+    DependencyGraph {
+        nodes: nodes.into_iter().collect(),
+        edges: edges.into_iter().collect(),
+    }
+}
+
+fn quote(type_name: &str) -> String {
+    format!(
+        "\"{}\"",
+        type_name.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dependencies;
+    use core::any::TypeId;
+
+    #[test]
+    fn unregistered_dependencies_are_nodes_too() {
+        let mut declarations = TypeIdMap::default();
+        declarations.insert(
+            TypeId::of::<i64>(),
+            Declaration {
+                type_name: "i64",
+                dependencies: <(i32, u8)>::dependencies(),
+            },
+        );
+
+        let graph = build(&declarations, vec!["i64", "u8"]);
+
+        assert_eq!(vec!["i32", "i64", "u8"], graph.nodes);
+        assert_eq!(vec![("i64", "i32"), ("i64", "u8")], graph.edges);
+    }
+
+    #[test]
+    fn renders_dot() {
+        let graph = DependencyGraph {
+            nodes: vec!["app::Handler", "app::Repository<\"users\">"],
+            edges: vec![("app::Handler", "app::Repository<\"users\">")],
+        };
+
+        let expected = r#"digraph dependencies {
+    "app::Handler";
+    "app::Repository<\"users\">";
+    "app::Handler" -> "app::Repository<\"users\">";
+}"#;
+
+        assert_eq!(expected, graph.to_dot());
+    }
+}
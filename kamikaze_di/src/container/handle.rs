@@ -0,0 +1,115 @@
+use alloc::rc::{Rc, Weak};
+use core::fmt;
+
+use super::resolver::Resolver;
+use super::{Container, Inner};
+use crate::Result;
+
+/// A handle to the container that closures can own.
+///
+/// Factories that store callbacks or spawn tasks can't hold on to the
+/// `&Container` they're given. A handle can be moved into them instead,
+/// and resolves like the container itself, see [Resolver](trait.Resolver.html).
+/// It only keeps a weak reference, so a handle captured by something the
+/// container owns doesn't keep the container alive. Resolving through a
+/// handle fails once the container is dropped.
+///
+/// Get one via [handle()](struct.Container.html#method.handle), or resolve
+/// it like any other dependency, every container provides it without
+/// registering it. Fields of derived types get it the same way. The
+/// container itself isn't resolvable, a shared item holding on to it would
+/// keep it alive forever.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// type Callback = Rc<dyn Fn() -> u32>;
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<u32>(42)?;
+/// builder.register_builder::<Callback, _>(|container| {
+///     let handle = container.handle();
+///
+///     Rc::new(move || handle.resolve::<u32>().unwrap())
+/// })?;
+///
+/// let container = builder.build();
+/// let callback = container.resolve::<Callback>()?;
+///
+/// assert_eq!(42, callback());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ContainerHandle {
+    container: Weak<Inner>,
+}
+
+impl ContainerHandle {
+    pub(super) fn new(container: &Container) -> ContainerHandle {
+        ContainerHandle {
+            container: Rc::downgrade(&container.inner),
+        }
+    }
+
+    /// Returns the container, if it wasn't dropped.
+    pub fn container(&self) -> Result<Container> {
+        let inner = self
+            .container
+            .upgrade()
+            .ok_or("container dropped before resolving through its handle")?;
+
+        Ok(Container { inner })
+    }
+}
+
+impl Resolver for ContainerHandle {
+    fn resolve<T: Clone + 'static>(&self) -> Result<T> {
+        self.container()?.resolve::<T>()
+    }
+
+    fn has<T: 'static>(&self) -> bool {
+        self.container().is_ok_and(|container| container.has::<T>())
+    }
+
+    fn try_resolve<T: ?Sized + 'static>(&self) -> Option<Rc<T>> {
+        self.container().ok()?.try_resolve::<T>()
+    }
+}
+
+impl fmt::Debug for ContainerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ContainerHandle")
+            .field("dropped", &(self.container.strong_count() == 0))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, Resolver};
+
+    #[test]
+    fn handles_dont_outlive_the_container() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+
+        let container = builder.build();
+        let handle = container.handle();
+
+        assert!(handle.has::<u32>());
+        assert_eq!(42, handle.resolve::<u32>().unwrap());
+
+        drop(container);
+
+        assert!(!handle.has::<u32>());
+        assert!(handle.resolve::<u32>().is_err());
+        assert!(handle.try_resolve::<u32>().is_none());
+    }
+}
@@ -0,0 +1,168 @@
+use alloc::rc::Rc;
+use core::any::Any;
+
+use super::Container;
+use crate::prelude::*;
+
+/// Second phase initialization, once a shared item is built.
+///
+/// The container calls `initialize()` right after building a shared item,
+/// before handing it out to anyone, so the item can use the container to
+/// finish setting itself up, like subscribing to an event bus. Resolving the
+/// item itself from `initialize()` is reported as a circular dependency. Items
+/// registered already built are not initialized.
+///
+/// With the `specialization` feature, implementing the trait is enough.
+/// Otherwise, types opt in via
+/// [register_initializer()](struct.ContainerBuilder.html#method.register_initializer).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, Initialize, Resolver};
+/// # use std::cell::RefCell;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Default)]
+/// struct EventBus { subscribers: RefCell<Vec<&'static str>> }
+///
+/// struct Mailer;
+///
+/// impl Initialize for Mailer {
+///     fn initialize(&self, container: &Container) {
+///         let bus = container.resolve::<Rc<EventBus>>().unwrap();
+///         bus.subscribers.borrow_mut().push("mailer");
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<Rc<EventBus>>(Default::default())?;
+/// builder.register_builder::<Rc<Mailer>, _>(|_| Rc::new(Mailer))?;
+/// # #[cfg(not(feature = "specialization"))]
+/// builder.register_initializer::<Rc<Mailer>>()?;
+///
+/// let container = builder.build();
+/// container.resolve::<Rc<Mailer>>()?;
+///
+/// let bus = container.resolve::<Rc<EventBus>>()?;
+/// assert_eq!(vec!["mailer"], *bus.subscribers.borrow());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Initialize {
+    /// Finishes setting up the item.
+    fn initialize(&self, container: &Container);
+}
+
+impl<T: Initialize + ?Sized> Initialize for Rc<T> {
+    fn initialize(&self, container: &Container) {
+        (**self).initialize(container)
+    }
+}
+
+/// Type erased initializer, see ContainerBuilder::register_initializer().
+pub(super) struct Initializer(Box<InitializeFn>);
+
+type InitializeFn = dyn Fn(&dyn Any, &Container);
+
+impl Initializer {
+    pub fn of<T: Initialize + 'static>() -> Initializer {
+        Initializer(Box::new(|item, container| {
+            item.downcast_ref::<T>()
+                .expect("could not downcast item to initialize")
+                .initialize(container)
+        }))
+    }
+
+    pub fn run(&self, item: &dyn Any, container: &Container) {
+        (self.0)(item, container)
+    }
+}
+
+impl core::fmt::Debug for Initializer {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("Initializer")
+    }
+}
+
+/// Initializes anything that implements Initialize, and nothing else, see
+/// the specialized module.
+#[cfg(feature = "specialization")]
+pub(super) trait MaybeInitialize {
+    fn maybe_initialize(&self, container: &Container);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+    use std::cell::Cell;
+
+    struct Counted {
+        initialized: Cell<usize>,
+    }
+
+    impl Initialize for Counted {
+        fn initialize(&self, container: &Container) {
+            self.initialized.set(self.initialized.get() + 1);
+
+            // dependencies are available
+            container.resolve::<u8>().unwrap();
+        }
+    }
+
+    fn counted() -> Rc<Counted> {
+        Rc::new(Counted {
+            initialized: Cell::new(0),
+        })
+    }
+
+    #[test]
+    fn built_items_are_initialized_once() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+        builder
+            .register_builder::<Rc<Counted>, _>(|_| counted())
+            .unwrap();
+        builder.register_initializer::<Rc<Counted>>().unwrap();
+
+        let container = builder.build();
+
+        let first = container.resolve::<Rc<Counted>>().unwrap();
+        container.resolve::<Rc<Counted>>().unwrap();
+
+        assert_eq!(1, first.initialized.get());
+    }
+
+    #[test]
+    #[cfg(feature = "specialization")]
+    fn initialize_types_need_no_initializer() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+        builder
+            .register_builder::<Rc<Counted>, _>(|_| counted())
+            .unwrap();
+        builder.register_builder::<u16, _>(|_| 1).unwrap();
+
+        let container = builder.build();
+
+        let item = container.resolve::<Rc<Counted>>().unwrap();
+        assert_eq!(1, item.initialized.get());
+        assert_eq!(1, container.resolve::<u16>().unwrap());
+    }
+
+    #[test]
+    fn registered_items_are_not_initialized() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Rc<Counted>>(counted()).unwrap();
+        builder.register_initializer::<Rc<Counted>>().unwrap();
+
+        let container = builder.build();
+
+        let item = container.resolve::<Rc<Counted>>().unwrap();
+        assert_eq!(0, item.initialized.get());
+    }
+}
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use alloc::rc::Weak;
 
 use super::private::Sealed;
 use crate::container::Container;
@@ -42,6 +42,8 @@ pub trait Injector<T>: Sealed {
 ///
 /// let mut container_builder = ContainerBuilder::new();
 /// container_builder.register::<i32>(42);
+/// # #[cfg(not(feature = "specialization"))]
+/// container_builder.register_automatic::<Point>()?;
 ///
 /// let container = container_builder.build();
 ///
@@ -88,6 +90,8 @@ where
 ///
 /// let mut container_builder = ContainerBuilder::new();
 /// container_builder.register::<i32>(42);
+/// # #[cfg(not(feature = "specialization"))]
+/// container_builder.register_automatic_as_rc::<Point>()?;
 ///
 /// let container = container_builder.build();
 ///
@@ -110,66 +114,27 @@ where
     fn resolve(container: &Container) -> Result<Self>;
 }
 
-impl<T> Injector<T> for Container
-where
-    T: Clone + 'static,
-{
-    default fn inject(&self) -> Result<T> {
-        debug!("injecting registered type");
-        self.get()
+/// Weak references are resolved from shared `Rc<T>`s, see
+/// [resolve_weak()](struct.Container.html#method.resolve_weak).
+impl<T: ?Sized + 'static> Inject for Weak<T> {
+    fn resolve(container: &Container) -> Result<Self> {
+        container.resolve_weak()
     }
 }
 
-// This would be amazing
-//use std::convert::TryFrom;
-//impl<T> TryFrom<Container> for T where T: Inject {
-//    fn from(other: &Container) -> Result<T> {
-//        Injector::<T>::inject(other)
-//    }
-//}
-
+// Without specialization, Inject and InjectAsRc types have to be registered,
+// see register_automatic() and register_automatic_as_rc().
+#[cfg(not(feature = "specialization"))]
 impl<T> Injector<T> for Container
 where
-    T: Inject + Clone + 'static,
+    T: Clone + 'static,
 {
     fn inject(&self) -> Result<T> {
-        debug!("injecting Inject type");
-
-        if !self.has::<T>() {
-            debug!("Inject type not known, auto-resolving");
-            let item = T::resolve(self)?;
-
-            use super::Resolver;
-            let resolver = Resolver::Shared(Box::new(item));
-
-            self.insert::<T>(resolver)?;
-        }
-
+        debug!("injecting registered type");
         self.get()
     }
 }
 
-impl<T> Injector<Rc<T>> for Container
-where
-    T: InjectAsRc + 'static,
-{
-    fn inject(&self) -> Result<Rc<T>> {
-        debug!("injecting InjectAsRc type");
-
-        if !self.has::<Rc<T>>() {
-            debug!("InjectAsRc type not known, auto-resolving");
-
-            let item = T::resolve(self)?;
-
-            use super::Resolver;
-            let resolver = Resolver::Shared(Box::new(Rc::new(item)));
-
-            self.insert::<Rc<T>>(resolver)?;
-        }
-
-        self.get()
-    }
-}
 #[cfg(test)]
 mod tests {
     use super::{Inject, Injector};
@@ -199,6 +164,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "specialization")]
     fn container_can_resolve_resolvables_automatically() {
         let container = ContainerBuilder::new().build();
 
@@ -208,6 +174,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "specialization")]
     fn auto_resolvables_can_get_chained() {
         let container = ContainerBuilder::new().build();
 
@@ -217,6 +184,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "specialization")]
     fn resolvables_get_stored() {
         use std::rc::Rc;
 
@@ -248,6 +216,153 @@ mod tests {
         assert!(a1_was_cloned);
     }
 
+    #[test]
+    #[cfg(feature = "specialization")]
+    fn mutable_services_are_auto_resolved_once() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct Counter {
+            count: u32,
+        }
+        impl Inject for Counter {
+            fn resolve(container: &Container) -> Result<Counter> {
+                Ok(Counter {
+                    count: container.inject()?,
+                })
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(40).unwrap();
+
+        let container = builder.build();
+
+        let counter: Rc<RefCell<Counter>> = container.inject().unwrap();
+        counter.borrow_mut().count += 2;
+
+        assert_eq!(
+            42,
+            container.resolve_mut::<Counter>().unwrap().borrow().count
+        );
+    }
+
+    #[test]
+    fn automatic_factories_create_new_items() {
+        use crate::Resolver;
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        #[derive(Clone)]
+        struct Counted {
+            count: usize,
+        }
+        impl Inject for Counted {
+            fn resolve(container: &Container) -> Result<Counted> {
+                let counter: Rc<Cell<usize>> = container.inject()?;
+                counter.set(counter.get() + 1);
+
+                Ok(Counted {
+                    count: counter.get(),
+                })
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register::<Rc<Cell<usize>>>(Default::default())
+            .unwrap();
+        builder.register_automatic_factory::<Counted>().unwrap();
+
+        let container = builder.build();
+
+        assert_eq!(1, container.resolve::<Counted>().unwrap().count);
+        assert_eq!(2, container.resolve::<Counted>().unwrap().count);
+    }
+
+    #[test]
+    #[cfg(feature = "specialization")]
+    fn weak_references_can_be_injected() {
+        use std::rc::{Rc, Weak};
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Rc<usize>>(Rc::new(42)).unwrap();
+
+        let container = builder.build();
+
+        let weak: Weak<usize> = container.inject().unwrap();
+        assert_eq!(Some(42), weak.upgrade().map(|item| *item));
+
+        drop(container);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn registered_resolvables_are_shared() {
+        use super::InjectAsRc;
+        use std::rc::Rc;
+
+        struct Pool {
+            x: X,
+        }
+        impl InjectAsRc for Pool {
+            fn resolve(container: &Container) -> Result<Pool> {
+                Ok(Pool {
+                    x: container.inject()?,
+                })
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_automatic::<Y>().unwrap();
+        builder.register_automatic::<X>().unwrap();
+        builder.register_automatic_as_rc::<Pool>().unwrap();
+
+        let container = builder.build();
+
+        let y: Y = container.inject().unwrap();
+        assert_eq!(42, y.x.inner);
+
+        let first: Rc<Pool> = container.inject().unwrap();
+        let second: Rc<Pool> = container.inject().unwrap();
+        assert_eq!(42, first.x.inner);
+        assert!(Rc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    #[cfg(feature = "specialization")]
+    fn optional_fields_are_injected_if_registered() {
+        use std::rc::Rc;
+
+        #[derive(Clone)]
+        struct Exporter {
+            metrics: Option<Rc<String>>,
+            tracing: Option<Rc<u8>>,
+        }
+        impl Inject for Exporter {
+            fn resolve(container: &Container) -> Result<Exporter> {
+                Ok(Exporter {
+                    metrics: container.inject()?,
+                    tracing: container.inject()?,
+                })
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register::<Rc<String>>(Rc::new("statsd".to_string()))
+            .unwrap();
+
+        let container = builder.build();
+        let exporter: Exporter = container.inject().unwrap();
+
+        assert_eq!(
+            Some("statsd"),
+            exporter.metrics.as_deref().map(String::as_str)
+        );
+        assert!(exporter.tracing.is_none());
+    }
+
     #[test]
     fn test_resolvable_interaction_with_auto_factory() {
         use std::rc::Rc;
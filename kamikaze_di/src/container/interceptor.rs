@@ -0,0 +1,207 @@
+use alloc::rc::Rc;
+use core::any::{Any, TypeId};
+use core::fmt;
+
+use crate::prelude::*;
+use crate::{Error, Result};
+
+/// A dependency being resolved.
+///
+/// See [Interceptor](trait.Interceptor.html).
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Resolution {
+    /// Id of the type being resolved.
+    pub type_id: TypeId,
+    /// Name of the type being resolved.
+    pub type_name: &'static str,
+}
+
+impl Resolution {
+    pub(super) fn of<T: 'static>() -> Resolution {
+        Resolution {
+            type_id: TypeId::of::<T>(),
+            type_name: core::any::type_name::<T>(),
+        }
+    }
+}
+
+impl fmt::Debug for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Resolution")
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+/// Hooks into every resolution, to log, time or veto it.
+///
+/// Interceptors are nested in the order they were added, the first one
+/// added being the outermost: `before_resolve()` runs first to last, and
+/// `after_resolve()` last to first. Dependencies resolved while resolving
+/// something else are intercepted too.
+///
+/// See [add_interceptor()](struct.MutableContainer.html#method.add_interceptor).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Error, Interceptor, Resolution, Resolver};
+/// # use std::any::Any;
+/// # use std::cell::RefCell;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Default)]
+/// struct Recorder { resolved: RefCell<Vec<&'static str>> }
+///
+/// impl Interceptor for Recorder {
+///     fn after_resolve(&self, resolution: &Resolution, result: Result<&dyn Any, &Error>) {
+///         if result.is_ok() {
+///             self.resolved.borrow_mut().push(resolution.type_name);
+///         }
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<u32>(42)?;
+///
+/// let container = builder.build_mutable();
+/// let recorder = Rc::new(Recorder::default());
+/// container.add_interceptor(recorder.clone());
+///
+/// container.resolve::<u32>()?;
+///
+/// assert_eq!(vec!["u32"], *recorder.resolved.borrow());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Interceptor {
+    /// Called before a dependency is resolved.
+    ///
+    /// Returning an item, boxed as the type being resolved, skips the
+    /// resolution and the interceptors after this one. Returning an error
+    /// vetoes the resolution.
+    fn before_resolve(&self, resolution: &Resolution) -> Result<Option<Box<dyn Any>>> {
+        let _ = resolution;
+
+        Ok(None)
+    }
+
+    /// Called once a dependency is resolved, or failed to resolve.
+    fn after_resolve(
+        &self,
+        resolution: &Resolution,
+        result: core::result::Result<&dyn Any, &Error>,
+    ) {
+        let _ = (resolution, result);
+    }
+}
+
+/// Interceptors added to a container, outermost first.
+#[derive(Clone, Default)]
+pub(super) struct Interceptors(pub Vec<Rc<dyn Interceptor>>);
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Interceptors").field(&self.0.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+    use std::cell::RefCell;
+
+    struct Logged {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+        answer: Option<u32>,
+    }
+
+    impl Interceptor for Logged {
+        fn before_resolve(&self, _: &Resolution) -> Result<Option<Box<dyn Any>>> {
+            self.log.borrow_mut().push(format!("before {}", self.name));
+
+            Ok(self
+                .answer
+                .map(|answer| -> Box<dyn Any> { Box::new(answer) }))
+        }
+
+        fn after_resolve(&self, _: &Resolution, result: std::result::Result<&dyn Any, &Error>) {
+            let item = result.ok().and_then(|item| item.downcast_ref::<u32>());
+
+            self.log
+                .borrow_mut()
+                .push(format!("after {} {:?}", self.name, item));
+        }
+    }
+
+    #[test]
+    fn interceptors_nest_in_order_and_short_circuit() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+
+        let container = builder.build();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        for (name, answer) in &[("outer", None), ("cache", Some(1)), ("inner", None)] {
+            container.add_interceptor(Rc::new(Logged {
+                name,
+                log: log.clone(),
+                answer: *answer,
+            }));
+        }
+
+        assert_eq!(1, container.resolve::<u32>().unwrap());
+        assert_eq!(
+            vec![
+                "before outer",
+                "before cache",
+                "after cache Some(1)",
+                "after outer Some(1)"
+            ],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn interceptors_can_veto() {
+        struct Veto;
+        impl Interceptor for Veto {
+            fn before_resolve(&self, resolution: &Resolution) -> Result<Option<Box<dyn Any>>> {
+                Err(format!("{} is off limits", resolution.type_name).into())
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+
+        let container = builder.build();
+        container.add_interceptor(Rc::new(Veto));
+
+        let error = container.resolve::<u32>().unwrap_err();
+        assert_eq!("u32 is off limits", error.to_string());
+    }
+
+    #[test]
+    fn items_of_the_wrong_type_are_errors() {
+        struct Wrong;
+        impl Interceptor for Wrong {
+            fn before_resolve(&self, _: &Resolution) -> Result<Option<Box<dyn Any>>> {
+                Ok(Some(Box::new("forty two")))
+            }
+        }
+
+        let container = ContainerBuilder::new().build();
+        container.add_interceptor(Rc::new(Wrong));
+
+        let error = container.resolve::<u32>().unwrap_err();
+        assert_eq!(
+            "Interceptor returned something other than u32",
+            error.to_string()
+        );
+    }
+}
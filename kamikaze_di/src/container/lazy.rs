@@ -0,0 +1,182 @@
+use alloc::rc::{Rc, Weak};
+use core::cell::OnceCell;
+use core::fmt;
+use core::ops::Deref;
+
+use super::injector::{Inject, Injector};
+use super::{Container, Inner};
+use crate::Result;
+
+/// Defers resolving a dependency until it is first used.
+///
+/// Lazy handles are resolved via the [Injector](trait.Injector.html) trait,
+/// so they can be used as fields of types that derive `Inject` or `InjectAsRc`.
+/// The dependency is only resolved the first time the handle is dereferenced,
+/// after that the value is cached and shared by all handles for the same type.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, Injector, Lazy};
+/// # use std::rc::Rc;
+/// # use std::cell::Cell;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let built = Rc::new(Cell::new(false));
+/// let built_flag = built.clone();
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_builder::<u32, _>(move |_| {
+///     built_flag.set(true);
+///     42
+/// })?;
+/// # #[cfg(not(feature = "specialization"))]
+/// builder.register_automatic::<Lazy<u32>>()?;
+///
+/// let container = builder.build();
+///
+/// let lazy: Lazy<u32> = container.inject()?;
+/// assert!(!built.get());
+///
+/// assert_eq!(*lazy, 42);
+/// assert!(built.get());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct Lazy<T> {
+    container: Weak<Inner>,
+    cell: Rc<OnceCell<T>>,
+}
+
+impl<T: Clone + 'static> Lazy<T> {
+    /// Resolves the dependency, if that hasn't happened already.
+    ///
+    /// Fails if the dependency can't be resolved or if the container
+    /// has been dropped in the meantime.
+    pub fn get(&self) -> Result<&T> {
+        if let Some(item) = self.cell.get() {
+            return Ok(item);
+        }
+
+        debug!("resolving lazy dependency");
+
+        let inner = self
+            .container
+            .upgrade()
+            .ok_or("container dropped before resolving lazy dependency")?;
+        let container = Container { inner };
+
+        let item = Injector::<T>::inject(&container)?;
+
+        Ok(self.cell.get_or_init(|| item))
+    }
+}
+
+impl<T: Clone + 'static> Deref for Lazy<T> {
+    type Target = T;
+
+    /// # Panics
+    ///
+    /// Panics if the dependency can't be resolved, use
+    /// [get()](struct.Lazy.html#method.get) to handle the error instead.
+    fn deref(&self) -> &T {
+        match self.get() {
+            Ok(item) => item,
+            Err(error) => panic!("could not resolve lazy dependency: {}", error),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Inject for Lazy<T> {
+    fn resolve(container: &Container) -> Result<Self> {
+        Ok(Lazy {
+            container: Rc::downgrade(&container.inner),
+            cell: Default::default(),
+        })
+    }
+}
+
+impl<T> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Lazy {
+            container: self.container.clone(),
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lazy")
+            .field("item", &self.cell.get())
+            .finish()
+    }
+}
+
+// Lazy handles are only injected on their own with specialization
+#[cfg(all(test, feature = "specialization"))]
+mod tests {
+    use super::Lazy;
+    use crate::{Container, ContainerBuilder, Inject, Injector, Result};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn lazy_handles_share_the_resolved_value() {
+        let calls = Rc::new(Cell::new(0));
+        let calls_in_factory = calls.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_factory::<i32, _>(move |_| {
+                calls_in_factory.set(calls_in_factory.get() + 1);
+                42
+            })
+            .unwrap();
+
+        let container = builder.build();
+
+        let first: Lazy<i32> = container.inject().unwrap();
+        let second: Lazy<i32> = container.inject().unwrap();
+        assert_eq!(0, calls.get());
+
+        assert_eq!(42, *first);
+        assert_eq!(42, *second);
+        assert_eq!(1, calls.get());
+    }
+
+    #[test]
+    fn lazy_fields_are_auto_resolved() {
+        #[derive(Clone)]
+        struct A {
+            inner: Lazy<i32>,
+        }
+        impl Inject for A {
+            fn resolve(container: &Container) -> Result<A> {
+                Ok(A {
+                    inner: container.inject()?,
+                })
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<i32>(42).unwrap();
+
+        let container = builder.build();
+        let a: A = container.inject().unwrap();
+
+        assert_eq!(42, *a.inner.get().unwrap());
+    }
+
+    #[test]
+    fn lazy_fails_once_the_container_is_gone() {
+        let container = ContainerBuilder::new().build();
+        let lazy: Lazy<i32> = container.inject().unwrap();
+
+        drop(container);
+
+        assert!(lazy.get().is_err());
+    }
+}
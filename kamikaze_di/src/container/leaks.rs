@@ -0,0 +1,90 @@
+use alloc::rc::Rc;
+use core::any::Any;
+use core::fmt;
+
+use crate::prelude::*;
+
+/// Counts the strong references of a shared `Rc<U>`, boxed as Any.
+pub(super) type LeakCheck = fn(&dyn Any) -> Option<usize>;
+
+pub(super) fn strong_count_of<U: ?Sized + 'static>(item: &dyn Any) -> Option<usize> {
+    item.downcast_ref::<Rc<U>>().map(Rc::strong_count)
+}
+
+/// A shared item still referenced outside the container.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Leak {
+    /// Name of the registered type.
+    pub type_name: &'static str,
+    /// Strong references to the item, not counting the container's own.
+    pub outside_references: usize,
+}
+
+/// Shared items still in use, and registrations nobody used.
+///
+/// Returned from [report_leaks()](struct.Container.html#method.report_leaks).
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct LeakReport {
+    /// Watched items with references outside the container, sorted by type name.
+    pub leaks: Vec<Leak>,
+    /// Registered types that were never resolved, sorted.
+    pub never_resolved: Vec<&'static str>,
+}
+
+impl LeakReport {
+    /// Returns true if there is nothing to report.
+    pub fn is_empty(&self) -> bool {
+        self.leaks.is_empty() && self.never_resolved.is_empty()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Leak report:")?;
+
+        for leak in &self.leaks {
+            write!(
+                f,
+                "\n  {} is still referenced {} time(s) outside the container",
+                leak.type_name, leak.outside_references
+            )?;
+        }
+
+        for type_name in &self.never_resolved {
+            write!(f, "\n  {} was never resolved", type_name)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_references_of_the_watched_type_only() {
+        let item = Rc::new(1u32);
+        let _kept = item.clone();
+        let boxed: Box<dyn Any> = Box::new(item);
+
+        assert_eq!(Some(2), strong_count_of::<u32>(&*boxed));
+        assert_eq!(None, strong_count_of::<u64>(&*boxed));
+    }
+
+    #[test]
+    fn displays_everything_found() {
+        let report = LeakReport {
+            leaks: vec![Leak {
+                type_name: "app::Db",
+                outside_references: 2,
+            }],
+            never_resolved: vec!["app::Mailer"],
+        };
+
+        assert_eq!(
+            "Leak report:\n  app::Db is still referenced 2 time(s) outside the container\n  app::Mailer was never resolved",
+            report.to_string()
+        );
+    }
+}
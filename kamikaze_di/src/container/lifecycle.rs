@@ -0,0 +1,364 @@
+use alloc::rc::Rc;
+#[cfg(feature = "std")]
+use core::time::Duration;
+
+use super::Container;
+#[cfg(feature = "std")]
+use crate::clock::Stopwatch;
+use crate::Result;
+
+/// Services that are started and stopped with the application.
+///
+/// Types opt in via
+/// [register_lifecycle()](struct.ContainerBuilder.html#method.register_lifecycle),
+/// and must be shared. [start_all()](struct.Container.html#method.start_all)
+/// builds them, then starts them dependencies first.
+/// [stop_all()](struct.Container.html#method.stop_all) stops them in reverse,
+/// so nothing is stopped while something that depends on it still runs.
+/// Started services are also stopped when the container shuts down, before
+/// any teardowns.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Lifecycle, Resolver, Result};
+/// # use std::cell::RefCell;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Default)]
+/// struct Log(RefCell<Vec<&'static str>>);
+///
+/// struct Database(Rc<Log>);
+///
+/// impl Lifecycle for Database {
+///     fn start(&self) -> Result<()> { Ok(self.0 .0.borrow_mut().push("database up")) }
+///     fn stop(&self) -> Result<()> { Ok(self.0 .0.borrow_mut().push("database down")) }
+/// }
+///
+/// struct Server(Rc<Log>, Rc<Database>);
+///
+/// impl Lifecycle for Server {
+///     fn start(&self) -> Result<()> { Ok(self.0 .0.borrow_mut().push("server up")) }
+///     fn stop(&self) -> Result<()> { Ok(self.0 .0.borrow_mut().push("server down")) }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<Rc<Log>>(Default::default())?;
+/// builder.register_builder::<Rc<Database>, _>(|container| {
+///     Rc::new(Database(container.resolve().unwrap()))
+/// })?;
+/// builder.register_builder::<Rc<Server>, _>(|container| {
+///     Rc::new(Server(container.resolve().unwrap(), container.resolve().unwrap()))
+/// })?;
+/// builder.register_lifecycle::<Rc<Server>>()?;
+/// builder.register_lifecycle::<Rc<Database>>()?;
+///
+/// let container = builder.build();
+///
+/// container.start_all()?;
+/// container.stop_all()?;
+///
+/// let log = container.resolve::<Rc<Log>>()?;
+/// assert_eq!(
+///     vec!["database up", "server up", "server down", "database down"],
+///     *log.0.borrow()
+/// );
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Lifecycle {
+    /// Starts the service, like opening connections or listening on a port.
+    fn start(&self) -> Result<()>;
+
+    /// Stops the service, like draining requests in flight.
+    fn stop(&self) -> Result<()>;
+}
+
+impl<T: Lifecycle + ?Sized> Lifecycle for Rc<T> {
+    fn start(&self) -> Result<()> {
+        (**self).start()
+    }
+
+    fn stop(&self) -> Result<()> {
+        (**self).stop()
+    }
+}
+
+/// How to get a service with a lifecycle, see ContainerBuilder::register_lifecycle().
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Managed {
+    resolve: fn(&Container) -> Result<Rc<dyn Lifecycle>>,
+    #[cfg(feature = "std")]
+    timeout: Option<Duration>,
+}
+
+impl Managed {
+    pub fn of<T: Lifecycle + Clone + 'static>() -> Managed {
+        Managed {
+            resolve: |container| Ok(Rc::new(container.get::<T>()?)),
+            #[cfg(feature = "std")]
+            timeout: None,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn with_timeout<T: Lifecycle + Clone + 'static>(timeout: Duration) -> Managed {
+        Managed {
+            timeout: Some(timeout),
+            ..Managed::of::<T>()
+        }
+    }
+
+    pub fn resolve(&self, type_name: &'static str, container: &Container) -> Result<Started> {
+        Ok(Started {
+            type_name,
+            service: (self.resolve)(container)?,
+            #[cfg(feature = "std")]
+            timeout: self.timeout,
+        })
+    }
+}
+
+/// A service that was started, or is about to be.
+pub(super) struct Started {
+    pub type_name: &'static str,
+    service: Rc<dyn Lifecycle>,
+    #[cfg(feature = "std")]
+    timeout: Option<Duration>,
+}
+
+impl Started {
+    pub fn start(&self) -> Result<()> {
+        debug!("starting {}", self.type_name);
+
+        self.run(|service| service.start())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        debug!("stopping {}", self.type_name);
+
+        self.run(|service| service.stop())
+    }
+
+    /// Services run on the caller's thread, so one that takes too long
+    /// can't be interrupted, only reported once it's done.
+    fn run(&self, step: impl FnOnce(&dyn Lifecycle) -> Result<()>) -> Result<()> {
+        #[cfg(feature = "std")]
+        let begun = Stopwatch::start();
+
+        step(&*self.service)?;
+
+        #[cfg(feature = "std")]
+        if let (Some(timeout), Some(took)) = (self.timeout, begun.elapsed()) {
+            if took > timeout {
+                return Err(
+                    format!("took {:?}, longer than its {:?} timeout", took, timeout).into(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for Started {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Started")
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+    use std::cell::RefCell;
+
+    struct Service {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+        fail_on: Option<&'static str>,
+    }
+
+    impl Service {
+        fn record(&self, step: &'static str) -> Result<()> {
+            self.log
+                .borrow_mut()
+                .push(format!("{} {}", step, self.name));
+
+            match self.fail_on {
+                Some(fail_on) if fail_on == step => Err(format!("{} broke", self.name).into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Lifecycle for Service {
+        fn start(&self) -> Result<()> {
+            self.record("start")
+        }
+
+        fn stop(&self) -> Result<()> {
+            self.record("stop")
+        }
+    }
+
+    #[derive(Clone)]
+    struct Queue(Rc<Service>);
+    #[derive(Clone)]
+    struct Worker(Rc<Service>);
+
+    impl Lifecycle for Queue {
+        fn start(&self) -> Result<()> {
+            self.0.start()
+        }
+        fn stop(&self) -> Result<()> {
+            self.0.stop()
+        }
+    }
+
+    impl Lifecycle for Worker {
+        fn start(&self) -> Result<()> {
+            self.0.start()
+        }
+        fn stop(&self) -> Result<()> {
+            self.0.stop()
+        }
+    }
+
+    fn builder(log: &Rc<RefCell<Vec<String>>>, fail_on: Option<&'static str>) -> ContainerBuilder {
+        let queue_log = log.clone();
+        let worker_log = log.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_builder::<Queue, _>(move |_| {
+                Queue(Rc::new(Service {
+                    name: "queue",
+                    log: queue_log,
+                    fail_on: None,
+                }))
+            })
+            .unwrap();
+        builder
+            .register_builder::<Worker, _>(move |container| {
+                container.resolve::<Queue>().unwrap();
+
+                Worker(Rc::new(Service {
+                    name: "worker",
+                    log: worker_log,
+                    fail_on,
+                }))
+            })
+            .unwrap();
+        builder.register_lifecycle::<Worker>().unwrap();
+        builder.register_lifecycle::<Queue>().unwrap();
+
+        builder
+    }
+
+    #[test]
+    fn failing_starts_stop_what_already_started() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let container = builder(&log, Some("start")).build();
+
+        let error = container.start_all().unwrap_err();
+
+        assert!(error.to_string().ends_with("Worker: worker broke"));
+        assert_eq!(
+            vec!["start queue", "start worker", "stop queue"],
+            *log.borrow()
+        );
+
+        // nothing is left to stop
+        container.stop_all().unwrap();
+        assert_eq!(3, log.borrow().len());
+    }
+
+    #[test]
+    fn stopping_goes_on_after_failures() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let container = builder(&log, Some("stop")).build();
+
+        container.start_all().unwrap();
+        assert!(container.start_all().is_err());
+
+        let error = container.stop_all().unwrap_err();
+
+        assert!(error.to_string().ends_with("Worker: worker broke"));
+        assert_eq!(
+            vec!["start queue", "start worker", "stop worker", "stop queue"],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn started_services_are_stopped_on_shutdown() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        drop({
+            let container = builder(&log, None).build();
+            container.start_all().unwrap();
+            container
+        });
+
+        assert_eq!(
+            vec!["start queue", "start worker", "stop worker", "stop queue"],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn only_shared_items_have_a_lifecycle() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let factory_log = log.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_factory::<Queue, _>(move |_| {
+                Queue(Rc::new(Service {
+                    name: "queue",
+                    log: factory_log.clone(),
+                    fail_on: None,
+                }))
+            })
+            .unwrap();
+        builder.register_lifecycle::<Queue>().unwrap();
+
+        assert!(builder.build().start_all().is_err());
+        assert!(log.borrow().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn slow_services_are_reported() {
+        struct Slow;
+
+        impl Lifecycle for Slow {
+            fn start(&self) -> Result<()> {
+                Ok(())
+            }
+
+            fn stop(&self) -> Result<()> {
+                std::thread::sleep(Duration::from_millis(5));
+                Ok(())
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Rc<Slow>>(Rc::new(Slow)).unwrap();
+        builder
+            .register_lifecycle_with_timeout::<Rc<Slow>>(Duration::from_millis(1))
+            .unwrap();
+
+        let container = builder.build();
+        container.start_all().unwrap();
+
+        let error = container.stop_all().unwrap_err().to_string();
+        assert!(error.contains("longer than its 1ms timeout"));
+    }
+}
@@ -0,0 +1,64 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+/// An item registered under a marker type, checked by the compiler.
+///
+/// Selects between several items of the same type like keys do, see
+/// [register_for()](struct.ContainerBuilder.html#method.register_for) and
+/// [resolve_for()](struct.Container.html#method.resolve_for). Depending
+/// on `Marked<K, T>` works too, it's a regular registration.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Marked, Resolver};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Primary;
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_for::<Primary, String>("primary".to_string())?;
+///
+/// let container = builder.build();
+/// let marked = container.resolve::<Marked<Primary, String>>()?;
+///
+/// assert_eq!("primary", marked.into_inner());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct Marked<K, T> {
+    item: T,
+    key: PhantomData<fn() -> K>,
+}
+
+impl<K, T> Marked<K, T> {
+    /// Marks an item with K.
+    pub fn new(item: T) -> Marked<K, T> {
+        Marked {
+            item,
+            key: PhantomData,
+        }
+    }
+
+    /// Returns the item.
+    pub fn into_inner(self) -> T {
+        self.item
+    }
+}
+
+impl<K, T: Clone> Clone for Marked<K, T> {
+    fn clone(&self) -> Self {
+        Marked::new(self.item.clone())
+    }
+}
+
+impl<K, T: fmt::Debug> fmt::Debug for Marked<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Marked")
+            .field("key", &core::any::type_name::<K>())
+            .field("item", &self.item)
+            .finish()
+    }
+}
@@ -0,0 +1,72 @@
+use core::cell::Cell;
+
+/// Hit/miss counters of a memoized scoped registration.
+///
+/// See [register_scoped_memoized()](struct.ContainerBuilder.html#method.register_scoped_memoized).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct MemoStats {
+    /// Scopes that reused an item built for an earlier scope.
+    pub hits: u64,
+    /// Scopes that had to build a new item.
+    pub misses: u64,
+}
+
+impl MemoStats {
+    /// Share of lookups served from the memo, between 0 and 1.
+    ///
+    /// Returns 0 if nothing was looked up yet.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+
+        if total == 0 {
+            return 0.0;
+        }
+
+        self.hits as f64 / total as f64
+    }
+}
+
+/// Counters shared between a memoized factory and the container.
+#[derive(Default, Debug)]
+pub(super) struct MemoCounters {
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+}
+
+impl MemoCounters {
+    pub fn hit(&self) {
+        self.hits.set(self.hits.get() + 1);
+    }
+
+    pub fn miss(&self) {
+        self.misses.set(self.misses.get() + 1);
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        MemoStats {
+            hits: self.hits.get(),
+            misses: self.misses.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_rate_handles_no_lookups() {
+        assert_eq!(0.0, MemoStats::default().hit_rate());
+    }
+
+    #[test]
+    fn hit_rate_is_a_ratio() {
+        let counters = MemoCounters::default();
+        counters.hit();
+        counters.hit();
+        counters.hit();
+        counters.miss();
+
+        assert_eq!(0.75, counters.stats().hit_rate());
+    }
+}
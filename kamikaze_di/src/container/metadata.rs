@@ -0,0 +1,128 @@
+use alloc::rc::Rc;
+use core::any::{Any, TypeId};
+use core::fmt;
+
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+use crate::Result;
+
+/// Metadata attached to types, by the type of the metadata.
+#[derive(Clone, Default)]
+pub(super) struct Metadata {
+    /// Types carrying each kind of metadata, in the order it was attached.
+    attached: TypeIdMap<Vec<Attached>>,
+}
+
+#[derive(Clone)]
+struct Attached {
+    type_id: TypeId,
+    type_name: &'static str,
+    metadata_name: &'static str,
+    /// The metadata, M boxed as Any.
+    metadata: Rc<dyn Any>,
+}
+
+impl Metadata {
+    pub fn add<T: 'static, M: 'static>(&mut self, metadata: M) -> Result<()> {
+        let attached = self.attached.entry(TypeId::of::<M>()).or_default();
+
+        if attached
+            .iter()
+            .any(|attached| attached.type_id == TypeId::of::<T>())
+        {
+            return Err(format!(
+                "{} already has {} metadata",
+                core::any::type_name::<T>(),
+                core::any::type_name::<M>()
+            )
+            .into());
+        }
+
+        attached.push(Attached {
+            type_id: TypeId::of::<T>(),
+            type_name: core::any::type_name::<T>(),
+            metadata_name: core::any::type_name::<M>(),
+            metadata: Rc::new(metadata),
+        });
+
+        Ok(())
+    }
+
+    /// Adds their metadata, skipping types that already carry the kind.
+    pub fn merge(&mut self, other: Metadata) {
+        for (metadata_type, theirs) in other.attached {
+            let ours = self.attached.entry(metadata_type).or_default();
+
+            for attached in theirs {
+                if !ours.iter().any(|item| item.type_id == attached.type_id) {
+                    ours.push(attached);
+                }
+            }
+        }
+    }
+
+    pub fn of<T: 'static, M: 'static>(&self) -> Option<&M> {
+        self.attached(TypeId::of::<M>())
+            .iter()
+            .find(|attached| attached.type_id == TypeId::of::<T>())
+            .and_then(|attached| attached.metadata.downcast_ref())
+    }
+
+    /// Names of the types carrying M, with their metadata.
+    pub fn all<M: 'static>(&self) -> Vec<(&'static str, &M)> {
+        self.attached(TypeId::of::<M>())
+            .iter()
+            .filter_map(|attached| Some((attached.type_name, attached.metadata.downcast_ref()?)))
+            .collect()
+    }
+
+    fn attached(&self, metadata_type: TypeId) -> &[Attached] {
+        self.attached
+            .get(&metadata_type)
+            .map_or(&[], |attached| attached)
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut attached: Vec<_> = self
+            .attached
+            .values()
+            .flatten()
+            .map(|attached| (attached.type_name, attached.metadata_name))
+            .collect();
+
+        attached.sort_unstable();
+
+        f.debug_list().entries(attached).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Route(&'static str);
+
+    #[test]
+    fn metadata_is_kept_by_kind() {
+        let mut metadata = Metadata::default();
+        metadata.add::<u32, Route>(Route("/users")).unwrap();
+        metadata.add::<u64, Route>(Route("/orders")).unwrap();
+        metadata.add::<u32, &str>("users").unwrap();
+
+        assert_eq!(Some(&Route("/users")), metadata.of::<u32, Route>());
+        assert_eq!(None, metadata.of::<i8, Route>());
+        assert_eq!(
+            vec![("u32", &Route("/users")), ("u64", &Route("/orders"))],
+            metadata.all::<Route>()
+        );
+
+        let error = metadata.add::<u32, Route>(Route("/")).unwrap_err();
+        assert_eq!(
+            "u32 already has kamikaze_di::container::metadata::tests::Route metadata",
+            error.to_string()
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use core::any::TypeId;
+use core::fmt;
+
+use super::builder::ContainerBuilder;
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+use crate::Result;
+
+/// Decorates the type a middleware was added for.
+type Apply = Box<dyn FnOnce(&mut ContainerBuilder) -> Result<()>>;
+
+/// Middleware of every type, see ContainerBuilder::add_middleware().
+#[derive(Default)]
+pub(super) struct Pipelines {
+    pipelines: TypeIdMap<Vec<Middleware>>,
+}
+
+struct Middleware {
+    priority: i32,
+    apply: Apply,
+}
+
+impl Pipelines {
+    pub fn add(&mut self, type_id: TypeId, priority: i32, apply: Apply) {
+        self.pipelines
+            .entry(type_id)
+            .or_default()
+            .push(Middleware { priority, apply });
+    }
+
+    pub fn merge(&mut self, other: Pipelines) {
+        for (type_id, theirs) in other.pipelines {
+            self.pipelines.entry(type_id).or_default().extend(theirs);
+        }
+    }
+
+    /// Wraps every type in its middleware, lowest priority innermost.
+    pub fn apply(self, builder: &mut ContainerBuilder) {
+        for (_, mut pipeline) in self.pipelines {
+            // stable, so equal priorities keep the order they were added in
+            pipeline.sort_by_key(|middleware| middleware.priority);
+
+            for middleware in pipeline {
+                if let Err(error) = (middleware.apply)(builder) {
+                    warn!("could not add middleware: {}", error);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Pipelines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pipelines")
+            .field("types", &self.pipelines.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, MergePolicy, Resolver};
+
+    #[test]
+    fn middleware_wraps_by_priority() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<String>("client".to_string()).unwrap();
+        builder
+            .add_middleware::<String, _>(10, |inner, _| format!("retry({})", inner))
+            .unwrap();
+        builder
+            .add_middleware::<String, _>(20, |inner, _| format!("log({})", inner))
+            .unwrap();
+        builder
+            .add_middleware::<String, _>(10, |inner, _| format!("metrics({})", inner))
+            .unwrap();
+
+        let container = builder.build();
+
+        assert_eq!(
+            "log(metrics(retry(client)))",
+            container.resolve::<String>().unwrap()
+        );
+    }
+
+    #[test]
+    fn merged_middleware_joins_the_pipeline() {
+        let mut core = ContainerBuilder::new();
+        core.register::<String>("client".to_string()).unwrap();
+        core.add_middleware::<String, _>(0, |inner, _| format!("retry({})", inner))
+            .unwrap();
+
+        let mut monitoring = ContainerBuilder::new();
+        monitoring.register::<String>("other".to_string()).unwrap();
+        monitoring
+            .add_middleware::<String, _>(5, |inner, _| format!("log({})", inner))
+            .unwrap();
+
+        core.merge(monitoring, MergePolicy::KeepFirst).unwrap();
+
+        assert_eq!(
+            "log(retry(client))",
+            core.build().resolve::<String>().unwrap()
+        );
+    }
+
+    #[test]
+    fn middleware_needs_a_registration() {
+        let mut builder = ContainerBuilder::new();
+
+        assert!(builder
+            .add_middleware::<String, _>(0, |inner, _| inner)
+            .is_err());
+    }
+}
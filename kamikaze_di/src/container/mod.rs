@@ -1,37 +1,282 @@
+pub mod arena;
+pub mod assisted;
+#[cfg(feature = "auto-register")]
+pub mod auto;
+pub mod bridge;
 pub mod builder;
+pub mod call;
+pub mod deferred;
+pub mod events;
+pub mod explain;
+pub mod fallback;
+pub mod fields;
+pub mod generic;
+pub mod graph;
+pub mod handle;
+pub mod initialize;
 pub mod injector;
+pub mod interceptor;
+pub mod lazy;
+pub mod leaks;
+pub mod lifecycle;
+pub mod marked;
+pub mod memo;
+pub mod module;
+pub mod mutable;
+pub mod null;
+pub mod observer;
+pub mod overrides;
+pub mod pool;
+pub mod provider;
 pub mod resolver;
+pub mod scope;
+pub mod stats;
+pub mod swap;
+pub mod usage;
+pub mod validation;
 
 mod cycle;
+mod describe;
+mod erased;
+mod metadata;
+mod middleware;
+mod pending;
+#[cfg(feature = "std")]
+mod per_thread;
+#[cfg(feature = "specialization")]
+mod specialized;
+mod tags;
+mod trace;
 
-use std::any::{Any, TypeId};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use alloc::collections::BTreeMap;
+use alloc::rc::{Rc, Weak};
+use core::any::{Any, TypeId};
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::panic::Location;
 
+use crate::collections::{HashMap, TypeIdMap, TypeIdSet};
+use crate::error::{Error, ErrorKind, InvariantViolation};
+use crate::prelude::*;
 use crate::Result;
+use arena::{Arena, ArenaHandle};
+use assisted::Assisted;
+use bridge::{Bridge, ExternalResolver};
+use builder::{ContainerBuilder, DefaultFallback};
+use call::{Callable, InjectAll};
 use cycle::CycleStopper;
+use deferred::{Deferrals, Deferred};
+use describe::Described;
+use erased::{ErasedBuilder, ErasedFactory};
+use events::{ContainerEvent, EventLog};
+use explain::{Explainer, ResolutionTrace, ResolvedVia};
+use fallback::{Fallback, Fallbacks};
+use fields::InjectFields;
+use generic::GenericFactory;
+use graph::DependencyGraph;
+use handle::ContainerHandle;
+use initialize::Initializer;
+use injector::Injector;
+use interceptor::{Interceptor, Interceptors, Resolution};
+use leaks::{Leak, LeakCheck, LeakReport};
+use lifecycle::{Managed, Started};
+use marked::Marked;
+use memo::{MemoCounters, MemoStats};
+use metadata::Metadata;
+use mutable::MutableContainer;
+use observer::{Observed, Observer, Observers};
+use overrides::{ActiveOverride, OverrideGuard, OverrideStack, Overrides};
+use pending::{Deadline, Pending, PendingResolve, Trail};
+use pool::{Pool, PooledHandle};
+use scope::{RequestScope, ScopeGuard, ScopeStack};
+use stats::{StatsRecorder, StatsReport};
+use swap::Swap;
+use tags::Tags;
+use trace::Depth;
+use usage::{LifetimeReport, UsageTracker};
+use validation::{CompositionReport, Declaration, Dependency, InvalidValues};
 
 /// Dependency container. Can be used with Resolver or Injector.
 ///
 /// See [Injector](trait.Injector.html) and [Resolver](trait.Resolver.html) on how to use.
-/// Use the [ContainerBuilder](struct.ContainerBuilder.html) to set up containers.
-#[derive(Debug)]
+/// Use the [ContainerBuilder](struct.ContainerBuilder.html) to set up containers,
+/// everything is registered there before the container is built.
+///
+/// Built containers only resolve, and describe what they were built with:
+/// registrations can't change once they're built. Build a
+/// [MutableContainer](struct.MutableContainer.html) for the few places
+/// that need to replace or remove registrations after that, like tests.
+///
+/// Containers are cheap to clone, and clones share everything, including
+/// items built later on. See [fork()](struct.Container.html#method.fork)
+/// for an independent copy.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_builder::<Rc<String>, _>(|_| Rc::new("built once".to_string()))?;
+///
+/// let container = builder.build();
+/// let clone = container.clone();
+///
+/// assert!(Rc::ptr_eq(
+///     &clone.resolve::<Rc<String>>()?,
+///     &container.resolve::<Rc<String>>()?
+/// ));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
 pub struct Container {
-    resolvers: RefCell<HashMap<TypeId, Resolver>>,
+    inner: Rc<Inner>,
+}
+
+impl fmt::Debug for Container {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let registrations: BTreeMap<_, _> = self
+            .registrations()
+            .map(|registration| (registration.type_name, registration.resolver_type))
+            .collect();
+
+        f.debug_struct("Container")
+            .field("registrations", &registrations)
+            .field("scope", &self.inner.scopes.current())
+            .finish()
+    }
+}
+
+/// Container state lives behind an Rc so handles like [Lazy](struct.Lazy.html)
+/// and [Provider](struct.Provider.html)
+/// can hold on to it weakly.
+#[derive(Default, Debug)]
+struct Inner {
+    resolvers: RefCell<TypeIdMap<Resolver>>,
     cycle_stopper: CycleStopper,
+    scopes: ScopeStack,
+    transformers: Rc<TypeIdMap<Box<dyn Any>>>,
+    declarations: TypeIdMap<Declaration>,
+    overrides: OverrideStack,
+    teardowns: RefCell<TypeIdMap<Teardown>>,
+    /// Shared items, in the order they were created.
+    constructed: RefCell<Vec<TypeId>>,
+    memo_counters: TypeIdMap<Rc<MemoCounters>>,
+    collections: Rc<TypeIdMap<Collection>>,
+    keyed: Rc<TypeIdMap<Keyed>>,
+    named: Rc<HashMap<String, Rc<dyn Any>>>,
+    registrations: RefCell<TypeIdMap<Registration>>,
+    usage: UsageTracker,
+    initializers: Rc<TypeIdMap<Initializer>>,
+    /// Services with a lifecycle, see ContainerBuilder::register_lifecycle().
+    lifecycles: TypeIdMap<Managed>,
+    /// Services start_all() started, in the order they were started.
+    started: RefCell<Vec<Started>>,
+    /// Pending<T> for every T registered as a future, until it's done.
+    pending: RefCell<TypeIdMap<Rc<dyn Any>>>,
+    /// Pending items being polled, see resolve_async_timeout().
+    trail: Trail,
+    /// See ContainerBuilder::async_timeout().
+    async_timeout: Option<core::time::Duration>,
+    interceptors: RefCell<Interceptors>,
+    fallbacks: RefCell<Fallbacks>,
+    observers: RefCell<Observers>,
+    warmers: TypeIdMap<Warmer>,
+    /// Startup priorities, see ContainerBuilder::register_eager_with_priority().
+    priorities: TypeIdMap<i32>,
+    /// Deprecation notes, see ContainerBuilder::deprecate().
+    deprecations: TypeIdMap<&'static str>,
+    /// Shared Rcs to look for leaks in, see ContainerBuilder::watch_for_leaks().
+    leak_checks: TypeIdMap<LeakCheck>,
+    /// Types whose items move on to the next generation, see rebuild_with().
+    carry_overs: TypeIdSet,
+    /// Box<FactoryWithArgs<T, A>> for every T registered with arguments.
+    arg_factories: TypeIdMap<Rc<RefCell<Box<dyn Any>>>>,
+    /// Shared items resolved at least once, so resolving them again skips
+    /// the resolver lookup. Entries go away when the item is replaced.
+    singletons: RefCell<TypeIdMap<Rc<dyn Any>>>,
+    /// Builders that built their item and can run again, see reset().
+    rebuilders: RefCell<TypeIdMap<ErasedBuilder>>,
+    /// Types whose builder panicked, taking the registration with it.
+    panicked: RefCell<TypeIdSet>,
+    /// Errors from one-shot builders, or their transformers, that took the
+    /// registration with them.
+    rejected: RefCell<TypeIdMap<Error>>,
+    events: EventLog,
+    stats: StatsRecorder,
+    /// Records resolutions while explaining, see explain().
+    explainer: Explainer,
+    /// Resolutions in progress, for tracing.
+    depth: Depth,
+    /// Handles to fill in once resolving is done, see resolve_deferred().
+    deferred: Deferrals,
+    /// See ContainerBuilder::fall_back_to_default().
+    default_fallback: DefaultFallback,
+    /// Generic factories, see ContainerBuilder::register_generic().
+    generics: Rc<TypeIdSet>,
+    tags: Rc<Tags>,
+    /// See ContainerBuilder::add_metadata().
+    metadata: Rc<Metadata>,
+    /// Small values, see ContainerBuilder::register_in_arena().
+    arena: Rc<Arena>,
+    /// Freeze on the first resolution, see ContainerBuilder::freeze_on_first_resolve().
+    strict: Cell<bool>,
+    /// Registrations can't change, see MutableContainer::freeze().
+    frozen: Cell<bool>,
+}
+
+/// What was registered where, for diagnostics.
+#[derive(Clone, Copy, Debug)]
+struct Registration {
+    type_name: &'static str,
+    location: &'static Location<'static>,
+}
+
+impl Registration {
+    #[track_caller]
+    fn of<T: 'static>() -> Registration {
+        Registration {
+            type_name: core::any::type_name::<T>(),
+            location: Location::caller(),
+        }
+    }
+}
+
+/// Type erased teardown callback, see ContainerBuilder::register_teardown().
+struct Teardown(Box<dyn FnOnce(Rc<dyn Any>)>);
+
+impl fmt::Debug for Teardown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Teardown")
+    }
 }
 
 // TODO these can be trait aliases, once that feature becomes stable
-/// Factories can be called multiple times
-pub type Factory<T> = dyn FnMut(&Container) -> T;
-/// Builders will only be called once
-pub type Builder<T> = dyn FnOnce(&Container) -> T;
+/// Transformers post-process items created by factories and builders
+pub type Transformer<T> = dyn Fn(T) -> Result<T>;
+/// Factories with arguments get per call data from the caller
+pub type FactoryWithArgs<T, A> = dyn FnMut(&Container, A) -> T;
+
+/// Runs the builder of a type, see Container::warm_up().
+type Warmer = fn(&Container) -> Result<()>;
+
+/// Priorities and boxed Rc<T>s of a collection, highest priority first.
+type Collection = Vec<(i32, Box<dyn Any>)>;
+
+/// Boxed Rc<T>s by key.
+type Keyed = BTreeMap<String, Box<dyn Any>>;
 
 impl Container {
     /// Creates an empty container.
     ///
-    /// Even though it will be empty, it will still resolve
-    /// dependencies via the [Injector](trait.Injector.html) trait.
+    /// Even though it will be empty, with the `specialization` feature it
+    /// will still resolve dependencies via the [Injector](trait.Injector.html)
+    /// trait.
     ///
     /// # Examples
     /// ```
@@ -51,198 +296,4779 @@ impl Container {
     /// }
     ///
     /// let container: Container = Container::new();
+    /// # #[cfg(feature = "specialization")]
+    /// # {
     /// let x: X = container.inject()?;
     ///
     /// assert_eq!(42, x.inner);
+    /// # }
     /// #
     /// # Ok(())
     /// # }
     /// ```
     pub fn new() -> Container {
+        Container::from_inner(Default::default())
+    }
+
+    fn from_inner(inner: Inner) -> Container {
         Container {
-            resolvers: RefCell::new(Default::default()),
-            cycle_stopper: Default::default(),
+            inner: Rc::new(inner),
         }
     }
 
-    fn has<T: 'static>(&self) -> bool {
-        debug!("has called");
+    /// Enters a new scope.
+    ///
+    /// Dependencies registered via
+    /// [register_scoped()](struct.ContainerBuilder.html#method.register_scoped)
+    /// are created once per scope and dropped when the returned handle
+    /// goes out of scope. Scopes can be nested, scoped dependencies are
+    /// always cached in the innermost one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    ///
+    /// let mut i = 0;
+    /// builder.register_scoped::<i32, _>(move |_| {
+    ///     i += 1;
+    ///     i
+    /// })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// {
+    ///     let scope = container.enter_scope();
+    ///     assert_eq!(1, scope.resolve::<i32>()?);
+    ///     assert_eq!(1, scope.resolve::<i32>()?);
+    /// }
+    ///
+    /// let scope = container.enter_scope();
+    /// assert_eq!(2, scope.resolve::<i32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enter_scope(&self) -> ScopeGuard<'_> {
+        ScopeGuard::new(self, None)
+    }
 
-        let type_id = TypeId::of::<T>();
+    /// Enters a new scope at a named level, like `"session"`.
+    ///
+    /// Dependencies registered for the level via
+    /// [register_in_scope()](struct.ContainerBuilder.html#method.register_in_scope)
+    /// are cached in the innermost scope at that level, even when resolved
+    /// from scopes nested inside it. Plain scoped dependencies are cached in
+    /// the innermost scope, whatever its level.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Player;
+    /// struct Frame;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_in_scope::<Rc<Player>, _>("level", |_| Rc::new(Player))?;
+    /// builder.register_in_scope::<Rc<Frame>, _>("frame", |_| Rc::new(Frame))?;
+    ///
+    /// let container = builder.build();
+    /// let level = container.enter_named_scope("level");
+    ///
+    /// let first = level.enter_named_scope("frame");
+    /// let player = first.resolve::<Rc<Player>>()?;
+    /// let frame = first.resolve::<Rc<Frame>>()?;
+    /// drop(first);
+    ///
+    /// let second = level.enter_named_scope("frame");
+    /// assert!(Rc::ptr_eq(&player, &second.resolve::<Rc<Player>>()?));
+    /// assert!(!Rc::ptr_eq(&frame, &second.resolve::<Rc<Frame>>()?));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enter_named_scope(&self, level: &'static str) -> ScopeGuard<'_> {
+        ScopeGuard::new(self, Some(level))
+    }
+
+    /// Enters a new scope, seeded with an item.
+    ///
+    /// Shorthand for [enter_scope()](struct.Container.html#method.enter_scope)
+    /// followed by [provide()](struct.ScopeGuard.html#method.provide).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, RequestContext, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let container = ContainerBuilder::new().build();
+    ///
+    /// let scope = container.enter_scope_with(RequestContext::new("Europe/Bucharest", "ro-RO", "RON"));
+    ///
+    /// assert_eq!("RON", scope.resolve::<RequestContext>()?.currency);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enter_scope_with<T: 'static>(&self, item: T) -> ScopeGuard<'_> {
+        let scope = self.enter_scope();
+        scope.provide(item);
 
-        self.resolvers.borrow().contains_key(&type_id)
+        scope
     }
 
-    fn get<T: Clone + 'static>(&self) -> Result<T> {
-        debug!("resolving type via .get()");
+    /// Begins a request, with a scope of its own.
+    ///
+    /// The [RequestScope](struct.RequestScope.html) caches the request's
+    /// scoped dependencies and resolves everything else from the container.
+    /// It doesn't borrow the container, so adapters for web frameworks can
+    /// keep it with the request, and drop it once the response is sent.
+    pub fn begin_request(&self) -> RequestScope {
+        RequestScope::new(self)
+    }
 
-        let type_id = TypeId::of::<T>();
-        let _guard = self.cycle_stopper.track(type_id);
+    /// Checks declared dependencies without building anything.
+    ///
+    /// Reports every declared dependency that is neither registered nor
+    /// auto-resolvable, every dependency cycle, and every item registered
+    /// with invalid values, like config sections failing their checks, in a
+    /// single [ValidationReport](struct.ValidationReport.html). Only dependencies declared via
+    /// [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies)
+    /// are checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, ErrorKind};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder::<i64, _>(|_| 42)?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    ///
+    /// let container = builder.build();
+    ///
+    /// match container.validate().unwrap_err().kind() {
+    ///     ErrorKind::Validation(report) => assert_eq!(vec![("i64", "i32")], report.missing),
+    ///     _ => unreachable!(),
+    /// }
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        debug!("validating container");
 
-        let resolver_type = self.get_resolver_type(type_id);
-        debug!("resolving via {:?}", resolver_type);
+        let mut report = validation::validate(&self.inner.declarations, |type_id| {
+            self.is_resolvable(type_id)
+        });
+        report.invalid = self.invalid_values();
 
-        match resolver_type {
-            Some(ResolverType::Factory) => self.call_factory::<T>(type_id),
-            Some(ResolverType::Builder) => {
-                self.consume_builder::<T>()?;
-                self.get_shared(type_id)
-            }
-            Some(ResolverType::Shared) => self.get_shared(type_id),
-            None => Err(format!("Type not registered: {:?}", type_id).into()),
+        if report.is_empty() {
+            Ok(())
+        } else {
+            Err(ErrorKind::Validation(report).into())
         }
     }
 
-    fn get_resolver_type(&self, type_id: TypeId) -> Option<ResolverType> {
-        self.resolvers.borrow().get(&type_id).map(|r| r.into())
+    /// Problems with the values of registered items, by type name.
+    fn invalid_values(&self) -> Vec<(&'static str, String)> {
+        self.inner
+            .metadata
+            .all::<InvalidValues>()
+            .into_iter()
+            .flat_map(|(type_name, invalid)| {
+                invalid
+                    .0
+                    .iter()
+                    .map(move |problem| (type_name, problem.clone()))
+            })
+            .collect()
     }
 
-    fn call_factory<T: 'static>(&self, type_id: TypeId) -> Result<T> {
-        if let Resolver::Factory(cell) = self
-            .resolvers
-            .borrow()
-            .get(&type_id)
-            .expect("could not find a registered factory")
-        {
-            let mut boxed = cell.borrow_mut();
-            let factory = boxed
-                .downcast_mut::<Box<Factory<T>>>()
-                .expect("could not downcast factory");
-
-            let item = factory(self);
-
-            return Ok(item);
-        }
+    /// Lists registered types and the dependencies declared between them.
+    ///
+    /// Render it with [to_dot()](struct.DependencyGraph.html#method.to_dot)
+    /// to get a picture of the wiring.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i32>(42)?;
+    /// builder.register_builder::<i64, _>(|_| 42)?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    ///
+    /// let graph = builder.build().dependency_graph();
+    ///
+    /// assert_eq!(vec!["i32", "i64"], graph.nodes);
+    /// assert_eq!(vec![("i64", "i32")], graph.edges);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let registrations = self.inner.registrations.borrow();
+        let registered = registrations
+            .values()
+            .map(|registration| registration.type_name);
 
-        panic!("Type {:?} not registered as factory", type_id)
+        graph::build(&self.inner.declarations, registered)
     }
 
-    fn consume_builder<T: 'static>(&self) -> Result<()> {
-        let type_id = TypeId::of::<T>();
+    /// Describes every registration as JSON, for tools outside the app.
+    ///
+    /// Dashboards, architecture linters or docs generators can read the
+    /// wiring from it without linking against the app. Registrations are
+    /// sorted by type name, each with its kind, its scope, whether it's
+    /// built, its tags, its declared dependencies and where it was
+    /// registered:
+    ///
+    /// ```json
+    /// {"registrations":[{"type_name":"u32","kind":"Shared","scope":"Singleton",
+    ///   "built":true,"tags":[],"dependencies":[],
+    ///   "registered_at":{"file":"src/main.rs","line":4,"column":9}}]}
+    /// ```
+    ///
+    /// As with [dependency_graph()](struct.Container.html#method.dependency_graph),
+    /// only declared dependencies are known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i32>(42)?;
+    /// builder.register_factory::<i64, _>(|_| 42)?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    /// builder.tag::<i64>("numbers")?;
+    ///
+    /// let json = builder.build().describe_json();
+    ///
+    /// assert!(json.starts_with(r#"{"registrations":[{"type_name":"i32","kind":"Shared""#));
+    /// assert!(json.contains(r#""scope":"Transient","built":false,"tags":["numbers"],"dependencies":["i32"]"#));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn describe_json(&self) -> String {
+        let resolvers = self.inner.resolvers.borrow();
+        let registrations = self.inner.registrations.borrow();
 
-        let builder = if let Resolver::Builder(boxed) = self
-            .resolvers
-            .borrow_mut()
-            .remove(&type_id)
-            .expect("could not find a registered resolver")
-        {
-            boxed
-                .downcast::<Box<Builder<T>>>()
-                .expect("could not downcast builder")
-        } else {
-            panic!("Type {:?} not registered as builder", type_id)
-        };
+        let mut described: Vec<_> = registrations
+            .iter()
+            .filter_map(|(type_id, registration)| {
+                let resolver_type = ResolverType::from(resolvers.get(type_id)?);
+                let dependencies = self
+                    .inner
+                    .declarations
+                    .get(type_id)
+                    .map(|declaration| {
+                        declaration
+                            .dependencies
+                            .iter()
+                            .map(Dependency::type_name)
+                            .collect()
+                    })
+                    .unwrap_or_default();
 
-        let item = builder(self);
-        let resolver = Resolver::Shared(Box::new(item));
+                Some(Described {
+                    info: RegistrationInfo {
+                        type_name: registration.type_name,
+                        resolver_type,
+                        built: resolver_type == ResolverType::Shared,
+                        registered_at: registration.location,
+                    },
+                    scope: usage::lifetime_of(resolver_type),
+                    tags: self.inner.tags.of(*type_id),
+                    dependencies,
+                })
+            })
+            .collect();
 
-        self.insert::<T>(resolver)
-    }
+        described.sort_by_key(|registration| registration.info.type_name);
 
-    fn get_shared<T: Clone + 'static>(&self, type_id: TypeId) -> Result<T> {
-        if let Resolver::Shared(boxed_any) = self
-            .resolvers
-            .borrow()
-            .get(&type_id)
-            .expect("could not find a registered type")
-        {
-            use std::borrow::Borrow;
+        describe::to_json(&described)
+    }
 
-            let borrowed_any: &dyn Any = boxed_any.borrow();
-            let borrowed_item: &T = borrowed_any
-                .downcast_ref()
-                .expect("could not downcast shared object");
+    /// Runs every wiring check at once.
+    ///
+    /// Validates the container, like [validate()](struct.Container.html#method.validate),
+    /// lists registered types that aren't roots and that nothing declares a
+    /// dependency on, and lists dependencies between forbidden pairs of
+    /// modules, like [assert_no_module_dependency()](struct.Container.html#method.assert_no_module_dependency).
+    /// Like the other checks, it only knows about declared dependencies.
+    ///
+    /// Usually run through [composition_root!](macro.composition_root.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Dependencies};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i32>(42)?;
+    /// builder.register::<u8>(1)?;
+    /// builder.register_builder::<i64, _>(|_| 42)?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    ///
+    /// let container = builder.build();
+    /// let report = container.composition_report(&<(i64,)>::dependencies(), &[]);
+    ///
+    /// assert_eq!(vec!["u8"], report.unused);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn composition_report(
+        &self,
+        roots: &[Dependency],
+        forbidden: &[(&str, &str)],
+    ) -> CompositionReport {
+        debug!("checking composition root");
 
-            return Ok(borrowed_item.clone());
-        }
+        let registrations = self.inner.registrations.borrow();
+        let registered = registrations
+            .iter()
+            .map(|(type_id, registration)| (type_id, registration.type_name));
 
-        panic!("Type {:?} not registered as shared dependency", type_id)
+        validation::composition_report(
+            &self.inner.declarations,
+            |type_id| self.is_resolvable(type_id),
+            registered,
+            &self.inner.deprecations,
+            roots,
+            forbidden,
+        )
     }
 
-    fn insert<T: 'static>(&self, resolver: Resolver) -> Result<()> {
-        debug!("inerting new type");
-
-        let type_id = TypeId::of::<T>();
+    /// Finds a chain of declared dependencies from From to To.
+    ///
+    /// Returns the type names along the chain, both ends included. Only
+    /// dependencies declared via
+    /// [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies)
+    /// are followed.
+    pub fn dependency_path<From: 'static, To: 'static>(&self) -> Option<Vec<&'static str>> {
+        validation::find_path(
+            &self.inner.declarations,
+            TypeId::of::<From>(),
+            TypeId::of::<To>(),
+        )
+    }
 
-        if self.has::<T>() {
-            return Err(format!("Container already has {:?}", type_id).into());
+    /// Panics if From depends on To, directly or not.
+    ///
+    /// Meant for architecture tests that enforce layering, see
+    /// [dependency_path()](struct.Container.html#method.dependency_path).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// struct WebHandler;
+    /// struct UserService;
+    /// struct DbPool;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.declare_dependencies::<WebHandler, (UserService,)>();
+    /// builder.declare_dependencies::<UserService, (DbPool,)>();
+    ///
+    /// let container = builder.build();
+    ///
+    /// container.assert_no_path::<DbPool, WebHandler>();
+    /// assert!(container.dependency_path::<WebHandler, DbPool>().is_some());
+    /// ```
+    pub fn assert_no_path<From: 'static, To: 'static>(&self) {
+        if let Some(path) = self.dependency_path::<From, To>() {
+            panic!("Forbidden dependency path: {}", path.join(" -> "));
         }
+    }
 
-        self.resolvers.borrow_mut().insert(type_id, resolver);
+    /// Panics if any type in one module declares a dependency on a type in another.
+    ///
+    /// Modules are given as paths, like `my_app::web`, and contain every type
+    /// whose name starts with them, including types in submodules.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn module_of<T>() -> &'static str {
+    /// #     let name = std::any::type_name::<T>();
+    /// #     &name[..name.rfind("::").unwrap()]
+    /// # }
+    /// mod web { pub struct Handler; }
+    /// mod db { pub struct Pool; }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.declare_dependencies::<web::Handler, (db::Pool,)>();
+    ///
+    /// let container = builder.build();
+    ///
+    /// // like container.assert_no_module_dependency("my_app::db", "my_app::web")
+    /// container.assert_no_module_dependency(module_of::<db::Pool>(), module_of::<web::Handler>());
+    /// ```
+    pub fn assert_no_module_dependency(&self, from: &str, to: &str) {
+        let found = validation::module_dependencies(&self.inner.declarations, from, to);
 
-        Ok(())
-    }
-}
+        if !found.is_empty() {
+            let found: Vec<_> = found
+                .iter()
+                .map(|(dependent, dependency)| format!("{} -> {}", dependent, dependency))
+                .collect();
 
-impl Default for Container {
-    fn default() -> Container {
-        Container::new()
+            panic!(
+                "Forbidden dependencies from {} on {}: {}",
+                from,
+                to,
+                found.join(", ")
+            );
+        }
     }
-}
 
-#[derive(Debug)]
-enum Resolver {
-    /// Factories get called multiple times
-    ///
-    /// Factories are called by the container, and they themselves will
-    /// call container.resolve() as they see fit. This means we can't
-    /// own a mutable borrow to the resolvers collection during the
-    /// calls. Thus we must use RefCell.
-    Factory(RefCell<Box<dyn Any>>),
-    Builder(Box<dyn Any>),
-    Shared(Box<dyn Any>),
-}
+    /// See [MutableContainer::push_overrides()](struct.MutableContainer.html#method.push_overrides).
+    pub(crate) fn push_overrides(&self, overrides: Overrides) {
+        debug!("pushing overrides");
 
-#[derive(Debug)]
-enum ResolverType {
-    Factory,
-    Builder,
-    Shared,
-}
+        self.inner.overrides.push(overrides);
+        self.inner.events.record(ContainerEvent::OverridesPushed);
+    }
 
-impl From<&Resolver> for ResolverType {
-    fn from(other: &Resolver) -> Self {
-        use ResolverType::*;
+    /// See [MutableContainer::pop_overrides()](struct.MutableContainer.html#method.pop_overrides).
+    pub(crate) fn pop_overrides(&self) -> Option<Overrides> {
+        debug!("popping overrides");
 
-        match other {
-            Resolver::Factory(_) => Factory,
-            Resolver::Builder(_) => Builder,
-            Resolver::Shared(_) => Shared,
+        let popped = self.inner.overrides.pop();
+        if popped.is_some() {
+            self.inner.events.record(ContainerEvent::OverridesPopped);
         }
+
+        popped
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::builder::ContainerBuilder;
-    use crate::Resolver;
+    /// See [MutableContainer::override_scoped()](struct.MutableContainer.html#method.override_scoped).
+    #[track_caller]
+    pub(crate) fn override_scoped<T: 'static>(&self, stub: T) -> OverrideGuard<'_> {
+        debug!("overriding type until guard is dropped");
 
-    #[test]
-    #[should_panic(expected = "Circular dependency")]
-    fn panics_on_circular_dendencies() {
-        let mut builder = ContainerBuilder::new();
+        let mut overrides = Overrides::new();
+        overrides.register(stub);
 
-        builder
-            .register_factory::<i32, _>(|container| {
-                use std::convert::TryInto;
+        OverrideGuard::new(self, overrides)
+    }
 
-                let base: i64 = container.resolve().unwrap();
-                let base: i32 = base.try_into().unwrap();
-                base - 1
+    /// Lists registered dependencies, sorted by type name.
+    ///
+    /// Overrides and scope seeds are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver, ResolverType};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(42)?;
+    /// builder.register_builder::<u64, _>(|_| 42)?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let pending: Vec<_> = container
+    ///     .registrations()
+    ///     .filter(|registration| !registration.built)
+    ///     .map(|registration| registration.type_name)
+    ///     .collect();
+    /// assert_eq!(vec!["u64"], pending);
+    ///
+    /// container.resolve::<u64>()?;
+    /// assert!(container.registrations().all(|registration| {
+    ///     registration.built && registration.resolver_type == ResolverType::Shared
+    /// }));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn registrations(&self) -> impl Iterator<Item = RegistrationInfo> {
+        let resolvers = self.inner.resolvers.borrow();
+        let registrations = self.inner.registrations.borrow();
+
+        let mut found: Vec<_> = registrations
+            .iter()
+            .filter_map(|(type_id, registration)| {
+                let resolver_type = ResolverType::from(resolvers.get(type_id)?);
+
+                Some(RegistrationInfo {
+                    type_name: registration.type_name,
+                    resolver_type,
+                    built: resolver_type == ResolverType::Shared,
+                    registered_at: registration.location,
+                })
+            })
+            .collect();
+
+        found.sort_by_key(|registration| registration.type_name);
+
+        found.into_iter()
+    }
+
+    /// Compares registered lifetimes to how dependencies were used so far.
+    ///
+    /// Suggests a singleton for transient dependencies resolved only once,
+    /// and a scoped lifetime for singletons only ever resolved inside one
+    /// scope. Usage is only tracked for registered dependencies, overrides
+    /// and scope seeds don't count. Best run after a representative workload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver, Scope};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_factory::<u32, _>(|_| 42)?;
+    ///
+    /// let container = builder.build();
+    /// container.resolve::<u32>()?;
+    ///
+    /// let report = container.lifetime_report();
+    ///
+    /// assert_eq!("u32", report.suggestions[0].type_name);
+    /// assert_eq!(Scope::Singleton, report.suggestions[0].suggested);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lifetime_report(&self) -> LifetimeReport {
+        self.inner.usage.report(|type_id| {
+            let type_name = self.inner.type_name(type_id)?;
+            let resolver_type = self.get_resolver_type(type_id)?;
+
+            Some((type_name, resolver_type))
+        })
+    }
+
+    /// Lists shared items still referenced outside the container, and
+    /// registrations that were never resolved.
+    ///
+    /// Meant for shutdown, once everything using the container is gone:
+    /// references left over by then point to dangling services, and types
+    /// never resolved are likely dead registrations. Only `Rc`s watched via
+    /// [watch_for_leaks()](struct.ContainerBuilder.html#method.watch_for_leaks)
+    /// or registered via [register_rc()](struct.ContainerBuilder.html#method.register_rc)
+    /// are checked for references.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_rc::<String>(Rc::new("db".to_string()))?;
+    /// builder.register::<u32>(42)?;
+    ///
+    /// let container = builder.build();
+    /// let db = container.resolve::<Rc<String>>()?;
+    ///
+    /// let report = container.report_leaks();
+    /// assert_eq!(1, report.leaks[0].outside_references);
+    /// assert_eq!(vec!["u32"], report.never_resolved);
+    ///
+    /// drop(db);
+    /// assert!(container.report_leaks().leaks.is_empty());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn report_leaks(&self) -> LeakReport {
+        let resolvers = self.inner.resolvers.borrow();
+
+        let mut leaks: Vec<_> = self
+            .inner
+            .leak_checks
+            .iter()
+            .filter_map(|(type_id, strong_count)| {
+                let item = match resolvers.get(type_id)? {
+                    Resolver::Shared(item) => item,
+                    _ => return None,
+                };
+
+                // one of them is the container's
+                let outside_references = strong_count(&**item)?.checked_sub(1)?;
+                if outside_references == 0 {
+                    return None;
+                }
+
+                Some(Leak {
+                    type_name: self.inner.type_name(*type_id)?,
+                    outside_references,
+                })
+            })
+            .collect();
+
+        leaks.sort_by_key(|leak| leak.type_name);
+
+        let mut never_resolved: Vec<_> = self
+            .inner
+            .registrations
+            .borrow()
+            .iter()
+            .filter(|(type_id, _)| resolvers.contains_key(*type_id))
+            .filter(|(type_id, _)| !self.inner.usage.was_resolved(**type_id))
+            .map(|(_, registration)| registration.type_name)
+            .collect();
+
+        never_resolved.sort_unstable();
+
+        LeakReport {
+            leaks,
+            never_resolved,
+        }
+    }
+
+    /// See [MutableContainer::set_max_depth()](struct.MutableContainer.html#method.set_max_depth).
+    pub(crate) fn set_max_depth(&self, max_depth: usize) {
+        debug!("limiting resolution depth");
+
+        self.inner.cycle_stopper.set_max_depth(Some(max_depth));
+    }
+
+    /// See [MutableContainer::add_interceptor()](struct.MutableContainer.html#method.add_interceptor).
+    pub(crate) fn add_interceptor(&self, interceptor: Rc<dyn Interceptor>) {
+        debug!("adding interceptor");
+
+        self.inner.interceptors.borrow_mut().0.push(interceptor);
+    }
+
+    /// See [MutableContainer::set_fallback()](struct.MutableContainer.html#method.set_fallback).
+    pub(crate) fn set_fallback(&self, fallback: Rc<dyn Fallback>) {
+        debug!("adding fallback");
+
+        self.inner.fallbacks.borrow_mut().0.push(fallback);
+    }
+
+    /// See [MutableContainer::bridge()](struct.MutableContainer.html#method.bridge).
+    pub(crate) fn bridge(&self, external: Rc<dyn ExternalResolver>) {
+        debug!("bridging to another container");
+
+        self.set_fallback(Rc::new(Bridge(external)));
+    }
+
+    /// Subscribes an observer to what the container does from now on.
+    ///
+    /// Forks and subsets made later keep the observer. See
+    /// [Observer](trait.Observer.html).
+    pub fn subscribe(&self, observer: Rc<dyn Observer>) {
+        debug!("adding observer");
+
+        self.inner.observers.borrow_mut().subscribe(observer);
+    }
+
+    /// Lists the overrides in effect, with where they were made.
+    ///
+    /// Every overridden type is listed once, with the override it resolves
+    /// to, sorted by type name. Handy for printing which test doubles were
+    /// in play when a test fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(42)?;
+    ///
+    /// let container = builder.build_mutable();
+    /// let _stub = container.override_scoped::<u32>(1);
+    ///
+    /// let active = container.active_overrides();
+    ///
+    /// assert_eq!("u32", active[0].type_name);
+    /// assert_eq!(file!(), active[0].overridden_at.file());
+    /// assert!(active[0].registered_at.is_some());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn active_overrides(&self) -> Vec<ActiveOverride> {
+        let registrations = self.inner.registrations.borrow();
+
+        let mut active: Vec<_> = self
+            .inner
+            .overrides
+            .active()
+            .into_iter()
+            .map(|(type_id, registration)| ActiveOverride {
+                type_name: registration.type_name,
+                registered_at: registrations
+                    .get(&type_id)
+                    .map(|registered| registered.location),
+                overridden_at: registration.location,
+            })
+            .collect();
+
+        active.sort_by_key(|active| active.type_name);
+
+        active
+    }
+
+    /// Returns a handle closures can own, see [ContainerHandle](struct.ContainerHandle.html).
+    pub fn handle(&self) -> ContainerHandle {
+        ContainerHandle::new(self)
+    }
+
+    /// See [MutableContainer::freeze()](struct.MutableContainer.html#method.freeze).
+    pub(crate) fn freeze(&self) {
+        debug!("freezing container");
+
+        self.inner.frozen.set(true);
+    }
+
+    /// See [MutableContainer::reopen()](struct.MutableContainer.html#method.reopen).
+    pub(crate) fn reopen(&self) {
+        debug!("reopening container");
+
+        self.inner.strict.set(false);
+        self.inner.frozen.set(false);
+    }
+
+    /// Returns true if registrations can't change, see [freeze()](struct.MutableContainer.html#method.freeze).
+    pub fn is_frozen(&self) -> bool {
+        self.inner.frozen.get()
+    }
+
+    /// See [MutableContainer::replace()](struct.MutableContainer.html#method.replace).
+    #[track_caller]
+    pub(crate) fn replace<T: 'static>(&self, item: T) -> Result<()> {
+        debug!("replacing type");
+
+        self.replace_resolver::<T>(Resolver::Shared(Rc::new(item)))
+    }
+
+    /// See [MutableContainer::replace_factory()](struct.MutableContainer.html#method.replace_factory).
+    #[track_caller]
+    pub(crate) fn replace_factory<T, F>(&self, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("replacing factory");
+
+        let factory = ErasedFactory::new(factory);
+
+        self.replace_resolver::<T>(Resolver::Factory(Rc::new(RefCell::new(factory))))
+    }
+
+    /// See [MutableContainer::swap()](struct.MutableContainer.html#method.swap).
+    #[track_caller]
+    pub(crate) fn swap<T: 'static>(&self, with: impl Into<Swap<T>>) -> Result<()> {
+        debug!("swapping type");
+
+        self.replace_resolver::<T>(with.into().resolver)
+    }
+
+    /// See [MutableContainer::take()](struct.MutableContainer.html#method.take).
+    pub(crate) fn take<T: 'static>(&self) -> Result<T> {
+        debug!("taking item out of the container");
+
+        self.check_open::<T>()?;
+
+        let type_id = TypeId::of::<T>();
+
+        if self.get_resolver_type(type_id) == Some(ResolverType::Builder) {
+            let _guard = self.inner.cycle_stopper.track::<T>()?;
+            self.consume_builder::<T>()?;
+        }
+
+        // the cached singleton is the container's own handle, not a holder
+        let singleton = self.inner.singletons.borrow_mut().remove(&type_id);
+        drop(singleton);
+
+        let mut resolvers = self.inner.resolvers.borrow_mut();
+        let item = match resolvers.remove(&type_id) {
+            Some(Resolver::Shared(item)) => item,
+            Some(other) => {
+                let resolver_type = ResolverType::from(&other);
+                resolvers.insert(type_id, other);
+
+                return Err(format!(
+                    "Can't take {}, it's registered as {:?}, only shared items can be taken",
+                    core::any::type_name::<T>(),
+                    resolver_type
+                )
+                .into());
+            }
+            None => {
+                drop(resolvers);
+
+                return Err(self.not_registered::<T>());
+            }
+        };
+
+        let item = item
+            .downcast::<T>()
+            .expect("could not downcast shared object");
+
+        let item = match Rc::try_unwrap(item) {
+            Ok(item) => item,
+            Err(item) => {
+                let holders = Rc::strong_count(&item) - 1;
+                resolvers.insert(type_id, Resolver::Shared(item));
+
+                return Err(format!(
+                    "Can't take {}, it's still shared with {} other container(s)",
+                    core::any::type_name::<T>(),
+                    holders
+                )
+                .into());
+            }
+        };
+        drop(resolvers);
+
+        self.inner
+            .constructed
+            .borrow_mut()
+            .retain(|constructed| *constructed != type_id);
+        self.inner.registrations.borrow_mut().remove(&type_id);
+        let teardown = self.inner.teardowns.borrow_mut().remove(&type_id);
+        let rebuilder = self.inner.rebuilders.borrow_mut().remove(&type_id);
+        drop(teardown);
+        drop(rebuilder);
+
+        Ok(item)
+    }
+
+    /// See [MutableContainer::remove()](struct.MutableContainer.html#method.remove).
+    pub(crate) fn remove<T: 'static>(&self) -> Result<()> {
+        debug!("removing type");
+
+        self.check_open::<T>()?;
+
+        let type_id = TypeId::of::<T>();
+
+        let resolver = self.inner.resolvers.borrow_mut().remove(&type_id);
+        let pending = self.inner.pending.borrow_mut().remove(&type_id);
+
+        if resolver.is_none() && pending.is_none() {
+            return Err(self.not_registered::<T>());
+        }
+
+        let singleton = self.inner.singletons.borrow_mut().remove(&type_id);
+        let teardown = self.inner.teardowns.borrow_mut().remove(&type_id);
+        let rebuilder = self.inner.rebuilders.borrow_mut().remove(&type_id);
+        self.inner
+            .constructed
+            .borrow_mut()
+            .retain(|constructed| *constructed != type_id);
+        self.inner.registrations.borrow_mut().remove(&type_id);
+
+        // items may hold on to the container, or use it in their teardown,
+        // so they go after all borrows are released
+        drop(singleton);
+        drop(rebuilder);
+        if let (Some(Resolver::Shared(item)), Some(Teardown(teardown))) = (resolver, teardown) {
+            debug!("tearing down {}", core::any::type_name::<T>());
+            teardown(item);
+        }
+        drop(pending);
+
+        self.inner.scopes.forget(type_id);
+
+        Ok(())
+    }
+
+    /// See [MutableContainer::reset()](struct.MutableContainer.html#method.reset).
+    pub(crate) fn reset<T: 'static>(&self) -> Result<()> {
+        debug!("resetting type");
+
+        self.check_open::<T>()?;
+
+        let type_id = TypeId::of::<T>();
+
+        match self.get_resolver_type(type_id) {
+            Some(ResolverType::Shared) => {}
+            Some(ResolverType::Builder) => return Ok(()),
+            Some(other) => {
+                return Err(format!(
+                    "Can't reset {}, it's registered as {:?}, only built items can be reset",
+                    core::any::type_name::<T>(),
+                    other
+                )
+                .into())
+            }
+            None => return Err(self.not_registered::<T>()),
+        }
+
+        let builder = self
+            .inner
+            .rebuilders
+            .borrow_mut()
+            .remove(&type_id)
+            .ok_or_else(|| {
+                format!(
+                    "Can't reset {}, it wasn't built by a builder that can run again",
+                    core::any::type_name::<T>()
+                )
+            })?;
+
+        self.rebuild_from(type_id, builder);
+
+        Ok(())
+    }
+
+    /// See [MutableContainer::reset_all()](struct.MutableContainer.html#method.reset_all).
+    pub(crate) fn reset_all(&self) -> Result<()> {
+        debug!("resetting all types");
+
+        if self.inner.frozen.get() {
+            return Err("Can't reset, the container is frozen, reopen() it first".into());
+        }
+
+        let rebuilders = core::mem::take(&mut *self.inner.rebuilders.borrow_mut());
+
+        for (type_id, builder) in rebuilders {
+            self.rebuild_from(type_id, builder);
+        }
+
+        Ok(())
+    }
+
+    /// Puts a builder back in place of the item it built.
+    fn rebuild_from(&self, type_id: TypeId, builder: ErasedBuilder) {
+        let previous = self
+            .inner
+            .resolvers
+            .borrow_mut()
+            .insert(type_id, Resolver::Builder(builder));
+        let previous_singleton = self.inner.singletons.borrow_mut().remove(&type_id);
+        self.inner
+            .constructed
+            .borrow_mut()
+            .retain(|constructed| *constructed != type_id);
+
+        // old items may hold on to the container
+        drop(previous);
+        drop(previous_singleton);
+    }
+
+    /// Creates an independent copy of the container's wiring.
+    ///
+    /// Registering, replacing or overriding dependencies in the fork leaves
+    /// the original untouched, which makes it cheap to try out variations of
+    /// the wiring, in property tests for example. Shared items are shared
+    /// with the fork, and so are factories, along with any state they keep.
+    /// Forks start without scopes or overrides, keep the interceptors added so
+    /// far, and don't run teardowns. Items still shared with a fork when the
+    /// original shuts down aren't torn down either.
+    ///
+    /// Builders and pending futures can't be copied, so forking fails while
+    /// some haven't run yet. Resolve their dependencies before forking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<String>>(Rc::new("shared".to_string()))?;
+    /// builder.register::<u32>(42)?;
+    ///
+    /// let container = builder.build();
+    /// let fork = container.fork_mutable()?;
+    ///
+    /// fork.replace::<u32>(1)?;
+    ///
+    /// assert_eq!(1, fork.resolve::<u32>()?);
+    /// assert_eq!(42, container.resolve::<u32>()?);
+    /// assert!(Rc::ptr_eq(
+    ///     &fork.resolve::<Rc<String>>()?,
+    ///     &container.resolve::<Rc<String>>()?
+    /// ));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fork(&self) -> Result<Container> {
+        debug!("forking container");
+
+        self.share_into_new("fork a container", |_| true)
+    }
+
+    /// Creates an independent copy of the container's wiring, that can change.
+    ///
+    /// Changing the fork leaves this container untouched, see
+    /// [fork()](struct.Container.html#method.fork) and
+    /// [MutableContainer](struct.MutableContainer.html).
+    pub fn fork_mutable(&self) -> Result<MutableContainer> {
+        self.fork().map(MutableContainer::new)
+    }
+
+    /// Creates a container with only T and its dependencies.
+    ///
+    /// Dependencies are followed transitively, as declared via
+    /// [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies).
+    /// Useful for test containers, or for handing a worker only what it
+    /// needs. Otherwise the new container behaves like a
+    /// [fork()](struct.Container.html#method.fork): registrations are shared,
+    /// and builders among them must have run already. Transformers and
+    /// collections are shared as a whole.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i32>(42)?;
+    /// builder.register::<String>("unrelated".to_string())?;
+    /// builder.register_factory::<i64, _>(|container| {
+    ///     container.resolve::<i32>().unwrap().into()
+    /// })?;
+    /// builder.declare_dependencies::<i64, (i32,)>();
+    ///
+    /// let subset = builder.build().subset_for::<i64>()?;
+    ///
+    /// assert_eq!(42, subset.resolve::<i64>()?);
+    /// assert!(subset.resolve::<String>().is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subset_for<T: 'static>(&self) -> Result<Container> {
+        debug!("extracting subset container");
+
+        let type_id = TypeId::of::<T>();
+        if !self.has::<T>() && !self.inner.declarations.contains_key(&type_id) {
+            return Err(self.not_registered::<T>());
+        }
+
+        let closure = validation::dependency_closure(&self.inner.declarations, type_id);
+        let action = format!("extract {}", core::any::type_name::<T>());
+
+        self.share_into_new(&action, |type_id| closure.contains(type_id))
+    }
+
+    /// Builds the next generation of the container.
+    ///
+    /// The wiring gets a fresh builder, so everything is registered anew,
+    /// except for items marked via
+    /// [carry_over()](struct.ContainerBuilder.html#method.carry_over) that
+    /// were already built: those are shared with the new generation, which
+    /// takes over their teardowns too, replacing whatever the wiring
+    /// registered for them. Carried items aren't initialized again, and the
+    /// marks carry over as well. Meant for reconfiguring long running
+    /// processes without dropping their connections and caches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Pool;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder::<Rc<Pool>, _>(|_| Rc::new(Pool))?;
+    /// builder.carry_over::<Rc<Pool>>();
+    /// builder.register::<String>("v1".to_string())?;
+    ///
+    /// let old = builder.build();
+    /// let pool = old.resolve::<Rc<Pool>>()?;
+    ///
+    /// let new = old.rebuild_with(|builder| {
+    ///     builder.register_builder::<Rc<Pool>, _>(|_| Rc::new(Pool))?;
+    ///     builder.register::<String>("v2".to_string())
+    /// })?;
+    ///
+    /// assert!(Rc::ptr_eq(&pool, &new.resolve::<Rc<Pool>>()?));
+    /// assert_eq!("v2", new.resolve::<String>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebuild_with<F>(&self, wiring: F) -> Result<Container>
+    where
+        F: FnOnce(&mut ContainerBuilder) -> Result<()>,
+    {
+        debug!("rebuilding container");
+
+        let mut builder = ContainerBuilder::new();
+        wiring(&mut builder)?;
+
+        let mut container = builder.build();
+        let inner = Rc::get_mut(&mut container.inner).expect("new containers aren't shared");
+
+        inner
+            .carry_overs
+            .extend(self.inner.carry_overs.iter().copied());
+
+        for type_id in &inner.carry_overs {
+            let item = match self.inner.resolvers.borrow().get(type_id) {
+                Some(Resolver::Shared(item)) => item.clone(),
+                _ => continue,
+            };
+
+            debug!(
+                "carrying over {}",
+                self.inner.type_name(*type_id).unwrap_or("<unknown>")
+            );
+
+            inner
+                .resolvers
+                .get_mut()
+                .insert(*type_id, Resolver::Shared(item));
+            inner.pending.get_mut().remove(type_id);
+
+            // carried items were built first, so they are torn down last
+            let constructed = inner.constructed.get_mut();
+            constructed.retain(|constructed| constructed != type_id);
+            constructed.insert(0, *type_id);
+
+            if let Some(teardown) = self.inner.teardowns.borrow_mut().remove(type_id) {
+                inner.teardowns.get_mut().insert(*type_id, teardown);
+            }
+
+            if let Some(registration) = self.inner.registrations.borrow().get(type_id) {
+                inner
+                    .registrations
+                    .get_mut()
+                    .insert(*type_id, *registration);
+            }
+        }
+
+        Ok(container)
+    }
+
+    /// Builds a new container sharing the kept registrations.
+    fn share_into_new<F>(&self, action: &str, keep: F) -> Result<Container>
+    where
+        F: Fn(&TypeId) -> bool,
+    {
+        let resolvers = self.inner.resolvers.borrow();
+
+        let mut pending: Vec<_> = resolvers
+            .iter()
+            .filter(|(type_id, _)| keep(type_id))
+            .filter(|(_, resolver)| ResolverType::from(*resolver) == ResolverType::Builder)
+            .map(|(type_id, _)| self.inner.type_name(*type_id).unwrap_or("<unknown>"))
+            .collect();
+
+        pending.extend(
+            self.inner
+                .pending
+                .borrow()
+                .keys()
+                .filter(|type_id| keep(type_id))
+                .map(|type_id| self.inner.type_name(*type_id).unwrap_or("<unknown>")),
+        );
+
+        if !pending.is_empty() {
+            pending.sort();
+
+            return Err(format!(
+                "Can't {} with builders that haven't run yet: {}",
+                action,
+                pending.join(", ")
+            )
+            .into());
+        }
+
+        let shared = resolvers
+            .iter()
+            .filter(|(type_id, _)| keep(type_id))
+            .filter_map(|(type_id, resolver)| Some((*type_id, resolver.share()?)))
+            .collect();
+
+        let mut inner = Inner::default();
+        inner.resolvers = RefCell::new(shared);
+        inner.transformers = self.inner.transformers.clone();
+        inner.initializers = self.inner.initializers.clone();
+        inner.interceptors = RefCell::new(self.inner.interceptors.borrow().clone());
+        inner.fallbacks = RefCell::new(self.inner.fallbacks.borrow().clone());
+        inner.observers = RefCell::new(self.inner.observers.borrow().clone());
+        inner.declarations = keep_entries(&self.inner.declarations, &keep);
+        inner.memo_counters = keep_entries(&self.inner.memo_counters, &keep);
+        inner.deprecations = keep_entries(&self.inner.deprecations, &keep);
+        inner.leak_checks = keep_entries(&self.inner.leak_checks, &keep);
+        inner.arg_factories = keep_entries(&self.inner.arg_factories, &keep);
+        inner.events = EventLog::with_capacity(self.inner.events.capacity());
+        inner.stats = StatsRecorder::new(self.inner.stats.is_enabled());
+        inner.default_fallback = self.inner.default_fallback;
+        inner.generics = self.inner.generics.clone();
+        inner.tags = self.inner.tags.clone();
+        inner.metadata = self.inner.metadata.clone();
+        inner.arena = self.inner.arena.clone();
+        inner.strict.set(self.inner.strict.get());
+        inner.async_timeout = self.inner.async_timeout;
+        inner
+            .cycle_stopper
+            .set_max_depth(self.inner.cycle_stopper.max_depth());
+        inner.collections = self.inner.collections.clone();
+        inner.keyed = self.inner.keyed.clone();
+        inner.named = self.inner.named.clone();
+        inner.registrations = RefCell::new(keep_entries(&self.inner.registrations.borrow(), &keep));
+
+        Ok(Container::from_inner(inner))
+    }
+
+    /// Runs every builder that hasn't run yet.
+    ///
+    /// Builders normally run the first time their dependency is resolved,
+    /// this moves the cost to startup instead. Builders run after the ones
+    /// they declare dependencies on, see
+    /// [register_builder_with_deps()](struct.ContainerBuilder.html#method.register_builder_with_deps),
+    /// and by priority then type name otherwise, see
+    /// [startup_order()](struct.Container.html#method.startup_order). All failures are
+    /// reported together, not just the first one, along with items
+    /// registered with invalid values, like config sections failing their
+    /// checks. Pending futures are not
+    /// awaited, see [register_pending()](struct.ContainerBuilder.html#method.register_pending).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver, ResolverType};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder::<u32, _>(|_| 42)?;
+    ///
+    /// let container = builder.build();
+    /// container.warm_up()?;
+    ///
+    /// assert!(container.registrations().all(|registration| registration.built));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn warm_up(&self) -> Result<()> {
+        debug!("warming up container");
+
+        let type_name = |type_id: &TypeId| self.inner.type_name(*type_id).unwrap_or("<unknown>");
+
+        let mut failures: Vec<_> = self
+            .invalid_values()
+            .into_iter()
+            .map(|(type_name, problem)| format!("{}: {}", type_name, problem))
+            .collect();
+
+        failures.extend(self.builder_order().iter().filter_map(|type_id| {
+            let warm = self.inner.warmers[type_id];
+
+            warm(self)
+                .err()
+                .map(|error| format!("{}: {}", type_name(type_id), error))
+        }));
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Warm up failed:\n  {}", failures.join("\n  ")).into())
+        }
+    }
+
+    /// Names of the builders, in the order warm_up() runs them.
+    ///
+    /// Builders run by priority, see
+    /// [register_eager_with_priority()](struct.ContainerBuilder.html#method.register_eager_with_priority),
+    /// then by type name, with declared dependencies moved before their
+    /// dependents. Builders that already ran are listed too.
+    pub fn startup_order(&self) -> Vec<&'static str> {
+        self.builder_order()
+            .iter()
+            .map(|type_id| self.inner.type_name(*type_id).unwrap_or("<unknown>"))
+            .collect()
+    }
+
+    fn builder_order(&self) -> Vec<TypeId> {
+        let mut types: Vec<_> = self.inner.warmers.keys().copied().collect();
+        // keep reports stable
+        types.sort_by_key(|type_id| (self.priority(type_id), self.inner.type_name(*type_id)));
+
+        validation::initialization_order(&self.inner.declarations, &types)
+    }
+
+    fn priority(&self, type_id: &TypeId) -> i32 {
+        self.inner.priorities.get(type_id).copied().unwrap_or(0)
+    }
+
+    /// Runs the builder of T, unless it ran already.
+    fn warm<T: 'static>(&self) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+
+        // builders run on their own when something else depends on them
+        if self.get_resolver_type(type_id) != Some(ResolverType::Builder) {
+            return Ok(());
+        }
+
+        {
+            let _guard = self.inner.cycle_stopper.track::<T>()?;
+
+            self.consume_builder::<T>()?;
+        }
+
+        self.fill_deferred()
+    }
+
+    /// Starts every service with a lifecycle, dependencies first.
+    ///
+    /// Services are built if they weren't already, by priority then type
+    /// name, and started in the order they were built in, so whatever a
+    /// service depends on is started before it. If a service fails to start, the ones started before it
+    /// are stopped again. Forks don't manage lifecycles. See
+    /// [Lifecycle](trait.Lifecycle.html).
+    pub fn start_all(&self) -> Result<()> {
+        debug!("starting services");
+
+        if !self.inner.started.borrow().is_empty() {
+            return Err("Services were already started".into());
+        }
+
+        let mut managed: Vec<_> = self
+            .inner
+            .lifecycles
+            .iter()
+            .map(|(type_id, managed)| {
+                let type_name = self.inner.type_name(*type_id).unwrap_or("<unknown>");
+
+                (type_name, *type_id, *managed)
+            })
+            .collect();
+
+        // keep the build order stable
+        managed.sort_by_key(|(type_name, type_id, _)| (self.priority(type_id), *type_name));
+
+        let mut services = Vec::with_capacity(managed.len());
+        for (type_name, type_id, managed) in managed {
+            let service = managed
+                .resolve(type_name, self)
+                .map_err(|error| format!("Could not start {}: {}", type_name, error))?;
+
+            if self.get_resolver_type(type_id) != Some(ResolverType::Shared) {
+                return Err(format!("Could not start {}, it isn't shared", type_name).into());
+            }
+
+            services.push((type_id, service));
+        }
+
+        let position = |type_id: &TypeId| {
+            self.inner
+                .constructed
+                .borrow()
+                .iter()
+                .position(|constructed| constructed == type_id)
+        };
+        services.sort_by_key(|(type_id, _)| position(type_id));
+
+        // services may use the container, so we don't hold borrows while
+        // starting them
+        for (_, service) in services {
+            if let Err(error) = service.start() {
+                if let Err(stop_error) = self.stop_all() {
+                    warn!("{}", stop_error);
+                }
+
+                return Err(format!("Could not start {}: {}", service.type_name, error).into());
+            }
+
+            self.inner.started.borrow_mut().push(service);
+        }
+
+        Ok(())
+    }
+
+    /// Stops the services started by [start_all()](struct.Container.html#method.start_all).
+    ///
+    /// Services are stopped in reverse, dependents before their
+    /// dependencies. A failing service doesn't keep the rest from being
+    /// stopped, all failures are reported together.
+    pub fn stop_all(&self) -> Result<()> {
+        debug!("stopping services");
+
+        let failures = self.inner.stop_started();
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Stopping failed:\n  {}", failures.join("\n  ")).into())
+        }
+    }
+
+    /// Tears down all shared dependencies created so far.
+    ///
+    /// Teardown callbacks registered via
+    /// [register_teardown()](struct.ContainerBuilder.html#method.register_teardown)
+    /// are called in reverse order of construction, so dependents are torn
+    /// down before their dependencies. Torn down dependencies are removed from
+    /// the container. This also happens when the container is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::RefCell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let log = Rc::new(RefCell::new(Vec::new()));
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<i32>(1)?;
+    /// builder.register_builder::<i64, _>(|_| 2)?;
+    ///
+    /// let i32_log = log.clone();
+    /// builder.register_teardown::<i32, _>(move |item| i32_log.borrow_mut().push(item.to_string()))?;
+    /// let i64_log = log.clone();
+    /// builder.register_teardown::<i64, _>(move |item| i64_log.borrow_mut().push(item.to_string()))?;
+    ///
+    /// let container = builder.build();
+    /// container.resolve::<i64>()?;
+    ///
+    /// container.shutdown();
+    ///
+    /// assert_eq!(vec!["2", "1"], *log.borrow());
+    /// assert!(!container.has::<i32>());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+
+    /// Resolves every item in the collection of T.
+    ///
+    /// Items come highest priority first, and in the order they were added
+    /// when priorities are the same. Returns an empty Vec if nothing was
+    /// added. See
+    /// [register_in_collection()](struct.ContainerBuilder.html#method.register_in_collection).
+    pub fn resolve_all<T: ?Sized + 'static>(&self) -> Vec<Rc<T>> {
+        debug!("resolving collection");
+
+        self.inner
+            .collections
+            .get(&TypeId::of::<T>())
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|(_, item)| {
+                        item.downcast_ref::<Rc<T>>()
+                            .expect("could not downcast collection")
+                            .clone()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Resolves the item of T registered under the key.
+    ///
+    /// See [register_keyed()](struct.ContainerBuilder.html#method.register_keyed).
+    /// The error lists the keys that are available.
+    pub fn resolve_keyed<T: ?Sized + 'static>(&self, key: &str) -> Result<Rc<T>> {
+        debug!("resolving keyed item");
+
+        self.keyed_items::<T>()
+            .and_then(|items| items.get(key))
+            .map(|item| {
+                item.downcast_ref::<Rc<T>>()
+                    .expect("could not downcast keyed item")
+                    .clone()
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No {} registered for key \"{}\", available keys: {}",
+                    core::any::type_name::<T>(),
+                    key,
+                    self.keys::<T>().join(", ")
+                )
+                .into()
+            })
+    }
+
+    /// Resolves the item of T registered under the marker type K.
+    ///
+    /// See [register_for()](struct.ContainerBuilder.html#method.register_for).
+    pub fn resolve_for<K: 'static, T: Clone + 'static>(&self) -> Result<T> {
+        debug!("resolving item for marker type");
+
+        self.get::<Marked<K, T>>().map(Marked::into_inner)
+    }
+
+    /// Lists the keys items of T are registered under, sorted.
+    ///
+    /// See [register_keyed()](struct.ContainerBuilder.html#method.register_keyed).
+    pub fn keys<T: ?Sized + 'static>(&self) -> Vec<&str> {
+        self.keyed_items::<T>()
+            .map(|items| items.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Resolves the item registered under the name.
+    ///
+    /// See [register_named()](struct.ContainerBuilder.html#method.register_named).
+    /// The error lists the names that are available.
+    pub fn resolve_named(&self, name: &str) -> Result<Rc<dyn Any>> {
+        debug!("resolving named item");
+
+        self.inner.named.get(name).cloned().ok_or_else(|| {
+            format!(
+                "No item named \"{}\", available names: {}",
+                name,
+                self.names().join(", ")
+            )
+            .into()
+        })
+    }
+
+    /// Resolves the item registered under the name, as T.
+    ///
+    /// Like [resolve_named()](struct.Container.html#method.resolve_named),
+    /// but also fails if the item is not a T.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_named("retries", 3u32)?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(3, *container.resolve_named_as::<u32>("retries")?);
+    /// assert!(container.resolve_named_as::<String>("retries").is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_named_as<T: 'static>(&self, name: &str) -> Result<Rc<T>> {
+        self.resolve_named(name)?.downcast::<T>().map_err(|_| {
+            format!(
+                "The item named \"{}\" is not a {}",
+                name,
+                core::any::type_name::<T>()
+            )
+            .into()
+        })
+    }
+
+    /// Resolves every type carrying the tag, in the order they were tagged.
+    ///
+    /// Each item is boxed as its registered type. Returns an empty list for
+    /// tags nothing carries, and fails if any tagged type can't be resolved.
+    /// See [tag()](struct.ContainerBuilder.html#method.tag).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(1)?;
+    /// builder.register::<String>("two".to_string())?;
+    /// builder.tag::<u32>("setting")?;
+    /// builder.tag::<String>("setting")?;
+    ///
+    /// let container = builder.build();
+    /// let settings = container.resolve_tagged("setting")?;
+    ///
+    /// assert_eq!(Some(&1), settings[0].downcast_ref::<u32>());
+    /// assert_eq!(Some("two"), settings[1].downcast_ref::<String>().map(String::as_str));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_tagged(&self, tag: &str) -> Result<Vec<Rc<dyn Any>>> {
+        debug!("resolving tagged types");
+
+        self.inner.tags.resolve(tag, self)
+    }
+
+    /// Resolves every type carrying the tag as `Rc<U>`.
+    ///
+    /// Fails if a type carrying the tag wasn't tagged as U, see
+    /// [tag_as()](struct.ContainerBuilder.html#method.tag_as).
+    pub fn resolve_tagged_as<U: ?Sized + 'static>(&self, tag: &str) -> Result<Vec<Rc<U>>> {
+        debug!("resolving tagged types as one type");
+
+        self.inner.tags.resolve_as(tag, self)
+    }
+
+    /// Returns the metadata of type M attached to T.
+    ///
+    /// See [add_metadata()](struct.ContainerBuilder.html#method.add_metadata).
+    pub fn metadata_of<T: 'static, M: 'static>(&self) -> Option<&M> {
+        self.inner.metadata.of::<T, M>()
+    }
+
+    /// Lists the types carrying metadata of type M, with their metadata.
+    ///
+    /// Types are listed by name, in the order the metadata was attached.
+    /// See [add_metadata()](struct.ContainerBuilder.html#method.add_metadata).
+    pub fn carrying_metadata<M: 'static>(&self) -> Vec<(&'static str, &M)> {
+        self.inner.metadata.all::<M>()
+    }
+
+    /// Lists the names items are registered under, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.inner.named.keys().map(String::as_str).collect();
+        names.sort_unstable();
+
+        names
+    }
+
+    fn keyed_items<T: ?Sized + 'static>(&self) -> Option<&Keyed> {
+        self.inner.keyed.get(&TypeId::of::<T>())
+    }
+
+    /// Resolves T, waiting for it if it was registered as a future.
+    ///
+    /// Pending futures are awaited once, by whoever resolves them first, and
+    /// the item is shared after that. Types that aren't pending resolve like
+    /// they do with [resolve()](trait.Resolver.html#tymethod.resolve).
+    ///
+    /// See [register_pending()](struct.ContainerBuilder.html#method.register_pending).
+    /// Gives up after the container's timeout, if it has one, see
+    /// [async_timeout()](struct.ContainerBuilder.html#method.async_timeout).
+    pub async fn resolve_async<T: Clone + 'static>(&self) -> Result<T> {
+        match self.inner.async_timeout {
+            Some(timeout) => self.resolve_async_timeout::<T>(timeout).await,
+            None => self.await_pending::<T>().await,
+        }
+    }
+
+    /// Resolves T like [resolve_async()](struct.Container.html#method.resolve_async),
+    /// giving up after a timeout.
+    ///
+    /// The error names the pending items still being waited for, ending with
+    /// the one that stalled. Pending futures are kept, so resolving again
+    /// picks up where this left off. Without a clock, like on
+    /// `wasm32-unknown-unknown`, the time is never up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::future::{pending, Future};
+    /// # use std::pin::{pin, Pin};
+    /// # use std::task::{Context, Poll, Waker};
+    /// # use std::time::Duration;
+    /// #
+    /// # fn block_on<F: Future>(future: F) -> F::Output {
+    /// #     let mut future = pin!(future);
+    /// #     let mut cx = Context::from_waker(Waker::noop());
+    /// #     loop {
+    /// #         if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+    /// #             return output;
+    /// #         }
+    /// #     }
+    /// # }
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let connection: Pin<Box<dyn Future<Output = String>>> = Box::pin(pending());
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_pending(connection)?;
+    ///
+    /// let container = builder.build();
+    /// let error = block_on(container.resolve_async_timeout::<String>(Duration::from_millis(10)))
+    ///     .err()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     "Timed out after 10ms resolving alloc::string::String, waiting for alloc::string::String",
+    ///     error.to_string()
+    /// );
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn resolve_async_timeout<T: Clone + 'static>(
+        &self,
+        timeout: core::time::Duration,
+    ) -> Result<T> {
+        let depth = self.inner.trail.depth();
+
+        if let Some(result) = Deadline::new(self.await_pending::<T>(), timeout).await {
+            return result;
+        }
+
+        let stalled = self.inner.trail.stalled_inside(depth).join(" -> ");
+
+        Err(format!(
+            "Timed out after {:?} resolving {}, waiting for {}",
+            timeout,
+            core::any::type_name::<T>(),
+            stalled
+        )
+        .into())
+    }
+
+    async fn await_pending<T: Clone + 'static>(&self) -> Result<T> {
+        let type_id = TypeId::of::<T>();
+
+        if !self.has::<T>() {
+            let pending = self.inner.pending.borrow().get(&type_id).cloned();
+
+            if let Some(pending) = pending {
+                debug!("awaiting pending item");
+
+                let pending = pending
+                    .downcast::<Pending<T>>()
+                    .expect("could not downcast pending item");
+
+                let trail = &self.inner.trail;
+
+                // everyone else waiting finds the item in the container
+                if let Some(item) = (PendingResolve { pending, trail }).await {
+                    self.inner.pending.borrow_mut().remove(&type_id);
+                    self.insert::<T>(Resolver::Shared(Rc::new(item)))?;
+                }
+            }
+        }
+
+        self.get()
+    }
+
+    /// Lists the most recent events, oldest first.
+    ///
+    /// Resolutions, failed ones included, overrides being pushed and popped,
+    /// and scopes starting and ending are recorded, once they're done. So
+    /// dependencies show up before whatever depends on them. Nothing is
+    /// recorded unless enabled via
+    /// [record_events()](struct.ContainerBuilder.html#method.record_events).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, ContainerEvent, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(42)?;
+    /// builder.record_events(2);
+    ///
+    /// let container = builder.build();
+    /// container.resolve::<u32>()?;
+    /// container.resolve::<u32>()?;
+    /// assert!(container.resolve::<u8>().is_err());
+    ///
+    /// let events = container.recent_events();
+    ///
+    /// assert_eq!(ContainerEvent::Resolved("u32"), events[0]);
+    /// assert_eq!("failed to resolve u8: Type not registered: u8", events[1].to_string());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn recent_events(&self) -> Vec<ContainerEvent> {
+        self.inner.events.recent()
+    }
+
+    /// Reports how each type was resolved so far.
+    ///
+    /// Lists how often types were resolved, how often that reused an
+    /// existing item, and how often and for how long factories and builders
+    /// ran. A type constructed on almost every resolution is often a
+    /// factory that could be shared. Nothing is recorded unless enabled via
+    /// [record_stats()](struct.ContainerBuilder.html#method.record_stats).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_factory::<u32, _>(|_| 42)?;
+    /// builder.register_builder::<u64, _>(|_| 42)?;
+    /// builder.record_stats();
+    ///
+    /// let container = builder.build();
+    /// for _ in 0..3 {
+    ///     container.resolve::<u32>()?;
+    ///     container.resolve::<u64>()?;
+    /// }
+    ///
+    /// let stats = container.stats();
+    /// println!("{}", stats);
+    ///
+    /// assert_eq!(3, stats.of::<u32>().unwrap().constructions);
+    /// assert_eq!(1, stats.of::<u64>().unwrap().constructions);
+    /// assert_eq!(2, stats.of::<u64>().unwrap().cache_hits);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> StatsReport {
+        self.inner
+            .stats
+            .report(|type_id| self.inner.type_name(type_id))
+    }
+
+    /// Resolves T, and reports how that went, step by step.
+    ///
+    /// Every type resolved along the way is listed under the type that
+    /// needed it, with what handed it out, whether it was a cache hit or a
+    /// new item, how long it took, and why it failed. The resolution is a
+    /// real one, builders that haven't run yet run. Handy for questions
+    /// like "why is this rebuilt on every call?".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver, ResolverType, ResolvedVia};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u8>(1)?;
+    /// builder.register_try_factory::<u32, _>(|container| Ok(container.resolve::<u8>()?.into()))?;
+    ///
+    /// let container = builder.build();
+    /// let trace = container.explain::<u32>();
+    /// println!("{}", trace);
+    ///
+    /// assert_eq!(ResolvedVia::Registration(ResolverType::Factory), trace.root.via);
+    /// assert!(trace.root.dependencies[0].cache_hit);
+    /// assert_eq!(vec!["u32"], trace.constructions());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn explain<T: Clone + 'static>(&self) -> ResolutionTrace {
+        debug!("explaining resolution");
+
+        self.inner.explainer.start();
+        // the outcome ends up in the trace
+        let _ = self.get::<T>();
+
+        ResolutionTrace {
+            root: self.inner.explainer.finish(),
+        }
+    }
+
+    /// Fills in the dependencies of an item that already exists.
+    ///
+    /// See [InjectFields](trait.InjectFields.html).
+    pub fn inject_into<T: InjectFields>(&self, item: &mut T) -> Result<()> {
+        debug!("injecting fields");
+
+        item.inject_fields(self).map_err(|error| {
+            format!(
+                "could not inject fields of {}: {}",
+                core::any::type_name::<T>(),
+                error
+            )
+            .into()
+        })
+    }
+
+    /// Calls a function, injecting its arguments.
+    ///
+    /// Every argument is injected like [inject()](trait.Injector.html#tymethod.inject)
+    /// would, before the function is called. If any of them can't be, the
+    /// function is not called at all. Takes functions and closures with up
+    /// to eight arguments, see [Callable](trait.Callable.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Db { url: String }
+    ///
+    /// trait Mailer { fn from(&self) -> &str; }
+    ///
+    /// struct Smtp;
+    /// impl Mailer for Smtp { fn from(&self) -> &str { "noreply@example.com" } }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<Db>>(Rc::new(Db { url: "db.local".to_string() }))?;
+    /// builder.register::<Rc<dyn Mailer>>(Rc::new(Smtp))?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let summary = container.call(|db: Rc<Db>, mailer: Rc<dyn Mailer>| {
+    ///     format!("{} via {}", mailer.from(), db.url)
+    /// })?;
+    ///
+    /// assert_eq!("noreply@example.com via db.local", summary);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call<Args, F: Callable<Args>>(&self, function: F) -> Result<F::Output> {
+        debug!("calling function with injected arguments");
+
+        function.call_with(self)
+    }
+
+    /// Resolves every item of a tuple, like `(Rc<A>, B)`, in one go.
+    ///
+    /// Each item is injected on its own, like the arguments of
+    /// [call()](struct.Container.html#method.call). All the items that
+    /// couldn't be are listed in the error, not just the first one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Db;
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<Db>>(Rc::new(Db))?;
+    /// builder.register::<u16>(8080)?;
+    ///
+    /// let container = builder.build();
+    /// let (_db, port) = container.resolve_tuple::<(Rc<Db>, u16)>()?;
+    ///
+    /// assert_eq!(8080, port);
+    ///
+    /// let error = container.resolve_tuple::<(u8, u16, String)>().unwrap_err();
+    /// assert!(error.to_string().contains("argument u8"));
+    /// assert!(error.to_string().contains("argument alloc::string::String"));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_tuple<T: InjectAll>(&self) -> Result<T> {
+        debug!("resolving tuple");
+
+        T::inject_all(self)
+    }
+
+    /// Resolves T, passing arguments on to its factory.
+    ///
+    /// Only works for types registered via
+    /// [register_factory_with_args()](struct.ContainerBuilder.html#method.register_factory_with_args),
+    /// and the arguments have to be of the type the factory takes.
+    /// Transformers run like they do for other factories.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Clone)]
+    /// struct TenantDb { url: String }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<String>("db.local".to_string())?;
+    /// builder.register_factory_with_args::<TenantDb, u32, _>(|container, tenant| {
+    ///     let host = container.resolve::<String>().unwrap();
+    ///     TenantDb { url: format!("{}/tenant_{}", host, tenant) }
+    /// })?;
+    ///
+    /// let container = builder.build();
+    /// let db = container.resolve_with::<TenantDb, u32>(42)?;
+    ///
+    /// assert_eq!("db.local/tenant_42", db.url);
+    /// assert!(container.resolve_with::<TenantDb, &str>("42").is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_with<T: 'static, A: 'static>(&self, args: A) -> Result<T> {
+        debug!("resolving type with arguments");
+
+        let type_id = TypeId::of::<T>();
+        let _guard = self.inner.cycle_stopper.track::<T>()?;
+
+        let cell = match self.inner.arg_factories.get(&type_id) {
+            Some(cell) => cell.clone(),
+            None => return Err(self.not_registered::<T>()),
+        };
+
+        let item = {
+            let mut boxed = cell.borrow_mut();
+            let factory = boxed
+                .downcast_mut::<Box<FactoryWithArgs<T, A>>>()
+                .ok_or_else(|| {
+                    format!(
+                        "{} takes other arguments than {}",
+                        core::any::type_name::<T>(),
+                        core::any::type_name::<A>()
+                    )
+                })?;
+
+            factory(self, args)
+        };
+
+        self.transform(item)
+    }
+
+    /// Resolves T, built from its dependencies and the caller's arguments.
+    ///
+    /// See [Assisted](trait.Assisted.html). Like
+    /// [resolve_with()](struct.Container.html#method.resolve_with), but
+    /// nothing needs registering, and transformers of T run on the item.
+    pub fn resolve_assisted<T: Assisted + 'static>(&self, args: T::Args) -> Result<T> {
+        debug!("resolving type with assisted injection");
+
+        let item = {
+            let _guard = self.inner.cycle_stopper.track::<T>()?;
+
+            T::resolve_assisted(self, args)
+                .map_err(|error| self.inner.cycle_stopper.add_path(error))?
+        };
+
+        self.transform(item)
+    }
+
+    /// Resolves an owned T, registered as T or as `Rc<T>`.
+    ///
+    /// T itself is preferred, which already returns a clone of shared items
+    /// and the fresh output of factories. Otherwise `Rc<T>` is resolved and
+    /// its item cloned, unless the container kept no other handle to it,
+    /// like with factories, in which case it's moved out without cloning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Clone)]
+    /// struct Settings { retries: u32 }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<Settings>>(Rc::new(Settings { retries: 3 }))?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let mut settings: Settings = container.resolve_owned()?;
+    /// settings.retries = 5;
+    ///
+    /// assert_eq!(3, container.resolve::<Rc<Settings>>()?.retries);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_owned<T: Clone + 'static>(&self) -> Result<T> {
+        debug!("resolving owned item");
+
+        if self.has::<T>() || !self.has::<Rc<T>>() {
+            return self.get::<T>();
+        }
+
+        let item = self.get::<Rc<T>>()?;
+
+        Ok(Rc::try_unwrap(item).unwrap_or_else(|shared| T::clone(&shared)))
+    }
+
+    /// Resolves T, or creates a default one if it isn't registered.
+    ///
+    /// Needs [fall_back_to_default()](struct.ContainerBuilder.html#method.fall_back_to_default),
+    /// otherwise unregistered types fail like they do for
+    /// [resolve()](trait.Resolver.html#tymethod.resolve). With
+    /// [DefaultFallback::Share](enum.DefaultFallback.html#variant.Share),
+    /// the default item is registered as shared the first time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, DefaultFallback};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(42)?;
+    /// builder.fall_back_to_default(DefaultFallback::Create);
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(42, container.resolve_or_default::<u32>()?);
+    /// assert_eq!(0, container.resolve_or_default::<u64>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_or_default<T: Default + Clone + 'static>(&self) -> Result<T> {
+        debug!("resolving type or its default");
+
+        let type_id = TypeId::of::<T>();
+
+        // pending items and factories with arguments are registered, just not resolvable here
+        let is_registered = self.has::<T>()
+            || self.inner.pending.borrow().contains_key(&type_id)
+            || self.inner.arg_factories.contains_key(&type_id);
+
+        if is_registered {
+            return self.get();
+        }
+
+        match self.inner.default_fallback {
+            DefaultFallback::Off => self.get(),
+            DefaultFallback::Create => {
+                debug!("creating default item");
+                Ok(T::default())
+            }
+            DefaultFallback::Share => {
+                debug!("sharing default item");
+                self.insert::<T>(Resolver::Shared(Rc::new(T::default())))?;
+                self.get()
+            }
+        }
+    }
+
+    /// See [MutableContainer::resolve_or_register_with()](struct.MutableContainer.html#method.resolve_or_register_with).
+    pub(crate) fn resolve_or_register_with<T, F>(&self, create: F) -> Result<T>
+    where
+        F: FnOnce(&Container) -> T,
+        T: Clone + 'static,
+    {
+        debug!("resolving type or registering it");
+
+        let type_id = TypeId::of::<T>();
+
+        // pending items and factories with arguments are registered, just not resolvable here
+        let is_registered = self.has::<T>()
+            || self.inner.pending.borrow().contains_key(&type_id)
+            || self.inner.arg_factories.contains_key(&type_id);
+
+        if is_registered {
+            return self.get();
+        }
+
+        self.check_open::<T>()?;
+
+        let item = create(self);
+        self.insert::<T>(Resolver::Shared(Rc::new(item)))?;
+
+        self.get()
+    }
+
+    /// Resolves the item a generic factory builds for T.
+    ///
+    /// The item is built the first time, and shared after that. Returns an
+    /// error if F isn't registered. See [GenericFactory](trait.GenericFactory.html).
+    pub fn resolve_generic<F: GenericFactory, T: 'static>(&self) -> Result<F::Output<T>> {
+        debug!("resolving generic type");
+
+        if self.has::<F::Output<T>>() {
+            return self.get();
+        }
+
+        if !self.inner.generics.contains(&TypeId::of::<F>()) {
+            return Err(format!(
+                "{} is not registered as a generic factory",
+                core::any::type_name::<F>()
+            )
+            .into());
+        }
+
+        let item = {
+            let _guard = self.inner.cycle_stopper.track::<F::Output<T>>()?;
+
+            F::create::<T>(self).map_err(|error| self.inner.cycle_stopper.add_path(error))?
+        };
+
+        self.insert::<F::Output<T>>(Resolver::Shared(Rc::new(item)))?;
+
+        self.get()
+    }
+
+    /// Hands out a handle to T, filled in once the current resolution is done.
+    ///
+    /// Lets services refer to each other: the one being built gets a handle
+    /// instead of T, and T is resolved after it exists, so T can depend on it
+    /// in turn. If T can't be resolved then, the resolution that asked for
+    /// the handle fails. Handles asked for outside of a resolution are filled
+    /// in after the next one. See [Deferred](struct.Deferred.html).
+    pub fn resolve_deferred<T: Clone + 'static>(&self) -> Result<Deferred<T>> {
+        debug!("deferring dependency");
+
+        Ok(self.inner.deferred.defer::<T>())
+    }
+
+    /// Borrows an item of T from its pool.
+    ///
+    /// The item goes back to the pool when the handle is dropped. Returns an
+    /// error if T has no pool, or if every item in it is in use. See
+    /// [register_pool()](struct.ContainerBuilder.html#method.register_pool).
+    pub fn resolve_pooled<T: 'static>(&self) -> Result<PooledHandle<T>> {
+        debug!("borrowing pooled item");
+
+        if !self.has::<Rc<Pool<T>>>() {
+            return Err(format!("{} has no pool registered", core::any::type_name::<T>()).into());
+        }
+
+        Pool::acquire(&self.get::<Rc<Pool<T>>>()?, self)
+    }
+
+    /// Copies a value out of the arena.
+    ///
+    /// See [register_in_arena()](struct.ContainerBuilder.html#method.register_in_arena).
+    pub fn resolve_from_arena<T: Copy + 'static>(&self) -> Result<T> {
+        debug!("resolving arena value");
+
+        self.inner.arena.get(self.inner.arena.handle::<T>()?)
+    }
+
+    /// Returns a handle to T's value in the arena, see [ArenaHandle](struct.ArenaHandle.html).
+    pub fn arena_handle<T: Copy + 'static>(&self) -> Result<ArenaHandle<T>> {
+        self.inner.arena.handle()
+    }
+
+    /// Copies the value a handle points at out of the arena.
+    ///
+    /// Fails if the handle came from another container.
+    pub fn resolve_handle<T: Copy + 'static>(&self, handle: ArenaHandle<T>) -> Result<T> {
+        self.inner.arena.get(handle)
+    }
+
+    /// Resolves a trait object registered as `Rc<dyn Trait>`.
+    ///
+    /// Like resolving `Rc<dyn Trait>`, without spelling out the `Rc`. See
+    /// [register_boxed_trait()](struct.ContainerBuilder.html#method.register_boxed_trait).
+    pub fn resolve_trait<T: ?Sized + 'static>(&self) -> Result<Rc<T>> {
+        debug!("resolving trait object");
+
+        self.get::<Rc<T>>()
+    }
+
+    /// Resolves a mutable service registered as `Rc<RefCell<T>>`.
+    ///
+    /// See [register_mut()](struct.ContainerBuilder.html#method.register_mut).
+    /// With the `specialization` feature, types implementing
+    /// [Inject](trait.Inject.html) are auto-resolved and shared from then on.
+    pub fn resolve_mut<T: 'static>(&self) -> Result<Rc<RefCell<T>>> {
+        debug!("resolving mutable service");
+
+        Injector::<Rc<RefCell<T>>>::inject(self)
+    }
+
+    /// Resolves a weak reference to a shared `Rc<T>`.
+    ///
+    /// Lets one side of a pair of services referencing each other hold a
+    /// weak handle, so they don't keep each other alive. The container keeps
+    /// the `Rc<T>`, so the handle can be upgraded for as long as the container,
+    /// or the scope for scoped dependencies, lives. Returns an error if the
+    /// container doesn't keep the resolved item, like for factories, since
+    /// the reference would be dangling.
+    ///
+    /// `Weak<T>` can also be injected, see [Injector](trait.Injector.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::RefCell;
+    /// # use std::rc::{Rc, Weak};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Parent { children: RefCell<Vec<Rc<Child>>> }
+    /// struct Child { parent: Weak<Parent> }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<Parent>>(Rc::new(Parent { children: RefCell::new(vec![]) }))?;
+    /// builder.register_factory::<Rc<Child>, _>(|container| {
+    ///     Rc::new(Child { parent: container.resolve_weak::<Parent>().unwrap() })
+    /// })?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// let child = container.resolve::<Rc<Child>>()?;
+    /// child.parent.upgrade().unwrap().children.borrow_mut().push(child.clone());
+    ///
+    /// assert_eq!(1, container.resolve::<Rc<Parent>>()?.children.borrow().len());
+    /// assert!(container.resolve_weak::<Child>().is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_weak<T: ?Sized + 'static>(&self) -> Result<Weak<T>> {
+        debug!("resolving weak reference");
+
+        let item = self.get::<Rc<T>>()?;
+
+        if Rc::strong_count(&item) == 1 {
+            return Err(format!(
+                "{} is not kept by the container, a weak reference to it would be dangling",
+                core::any::type_name::<Rc<T>>()
+            )
+            .into());
+        }
+
+        Ok(Rc::downgrade(&item))
+    }
+
+    /// Returns hit/miss counters for a memoized scoped registration.
+    ///
+    /// Returns None if T wasn't registered via
+    /// [register_scoped_memoized()](struct.ContainerBuilder.html#method.register_scoped_memoized).
+    pub fn memo_stats<T: 'static>(&self) -> Option<MemoStats> {
+        self.inner
+            .memo_counters
+            .get(&TypeId::of::<T>())
+            .map(|counters| counters.stats())
+    }
+
+    /// Returns the name of a registered type.
+    ///
+    /// Returns None if nothing is registered for the type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::any::TypeId;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(42)?;
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(Some("u32"), container.type_name_of(TypeId::of::<u32>()));
+    /// assert_eq!(None, container.type_name_of(TypeId::of::<i8>()));
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn type_name_of(&self, type_id: TypeId) -> Option<&'static str> {
+        self.inner.type_name(type_id)
+    }
+
+    fn has<T: 'static>(&self) -> bool {
+        debug!("has called");
+
+        let type_id = TypeId::of::<T>();
+
+        self.inner.overrides.has(type_id)
+            || self.inner.scopes.has_seed(type_id)
+            || self.is_resolvable(type_id)
+    }
+
+    /// Resolves `Rc<T>` if it's registered.
+    fn get_optional<T: ?Sized + 'static>(&self) -> Result<Option<Rc<T>>> {
+        if !self.has::<Rc<T>>() {
+            debug!("optional dependency not registered");
+            return Ok(None);
+        }
+
+        self.get::<Rc<T>>().map(Some)
+    }
+
+    fn get<T: Clone + 'static>(&self) -> Result<T> {
+        if self.inner.strict.get() {
+            self.inner.frozen.set(true);
+        }
+
+        if let Some(item) = self.get_cached::<T>() {
+            return Ok(item);
+        }
+
+        // left over from resolving outside of get(), like warm_up()
+        self.inner.observers.borrow().take_hit();
+
+        let explaining = self.inner.explainer.is_active();
+        if explaining {
+            let via = self.resolved_via(TypeId::of::<T>());
+            self.inner.explainer.enter(core::any::type_name::<T>(), via);
+        }
+
+        let result = {
+            let _span = trace::resolving(core::any::type_name::<T>(), &self.inner.depth);
+            let result = self.get_intercepted();
+            trace::resolved(&result);
+
+            result
+        };
+        let cache_hit = self.inner.observers.borrow().take_hit();
+
+        if explaining {
+            self.inner.explainer.exit(&result, cache_hit);
+        }
+        self.inner.events.record_resolution(&result);
+        self.observe_resolution(&result, cache_hit);
+
+        result
+    }
+
+    /// What's about to resolve T, see explain().
+    fn resolved_via(&self, type_id: TypeId) -> ResolvedVia {
+        if self.inner.overrides.has(type_id) {
+            return ResolvedVia::Override;
+        }
+
+        if self.inner.scopes.has_seed(type_id) {
+            return ResolvedVia::ScopeSeed;
+        }
+
+        match self.get_resolver_type(type_id) {
+            Some(resolver_type) => ResolvedVia::Registration(resolver_type),
+            None => ResolvedVia::Unregistered,
+        }
+    }
+
+    fn observe_resolution<T>(&self, result: &Result<T>, cache_hit: bool) {
+        let type_name = core::any::type_name::<T>();
+
+        self.notify(&match result {
+            Ok(_) => Observed::Resolved {
+                type_name,
+                cache_hit,
+            },
+            Err(error) => Observed::Error { type_name, error },
+        });
+    }
+
+    fn notify(&self, event: &Observed) {
+        if self.inner.observers.borrow().is_empty() {
+            return;
+        }
+
+        // cloned, so observers can resolve and subscribe
+        let observers = self.inner.observers.borrow().clone();
+        observers.notify(event);
+    }
+
+    /// Records that T was handed an item that already existed.
+    fn hit(&self, type_id: TypeId) {
+        self.inner.stats.hit(type_id);
+        self.inner.observers.borrow().hit();
+    }
+
+    fn get_intercepted<T: Clone + 'static>(&self) -> Result<T> {
+        if self.inner.interceptors.borrow().0.is_empty() {
+            return self.get_unintercepted();
+        }
+
+        // cloned, so interceptors can add interceptors
+        let Interceptors(interceptors) = self.inner.interceptors.borrow().clone();
+
+        let resolution = Resolution::of::<T>();
+        let mut entered = 0;
+        let mut result = None;
+
+        for interceptor in &interceptors {
+            entered += 1;
+
+            match interceptor.before_resolve(&resolution) {
+                Ok(None) => continue,
+                Ok(Some(item)) => {
+                    debug!("resolution short-circuited by an interceptor");
+
+                    let item = item.downcast::<T>().map(|item| *item).map_err(|_| {
+                        format!(
+                            "Interceptor returned something other than {}",
+                            resolution.type_name
+                        )
+                        .into()
+                    });
+                    result = Some(item);
+                    break;
+                }
+                Err(error) => {
+                    debug!("resolution vetoed by an interceptor");
+                    result = Some(Err(error));
+                    break;
+                }
+            }
+        }
+
+        let result = result.unwrap_or_else(|| self.get_unintercepted());
+
+        for interceptor in interceptors[..entered].iter().rev() {
+            let outcome = result.as_ref().map(|item| -> &dyn Any { item });
+            interceptor.after_resolve(&resolution, outcome);
+        }
+
+        result
+    }
+
+    fn get_unintercepted<T: Clone + 'static>(&self) -> Result<T> {
+        debug!("resolving type via .get()");
+
+        let type_id = TypeId::of::<T>();
+
+        // built singletons can't be part of a cycle, skip tracking them
+        let overridden = self.inner.overrides.has(type_id) || self.inner.scopes.has_seed(type_id);
+        if !overridden {
+            if let Some(item) = self.get_singleton::<T>(type_id) {
+                self.inner
+                    .usage
+                    .record(type_id, self.inner.scopes.current());
+                self.inner.stats.resolved(type_id);
+                self.hit(type_id);
+
+                return Ok(item);
+            }
+        }
+
+        let item = {
+            let _guard = self.inner.cycle_stopper.track::<T>()?;
+
+            self.get_tracked::<T>(type_id)
+                .map_err(|error| self.inner.cycle_stopper.add_path(error))?
+        };
+
+        self.fill_deferred()?;
+
+        Ok(item)
+    }
+
+    /// Fills in deferred handles, once the outermost resolution is done.
+    fn fill_deferred(&self) -> Result<()> {
+        if !self.inner.cycle_stopper.is_idle() {
+            return Ok(());
+        }
+
+        self.inner.deferred.fill(self)
+    }
+
+    /// Resolves T, once it's tracked by the cycle stopper.
+    fn get_tracked<T: Clone + 'static>(&self, type_id: TypeId) -> Result<T> {
+        if let Some(result) = self.inner.overrides.get(self, type_id) {
+            debug!("resolving via override");
+            return result;
+        }
+
+        if let Some(item) = self.inner.scopes.get_seed(type_id) {
+            debug!("resolving via scope seed");
+            return Ok(item);
+        }
+
+        let resolver_type = self.get_resolver_type(type_id);
+        debug!("resolving via {:?}", resolver_type);
+
+        if resolver_type.is_some() {
+            self.inner
+                .usage
+                .record(type_id, self.inner.scopes.current());
+            self.inner.stats.resolved(type_id);
+        }
+
+        match resolver_type {
+            Some(ResolverType::Factory) => self.call_factory::<T>(type_id),
+            Some(ResolverType::Builder) => {
+                self.consume_builder::<T>()?;
+                self.get_shared(type_id)
+            }
+            Some(ResolverType::Shared) => {
+                self.hit(type_id);
+                self.get_shared(type_id)
+            }
+            Some(ResolverType::Scoped) => self.get_scoped(type_id),
+            None if type_id == TypeId::of::<ContainerHandle>() => Ok(self.get_handle()),
+            None => self.get_fallback::<T>(),
+        }
+    }
+
+    /// Hands out a handle to the container, resolvable without registering it.
+    fn get_handle<T: 'static>(&self) -> T {
+        let handle: Box<dyn Any> = Box::new(self.handle());
+
+        *handle
+            .downcast()
+            .expect("only called when T is ContainerHandle")
+    }
+
+    /// Registered, or resolvable without registering it.
+    fn is_resolvable(&self, type_id: TypeId) -> bool {
+        type_id == TypeId::of::<ContainerHandle>()
+            || self.inner.resolvers.borrow().contains_key(&type_id)
+    }
+
+    fn get_fallback<T: 'static>(&self) -> Result<T> {
+        // cloned, so fallbacks can resolve through other fallbacks
+        let fallbacks = self.inner.fallbacks.borrow().clone();
+
+        fallbacks
+            .resolve(self)
+            .unwrap_or_else(|| Err(self.not_registered::<T>()))
+    }
+
+    fn not_registered<T: 'static>(&self) -> Error {
+        let type_name = core::any::type_name::<T>();
+
+        if self.inner.panicked.borrow().contains(&TypeId::of::<T>()) {
+            return format!("The builder of {} panicked, it can't run again", type_name).into();
+        }
+
+        if let Some(error) = self.inner.rejected.borrow().get(&TypeId::of::<T>()) {
+            return error.clone();
+        }
+
+        if self.inner.pending.borrow().contains_key(&TypeId::of::<T>()) {
+            return format!("{} is still pending, use resolve_async() for it", type_name).into();
+        }
+
+        if self.inner.arg_factories.contains_key(&TypeId::of::<T>()) {
+            return format!("{} takes arguments, use resolve_with() for it", type_name).into();
+        }
+
+        let mut message = format!("Type not registered: {}", type_name);
+
+        // Box<dyn Trait> can't be cloned, so it can't be resolved either. Point
+        // out registering a box where an Rc was expected, the usual mix up.
+        if let Some(boxed) = boxed_counterpart(type_name) {
+            let is_registered = self
+                .inner
+                .registrations
+                .borrow()
+                .values()
+                .any(|registration| registration.type_name == boxed);
+
+            if is_registered {
+                message.push_str(&format!(
+                    ". {} is registered instead, but boxes can't be shared, register it as {}",
+                    boxed, type_name
+                ));
+            }
+        }
+
+        message.into()
+    }
+
+    fn get_resolver_type(&self, type_id: TypeId) -> Option<ResolverType> {
+        self.inner
+            .resolvers
+            .borrow()
+            .get(&type_id)
+            .map(|r| r.into())
+    }
+
+    fn call_factory<T: 'static>(&self, type_id: TypeId) -> Result<T> {
+        // not borrowed while the factory runs, it might need to consume builders
+        let cell = match self.inner.resolvers.borrow().get(&type_id) {
+            Some(Resolver::Factory(cell)) => cell.clone(),
+            other => {
+                let found = other.map(ResolverType::from);
+
+                return Err(InvariantViolation::new::<T>(ResolverType::Factory, found).into());
+            }
+        };
+
+        self.inner
+            .stats
+            .construct(type_id, || self.run_factory(&cell))
+    }
+
+    fn get_scoped<T: Clone + 'static>(&self, type_id: TypeId) -> Result<T> {
+        let (cell, level) = match self.inner.resolvers.borrow().get(&type_id) {
+            Some(Resolver::Scoped(cell, level)) => (cell.clone(), *level),
+            other => {
+                let found = other.map(ResolverType::from);
+
+                return Err(InvariantViolation::new::<T>(ResolverType::Scoped, found).into());
+            }
+        };
+
+        let scope = match level {
+            Some(level) => self.inner.scopes.innermost(level).ok_or_else(|| {
+                format!(
+                    "Scoped type {} resolved outside of a \"{}\" scope",
+                    core::any::type_name::<T>(),
+                    level
+                )
+            })?,
+            None => self.inner.scopes.current().ok_or_else(|| {
+                format!(
+                    "Scoped type {} resolved outside of a scope",
+                    core::any::type_name::<T>()
+                )
+            })?,
+        };
+
+        if let Some(item) = self.inner.scopes.get::<T>(scope, type_id) {
+            self.hit(type_id);
+            return Ok(item);
+        }
+
+        let item: T = self
+            .inner
+            .stats
+            .construct(type_id, || self.run_factory(&cell))?;
+
+        self.inner
+            .scopes
+            .insert(scope, type_id, Box::new(item.clone()));
+
+        Ok(item)
+    }
+
+    fn run_factory<T: 'static>(&self, cell: &RefCell<ErasedFactory>) -> Result<T> {
+        // only if T got past the cycle stopper, like when a factory is
+        // shared between types
+        let mut factory = cell
+            .try_borrow_mut()
+            .map_err(|_| self.inner.cycle_stopper.cycle::<T>())?;
+        let item = factory.call::<T>(self)?;
+        drop(factory);
+
+        self.transform(item)
+    }
+
+    fn transform<T: 'static>(&self, item: T) -> Result<T> {
+        match self.inner.transformers.get(&TypeId::of::<T>()) {
+            Some(boxed) => {
+                let transformer = boxed
+                    .downcast_ref::<Box<Transformer<T>>>()
+                    .expect("could not downcast transformer");
+
+                transformer(item)
+            }
+            None => Ok(item),
+        }
+    }
+
+    fn consume_builder<T: 'static>(&self) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+
+        let resolver = self.inner.resolvers.borrow_mut().remove(&type_id);
+        let builder = match resolver {
+            Some(Resolver::Builder(builder)) => builder,
+            other => {
+                let found = other.as_ref().map(ResolverType::from);
+
+                // put back whatever we took out, we only wanted builders
+                if let Some(resolver) = other {
+                    self.inner.resolvers.borrow_mut().insert(type_id, resolver);
+                }
+
+                return Err(InvariantViolation::new::<T>(ResolverType::Builder, found).into());
+            }
+        };
+
+        let mut leftover = None;
+        let item = self.inner.stats.construct(type_id, || {
+            let running = BuilderRun {
+                panicked: &self.inner.panicked,
+                type_id,
+            };
+            let (item, builder) = builder.call::<T>(self);
+            core::mem::forget(running);
+            leftover = builder;
+
+            item
+        });
+
+        let item = match item.and_then(|item| self.transform(item)) {
+            Ok(item) => item,
+            Err(error) => {
+                match leftover {
+                    // failed builders run again the next time T is resolved
+                    Some(builder) => {
+                        self.inner
+                            .resolvers
+                            .borrow_mut()
+                            .insert(type_id, Resolver::Builder(builder));
+                    }
+                    // one-shot builders are gone, but the error shouldn't be
+                    None => {
+                        self.inner
+                            .rejected
+                            .borrow_mut()
+                            .insert(type_id, error.clone());
+                    }
+                }
+
+                return Err(error);
+            }
+        };
+
+        if let Some(builder) = leftover {
+            // kept for reset()
+            self.inner.rebuilders.borrow_mut().insert(type_id, builder);
+        }
+
+        trace::built::<T>();
+        self.notify(&Observed::BuilderConsumed {
+            type_name: core::any::type_name::<T>(),
+        });
+
+        let resolver = Resolver::Shared(Rc::new(item));
+
+        self.insert::<T>(resolver)
+    }
+
+    /// Hands out a built singleton, skipping the hooks nobody uses.
+    ///
+    /// Singletons are cached downcastable to T, so this is a lookup and a
+    /// clone. With interceptors, observers, the event log or tracing in
+    /// play, resolutions take the full path instead.
+    fn get_cached<T: Clone + 'static>(&self) -> Option<T> {
+        let hooked = cfg!(feature = "tracing")
+            || self.inner.events.is_enabled()
+            || self.inner.explainer.is_active()
+            || !self.inner.interceptors.borrow().0.is_empty()
+            || !self.inner.observers.borrow().is_empty();
+        if hooked {
+            return None;
+        }
+
+        let type_id = TypeId::of::<T>();
+        if self.inner.overrides.has(type_id) || self.inner.scopes.has_seed(type_id) {
+            return None;
+        }
+
+        let item = self.get_singleton::<T>(type_id)?;
+
+        self.inner
+            .usage
+            .record(type_id, self.inner.scopes.current());
+        self.inner.stats.resolved(type_id);
+        self.inner.stats.hit(type_id);
+
+        Some(item)
+    }
+
+    fn get_singleton<T: Clone + 'static>(&self, type_id: TypeId) -> Option<T> {
+        let singletons = self.inner.singletons.borrow();
+        let item: &T = singletons
+            .get(&type_id)?
+            .downcast_ref()
+            .expect("could not downcast singleton");
+
+        Some(item.clone())
+    }
+
+    fn get_shared<T: Clone + 'static>(&self, type_id: TypeId) -> Result<T> {
+        let resolvers = self.inner.resolvers.borrow();
+
+        match resolvers.get(&type_id) {
+            Some(Resolver::Shared(boxed_any)) => {
+                let item: &T = boxed_any
+                    .downcast_ref()
+                    .expect("could not downcast shared object");
+
+                self.inner
+                    .singletons
+                    .borrow_mut()
+                    .insert(type_id, boxed_any.clone());
+
+                Ok(item.clone())
+            }
+            other => {
+                let found = other.map(ResolverType::from);
+
+                Err(InvariantViolation::new::<T>(ResolverType::Shared, found).into())
+            }
+        }
+    }
+
+    fn insert<T: 'static>(&self, resolver: Resolver) -> Result<()> {
+        debug!("inerting new type");
+
+        let type_id = TypeId::of::<T>();
+
+        let mut resolvers = self.inner.resolvers.borrow_mut();
+        if resolvers.contains_key(&type_id) {
+            return Err(format!("Container already has {}", core::any::type_name::<T>()).into());
+        }
+
+        let shared = match &resolver {
+            Resolver::Shared(item) => Some(item.clone()),
+            _ => None,
+        };
+
+        if shared.is_some() {
+            self.inner.constructed.borrow_mut().push(type_id);
+        }
+
+        trace::registered::<T>(ResolverType::from(&resolver));
+        resolvers.insert(type_id, resolver);
+        self.inner
+            .registrations
+            .borrow_mut()
+            .insert(type_id, Registration::of::<T>());
+
+        // initializers can resolve their own dependencies
+        drop(resolvers);
+        if let Some(item) = shared {
+            self.initialize::<T>(&*item);
+        }
+
+        Ok(())
+    }
+
+    fn initialize<T: 'static>(&self, item: &dyn Any) {
+        match self.inner.initializers.get(&TypeId::of::<T>()) {
+            Some(initializer) => {
+                debug!("initializing new item");
+                initializer.run(item, self);
+            }
+            None => self.initialize_automatically::<T>(item),
+        }
+    }
+
+    #[cfg(feature = "specialization")]
+    fn initialize_automatically<T: 'static>(&self, item: &dyn Any) {
+        use initialize::MaybeInitialize;
+
+        item.downcast_ref::<T>()
+            .expect("could not downcast item to initialize")
+            .maybe_initialize(self);
+    }
+
+    #[cfg(not(feature = "specialization"))]
+    fn initialize_automatically<T: 'static>(&self, _: &dyn Any) {}
+
+    #[track_caller]
+    /// Fails if registrations can't change, see freeze().
+    fn check_open<T: 'static>(&self) -> Result<()> {
+        if self.inner.frozen.get() {
+            return Err(format!(
+                "Can't change {}, the container is frozen, reopen() it first",
+                core::any::type_name::<T>()
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn replace_resolver<T: 'static>(&self, resolver: Resolver) -> Result<()> {
+        self.check_open::<T>()?;
+
+        let type_id = TypeId::of::<T>();
+
+        let mut resolvers = self.inner.resolvers.borrow_mut();
+        if !resolvers.contains_key(&type_id) {
+            return Err(self.not_registered::<T>());
+        }
+
+        let mut constructed = self.inner.constructed.borrow_mut();
+        constructed.retain(|constructed| *constructed != type_id);
+        if let Resolver::Shared(_) = resolver {
+            constructed.push(type_id);
+        }
+        drop(constructed);
+
+        let kind = ResolverType::from(&resolver);
+        trace::registered::<T>(kind);
+        let previous = resolvers.insert(type_id, resolver);
+        let previous_singleton = self.inner.singletons.borrow_mut().remove(&type_id);
+        let previous_rebuilder = self.inner.rebuilders.borrow_mut().remove(&type_id);
+        self.inner
+            .registrations
+            .borrow_mut()
+            .insert(type_id, Registration::of::<T>());
+
+        // replaced items may hold on to the container, drop them after
+        // releasing the borrow
+        drop(resolvers);
+        drop(previous);
+        drop(previous_singleton);
+        drop(previous_rebuilder);
+
+        self.notify(&Observed::Registered {
+            type_name: core::any::type_name::<T>(),
+            kind,
+        });
+
+        self.inner.scopes.forget(type_id);
+
+        Ok(())
+    }
+}
+
+fn boxed_counterpart(type_name: &str) -> Option<String> {
+    type_name
+        .strip_prefix("alloc::rc::Rc<")
+        .map(|pointee| format!("alloc::boxed::Box<{}", pointee))
+}
+
+impl Inner {
+    /// Looks up the name of a registered type.
+    ///
+    /// Names are stored when registering, so diagnostics only holding a
+    /// TypeId don't need the type itself.
+    fn type_name(&self, type_id: TypeId) -> Option<&'static str> {
+        self.registrations
+            .borrow()
+            .get(&type_id)
+            .map(|registration| registration.type_name)
+    }
+
+    /// Stops started services, last started first, and returns the failures.
+    fn stop_started(&self) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        loop {
+            let service = match self.started.borrow_mut().pop() {
+                Some(service) => service,
+                None => break,
+            };
+
+            if let Err(error) = service.stop() {
+                failures.push(format!("{}: {}", service.type_name, error));
+            }
+        }
+
+        failures
+    }
+
+    fn shutdown(&self) {
+        debug!("shutting down");
+
+        for failure in self.stop_started() {
+            warn!("could not stop {}", failure);
+        }
+
+        // teardowns only get items nothing else holds on to
+        drop(core::mem::take(&mut *self.singletons.borrow_mut()));
+
+        // Teardowns may use the container, so we don't hold borrows while
+        // calling them.
+        loop {
+            let type_id = match self.constructed.borrow_mut().pop() {
+                Some(type_id) => type_id,
+                None => break,
+            };
+
+            let resolver = self.resolvers.borrow_mut().remove(&type_id);
+            let teardown = self.teardowns.borrow_mut().remove(&type_id);
+
+            if let (Some(Resolver::Shared(item)), Some(Teardown(teardown))) = (resolver, teardown) {
+                debug!(
+                    "tearing down {}",
+                    self.type_name(type_id).unwrap_or("<unknown>")
+                );
+                teardown(item);
+            }
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Default for Container {
+    fn default() -> Container {
+        Container::new()
+    }
+}
+
+#[derive(Debug)]
+enum Resolver {
+    /// Factories get called multiple times
+    ///
+    /// Factories are called by the container, and they themselves will
+    /// call container.resolve() as they see fit. This means we can't
+    /// own a mutable borrow to the resolvers collection during the
+    /// calls. Thus we must use RefCell.
+    ///
+    /// Everything but builders is behind an Rc, so forks can share them.
+    Factory(Rc<RefCell<ErasedFactory>>),
+    Builder(ErasedBuilder),
+    Shared(Rc<dyn Any>),
+    /// Scoped factories get called once per scope, the innermost one
+    /// unless they name the level to cache their items at
+    Scoped(Rc<RefCell<ErasedFactory>>, Option<&'static str>),
+}
+
+impl Resolver {
+    /// Shares the resolver with a fork, builders can't be shared.
+    fn share(&self) -> Option<Resolver> {
+        match self {
+            Resolver::Factory(cell) => Some(Resolver::Factory(cell.clone())),
+            Resolver::Builder(_) => None,
+            Resolver::Shared(item) => Some(Resolver::Shared(item.clone())),
+            Resolver::Scoped(cell, level) => Some(Resolver::Scoped(cell.clone(), *level)),
+        }
+    }
+}
+
+/// Remembers a builder that panicked while running.
+///
+/// Builders are taken out of the container to run, so a panic would leave
+/// the type looking like it was never registered.
+struct BuilderRun<'a> {
+    panicked: &'a RefCell<TypeIdSet>,
+    type_id: TypeId,
+}
+
+impl<'a> Drop for BuilderRun<'a> {
+    fn drop(&mut self) {
+        // only dropped while unwinding, don't panic again
+        if let Ok(mut panicked) = self.panicked.try_borrow_mut() {
+            panicked.insert(self.type_id);
+        }
+    }
+}
+
+fn keep_entries<V: Clone, F>(map: &TypeIdMap<V>, keep: F) -> TypeIdMap<V>
+where
+    F: Fn(&TypeId) -> bool,
+{
+    map.iter()
+        .filter(|(type_id, _)| keep(type_id))
+        .map(|(type_id, value)| (*type_id, value.clone()))
+        .collect()
+}
+
+/// Takes an item out of an Rc that is known not to be shared.
+fn unshare<T>(rc: Rc<T>) -> T {
+    match Rc::try_unwrap(rc) {
+        Ok(item) => item,
+        Err(_) => unreachable!("resolvers are not shared before the container is built"),
+    }
+}
+
+/// The ways a dependency can be registered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ResolverType {
+    /// A new item is created every time the dependency is resolved.
+    Factory,
+    /// The item is created on first use, and then shared.
+    Builder,
+    /// The item is shared.
+    Shared,
+    /// The item is created once per scope.
+    Scoped,
+}
+
+/// Describes a registered dependency.
+///
+/// See [registrations()](struct.Container.html#method.registrations).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct RegistrationInfo {
+    /// Name of the registered type.
+    pub type_name: &'static str,
+    /// How the dependency is resolved.
+    ///
+    /// Builders show up as shared once they have run.
+    pub resolver_type: ResolverType,
+    /// True if a shared item exists already.
+    ///
+    /// Factories and scoped dependencies never keep one in the container.
+    pub built: bool,
+    /// Where the dependency was registered.
+    pub registered_at: &'static Location<'static>,
+}
+
+impl From<&Resolver> for ResolverType {
+    fn from(other: &Resolver) -> Self {
+        use ResolverType::*;
+
+        match other {
+            Resolver::Factory(_) => Factory,
+            Resolver::Builder(_) => Builder,
+            Resolver::Shared(_) => Shared,
+            Resolver::Scoped(..) => Scoped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::builder::ContainerBuilder;
+    use super::{Dependency, ResolverType};
+    use crate::error::{ErrorKind, InvariantViolation};
+    use crate::Resolver;
+    use std::any::TypeId;
+
+    #[test]
+    fn wrong_resolver_types_are_reported_as_invariant_violations() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<i32>(42).unwrap();
+
+        let container = builder.build();
+
+        let error = container
+            .call_factory::<i32>(TypeId::of::<i32>())
+            .unwrap_err();
+        let expected =
+            InvariantViolation::new::<i32>(ResolverType::Factory, Some(ResolverType::Shared));
+        assert_eq!(&ErrorKind::InvariantViolation(expected), error.kind());
+
+        // the shared item is still there
+        assert!(container.consume_builder::<i32>().is_err());
+        assert_eq!(42, container.resolve::<i32>().unwrap());
+    }
+
+    #[test]
+    fn missing_resolvers_are_reported_as_invariant_violations() {
+        let container = ContainerBuilder::new().build();
+
+        let error = container
+            .get_shared::<i32>(TypeId::of::<i32>())
+            .unwrap_err();
+        let expected = InvariantViolation::new::<i32>(ResolverType::Shared, None);
+
+        assert_eq!(&ErrorKind::InvariantViolation(expected), error.kind());
+    }
+
+    #[test]
+    fn transformer_errors_are_returned_from_resolve() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_builder::<i32, _>(|_| 0).unwrap();
+        builder.add_transformer::<i32, _>(|_| Err("invalid".into()));
+        builder.register_try_builder::<i64, _>(|_| Ok(0)).unwrap();
+        builder.add_transformer::<i64, _>(|item| match item {
+            0 => Err("zero".into()),
+            _ => Ok(item),
+        });
+
+        let container = builder.build();
+
+        for _ in 0..2 {
+            assert_eq!(
+                "invalid",
+                container.resolve::<i32>().unwrap_err().to_string()
+            );
+            assert_eq!("zero", container.resolve::<i64>().unwrap_err().to_string());
+        }
+        assert!(container.has::<i64>());
+    }
+
+    #[test]
+    fn decorators_stack_on_every_resolver_type() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<i8>(1).unwrap();
+        builder.register_builder::<i16, _>(|_| 1).unwrap();
+        builder.register_factory::<i32, _>(|_| 1).unwrap();
+        builder.register_scoped::<i64, _>(|_| 1).unwrap();
+
+        builder.decorate::<i8, _>(|item, _| item * 2).unwrap();
+        builder.decorate::<i8, _>(|item, _| item + 1).unwrap();
+        builder.decorate::<i16, _>(|item, _| item * 3).unwrap();
+        builder.decorate::<i32, _>(|item, _| item * 4).unwrap();
+        builder.decorate::<i64, _>(|item, _| item * 5).unwrap();
+        assert!(builder.decorate::<u8, _>(|item, _| item).is_err());
+
+        let container = builder.build();
+        let scope = container.enter_scope();
+
+        assert_eq!(3, scope.resolve::<i8>().unwrap());
+        assert_eq!(3, scope.resolve::<i16>().unwrap());
+        assert_eq!(4, scope.resolve::<i32>().unwrap());
+        assert_eq!(5, scope.resolve::<i64>().unwrap());
+    }
+
+    #[test]
+    fn fallbacks_are_used_only_while_the_primary_fails() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let up = Rc::new(Cell::new(false));
+        let health = up.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_with_fallback::<i32, _, _>(
+                move |_| {
+                    if health.get() {
+                        Ok(1)
+                    } else {
+                        Err("down".into())
+                    }
+                },
+                |_| 2,
+            )
+            .unwrap();
+
+        let container = builder.build();
+
+        assert_eq!(2, container.resolve::<i32>().unwrap());
+        assert_eq!(2, container.resolve::<i32>().unwrap());
+
+        up.set(true);
+        assert_eq!(1, container.resolve::<i32>().unwrap());
+    }
+
+    #[test]
+    fn replacing_drops_cached_items() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_scoped::<i32, _>(|_| 1).unwrap();
+        builder.register_builder::<i64, _>(|_| 1).unwrap();
+
+        let container = builder.build();
+        let scope = container.enter_scope();
+
+        assert_eq!(1, scope.resolve::<i32>().unwrap());
+        assert_eq!(1, scope.resolve::<i64>().unwrap());
+
+        container.replace_factory::<i32, _>(|_| 2).unwrap();
+        container.replace::<i64>(2).unwrap();
+
+        assert_eq!(2, scope.resolve::<i32>().unwrap());
+        assert_eq!(2, scope.resolve::<i64>().unwrap());
+    }
+
+    #[test]
+    fn boxed_trait_objects_resolved_as_rc_get_a_hint() {
+        use std::fmt::Debug;
+        use std::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Box<dyn Debug>>(Box::new(42)).unwrap();
+
+        let container = builder.build();
+        let error = container.resolve::<Rc<dyn Debug>>().unwrap_err();
+
+        assert_eq!(
+            "Type not registered: alloc::rc::Rc<dyn core::fmt::Debug>. \
+             alloc::boxed::Box<dyn core::fmt::Debug> is registered instead, \
+             but boxes can't be shared, register it as alloc::rc::Rc<dyn core::fmt::Debug>",
+            error.to_string()
+        );
+
+        let error = container.resolve::<Rc<i32>>().unwrap_err();
+        assert_eq!("Type not registered: alloc::rc::Rc<i32>", error.to_string());
+    }
+
+    #[test]
+    fn boxed_trait_objects_are_shared_without_the_box() {
+        use std::fmt::Debug;
+        use std::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_boxed_trait::<dyn Debug>(Box::new(42))
+            .unwrap();
+
+        let container = builder.build();
+
+        let item = container.resolve_trait::<dyn Debug>().unwrap();
+        assert_eq!("42", format!("{:?}", item));
+        assert!(Rc::ptr_eq(
+            &item,
+            &container.resolve::<Rc<dyn Debug>>().unwrap()
+        ));
+        assert!(container.resolve::<Rc<Box<dyn Debug>>>().is_err());
+    }
+
+    #[test]
+    fn registered_rcs_keep_their_pointer() {
+        use std::fmt::Debug;
+        use std::rc::Rc;
+
+        let shared: Rc<dyn Debug> = Rc::new(42);
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_rc::<dyn Debug>(shared.clone()).unwrap();
+        assert!(builder.register_rc::<dyn Debug>(Rc::new(1)).is_err());
+
+        let container = builder.build();
+
+        assert!(Rc::ptr_eq(
+            &shared,
+            &container.resolve_trait::<dyn Debug>().unwrap()
+        ));
+        // ours, and the one the container keeps
+        assert_eq!(2, Rc::strong_count(&shared));
+    }
+
+    #[test]
+    fn nested_failures_name_the_resolution_path() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_try_factory::<i64, _>(|container| Ok(container.resolve::<i32>()?.into()))
+            .unwrap();
+        builder
+            .register_try_factory::<i32, _>(|container| Ok(container.resolve::<i16>()?.into()))
+            .unwrap();
+
+        let container = builder.build();
+        let error = container.resolve::<i64>().unwrap_err();
+
+        assert_eq!(&["i64", "i32", "i16"], error.path());
+        assert_eq!(
+            "while resolving i64 -> i32 -> i16: Type not registered: i16",
+            error.to_string()
+        );
+
+        // the path is reported from wherever resolving started
+        let error = container.resolve::<i32>().unwrap_err();
+        assert_eq!(
+            "while resolving i32 -> i16: Type not registered: i16",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn defaults_are_only_created_when_asked_for() {
+        use crate::DefaultFallback;
+        use std::rc::Rc;
+
+        let container = ContainerBuilder::new().build();
+        assert_eq!(
+            "Type not registered: u32",
+            container
+                .resolve_or_default::<u32>()
+                .unwrap_err()
+                .to_string()
+        );
+
+        let mut builder = ContainerBuilder::new();
+        builder.fall_back_to_default(DefaultFallback::Create);
+        let container = builder.build();
+
+        let first = container.resolve_or_default::<Rc<u32>>().unwrap();
+        assert!(!Rc::ptr_eq(
+            &first,
+            &container.resolve_or_default().unwrap()
+        ));
+        assert!(container.resolve::<Rc<u32>>().is_err());
+
+        let mut builder = ContainerBuilder::new();
+        builder.fall_back_to_default(DefaultFallback::Share);
+        let container = builder.build();
+
+        let first = container.resolve_or_default::<Rc<u32>>().unwrap();
+        assert!(Rc::ptr_eq(&first, &container.resolve::<Rc<u32>>().unwrap()));
+    }
+
+    #[test]
+    fn failing_factories_are_named_at_the_end_of_the_path() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_try_factory::<i64, _>(|container| Ok(container.resolve::<i32>()?.into()))
+            .unwrap();
+        builder
+            .register_try_factory::<i32, _>(|_| Err("host unreachable".into()))
+            .unwrap();
+
+        let error = builder.build().resolve::<i64>().unwrap_err();
+
+        assert_eq!(
+            "while resolving i64 -> i32: Could not create i32: host unreachable",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn registrations_describe_resolver_types() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_factory::<i32, _>(|_| 1).unwrap();
+        builder.register_scoped::<i64, _>(|_| 2).unwrap();
+
+        let container = builder.build();
+        let _scope = container.enter_scope();
+        container.resolve::<i32>().unwrap();
+        container.resolve::<i64>().unwrap();
+
+        let described: Vec<_> = container
+            .registrations()
+            .map(|registration| {
+                (
+                    registration.type_name,
+                    registration.resolver_type,
+                    registration.built,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("i32", ResolverType::Factory, false),
+                ("i64", ResolverType::Scoped, false)
+            ],
+            described
+        );
+    }
+
+    #[test]
+    fn warm_up_reports_every_failure() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_builder::<i32, _>(|_| 1).unwrap();
+        builder.register_builder::<i64, _>(|_| 2).unwrap();
+        builder.register_builder::<u8, _>(|_| 3).unwrap();
+        builder.add_transformer::<i32, _>(|_| Err("no i32 today".into()));
+        builder.add_transformer::<i64, _>(|_| Err("no i64 either".into()));
+
+        let container = builder.build();
+
+        let error = container.warm_up().unwrap_err();
+        assert_eq!(
+            "Warm up failed:\n  i32: no i32 today\n  i64: no i64 either",
+            error.to_string()
+        );
+        assert_eq!(3, container.resolve::<u8>().unwrap());
+    }
+
+    #[test]
+    fn warm_up_runs_dependencies_first() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_builder_with_deps::<i32, (i64,), _>(|_| 1)
+            .unwrap();
+        builder.register_builder::<i64, _>(|_| 2).unwrap();
+        builder.add_transformer::<i32, _>(|_| Err("no i32 today".into()));
+        builder.add_transformer::<i64, _>(|_| Err("no i64 either".into()));
+
+        let container = builder.build();
+
+        let error = container.warm_up().unwrap_err();
+        assert_eq!(
+            "Warm up failed:\n  i64: no i64 either\n  i32: no i32 today",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn cached_singletons_are_still_counted_and_observed() {
+        use super::Observed;
+        use alloc::rc::Rc;
+        use core::cell::Cell;
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(1).unwrap();
+        builder.record_stats();
+
+        let container = builder.build();
+        container.resolve::<u32>().unwrap();
+        container.resolve::<u32>().unwrap();
+
+        let hits = Rc::new(Cell::new(0));
+        let counter = hits.clone();
+        container.subscribe(Rc::new(move |event: &Observed| {
+            if let Observed::Resolved {
+                cache_hit: true, ..
+            } = event
+            {
+                counter.set(counter.get() + 1);
+            }
+        }));
+        container.resolve::<u32>().unwrap();
+
+        assert_eq!(1, hits.get());
+        let report = container.stats();
+        let stats = report.of::<u32>().unwrap();
+        assert_eq!((3, 3), (stats.resolutions, stats.cache_hits));
+    }
+
+    #[test]
+    fn watched_builders_are_checked_once_built() {
+        use super::Leak;
+        use alloc::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_builder::<Rc<u32>, _>(|_| Rc::new(1))
+            .unwrap();
+        builder.register_factory::<i32, _>(|_| 2).unwrap();
+        builder.watch_for_leaks::<u32>();
+
+        let container = builder.build();
+        assert!(container.report_leaks().leaks.is_empty());
+
+        let kept = container.resolve::<Rc<u32>>().unwrap();
+        let _also_kept = kept.clone();
+
+        let report = container.report_leaks();
+        assert_eq!(
+            vec![Leak {
+                type_name: "alloc::rc::Rc<u32>",
+                outside_references: 2,
+            }],
+            report.leaks
+        );
+        assert_eq!(vec!["i32"], report.never_resolved);
+    }
+
+    #[test]
+    fn builders_start_by_priority_after_their_dependencies() {
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
+
+        let built = Rc::new(RefCell::new(Vec::new()));
+
+        let mut builder = ContainerBuilder::new();
+        let log = built.clone();
+        builder
+            .register_eager_with_priority::<u8, _>(-1, move |_| {
+                log.borrow_mut().push("u8");
+                1
+            })
+            .unwrap();
+        let log = built.clone();
+        builder
+            .register_eager_with_priority::<u64, _>(5, move |_| {
+                log.borrow_mut().push("u64");
+                2
+            })
+            .unwrap();
+        let log = built.clone();
+        builder
+            .register_builder_with_deps::<i32, (u64,), _>(move |_| {
+                log.borrow_mut().push("i32");
+                3
+            })
+            .unwrap();
+        let log = built.clone();
+        builder
+            .register_builder::<u32, _>(move |_| {
+                log.borrow_mut().push("u32");
+                4
+            })
+            .unwrap();
+
+        let container = builder.build();
+        // i32 pulls u64 forward, ahead of u32
+        assert_eq!(vec!["u8", "u64", "i32", "u32"], container.startup_order());
+
+        container.warm_up().unwrap();
+        assert_eq!(vec!["u8", "u64", "i32", "u32"], *built.borrow());
+    }
+
+    #[test]
+    fn panicking_builders_are_reported() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_builder::<u32, _>(|_| panic!("no database"))
+            .unwrap();
+
+        let container = builder.build();
+
+        let result = catch_unwind(AssertUnwindSafe(|| container.resolve::<u32>()));
+        assert!(result.is_err());
+
+        let error = container.resolve::<u32>().err().unwrap();
+        assert_eq!(
+            "The builder of u32 panicked, it can't run again",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn the_container_resolves_handles_to_itself() {
+        use super::ContainerHandle;
+        use alloc::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+        builder
+            .register_builder::<Rc<ContainerHandle>, _>(|container| {
+                Rc::new(container.resolve().unwrap())
+            })
+            .unwrap();
+        builder.declare_dependencies::<Rc<ContainerHandle>, (ContainerHandle,)>();
+
+        let container = builder.build();
+        assert!(container.has::<ContainerHandle>());
+        container.validate().unwrap();
+
+        let handle = container.resolve::<Rc<ContainerHandle>>().unwrap();
+        assert_eq!(42, handle.resolve::<u32>().unwrap());
+
+        // handles don't keep the container alive
+        drop(container);
+        assert!(handle.resolve::<u32>().is_err());
+    }
+
+    #[test]
+    fn conversions_resolve_their_source() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_conversion::<u8, u64>().unwrap();
+        builder.register_try_conversion::<u64, i8>().unwrap();
+
+        let container = builder.build();
+        assert_eq!(
+            "Container validation failed:\n  u64 depends on u8, which is not registered",
+            container.validate().err().unwrap().to_string()
+        );
+        assert_eq!(
+            "while resolving u64 -> u8: Type not registered: u8",
+            container.resolve::<u64>().err().unwrap().to_string()
+        );
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(200).unwrap();
+        builder.register_conversion::<u8, u64>().unwrap();
+        builder.register_try_conversion::<u64, i8>().unwrap();
+
+        let container = builder.build();
+        assert_eq!(200, container.resolve::<u64>().unwrap());
+        assert!(container.resolve::<i8>().is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn timeouts_name_the_pending_item_that_stalled() {
+        use super::Container;
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
+        use core::future::{pending, Future};
+        use core::pin::{pin, Pin};
+        use core::task::{Context, Poll, Waker};
+        use core::time::Duration;
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let mut future = pin!(future);
+            let mut cx = Context::from_waker(Waker::noop());
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            }
+        }
+
+        let slot: Rc<RefCell<Option<Container>>> = Default::default();
+        let container_slot = slot.clone();
+
+        let stalled: Pin<Box<dyn Future<Output = u8>>> = Box::pin(pending());
+        let waiting: Pin<Box<dyn Future<Output = u32>>> = Box::pin(async move {
+            let container = container_slot.borrow().clone().unwrap();
+            let item = container
+                .resolve_async_timeout::<u8>(Duration::from_secs(60))
+                .await
+                .unwrap();
+
+            u32::from(item)
+        });
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_pending(stalled).unwrap();
+        builder.register_pending(waiting).unwrap();
+        builder.async_timeout(Duration::from_millis(10));
+
+        let container = builder.build();
+        *slot.borrow_mut() = Some(container.clone());
+
+        let error = block_on(container.resolve_async::<u32>()).err().unwrap();
+        assert_eq!(
+            "Timed out after 10ms resolving u32, waiting for u32 -> u8",
+            error.to_string()
+        );
+        assert_eq!(0, container.inner.trail.depth());
+
+        slot.borrow_mut().take();
+    }
+
+    #[test]
+    fn failed_transactions_register_nothing() {
+        let mut builder = ContainerBuilder::with_profile("prod");
+        builder.register::<u32>(1).unwrap();
+
+        builder
+            .transaction(|tx| {
+                tx.register::<u64>(2)?;
+                tx.register::<u64>(3)
+            })
+            .unwrap_err();
+        let error = builder
+            .transaction(|tx| {
+                tx.register::<i8>(4)?;
+                tx.register::<u32>(5)
+            })
+            .unwrap_err();
+        assert_eq!("Can't merge builders, both have: u32", error.to_string());
+        assert!(!builder.has::<u64>() && !builder.has::<i8>());
+
+        builder
+            .transaction(|tx| {
+                tx.register::<u64>(6)?;
+                tx.register_for_profile("prod", |tx| tx.register::<i8>(7))
+            })
+            .unwrap();
+
+        let container = builder.build();
+        assert_eq!(6, container.resolve::<u64>().unwrap());
+        assert_eq!(7, container.resolve::<i8>().unwrap());
+    }
+
+    #[test]
+    fn duplicates_follow_the_policy() {
+        use crate::DuplicatePolicy;
+
+        let mut builder = ContainerBuilder::new();
+        builder.on_duplicate(DuplicatePolicy::Ignore);
+        builder.register::<u32>(1).unwrap();
+        builder.register_factory::<u32, _>(|_| 2).unwrap();
+        builder
+            .register_factory_with_args::<u32, u8, _>(|_, arg| arg.into())
+            .unwrap();
+
+        builder.on_duplicate(DuplicatePolicy::Replace);
+        builder.register_factory::<u64, _>(|_| 3).unwrap();
+        builder.register_builder::<u64, _>(|_| 4).unwrap();
+
+        builder.on_duplicate(DuplicatePolicy::Collect);
+        builder.register_factory::<i8, _>(|_| 5).unwrap();
+        let error = builder.register::<i8>(6).unwrap_err();
+        assert_eq!(
+            "Can't collect i8, only registered items can be collected",
+            error.to_string()
+        );
+
+        let container = builder.build();
+        assert_eq!(1, container.resolve::<u32>().unwrap());
+        assert_eq!(4, container.resolve::<u64>().unwrap());
+        assert_eq!(
+            Some(ResolverType::Shared),
+            container.get_resolver_type(TypeId::of::<u64>())
+        );
+        assert_eq!(5, container.resolve::<i8>().unwrap());
+    }
+
+    #[test]
+    fn explaining_shows_what_gets_rebuilt() {
+        use super::{ResolvedVia, ResolverType};
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+        builder
+            .register_try_builder::<u16, _>(|container| Ok(container.resolve::<u8>()?.into()))
+            .unwrap();
+        builder
+            .register_try_factory::<u32, _>(|container| Ok(container.resolve::<u16>()?.into()))
+            .unwrap();
+        builder
+            .register_try_factory::<u64, _>(|container| Ok(container.resolve::<i8>()? as u64))
+            .unwrap();
+
+        let container = builder.build();
+
+        let first = container.explain::<u32>();
+        assert_eq!(vec!["u16", "u32"], first.constructions());
+        assert_eq!(
+            ResolvedVia::Registration(ResolverType::Builder),
+            first.root.dependencies[0].via
+        );
+        assert!(first.root.dependencies[0].dependencies[0].cache_hit);
+
+        let second = container.explain::<u32>();
+        assert_eq!(vec!["u32"], second.constructions());
+        assert_eq!(
+            ResolvedVia::Registration(ResolverType::Shared),
+            second.root.dependencies[0].via
+        );
+        assert!(second.root.dependencies[0].cache_hit);
+        assert!(second.root.dependencies[0].dependencies.is_empty());
+
+        let failed = container.explain::<u64>();
+        assert!(failed.root.error.is_some());
+        assert_eq!(ResolvedVia::Unregistered, failed.root.dependencies[0].via);
+        assert_eq!(
+            Some("while resolving u64 -> i8: Type not registered: i8".to_string()),
+            failed.root.dependencies[0].error
+        );
+
+        // explaining is over, resolving records nothing
+        container.resolve::<u32>().unwrap();
+        assert!(!container.inner.explainer.is_active());
+    }
+
+    #[test]
+    fn weak_cached_items_are_rebuilt_once_dropped() {
+        use super::Container;
+        use alloc::rc::Rc;
+        use core::cell::Cell;
+
+        let built = Rc::new(Cell::new(0));
+        let counter = built.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_weak_cached::<String, _>(move |_| {
+                counter.set(counter.get() + 1);
+                "rules".to_string()
+            })
+            .unwrap();
+        builder.record_stats();
+
+        let container: Container = builder.build();
+
+        let first = container.resolve::<Rc<String>>().unwrap();
+        let second = container.resolve::<Rc<String>>().unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(1, built.get());
+        assert_eq!(1, container.stats().of::<Rc<String>>().unwrap().cache_hits);
+
+        drop((first, second));
+
+        container.resolve::<Rc<String>>().unwrap();
+        assert_eq!(2, built.get());
+    }
+
+    #[test]
+    fn definitions_combine_options() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .define::<u8>()
+            .priority(-1)
+            .tagged("numbers")
+            .from_factory(|_| 1)
+            .unwrap();
+        builder
+            .define::<u16>()
+            .in_scope("request")
+            .from_factory(|_| 2)
+            .unwrap();
+        builder
+            .define::<u32>()
+            .transient()
+            .tagged("numbers")
+            .from_factory(|_| 3)
+            .unwrap();
+
+        let error = builder
+            .define::<u64>()
+            .scoped()
+            .lazy()
+            .from_factory(|_| 4)
+            .unwrap_err();
+        assert_eq!(
+            "u64 is Scoped, only singletons can be lazy or have a priority",
+            error.to_string()
+        );
+
+        builder.register_teardown::<i8, _>(|_| ()).unwrap();
+        let error = builder
+            .define::<i8>()
+            .tagged("numbers")
+            .teardown(|_| ())
+            .from_factory(|_| 5)
+            .unwrap_err();
+        assert!(error.to_string().contains("i8"));
+        assert!(!builder.has::<i8>());
+
+        let container = builder.build();
+
+        assert_eq!(vec!["u8"], container.startup_order());
+        assert_eq!(
+            Some(ResolverType::Factory),
+            container.get_resolver_type(TypeId::of::<u32>())
+        );
+        assert_eq!(2, container.resolve_tagged("numbers").unwrap().len());
+        assert!(container.resolve::<u16>().is_err());
+        assert_eq!(
+            2,
+            container
+                .enter_named_scope("request")
+                .resolve::<u16>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn marker_types_keep_items_apart() {
+        struct Primary;
+        struct Replica;
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_for::<Primary, u32>(1).unwrap();
+        builder.register_for::<Replica, u32>(2).unwrap();
+        builder.register::<u32>(3).unwrap();
+
+        assert!(builder
+            .register_for::<Primary, u32>(4)
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Container already has"));
+
+        let container = builder.build();
+
+        assert_eq!(1, container.resolve_for::<Primary, u32>().unwrap());
+        assert_eq!(2, container.resolve_for::<Replica, u32>().unwrap());
+        assert_eq!(3, container.resolve::<u32>().unwrap());
+        assert!(container.resolve_for::<Primary, u8>().is_err());
+    }
+
+    #[test]
+    fn forks_need_builders_to_have_run() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_builder::<i32, _>(|_| 1).unwrap();
+
+        let container = builder.build();
+
+        let error = container.fork().unwrap_err();
+        assert_eq!(
+            "Can't fork a container with builders that haven't run yet: i32",
+            error.to_string()
+        );
+
+        container.resolve::<i32>().unwrap();
+        assert_eq!(1, container.fork().unwrap().resolve::<i32>().unwrap());
+    }
+
+    #[test]
+    fn subsets_only_need_their_own_builders_to_have_run() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_builder::<i32, _>(|_| 1).unwrap();
+        builder.register_builder::<u8, _>(|_| 2).unwrap();
+        builder.register_factory::<i64, _>(|_| 3).unwrap();
+        builder.declare_dependencies::<i64, (i32,)>();
+
+        let container = builder.build();
+
+        let error = container.subset_for::<i64>().unwrap_err();
+        assert_eq!(
+            "Can't extract i64 with builders that haven't run yet: i32",
+            error.to_string()
+        );
+
+        container.resolve::<i32>().unwrap();
+        let subset = container.subset_for::<i64>().unwrap();
+
+        assert_eq!(1, subset.resolve::<i32>().unwrap());
+        assert!(subset.resolve::<u8>().is_err());
+        assert!(container.subset_for::<u16>().is_err());
+    }
+
+    #[test]
+    fn teardowns_are_skipped_for_items_shared_with_forks() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let torn_down = Rc::new(Cell::new(0));
+        let counter = torn_down.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<i32>(1).unwrap();
+        builder
+            .register_teardown::<i32, _>(move |_| counter.set(counter.get() + 1))
+            .unwrap();
+
+        let container = builder.build();
+        let fork = container.fork().unwrap();
+
+        container.shutdown();
+        drop(fork);
+        assert_eq!(0, torn_down.get());
+    }
+
+    #[test]
+    fn carried_over_items_are_torn_down_once_by_the_last_generation() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let torn_down = Rc::new(Cell::new(0));
+        let counter = torn_down.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_builder::<i32, _>(|_| 1).unwrap();
+        builder.carry_over::<i32>();
+        builder
+            .register_teardown::<i32, _>(move |_| counter.set(counter.get() + 1))
+            .unwrap();
+        builder.register_builder::<u8, _>(|_| 1).unwrap();
+
+        let first = builder.build();
+        first.resolve::<i32>().unwrap();
+        first.resolve::<u8>().unwrap();
+
+        let second = first
+            .rebuild_with(|builder| {
+                builder.register_builder::<i32, _>(|_| 2)?;
+                builder.register_builder::<u8, _>(|_| 2)
+            })
+            .unwrap();
+        drop(first);
+
+        // marks carry on to later generations
+        let third = second.rebuild_with(|_| Ok(())).unwrap();
+        drop(second);
+
+        assert_eq!(1, third.resolve::<i32>().unwrap());
+        assert!(third.resolve::<u8>().is_err());
+        assert_eq!(0, torn_down.get());
+
+        drop(third);
+        assert_eq!(1, torn_down.get());
+    }
+
+    #[test]
+    fn types_with_arguments_are_only_resolved_with_them() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_factory_with_args::<i64, i32, _>(|_, base| i64::from(base) * 2)
+            .unwrap();
+
+        assert!(builder.register::<i64>(1).is_err());
+
+        let fork = builder.build().fork().unwrap();
+
+        assert_eq!(42, fork.resolve_with::<i64, i32>(21).unwrap());
+        assert_eq!(
+            "i64 takes arguments, use resolve_with() for it",
+            fork.resolve::<i64>().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn collections_are_sorted_by_priority_and_stable_for_ties() {
+        use std::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        for (item, priority) in [(1, 0), (2, 5), (3, 0), (4, 5), (5, -1), (6, 0)] {
+            builder.register_in_collection_with_priority::<u32>(Rc::new(item), priority);
+        }
+
+        let container = builder.build();
+        let items: Vec<u32> = container
+            .resolve_all::<u32>()
+            .iter()
+            .map(|item| **item)
+            .collect();
+
+        assert_eq!(vec![2, 4, 1, 3, 6, 5], items);
+    }
+
+    #[test]
+    fn aliases_of_missing_registrations_fail_validation() {
+        use std::fmt::Display;
+        use std::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .alias::<String, dyn Display, _>(|name| name)
+            .unwrap();
+
+        let container = builder.build();
+
+        assert!(container.validate().is_err());
+        assert!(container
+            .resolve::<Rc<dyn Display>>()
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Could not resolve alloc::rc::Rc<dyn core::fmt::Display>, an alias of"));
+    }
+
+    #[test]
+    fn merging_follows_the_conflict_policy() {
+        use super::builder::MergePolicy;
+        use std::rc::Rc;
+
+        trait Mailer {
+            fn name(&self) -> &'static str;
+        }
+
+        struct NullMailer;
+        impl Mailer for NullMailer {
+            fn name(&self) -> &'static str {
+                "null"
+            }
+        }
+
+        impl crate::NullObject for dyn Mailer {
+            fn null() -> Rc<dyn Mailer> {
+                Rc::new(NullMailer)
+            }
+        }
+
+        struct Smtp;
+        impl Mailer for Smtp {
+            fn name(&self) -> &'static str {
+                "smtp"
+            }
+        }
+
+        let first = || {
+            let mut builder = ContainerBuilder::new();
+            builder.register::<u32>(1).unwrap();
+            builder.register_null::<dyn Mailer>().unwrap();
+            builder
+                .register_keyed::<str>("eu", Rc::from("eu-west-1"))
+                .unwrap();
+            builder.register_in_collection::<str>(Rc::from("first"));
+            builder
+        };
+        let second = || {
+            let mut builder = ContainerBuilder::new();
+            builder.register::<u32>(2).unwrap();
+            builder.register::<Rc<dyn Mailer>>(Rc::new(Smtp)).unwrap();
+            builder
+                .register_keyed::<str>("eu", Rc::from("eu-central-1"))
+                .unwrap();
+            builder.register_in_collection_with_priority::<str>(Rc::from("second"), 1);
+            builder
+        };
+
+        let mut builder = first();
+        let error = builder.merge(second(), MergePolicy::Error).unwrap_err();
+        assert_eq!(
+            "Can't merge builders, both have: str for key \"eu\", u32",
+            error.to_string()
+        );
+        assert_eq!(1, builder.build().resolve::<u32>().unwrap());
+
+        let mut builder = first();
+        builder.merge(second(), MergePolicy::KeepFirst).unwrap();
+        let container = builder.build();
+        assert_eq!(1, container.resolve::<u32>().unwrap());
+        assert_eq!(
+            "smtp",
+            container.resolve::<Rc<dyn Mailer>>().unwrap().name()
+        );
+        assert_eq!("eu-west-1", &*container.resolve_keyed::<str>("eu").unwrap());
+        let names: Vec<String> = container
+            .resolve_all::<str>()
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(vec!["second", "first"], names);
+
+        let mut builder = first();
+        builder.merge(second(), MergePolicy::KeepLast).unwrap();
+        let container = builder.build();
+        assert_eq!(2, container.resolve::<u32>().unwrap());
+        assert_eq!(
+            "eu-central-1",
+            &*container.resolve_keyed::<str>("eu").unwrap()
+        );
+    }
+
+    #[test]
+    fn factories_can_depend_on_builders() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_builder::<u32, _>(|_| 42).unwrap();
+        builder
+            .register_factory::<u64, _>(|container| container.resolve::<u32>().unwrap().into())
+            .unwrap();
+
+        let container = builder.build();
+
+        assert_eq!(42, container.resolve::<u64>().unwrap());
+    }
+
+    #[test]
+    fn removed_types_can_be_registered_again() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let torn_down = Rc::new(Cell::new(0));
+        let counter = torn_down.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Rc<u32>>(Rc::new(1)).unwrap();
+        builder
+            .register_teardown::<Rc<u32>, _>(move |_| counter.set(counter.get() + 1))
+            .unwrap();
+        builder
+            .register_scoped::<Rc<i32>, _>(|_| Rc::new(1))
+            .unwrap();
+
+        let container = builder.build();
+        let held = container.resolve::<Rc<u32>>().unwrap();
+
+        container.remove::<Rc<u32>>().unwrap();
+        assert_eq!(1, torn_down.get());
+        assert_eq!(1, *held);
+        assert!(!container.has::<Rc<u32>>());
+        assert!(container.remove::<Rc<u32>>().is_err());
+
+        container
+            .insert::<Rc<u32>>(super::Resolver::Shared(Rc::new(Rc::new(2u32))))
+            .unwrap();
+        assert_eq!(2, *container.resolve::<Rc<u32>>().unwrap());
+
+        let scope = container.enter_scope();
+        let scoped = scope.resolve::<Rc<i32>>().unwrap();
+        scope.remove::<Rc<i32>>().unwrap();
+        assert!(scope.resolve::<Rc<i32>>().is_err());
+        assert_eq!(1, Rc::strong_count(&scoped));
+
+        // teardowns went away with the item
+        drop(scope);
+        drop(container);
+        assert_eq!(1, torn_down.get());
+    }
+
+    #[test]
+    fn named_items_are_found_by_name_only() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_named("retries", 3u32).unwrap();
+        builder.register_named("host", "localhost").unwrap();
+        assert!(builder.register_named("host", "example.com").is_err());
+
+        let container = builder.build();
+        assert!(container.resolve::<u32>().is_err());
+        assert_eq!(vec!["host", "retries"], container.names());
+        assert_eq!(
+            "localhost",
+            *container.resolve_named_as::<&str>("host").unwrap()
+        );
+        assert_eq!(
+            "The item named \"retries\" is not a i64",
+            container
+                .resolve_named_as::<i64>("retries")
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "No item named \"port\", available names: host, retries",
+            container.resolve_named("port").err().unwrap().to_string()
+        );
+
+        let fork = container.fork().unwrap();
+        assert_eq!(3, *fork.resolve_named_as::<u32>("retries").unwrap());
+    }
+
+    #[test]
+    fn only_items_nothing_else_shares_can_be_taken() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let torn_down = Rc::new(Cell::new(false));
+        let flag = torn_down.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<String>("bootstrap".to_string()).unwrap();
+        builder.register_builder::<u64, _>(|_| 42).unwrap();
+        builder.register_factory::<u32, _>(|_| 42).unwrap();
+        builder.register::<i8>(1).unwrap();
+        builder
+            .register_teardown::<i8, _>(move |_| flag.set(true))
+            .unwrap();
+
+        let container = builder.build();
+        assert_eq!("bootstrap", container.resolve::<String>().unwrap());
+        assert_eq!(42, container.take::<u64>().unwrap());
+
+        let fork = container.fork().unwrap();
+        assert!(container
+            .take::<String>()
+            .unwrap_err()
+            .to_string()
+            .contains("still shared with 1 other container(s)"));
+        drop(fork);
+
+        assert_eq!("bootstrap", container.take::<String>().unwrap());
+        assert!(container.resolve::<String>().is_err());
+
+        assert!(container.take::<u32>().is_err());
+        assert_eq!(42, container.resolve::<u32>().unwrap());
+
+        assert_eq!(1, container.take::<i8>().unwrap());
+        container.shutdown();
+        assert!(!torn_down.get());
+    }
+
+    #[test]
+    fn owned_items_are_only_cloned_when_shared() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Counted(Rc<Cell<u32>>);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Counted {
+                self.0.set(self.0.get() + 1);
+                Counted(self.0.clone())
+            }
+        }
+
+        let clones = Rc::new(Cell::new(0));
+
+        let mut builder = ContainerBuilder::new();
+        let counter = clones.clone();
+        builder
+            .register_factory::<Rc<Counted>, _>(move |_| Rc::new(Counted(counter.clone())))
+            .unwrap();
+        builder.register::<Rc<u32>>(Rc::new(42)).unwrap();
+
+        let container = builder.build();
+
+        container.resolve_owned::<Counted>().unwrap();
+        assert_eq!(0, clones.get());
+
+        assert_eq!(42, container.resolve_owned::<u32>().unwrap());
+        assert_eq!(42, *container.resolve::<Rc<u32>>().unwrap());
+        assert!(container.resolve_owned::<String>().is_err());
+    }
+
+    #[test]
+    fn failed_builders_keep_their_decorators() {
+        let mut attempts = 0;
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_try_builder::<u32, _>(move |_| {
+                attempts += 1;
+                if attempts == 1 {
+                    Err("not ready".into())
+                } else {
+                    Ok(attempts)
+                }
+            })
+            .unwrap();
+        builder
+            .decorate::<u32, _>(|attempts, _| attempts * 10)
+            .unwrap();
+
+        let container = builder.build();
+
+        assert!(container
+            .warm_up()
+            .unwrap_err()
+            .to_string()
+            .ends_with("Could not create u32: not ready"));
+        assert_eq!(20, container.resolve::<u32>().unwrap());
+        assert_eq!(20, container.resolve::<u32>().unwrap());
+    }
+
+    #[test]
+    fn contracts_need_a_factory_with_the_same_arguments() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+        builder
+            .register_factory_with_args::<i64, i32, _>(|_, base| i64::from(base) * 2)
+            .unwrap();
+
+        let any = |_: &u8| Ok(());
+        assert!(builder
+            .with_contract::<u8, (), _, _>(|_| Ok(()), any)
+            .is_err());
+        assert_eq!(
+            "i64 takes other arguments than u8",
+            builder
+                .with_contract::<i64, u8, _, _>(|_| Ok(()), |_| Ok(()))
+                .unwrap_err()
+                .to_string()
+        );
+
+        builder
+            .with_contract::<i64, i32, _, _>(
+                |base| match *base >= 0 {
+                    true => Ok(()),
+                    false => Err("negative base".into()),
+                },
+                |_| Ok(()),
+            )
+            .unwrap();
+
+        let container = builder.build();
+        assert_eq!(42, container.resolve_with::<i64, i32>(21).unwrap());
+
+        let broken = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            container.resolve_with::<i64, i32>(-1)
+        }));
+        assert_eq!(cfg!(debug_assertions), broken.is_err());
+    }
+
+    #[test]
+    fn missing_keys_list_the_available_ones() {
+        use std::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_keyed::<str>("eu", Rc::from("eu-west-1"))
+            .unwrap();
+        builder
+            .register_keyed::<str>("us", Rc::from("us-east-1"))
+            .unwrap();
+
+        assert!(builder
+            .register_keyed::<str>("eu", Rc::from("eu-central-1"))
+            .is_err());
+
+        let container = builder.build();
+
+        assert_eq!("eu-west-1", &*container.resolve_keyed::<str>("eu").unwrap());
+        assert_eq!(
+            "No str registered for key \"asia\", available keys: eu, us",
+            container
+                .resolve_keyed::<str>("asia")
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(container.keys::<[u8]>().is_empty());
+    }
+
+    #[test]
+    fn scoped_items_are_dropped_with_the_scope() {
+        use std::rc::Rc;
+
+        let shared = Rc::new(42);
+        let shared_in_factory = shared.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_scoped::<Rc<i32>, _>(move |_| shared_in_factory.clone())
+            .unwrap();
+
+        let container = builder.build();
+
+        {
+            let outer = container.enter_scope();
+            let _outer_item = outer.resolve::<Rc<i32>>().unwrap();
+
+            {
+                let inner = container.enter_scope();
+                let _inner_item = inner.resolve::<Rc<i32>>().unwrap();
+                // ours, the factory's, both scopes and both resolved items
+                assert_eq!(6, Rc::strong_count(&shared));
+            }
+
+            assert_eq!(4, Rc::strong_count(&shared));
+        }
+
+        assert_eq!(2, Rc::strong_count(&shared));
+    }
+
+    #[test]
+    #[should_panic(expected = "web::Handler -> kamikaze_di::container::tests::db::Pool")]
+    fn reports_forbidden_module_dependencies() {
+        let mut builder = ContainerBuilder::new();
+        builder.declare_dependencies::<web::Handler, (db::Pool, i32)>();
+
+        builder.build().assert_no_module_dependency(
+            "kamikaze_di::container::tests::web",
+            "kamikaze_di::container::tests::db",
+        );
+    }
+
+    mod web {
+        pub struct Handler;
+    }
+
+    mod db {
+        pub struct Pool;
+    }
+
+    #[test]
+    fn reports_circular_dependencies() {
+        let mut builder = ContainerBuilder::new();
+
+        builder
+            .register_try_factory::<i32, _>(|container| {
+                let base: i64 = container.resolve()?;
+                Ok(base as i32 - 1)
             })
             .unwrap();
 
         builder
-            .register_factory::<i64, _>(|container| {
-                let base: i32 = container.resolve().unwrap();
-                let base: i64 = base.into();
-                base - 1
+            .register_try_factory::<i64, _>(|container| {
+                let base: i32 = container.resolve()?;
+                Ok(i64::from(base) - 1)
             })
             .unwrap();
 
+        builder
+            .register_factory::<u8, _>(|container| container.resolve::<u8>().map_or(0, |_| 1))
+            .unwrap();
+
         let container = builder.build();
 
-        container.resolve::<i32>().unwrap();
+        let error = container.resolve::<i32>().err().unwrap();
+        assert_eq!(
+            "Circular dependency detected: i32 -> i64 -> i32",
+            error.to_string()
+        );
+
+        // the container is still usable afterwards
+        assert_eq!(0, container.resolve::<u8>().unwrap());
+        assert!(container.resolve::<i64>().is_err());
+    }
+
+    #[test]
+    fn frozen_containers_keep_their_registrations() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+        builder.register::<i8>(1).unwrap();
+        builder.freeze_on_first_resolve();
+
+        let container = builder.build();
+        assert!(!container.is_frozen());
+
+        // failed resolutions count too
+        assert!(container.resolve::<u64>().is_err());
+        assert!(container.is_frozen());
+
+        let error = container.replace::<u32>(1).unwrap_err();
+        assert_eq!(
+            "Can't change u32, the container is frozen, reopen() it first",
+            error.to_string()
+        );
+        assert!(container.swap::<u32>(1).is_err());
+        assert!(container.take::<u32>().is_err());
+        assert!(container.remove::<i8>().is_err());
+        assert_eq!(42, container.resolve::<u32>().unwrap());
+
+        container.reopen();
+        container.remove::<i8>().unwrap();
+        container.resolve::<u32>().unwrap();
+        assert!(!container.is_frozen());
+
+        container.freeze();
+        assert!(container.take::<u32>().is_err());
+    }
+
+    #[test]
+    fn reset_items_are_built_again() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let built = Rc::new(Cell::new(0));
+        let counter = built.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+        builder.register_builder::<i8, _>(|_| 2).unwrap();
+        builder
+            .register_rebuildable::<u32, _>(move |_| {
+                counter.set(counter.get() + 1);
+                counter.get()
+            })
+            .unwrap();
+        builder
+            .register_try_builder::<i32, _>(|container| {
+                let item: u32 = container.resolve()?;
+                Ok(-(item as i32))
+            })
+            .unwrap();
+
+        let container = builder.build();
+
+        // nothing was built yet
+        container.reset::<u32>().unwrap();
+        assert_eq!(-1, container.resolve::<i32>().unwrap());
+        assert_eq!(2, container.resolve::<i8>().unwrap());
+
+        container.reset::<u32>().unwrap();
+        assert_eq!(2, container.resolve::<u32>().unwrap());
+        assert_eq!(2, container.resolve::<u32>().unwrap());
+        assert_eq!(-1, container.resolve::<i32>().unwrap());
+
+        container.reset_all().unwrap();
+        assert_eq!(-3, container.resolve::<i32>().unwrap());
+        assert_eq!(3, built.get());
+
+        let error = container.reset::<i8>().unwrap_err();
+        assert_eq!(
+            "Can't reset i8, it wasn't built by a builder that can run again",
+            error.to_string()
+        );
+        assert!(container.reset::<u8>().is_err());
+        assert!(container.reset::<u64>().is_err());
+
+        container.freeze();
+        let error = container.reset::<u32>().unwrap_err();
+        assert_eq!(
+            "Can't change u32, the container is frozen, reopen() it first",
+            error.to_string()
+        );
+        assert!(container.reset_all().is_err());
+        assert_eq!(3, container.resolve::<u32>().unwrap());
+        container.reopen();
+
+        // replacements aren't rebuilt
+        container.replace::<u32>(7).unwrap();
+        assert!(container.reset::<u32>().is_err());
+        assert_eq!(7, container.resolve::<u32>().unwrap());
+    }
+
+    #[test]
+    fn missing_items_are_registered_once() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+        builder.register_factory::<u16, _>(|_| 2).unwrap();
+
+        let container = builder.build();
+
+        let registered = container
+            .resolve_or_register_with::<u8, _>(|_| unreachable!())
+            .unwrap();
+        assert_eq!(1, registered);
+        assert_eq!(
+            2,
+            container
+                .resolve_or_register_with::<u16, _>(|_| unreachable!())
+                .unwrap()
+        );
+
+        let created = container
+            .resolve_or_register_with::<u32, _>(|container| {
+                let item: u8 = container.resolve().unwrap();
+                u32::from(item) + 2
+            })
+            .unwrap();
+        assert_eq!(3, created);
+        assert_eq!(3, container.resolve::<u32>().unwrap());
+        assert_eq!(
+            Some(ResolverType::Shared),
+            container.get_resolver_type(TypeId::of::<u32>())
+        );
+
+        container.freeze();
+        assert!(container.resolve_or_register_with::<u64, _>(|_| 4).is_err());
+        assert_eq!(
+            3,
+            container
+                .resolve_or_register_with::<u32, _>(|_| unreachable!())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn debug_output_names_types() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+        builder.register_factory::<i64, _>(|_| 42).unwrap();
+        builder.declare_dependencies::<i64, (u32,)>();
+
+        let expected = "registrations: {\"i64\": Factory, \"u32\": Shared}";
+        assert!(format!("{:?}", builder).contains(expected));
+
+        let container = builder.build();
+        let output = format!("{:?}", container);
+
+        assert!(output.contains(expected));
+        assert!(!output.contains("TypeId"));
+        assert!(!format!("{:?}", Dependency::of::<u32>()).contains("TypeId"));
     }
 }
 
@@ -251,4 +5077,6 @@ mod private {
     pub trait Sealed {}
 
     impl Sealed for super::Container {}
+    impl Sealed for super::ContainerHandle {}
+    impl Sealed for super::RequestScope {}
 }
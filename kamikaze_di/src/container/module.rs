@@ -0,0 +1,42 @@
+use super::builder::ContainerBuilder;
+use crate::Result;
+
+/// Groups registrations together.
+///
+/// Libraries can ship modules with their wiring, applications then
+/// compose them with
+/// [add_module()](struct.ContainerBuilder.html#method.add_module).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Module, Resolver, Result};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct NumbersModule;
+///
+/// impl Module for NumbersModule {
+///     fn register(&self, builder: &mut ContainerBuilder) -> Result<()> {
+///         builder.register::<i32>(42)?;
+///         builder.register::<i64>(43)?;
+///
+///         Ok(())
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.add_module(NumbersModule)?;
+///
+/// let container = builder.build();
+///
+/// assert_eq!(container.resolve::<i32>()?, 42);
+/// assert_eq!(container.resolve::<i64>()?, 43);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Module {
+    /// Registers the module's dependencies.
+    fn register(&self, builder: &mut ContainerBuilder) -> Result<()>;
+}
@@ -0,0 +1,536 @@
+use alloc::rc::Rc;
+use core::fmt;
+use core::ops::Deref;
+
+use super::bridge::ExternalResolver;
+use super::fallback::Fallback;
+use super::interceptor::Interceptor;
+use super::overrides::{OverrideGuard, Overrides};
+use super::swap::Swap;
+use super::Container;
+use crate::Result;
+
+/// A container whose registrations can change after it's built.
+///
+/// Containers built with [build()](struct.ContainerBuilder.html#method.build)
+/// only resolve and describe what they were built with. Building with
+/// [build_mutable()](struct.ContainerBuilder.html#method.build_mutable)
+/// instead hands out this handle, which adds replacing, removing and
+/// overriding registrations, and changing how resolution works, like
+/// adding interceptors or fallbacks. It derefs to the container, for
+/// resolving, and the container can be handed out to code that shouldn't
+/// change it.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, Resolver};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<u32>(42)?;
+///
+/// let mutable = builder.build_mutable();
+/// let container: Container = mutable.container().clone();
+///
+/// mutable.replace::<u32>(1)?;
+///
+/// assert_eq!(1, container.resolve::<u32>()?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MutableContainer {
+    container: Container,
+}
+
+impl MutableContainer {
+    pub(super) fn new(container: Container) -> MutableContainer {
+        MutableContainer { container }
+    }
+
+    /// Returns the container, which can resolve but not change anything.
+    pub fn container(&self) -> &Container {
+        &self.container
+    }
+
+    /// Creates an independent, mutable, copy of the container's wiring.
+    ///
+    /// See [fork()](struct.Container.html#method.fork).
+    pub fn fork(&self) -> Result<MutableContainer> {
+        self.container.fork_mutable()
+    }
+
+    /// Pushes a frame of overrides.
+    ///
+    /// Until the frame is popped, overridden types resolve to the stubs
+    /// in the frame instead of their registrations. When several frames
+    /// override the same type, the last pushed one wins. This lets nested
+    /// test helpers each layer their own stubs.
+    ///
+    /// See [Overrides](struct.Overrides.html) for an example.
+    pub fn push_overrides(&self, overrides: Overrides) {
+        self.container.push_overrides(overrides)
+    }
+
+    /// Pops the last pushed frame of overrides, if there is one.
+    pub fn pop_overrides(&self) -> Option<Overrides> {
+        self.container.pop_overrides()
+    }
+
+    /// Overrides T with a stub until the returned guard is dropped.
+    ///
+    /// Shorthand for pushing a frame of [Overrides](struct.Overrides.html)
+    /// with a single stub, that is removed when the guard goes out of scope,
+    /// even if guards are dropped out of order. The registration of T, and
+    /// anything it already created, is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder::<u32, _>(|_| 42)?;
+    ///
+    /// let container = builder.build_mutable();
+    /// assert_eq!(42, container.resolve::<u32>()?);
+    ///
+    /// {
+    ///     let _stub = container.override_scoped::<u32>(1);
+    ///     assert_eq!(1, container.resolve::<u32>()?);
+    /// }
+    ///
+    /// assert_eq!(42, container.resolve::<u32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn override_scoped<T: 'static>(&self, stub: T) -> OverrideGuard<'_> {
+        self.container.override_scoped::<T>(stub)
+    }
+
+    /// Limits how deep resolutions can nest.
+    ///
+    /// A safety net for pathological graphs and runaway automatic
+    /// resolution: once a resolution needs more than `max_depth` others
+    /// inside it, it fails with the path so far, instead of overflowing
+    /// the stack. Built singletons don't count, they're handed out without
+    /// resolving anything. There's no limit by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_factory::<u8, _>(|_| 1)?;
+    /// builder.register_try_factory::<u16, _>(|container| Ok(container.resolve::<u8>()?.into()))?;
+    /// builder.register_try_factory::<u32, _>(|container| Ok(container.resolve::<u16>()?.into()))?;
+    ///
+    /// let container = builder.build_mutable();
+    /// container.set_max_depth(2);
+    ///
+    /// assert_eq!(1, container.resolve::<u16>()?);
+    ///
+    /// let error = container.resolve::<u32>().err().unwrap();
+    /// assert_eq!(vec!["u32", "u16", "u8"], error.path());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_max_depth(&self, max_depth: usize) {
+        self.container.set_max_depth(max_depth)
+    }
+
+    /// Adds an interceptor, nested inside the ones added before it.
+    ///
+    /// See [Interceptor](trait.Interceptor.html).
+    pub fn add_interceptor(&self, interceptor: Rc<dyn Interceptor>) {
+        self.container.add_interceptor(interceptor)
+    }
+
+    /// Adds a fallback, tried after the ones added before it.
+    ///
+    /// Fallbacks are only asked for types that aren't registered, overridden
+    /// or seeded in a scope. They're asked every time, the container doesn't
+    /// keep what they return. See [Fallback](trait.Fallback.html).
+    pub fn set_fallback(&self, fallback: Rc<dyn Fallback>) {
+        self.container.set_fallback(fallback)
+    }
+
+    /// Delegates types that aren't registered to another container.
+    ///
+    /// The other container is asked like a fallback, after the fallbacks
+    /// added before it, and can resolve what it needs from this one. A type
+    /// neither of them has, which the other container asks for right back,
+    /// fails instead of bouncing between them. See
+    /// [ExternalResolver](trait.ExternalResolver.html).
+    pub fn bridge(&self, external: Rc<dyn ExternalResolver>) {
+        self.container.bridge(external)
+    }
+
+    /// Stops registrations from changing.
+    ///
+    /// [replace()](struct.MutableContainer.html#method.replace), [swap()](struct.MutableContainer.html#method.swap),
+    /// [take()](struct.MutableContainer.html#method.take) and [remove()](struct.MutableContainer.html#method.remove)
+    /// return an error until the container is reopened. Containers built
+    /// with [freeze_on_first_resolve()](struct.ContainerBuilder.html#method.freeze_on_first_resolve)
+    /// freeze on their own.
+    pub fn freeze(&self) {
+        self.container.freeze()
+    }
+
+    /// Lets registrations change again, see [freeze()](struct.MutableContainer.html#method.freeze).
+    ///
+    /// Also stops freezing on the next resolution, so the container stays
+    /// open until it's frozen again.
+    pub fn reopen(&self) {
+        self.container.reopen()
+    }
+
+    /// Replaces the registration of T with an item.
+    ///
+    /// Unlike registering, this overwrites what is already there, which is
+    /// handy for swapping real services for stubs in tests. Items already
+    /// created for T, like built or scoped ones, are dropped without
+    /// running teardowns. Returns an error if T is not registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder::<u32, _>(|_| 42)?;
+    ///
+    /// let container = builder.build_mutable();
+    /// assert_eq!(42, container.resolve::<u32>()?);
+    ///
+    /// container.replace::<u32>(1)?;
+    /// assert_eq!(1, container.resolve::<u32>()?);
+    ///
+    /// assert!(container.replace::<i8>(1).is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn replace<T: 'static>(&self, item: T) -> Result<()> {
+        self.container.replace::<T>(item)
+    }
+
+    /// Replaces the registration of T with a factory.
+    ///
+    /// See [replace()](struct.MutableContainer.html#method.replace).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<u32>(42)?;
+    ///
+    /// let container = builder.build_mutable();
+    ///
+    /// let mut i = 0;
+    /// container.replace_factory::<u32, _>(move |_| {
+    ///     i += 1;
+    ///     i
+    /// })?;
+    ///
+    /// assert_eq!(1, container.resolve::<u32>()?);
+    /// assert_eq!(2, container.resolve::<u32>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn replace_factory<T, F>(&self, factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        self.container.replace_factory::<T, F>(factory)
+    }
+
+    /// Hot-swaps the registration of T, while the container is in use.
+    ///
+    /// Takes an item, or a [Swap](struct.Swap.html) for factories and
+    /// builders. The shared item cached for T, if any, is dropped along with
+    /// the old registration, so the next resolution gets the new version.
+    /// Whoever resolved T before keeps the old one, as do items built from
+    /// it, until they resolve it again. Returns an error if T is not
+    /// registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver, Swap};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Config { level: &'static str }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<Config>>(Rc::new(Config { level: "info" }))?;
+    ///
+    /// let container = builder.build_mutable();
+    /// let old = container.resolve::<Rc<Config>>()?;
+    ///
+    /// // after reloading the configuration
+    /// container.swap::<Rc<Config>>(Rc::new(Config { level: "debug" }))?;
+    /// assert_eq!("debug", container.resolve::<Rc<Config>>()?.level);
+    /// assert_eq!("info", old.level);
+    ///
+    /// container.swap::<Rc<Config>>(Swap::builder(|_| Rc::new(Config { level: "warn" })))?;
+    /// assert_eq!("warn", container.resolve::<Rc<Config>>()?.level);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn swap<T: 'static>(&self, with: impl Into<Swap<T>>) -> Result<()> {
+        self.container.swap::<T>(with)
+    }
+
+    /// Moves the item of T out of the container.
+    ///
+    /// Meant for things that are used up exactly once, like a bootstrapper
+    /// or the receiving end of a channel. T is no longer registered
+    /// afterwards, and its teardown doesn't run. Builders run first, if they
+    /// haven't already. Returns an error if T is not registered as a shared
+    /// item, or if the item is still shared with a
+    /// [fork()](struct.Container.html#method.fork), in which case T stays
+    /// registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::sync::mpsc::{channel, Receiver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let (sender, receiver) = channel::<u32>();
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Receiver<u32>>(receiver)?;
+    ///
+    /// let container = builder.build_mutable();
+    ///
+    /// let receiver = container.take::<Receiver<u32>>()?;
+    /// sender.send(42).unwrap();
+    ///
+    /// assert_eq!(42, receiver.recv().unwrap());
+    /// assert!(container.take::<Receiver<u32>>().is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take<T: 'static>(&self) -> Result<T> {
+        self.container.take::<T>()
+    }
+
+    /// Unregisters T, dropping everything the container keeps for it.
+    ///
+    /// Meant for freeing heavy services in long running apps. The resolver
+    /// goes away along with the cached item, items in scopes, and a pending
+    /// future. Shared items are torn down, see
+    /// [register_teardown()](struct.ContainerBuilder.html#method.register_teardown).
+    /// Anything that already resolved T keeps its item, and T can be
+    /// registered again afterwards. Returns an error if T is not registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Index { entries: Vec<u64> }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_builder::<Rc<Index>, _>(|_| Rc::new(Index { entries: vec![0; 1024] }))?;
+    ///
+    /// let container = builder.build_mutable();
+    /// let index = container.resolve::<Rc<Index>>()?;
+    ///
+    /// container.remove::<Rc<Index>>()?;
+    ///
+    /// assert!(!container.has::<Rc<Index>>());
+    /// assert!(container.resolve::<Rc<Index>>().is_err());
+    /// assert_eq!(1024, index.entries.len());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove<T: 'static>(&self) -> Result<()> {
+        self.container.remove::<T>()
+    }
+
+    /// Drops the built item of T, so the next resolution builds it again.
+    ///
+    /// Meant for reloading configuration, or for tests. Only works for
+    /// builders that can run again, registered via
+    /// [register_rebuildable()](struct.ContainerBuilder.html#method.register_rebuildable)
+    /// or [register_try_builder()](struct.ContainerBuilder.html#method.register_try_builder).
+    /// Builders registered via
+    /// [register_builder()](struct.ContainerBuilder.html#method.register_builder)
+    /// run only once, so resetting their items fails, and so does resetting
+    /// while the container is frozen.
+    /// Anything that already resolved T keeps the old item, which isn't torn
+    /// down, the teardown is kept for the new one. Resetting a builder that
+    /// hasn't run yet does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Settings { version: u32 }
+    ///
+    /// let version = Rc::new(Cell::new(1));
+    /// let current = version.clone();
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_rebuildable::<Rc<Settings>, _>(move |_| {
+    ///     Rc::new(Settings { version: current.get() })
+    /// })?;
+    ///
+    /// let container = builder.build_mutable();
+    /// let old = container.resolve::<Rc<Settings>>()?;
+    ///
+    /// version.set(2);
+    /// container.reset::<Rc<Settings>>()?;
+    ///
+    /// assert_eq!(2, container.resolve::<Rc<Settings>>()?.version);
+    /// assert_eq!(1, old.version);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reset<T: 'static>(&self) -> Result<()> {
+        self.container.reset::<T>()
+    }
+
+    /// Drops every item built by a builder that can run again.
+    ///
+    /// See [reset()](struct.MutableContainer.html#method.reset). Other shared
+    /// items are kept. Fails while the container is frozen.
+    pub fn reset_all(&self) -> Result<()> {
+        self.container.reset_all()
+    }
+
+    /// Resolves T, or registers the item `create` builds if T isn't registered.
+    ///
+    /// The item is shared from then on, like with
+    /// [register()](struct.ContainerBuilder.html#method.register), and
+    /// `create` only runs if T isn't registered, so there's no need to check
+    /// [has()](trait.Resolver.html#tymethod.has) first. Fails if the
+    /// container is frozen and T isn't registered, see
+    /// [freeze()](struct.MutableContainer.html#method.freeze).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::ContainerBuilder;
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// struct Cache { capacity: usize }
+    ///
+    /// let container = ContainerBuilder::new().build_mutable();
+    ///
+    /// let cache = container.resolve_or_register_with::<Rc<Cache>, _>(|_| {
+    ///     Rc::new(Cache { capacity: 64 })
+    /// })?;
+    /// let same = container.resolve_or_register_with::<Rc<Cache>, _>(|_| unreachable!())?;
+    ///
+    /// assert!(Rc::ptr_eq(&cache, &same));
+    /// assert_eq!(64, same.capacity);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_or_register_with<T, F>(&self, create: F) -> Result<T>
+    where
+        F: FnOnce(&Container) -> T,
+        T: Clone + 'static,
+    {
+        self.container.resolve_or_register_with::<T, F>(create)
+    }
+}
+
+impl Deref for MutableContainer {
+    type Target = Container;
+
+    fn deref(&self) -> &Container {
+        &self.container
+    }
+}
+
+impl fmt::Debug for MutableContainer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("MutableContainer")
+            .field(&self.container)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, Resolver};
+
+    #[test]
+    fn changes_show_through_the_container() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+        builder.register::<u64>(42).unwrap();
+
+        let mutable = builder.build_mutable();
+        let container = mutable.container().clone();
+
+        mutable.replace::<u32>(1).unwrap();
+        mutable.remove::<u64>().unwrap();
+
+        assert_eq!(1, container.resolve::<u32>().unwrap());
+        assert!(!container.has::<u64>());
+    }
+
+    #[test]
+    fn forks_change_on_their_own() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(42).unwrap();
+
+        let container = builder.build();
+        let fork = container.fork_mutable().unwrap();
+        let fork_of_fork = fork.fork().unwrap();
+
+        fork.replace::<u32>(1).unwrap();
+        fork_of_fork.replace::<u32>(2).unwrap();
+
+        assert_eq!(42, container.resolve::<u32>().unwrap());
+        assert_eq!(1, fork.resolve::<u32>().unwrap());
+        assert_eq!(2, fork_of_fork.resolve::<u32>().unwrap());
+    }
+}
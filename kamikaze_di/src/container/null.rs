@@ -0,0 +1,112 @@
+use alloc::rc::Rc;
+
+/// Do-nothing implementation of a trait, for optional integrations.
+///
+/// Implemented for `dyn Trait`, usually by the `#[null_object]` attribute
+/// from kamikaze_di_derive, which generates a `NullTrait` struct whose
+/// methods do nothing. Register it via
+/// [register_null()](struct.ContainerBuilder.html#method.register_null), so
+/// services can always resolve the integration, whether it's enabled or not.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, NullObject, Resolver};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// trait Analytics {
+///     fn track(&self, event: &str) -> bool;
+/// }
+///
+/// struct NullAnalytics;
+///
+/// impl Analytics for NullAnalytics {
+///     fn track(&self, _: &str) -> bool { false }
+/// }
+///
+/// impl NullObject for dyn Analytics {
+///     fn null() -> Rc<dyn Analytics> { Rc::new(NullAnalytics) }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_null::<dyn Analytics>()?;
+///
+/// let container = builder.build();
+/// let analytics = container.resolve::<Rc<dyn Analytics>>()?;
+///
+/// assert!(!analytics.track("signup"));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait NullObject {
+    /// Creates the null object.
+    fn null() -> Rc<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+
+    trait Mailer {
+        fn name(&self) -> &'static str;
+    }
+
+    struct NullMailer;
+    impl Mailer for NullMailer {
+        fn name(&self) -> &'static str {
+            "null"
+        }
+    }
+
+    struct SmtpMailer;
+    impl Mailer for SmtpMailer {
+        fn name(&self) -> &'static str {
+            "smtp"
+        }
+    }
+
+    impl NullObject for dyn Mailer {
+        fn null() -> Rc<dyn Mailer> {
+            Rc::new(NullMailer)
+        }
+    }
+
+    fn smtp() -> Rc<dyn Mailer> {
+        Rc::new(SmtpMailer)
+    }
+
+    fn resolved_name(builder: ContainerBuilder) -> &'static str {
+        builder.build().resolve::<Rc<dyn Mailer>>().unwrap().name()
+    }
+
+    #[test]
+    fn real_implementations_replace_null_objects() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_null::<dyn Mailer>().unwrap();
+        builder.register::<Rc<dyn Mailer>>(smtp()).unwrap();
+
+        assert_eq!("smtp", resolved_name(builder));
+    }
+
+    #[test]
+    fn null_objects_dont_replace_real_implementations() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Rc<dyn Mailer>>(smtp()).unwrap();
+        builder.register_null::<dyn Mailer>().unwrap();
+
+        assert_eq!("smtp", resolved_name(builder));
+    }
+
+    #[test]
+    fn real_implementations_are_only_replaced_once() {
+        let mut builder = ContainerBuilder::new();
+        builder.register_null::<dyn Mailer>().unwrap();
+        builder.register::<Rc<dyn Mailer>>(smtp()).unwrap();
+
+        assert!(builder.register::<Rc<dyn Mailer>>(smtp()).is_err());
+    }
+}
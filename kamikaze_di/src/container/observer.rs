@@ -0,0 +1,264 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+use core::fmt;
+
+use super::ResolverType;
+use crate::prelude::*;
+use crate::Error;
+
+/// Something an [Observer](trait.Observer.html) is told about.
+#[derive(Clone, Copy, Debug)]
+pub enum Observed<'a> {
+    /// A type was registered, or its registration replaced.
+    Registered {
+        /// Name of the type.
+        type_name: &'static str,
+        /// How the type resolves.
+        kind: ResolverType,
+    },
+    /// A type was resolved.
+    Resolved {
+        /// Name of the type.
+        type_name: &'static str,
+        /// Whether the item already existed, shared or scoped.
+        cache_hit: bool,
+    },
+    /// A builder ran, its item is shared from now on.
+    BuilderConsumed {
+        /// Name of the type.
+        type_name: &'static str,
+    },
+    /// A factory registered with a fallback failed, the fallback was used.
+    FellBack {
+        /// Name of the type.
+        type_name: &'static str,
+        /// Why the primary factory failed.
+        error: &'a Error,
+    },
+    /// A type failed to resolve.
+    Error {
+        /// Name of the type.
+        type_name: &'static str,
+        /// What went wrong.
+        error: &'a Error,
+    },
+}
+
+/// Gets told about what a container does, for metrics or debug tooling.
+///
+/// Unlike [Interceptor](trait.Interceptor.html), observers can't change
+/// what's resolved. Closures taking an [Observed](enum.Observed.html)
+/// event are observers too.
+///
+/// See [subscribe()](struct.Container.html#method.subscribe), and
+/// [subscribe()](struct.ContainerBuilder.html#method.subscribe) to also
+/// see registrations.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Observed, Resolver};
+/// # use std::cell::Cell;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let hits = Rc::new(Cell::new(0));
+/// let counter = hits.clone();
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_builder::<Rc<String>, _>(|_| Rc::new("shared".to_string()))?;
+///
+/// let container = builder.build();
+/// container.subscribe(Rc::new(move |event: &Observed| {
+///     if let Observed::Resolved { cache_hit: true, .. } = event {
+///         counter.set(counter.get() + 1);
+///     }
+/// }));
+///
+/// for _ in 0..3 {
+///     container.resolve::<Rc<String>>()?;
+/// }
+///
+/// // the first one ran the builder
+/// assert_eq!(2, hits.get());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Observer {
+    /// Called once something happened.
+    fn notify(&self, event: &Observed);
+}
+
+impl<F: Fn(&Observed)> Observer for F {
+    fn notify(&self, event: &Observed) {
+        self(event)
+    }
+}
+
+/// Observers subscribed to a container or builder.
+#[derive(Clone, Default)]
+pub(super) struct Observers {
+    observers: Vec<Rc<dyn Observer>>,
+    /// Whether the last resolution was a cache hit, see hit().
+    hit: Cell<bool>,
+}
+
+impl Observers {
+    pub fn subscribe(&mut self, observer: Rc<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    pub fn merge(&mut self, other: Observers) {
+        self.observers.extend(other.observers);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    /// Marks the resolution in progress as a cache hit.
+    ///
+    /// Hits are the last thing before a resolution returns, so dependencies
+    /// resolved earlier can't mark their dependents.
+    pub fn hit(&self) {
+        self.hit.set(true);
+    }
+
+    /// Whether the resolution that just finished was a cache hit, and
+    /// clears the mark for the next one.
+    pub fn take_hit(&self) -> bool {
+        self.hit.replace(false)
+    }
+
+    /// Tells every observer, in the order they subscribed.
+    pub fn notify(&self, event: &Observed) {
+        for observer in &self.observers {
+            observer.notify(event);
+        }
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Observers")
+            .field(&self.observers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+    use core::cell::RefCell;
+
+    fn recorder() -> (Rc<RefCell<Vec<String>>>, Rc<dyn Observer>) {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let events = log.clone();
+
+        let observer = Rc::new(move |event: &Observed| {
+            events.borrow_mut().push(match event {
+                Observed::Registered { type_name, kind } => {
+                    format!("registered {} as {:?}", type_name, kind)
+                }
+                Observed::Resolved {
+                    type_name,
+                    cache_hit,
+                } => format!("resolved {}, hit: {}", type_name, cache_hit),
+                Observed::BuilderConsumed { type_name } => format!("built {}", type_name),
+                Observed::FellBack { type_name, error } => {
+                    format!("fell back {}: {}", type_name, error)
+                }
+                Observed::Error { type_name, error } => {
+                    format!("failed {}: {}", type_name, error)
+                }
+            });
+        });
+
+        (log, observer)
+    }
+
+    #[test]
+    fn resolutions_are_observed() {
+        let (log, observer) = recorder();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+        builder.subscribe(observer);
+        builder
+            .register_builder::<u32, _>(|container| {
+                let item: u8 = container.resolve().unwrap();
+                u32::from(item)
+            })
+            .unwrap();
+        builder.register_factory::<u16, _>(|_| 2).unwrap();
+
+        let container = builder.build();
+
+        container.resolve::<u32>().unwrap();
+        container.resolve::<u32>().unwrap();
+        container.resolve::<u16>().unwrap();
+        assert!(container.resolve::<u64>().is_err());
+
+        assert_eq!(
+            vec![
+                "registered u32 as Builder",
+                "registered u16 as Factory",
+                "resolved u8, hit: true",
+                "built u32",
+                "resolved u32, hit: false",
+                "resolved u32, hit: true",
+                "resolved u16, hit: false",
+                "failed u64: Type not registered: u64",
+            ],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn fallbacks_are_observed() {
+        let (log, observer) = recorder();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_with_fallback::<u16, _, _>(|_| Err("cluster is down".into()), |_| 2)
+            .unwrap();
+
+        let container = builder.build();
+        container.subscribe(observer);
+
+        assert_eq!(2, container.resolve::<u16>().unwrap());
+        assert_eq!(2, container.resolve::<u16>().unwrap());
+
+        assert_eq!(
+            vec![
+                "fell back u16: cluster is down",
+                "resolved u16, hit: false",
+                "fell back u16: cluster is down",
+                "resolved u16, hit: false",
+            ],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn replacements_are_observed_by_the_container_and_its_forks() {
+        let (log, observer) = recorder();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u8>(1).unwrap();
+
+        let container = builder.build();
+        container.subscribe(observer);
+
+        container.replace::<u8>(2).unwrap();
+        container.fork().unwrap().resolve::<u8>().unwrap();
+
+        assert_eq!(
+            vec!["registered u8 as Shared", "resolved u8, hit: true"],
+            *log.borrow()
+        );
+    }
+}
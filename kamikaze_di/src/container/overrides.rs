@@ -0,0 +1,320 @@
+use alloc::rc::Rc;
+use core::any::TypeId;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::ops::Deref;
+use core::panic::Location;
+
+use super::erased::ErasedFactory;
+use super::events::ContainerEvent;
+use super::{Container, Registration, Resolver};
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+
+/// A set of stubs that temporarily take precedence over registrations.
+///
+/// Push them on a container with
+/// [push_overrides()](struct.MutableContainer.html#method.push_overrides).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Overrides, Resolver};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<u32>(42)?;
+///
+/// let container = builder.build_mutable();
+///
+/// let mut overrides = Overrides::new();
+/// overrides.register::<u32>(1);
+/// container.push_overrides(overrides);
+///
+/// assert_eq!(1, container.resolve::<u32>()?);
+///
+/// container.pop_overrides();
+///
+/// assert_eq!(42, container.resolve::<u32>()?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default, Debug)]
+pub struct Overrides {
+    resolvers: TypeIdMap<Resolver>,
+    registrations: TypeIdMap<Registration>,
+}
+
+impl Overrides {
+    /// Constructor.
+    pub fn new() -> Overrides {
+        Default::default()
+    }
+
+    /// Overrides a dependency with an item.
+    ///
+    /// Overriding the same type twice in one frame keeps the last item.
+    #[track_caller]
+    pub fn register<T: 'static>(&mut self, item: T) {
+        debug!("registering override");
+
+        let resolver = Resolver::Shared(Rc::new(item));
+
+        self.insert::<T>(resolver);
+    }
+
+    /// Overrides a dependency with a factory.
+    ///
+    /// Overriding the same type twice in one frame keeps the last factory.
+    #[track_caller]
+    pub fn register_factory<T, F>(&mut self, factory: F)
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering override factory");
+
+        let factory = ErasedFactory::new(factory);
+        let resolver = Resolver::Factory(Rc::new(RefCell::new(factory)));
+
+        self.insert::<T>(resolver);
+    }
+
+    /// Returns true if the type is overridden in this frame.
+    pub fn has<T: 'static>(&self) -> bool {
+        self.resolvers.contains_key(&TypeId::of::<T>())
+    }
+
+    #[track_caller]
+    fn insert<T: 'static>(&mut self, resolver: Resolver) {
+        let type_id = TypeId::of::<T>();
+
+        self.resolvers.insert(type_id, resolver);
+        self.registrations.insert(type_id, Registration::of::<T>());
+    }
+}
+
+/// An override in effect.
+///
+/// See [active_overrides()](struct.Container.html#method.active_overrides).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct ActiveOverride {
+    /// Name of the overridden type.
+    pub type_name: &'static str,
+    /// Where the type was registered, None if it's only overridden.
+    pub registered_at: Option<&'static Location<'static>>,
+    /// Where the override in effect was made.
+    pub overridden_at: &'static Location<'static>,
+}
+
+impl fmt::Display for ActiveOverride {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} overridden at {}", self.type_name, self.overridden_at)?;
+
+        match self.registered_at {
+            Some(location) => write!(f, ", registered at {}", location),
+            None => write!(f, ", not registered"),
+        }
+    }
+}
+
+/// Handle to a single override.
+///
+/// While the handle is alive, the overridden type resolves to the stub.
+/// Dropping it restores the registration. The handle derefs to the
+/// container, so it can be used to resolve dependencies directly.
+///
+/// See [override_scoped()](struct.MutableContainer.html#method.override_scoped).
+#[derive(Debug)]
+pub struct OverrideGuard<'a> {
+    container: &'a Container,
+    id: usize,
+}
+
+impl<'a> OverrideGuard<'a> {
+    pub(super) fn new(container: &'a Container, overrides: Overrides) -> OverrideGuard<'a> {
+        let id = container.inner.overrides.push(overrides);
+        container
+            .inner
+            .events
+            .record(ContainerEvent::OverridesPushed);
+
+        OverrideGuard { container, id }
+    }
+}
+
+impl<'a> Deref for OverrideGuard<'a> {
+    type Target = Container;
+
+    fn deref(&self) -> &Container {
+        self.container
+    }
+}
+
+impl<'a> Drop for OverrideGuard<'a> {
+    fn drop(&mut self) {
+        self.container.inner.overrides.remove(self.id);
+        self.container
+            .inner
+            .events
+            .record(ContainerEvent::OverridesPopped);
+    }
+}
+
+/// Override frames pushed on a container, innermost last.
+#[derive(Default, Debug)]
+pub(super) struct OverrideStack {
+    frames: RefCell<Vec<(usize, Overrides)>>,
+    next_id: Cell<usize>,
+}
+
+impl OverrideStack {
+    pub fn push(&self, overrides: Overrides) -> usize {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        self.frames.borrow_mut().push((id, overrides));
+
+        id
+    }
+
+    pub fn pop(&self) -> Option<Overrides> {
+        self.frames
+            .borrow_mut()
+            .pop()
+            .map(|(_, overrides)| overrides)
+    }
+
+    pub fn remove(&self, id: usize) {
+        let mut frames = self.frames.borrow_mut();
+        let frame = frames
+            .iter()
+            .position(|(frame_id, _)| *frame_id == id)
+            .map(|position| frames.remove(position));
+
+        // stubs may hold on to the container, drop them after
+        // releasing the borrow
+        drop(frames);
+        drop(frame);
+    }
+
+    /// Overrides in effect, the innermost one for every overridden type.
+    pub fn active(&self) -> Vec<(TypeId, Registration)> {
+        let mut active: TypeIdMap<Registration> = TypeIdMap::default();
+
+        for (_, frame) in self.frames.borrow().iter() {
+            active.extend(frame.registrations.iter());
+        }
+
+        active.into_iter().collect()
+    }
+
+    pub fn has(&self, type_id: TypeId) -> bool {
+        self.frames
+            .borrow()
+            .iter()
+            .any(|(_, frame)| frame.resolvers.contains_key(&type_id))
+    }
+
+    /// Resolves from the innermost frame overriding the type, if there is one.
+    pub fn get<T: Clone + 'static>(
+        &self,
+        container: &Container,
+        type_id: TypeId,
+    ) -> Option<crate::Result<T>> {
+        let frames = self.frames.borrow();
+        let resolver = frames
+            .iter()
+            .rev()
+            .find_map(|(_, frame)| frame.resolvers.get(&type_id))?;
+
+        let result = match resolver {
+            Resolver::Shared(boxed) => Ok(boxed
+                .downcast_ref::<T>()
+                .expect("could not downcast override")
+                .clone()),
+            Resolver::Factory(cell) => container.run_factory(cell),
+            _ => unreachable!("overrides only hold shared items and factories"),
+        };
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, Overrides, Resolver};
+
+    #[test]
+    fn inner_frames_win() {
+        let container = ContainerBuilder::new().build();
+
+        let mut outer = Overrides::new();
+        outer.register::<i32>(1);
+        outer.register::<i64>(1);
+        container.push_overrides(outer);
+
+        let mut inner = Overrides::new();
+        inner.register_factory::<i32, _>(|_| 2);
+        container.push_overrides(inner);
+
+        assert_eq!(2, container.resolve::<i32>().unwrap());
+        assert_eq!(1, container.resolve::<i64>().unwrap());
+
+        let popped = container.pop_overrides().unwrap();
+        assert!(popped.has::<i32>());
+
+        assert_eq!(1, container.resolve::<i32>().unwrap());
+
+        container.pop_overrides();
+        assert!(!container.has::<i32>());
+        assert!(container.pop_overrides().is_none());
+    }
+
+    #[test]
+    fn guards_can_be_dropped_out_of_order() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<i32>(0).unwrap();
+
+        let container = builder.build();
+
+        let first = container.override_scoped::<i32>(1);
+        let second = container.override_scoped::<i32>(2);
+        assert_eq!(2, container.resolve::<i32>().unwrap());
+
+        drop(first);
+        assert_eq!(2, container.resolve::<i32>().unwrap());
+
+        drop(second);
+        assert_eq!(0, container.resolve::<i32>().unwrap());
+    }
+
+    #[test]
+    fn active_overrides_point_at_both_sources() {
+        let mut builder = ContainerBuilder::new();
+        let registered_line = line!() + 1;
+        builder.register::<i32>(0).unwrap();
+
+        let container = builder.build();
+
+        let mut outer = Overrides::new();
+        outer.register::<i32>(1);
+        outer.register::<i64>(1);
+        container.push_overrides(outer);
+        let overridden_line = line!() + 1;
+        let _inner = container.override_scoped::<i32>(2);
+
+        let active = container.active_overrides();
+        assert_eq!(2, active.len());
+
+        assert_eq!("i32", active[0].type_name);
+        assert_eq!(overridden_line, active[0].overridden_at.line());
+        assert_eq!(registered_line, active[0].registered_at.unwrap().line());
+
+        assert_eq!("i64", active[1].type_name);
+        assert_eq!(None, active[1].registered_at);
+        assert!(active[1].to_string().ends_with("not registered"));
+    }
+}
@@ -0,0 +1,241 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+
+use crate::clock::{self, Stopwatch};
+use crate::prelude::*;
+
+/// A future registered via ContainerBuilder::register_pending().
+///
+/// The future is polled by whoever resolves it first, the others wait
+/// to be woken up once it's done.
+pub(super) struct Pending<T> {
+    future: RefCell<Option<Pin<Box<dyn Future<Output = T>>>>>,
+    waiters: RefCell<Vec<Waker>>,
+}
+
+impl<T> Pending<T> {
+    pub fn new<F: Future<Output = T> + 'static>(future: F) -> Pending<T> {
+        Pending {
+            future: RefCell::new(Some(Box::pin(future))),
+            waiters: Default::default(),
+        }
+    }
+}
+
+/// Awaits a pending future.
+///
+/// Resolves to the item for the caller that finished the future, and to
+/// None for everyone else, who should find the item in the container.
+pub(super) struct PendingResolve<'a, T> {
+    pub pending: Rc<Pending<T>>,
+    pub trail: &'a Trail,
+}
+
+impl<'a, T: 'static> Future for PendingResolve<'a, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        // taken out while polling, so the future can resolve other pending items
+        let mut future = match self.pending.future.borrow_mut().take() {
+            Some(future) => future,
+            None => return Poll::Ready(None),
+        };
+
+        let polling = self.trail.enter(core::any::type_name::<T>());
+
+        match future.as_mut().poll(cx) {
+            Poll::Ready(item) => {
+                let waiters: Vec<_> = self.pending.waiters.borrow_mut().drain(..).collect();
+                for waiter in waiters {
+                    waiter.wake();
+                }
+
+                Poll::Ready(Some(item))
+            }
+            Poll::Pending => {
+                polling.stalled();
+                *self.pending.future.borrow_mut() = Some(future);
+
+                let mut waiters = self.pending.waiters.borrow_mut();
+                if !waiters.iter().any(|waiter| waiter.will_wake(cx.waker())) {
+                    waiters.push(cx.waker().clone());
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Pending items being polled, to tell which one stalled.
+#[derive(Debug, Default)]
+pub(super) struct Trail {
+    /// Outermost first.
+    polling: RefCell<Vec<&'static str>>,
+    /// What was being polled when the innermost item last stalled.
+    stalled: RefCell<Vec<&'static str>>,
+}
+
+impl Trail {
+    fn enter(&self, type_name: &'static str) -> Polling<'_> {
+        let mut polling = self.polling.borrow_mut();
+
+        // a new round of polling, forget the last one
+        if polling.is_empty() {
+            self.stalled.borrow_mut().clear();
+        }
+        polling.push(type_name);
+
+        Polling { trail: self }
+    }
+
+    /// Pending items being polled around the caller.
+    pub fn depth(&self) -> usize {
+        self.polling.borrow().len()
+    }
+
+    /// The items that stalled, inside the ones being polled up to depth.
+    pub fn stalled_inside(&self, depth: usize) -> Vec<&'static str> {
+        let polling = self.polling.borrow();
+        let stalled = self.stalled.borrow();
+
+        if depth <= polling.len() && stalled.starts_with(&polling[..depth]) {
+            stalled[depth..].to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Keeps an item on the trail while it's polled.
+struct Polling<'a> {
+    trail: &'a Trail,
+}
+
+impl<'a> Polling<'a> {
+    /// Records the trail, unless an item inside this one stalled already.
+    fn stalled(&self) {
+        let polling = self.trail.polling.borrow();
+        let mut stalled = self.trail.stalled.borrow_mut();
+
+        if !stalled.starts_with(&polling) {
+            *stalled = polling.clone();
+        }
+    }
+}
+
+impl<'a> Drop for Polling<'a> {
+    fn drop(&mut self) {
+        self.trail.polling.borrow_mut().pop();
+    }
+}
+
+/// Gives up on a future that takes longer than its timeout.
+///
+/// The time is checked whenever the future is polled, and a timer has it
+/// polled once the time is up. Without a clock, it never gives up.
+pub(super) struct Deadline<F> {
+    future: Pin<Box<F>>,
+    started: Stopwatch,
+    timeout: Duration,
+    timer_set: bool,
+}
+
+impl<F: Future> Deadline<F> {
+    pub fn new(future: F, timeout: Duration) -> Deadline<F> {
+        Deadline {
+            future: Box::pin(future),
+            started: Stopwatch::start(),
+            timeout,
+            timer_set: false,
+        }
+    }
+}
+
+impl<F: Future> Future for Deadline<F> {
+    /// None if the time ran out.
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<F::Output>> {
+        if let Poll::Ready(item) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Some(item));
+        }
+
+        match self.started.elapsed() {
+            Some(elapsed) if elapsed >= self.timeout => return Poll::Ready(None),
+            Some(elapsed) if !self.timer_set => {
+                self.timer_set = true;
+                clock::wake_after(self.timeout - elapsed, cx.waker().clone());
+            }
+            _ => {}
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Pending until released.
+    struct Gate(Rc<Cell<bool>>);
+
+    impl Future for Gate {
+        type Output = u32;
+
+        fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<u32> {
+            if self.0.get() {
+                Poll::Ready(42)
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_first_waiter_gets_the_item() {
+        let open = Rc::new(Cell::new(false));
+        let pending = Rc::new(Pending::new(Gate(open.clone())));
+
+        let trail = Trail::default();
+        let mut first = PendingResolve {
+            pending: pending.clone(),
+            trail: &trail,
+        };
+        let mut second = PendingResolve {
+            pending,
+            trail: &trail,
+        };
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Poll::Pending, Pin::new(&mut first).poll(&mut cx));
+        assert_eq!(Poll::Pending, Pin::new(&mut second).poll(&mut cx));
+
+        open.set(true);
+        assert_eq!(Poll::Ready(Some(42)), Pin::new(&mut second).poll(&mut cx));
+        assert_eq!(Poll::Ready(None), Pin::new(&mut first).poll(&mut cx));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn deadlines_give_up_once_the_time_is_up() {
+        let open = Rc::new(Cell::new(false));
+        let mut deadline = Deadline::new(Gate(open.clone()), Duration::from_millis(5));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Poll::Pending, Pin::new(&mut deadline).poll(&mut cx));
+
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(Poll::Ready(None), Pin::new(&mut deadline).poll(&mut cx));
+
+        let mut deadline = Deadline::new(Gate(open.clone()), Duration::from_secs(60));
+        open.set(true);
+        assert_eq!(Poll::Ready(Some(42)), Pin::new(&mut deadline).poll(&mut cx));
+    }
+}
@@ -0,0 +1,120 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::Container;
+
+thread_local! {
+    /// Items created by thread local factories, by registration.
+    static ITEMS: RefCell<HashMap<usize, Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// Ids of thread local registrations, unique in the process.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A thread local factory, see ContainerBuilder::register_thread_local().
+///
+/// Containers don't leave the thread they're built on, so the item is only
+/// ever stored on that thread, and dropped together with the registration.
+pub(super) struct PerThread<F> {
+    id: usize,
+    factory: F,
+}
+
+impl<F> PerThread<F> {
+    pub fn new(factory: F) -> PerThread<F> {
+        PerThread {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            factory,
+        }
+    }
+
+    /// Returns this thread's item, creating it the first time.
+    pub fn get_or_create<T>(&mut self, container: &Container) -> T
+    where
+        F: FnMut(&Container) -> T,
+        T: Clone + 'static,
+    {
+        let existing = ITEMS.with(|items| {
+            items.borrow().get(&self.id).map(|item| {
+                item.downcast_ref::<T>()
+                    .expect("could not downcast thread local item")
+                    .clone()
+            })
+        });
+
+        if let Some(item) = existing {
+            return item;
+        }
+
+        debug!("creating thread local item");
+
+        // not borrowed while creating, the factory may need other thread local items
+        let item = (self.factory)(container);
+        ITEMS.with(|items| items.borrow_mut().insert(self.id, Box::new(item.clone())));
+
+        item
+    }
+}
+
+impl<F> Drop for PerThread<F> {
+    fn drop(&mut self) {
+        // the thread may be exiting already, its items are going away anyway
+        let item = ITEMS
+            .try_with(|items| items.borrow_mut().remove(&self.id))
+            .ok()
+            .flatten();
+
+        // items may hold on to other thread local items
+        drop(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Container, ContainerBuilder, Resolver};
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    fn wire() -> Container {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_thread_local::<usize, _>(|_| CREATED.fetch_add(1, Ordering::SeqCst))
+            .unwrap();
+
+        builder.build()
+    }
+
+    #[test]
+    fn items_are_kept_per_thread_and_container() {
+        let container = wire();
+        let first = container.resolve::<usize>().unwrap();
+        let again = container.resolve::<usize>().unwrap();
+        let other_container = wire().resolve::<usize>().unwrap();
+        let other_thread = std::thread::spawn(|| wire().resolve::<usize>().unwrap())
+            .join()
+            .unwrap();
+
+        assert_eq!(first, again);
+        assert_ne!(first, other_container);
+        assert_ne!(first, other_thread);
+    }
+
+    #[test]
+    fn items_are_dropped_with_their_container() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_thread_local::<Rc<String>, _>(|_| Rc::new("postgres://prod".to_string()))
+            .unwrap();
+
+        let container = builder.build();
+        let item = container.resolve::<Rc<String>>().unwrap();
+        assert_eq!(2, Rc::strong_count(&item));
+
+        drop(container);
+        assert_eq!(1, Rc::strong_count(&item));
+    }
+}
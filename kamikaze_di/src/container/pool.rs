@@ -0,0 +1,228 @@
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use super::Container;
+use crate::prelude::*;
+use crate::Result;
+
+/// Creates items for a pool, see ContainerBuilder::register_pool().
+type PoolFactory<T> = RefCell<Box<dyn FnMut(&Container) -> T>>;
+
+/// A fixed number of reusable items of T.
+///
+/// Items are created as they're needed, up to the pool's size.
+pub(super) struct Pool<T> {
+    size: usize,
+    created: Cell<usize>,
+    idle: RefCell<Vec<T>>,
+    factory: PoolFactory<T>,
+}
+
+impl<T: 'static> Pool<T> {
+    pub fn new<F>(size: usize, factory: F) -> Pool<T>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+    {
+        Pool {
+            size,
+            created: Cell::new(0),
+            idle: RefCell::new(Vec::with_capacity(size)),
+            factory: RefCell::new(Box::new(factory)),
+        }
+    }
+
+    /// Hands out an idle item, or creates one if the pool isn't full yet.
+    pub fn acquire(pool: &Rc<Pool<T>>, container: &Container) -> Result<PooledHandle<T>> {
+        let idle = pool.idle.borrow_mut().pop();
+
+        let item = match idle {
+            Some(item) => item,
+            None if pool.created.get() < pool.size => {
+                let mut factory = pool.factory.try_borrow_mut().map_err(|_| {
+                    format!(
+                        "the pool of {} needs one of its own items to create one",
+                        core::any::type_name::<T>()
+                    )
+                })?;
+
+                let item = factory(container);
+                pool.created.set(pool.created.get() + 1);
+
+                item
+            }
+            None => {
+                return Err(format!(
+                    "all {} pooled {} are in use",
+                    pool.size,
+                    core::any::type_name::<T>()
+                )
+                .into())
+            }
+        };
+
+        Ok(PooledHandle {
+            item: Some(item),
+            pool: pool.clone(),
+        })
+    }
+}
+
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("size", &self.size)
+            .field("created", &self.created.get())
+            .field("idle", &self.idle.borrow().len())
+            .finish()
+    }
+}
+
+/// An item borrowed from a pool, given back when the handle is dropped.
+///
+/// Get them via [resolve_pooled()](struct.Container.html#method.resolve_pooled),
+/// for pools registered via
+/// [register_pool()](struct.ContainerBuilder.html#method.register_pool).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::ContainerBuilder;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Connection {
+///     queries: u32,
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_pool::<Connection, _>(1, |_| Connection { queries: 0 })?;
+///
+/// let container = builder.build();
+///
+/// {
+///     let mut connection = container.resolve_pooled::<Connection>()?;
+///     connection.queries += 1;
+///
+///     // the only connection is taken
+///     assert!(container.resolve_pooled::<Connection>().is_err());
+/// }
+///
+/// // and given back
+/// assert_eq!(1, container.resolve_pooled::<Connection>()?.queries);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct PooledHandle<T> {
+    // only None while being dropped
+    item: Option<T>,
+    pool: Rc<Pool<T>>,
+}
+
+impl<T> Deref for PooledHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.item
+            .as_ref()
+            .expect("pooled item was already given back")
+    }
+}
+
+impl<T> DerefMut for PooledHandle<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.item
+            .as_mut()
+            .expect("pooled item was already given back")
+    }
+}
+
+impl<T> Drop for PooledHandle<T> {
+    fn drop(&mut self) {
+        if let Some(item) = self.item.take() {
+            self.pool.idle.borrow_mut().push(item);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PooledHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PooledHandle")
+            .field("item", &self.item)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerBuilder, Resolver};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn items_are_created_up_to_the_size_and_reused() {
+        let created = Rc::new(Cell::new(0));
+        let counter = created.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_pool::<Vec<u8>, _>(2, move |_| {
+                counter.set(counter.get() + 1);
+                Vec::with_capacity(1024)
+            })
+            .unwrap();
+
+        let container = builder.build();
+
+        let first = container.resolve_pooled::<Vec<u8>>().unwrap();
+        let second = container.resolve_pooled::<Vec<u8>>().unwrap();
+
+        let error = container.resolve_pooled::<Vec<u8>>().err().unwrap();
+        assert!(error.to_string().starts_with("all 2 pooled"));
+
+        drop(first);
+        drop(second);
+
+        for _ in 0..5 {
+            let mut buffer = container.resolve_pooled::<Vec<u8>>().unwrap();
+            buffer.push(1);
+        }
+
+        assert_eq!(2, created.get());
+    }
+
+    #[test]
+    fn factories_can_resolve_dependencies() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<String>("db://".to_string()).unwrap();
+        builder
+            .register_pool::<String, _>(1, |container| {
+                let dsn: String = container.resolve().unwrap();
+                dsn + "primary"
+            })
+            .unwrap();
+
+        assert!(builder
+            .register_pool::<String, _>(1, |_| String::new())
+            .is_err());
+        assert!(builder.register_pool::<u8, _>(0, |_| 0).is_err());
+
+        let container = builder.build();
+
+        assert_eq!(
+            "db://primary",
+            *container.resolve_pooled::<String>().unwrap()
+        );
+        // the pool doesn't replace the registration
+        assert_eq!("db://", container.resolve::<String>().unwrap());
+    }
+
+    #[test]
+    fn pools_have_to_be_registered() {
+        let container = ContainerBuilder::new().build();
+
+        assert!(container.resolve_pooled::<u8>().is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use alloc::rc::{Rc, Weak};
+use core::fmt;
+use core::marker::PhantomData;
+
+use super::injector::{Inject, Injector};
+use super::{Container, Inner};
+use crate::Result;
+
+/// Asks the container for a dependency every time it is needed.
+///
+/// Providers are resolved via the [Injector](trait.Injector.html) trait, so
+/// they can be used as fields of types that derive `Inject` or `InjectAsRc`.
+/// Unlike [Lazy](struct.Lazy.html), nothing is cached: every
+/// [get()](struct.Provider.html#method.get) resolves T again, so factories
+/// create a new item each time and shared items are handed out again.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Injector, Provider};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// let mut next_id = 0;
+/// builder.register_factory::<u32, _>(move |_| {
+///     next_id += 1;
+///     next_id
+/// })?;
+/// # #[cfg(not(feature = "specialization"))]
+/// builder.register_automatic::<Provider<u32>>()?;
+///
+/// let container = builder.build();
+///
+/// let ids: Provider<u32> = container.inject()?;
+///
+/// assert_eq!(1, ids.get()?);
+/// assert_eq!(2, ids.get()?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct Provider<T> {
+    container: Weak<Inner>,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> Provider<T> {
+    /// Resolves the dependency.
+    ///
+    /// Fails if the dependency can't be resolved or if the container
+    /// has been dropped in the meantime.
+    pub fn get(&self) -> Result<T>
+    where
+        Container: Injector<T>,
+    {
+        debug!("providing dependency");
+
+        let inner = self
+            .container
+            .upgrade()
+            .ok_or("container dropped before providing dependency")?;
+
+        Container { inner }.inject()
+    }
+}
+
+impl<T: 'static> Inject for Provider<T> {
+    fn resolve(container: &Container) -> Result<Self> {
+        Ok(Provider {
+            container: Rc::downgrade(&container.inner),
+            item: PhantomData,
+        })
+    }
+}
+
+impl<T> Clone for Provider<T> {
+    fn clone(&self) -> Self {
+        Provider {
+            container: self.container.clone(),
+            item: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Provider<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Provider")
+            .field("type_name", &core::any::type_name::<T>())
+            .finish()
+    }
+}
+
+// providers are only injected on their own with specialization
+#[cfg(all(test, feature = "specialization"))]
+mod tests {
+    use super::Provider;
+    use crate::{Container, ContainerBuilder, Inject, Injector, Result};
+    use std::rc::Rc;
+
+    #[test]
+    fn providers_respect_the_registration_lifetime() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register::<Rc<String>>(Rc::new("shared".to_string()))
+            .unwrap();
+        builder
+            .register_factory::<Rc<u32>, _>(|_| Rc::new(42))
+            .unwrap();
+
+        let container = builder.build();
+
+        let shared: Provider<Rc<String>> = container.inject().unwrap();
+        assert!(Rc::ptr_eq(&shared.get().unwrap(), &shared.get().unwrap()));
+
+        let created: Provider<Rc<u32>> = container.inject().unwrap();
+        assert!(!Rc::ptr_eq(
+            &created.get().unwrap(),
+            &created.get().unwrap()
+        ));
+    }
+
+    #[test]
+    fn services_create_children_on_demand() {
+        #[derive(Clone)]
+        struct Worker {
+            id: u32,
+        }
+        impl Inject for Worker {
+            fn resolve(container: &Container) -> Result<Worker> {
+                Ok(Worker {
+                    id: container.inject()?,
+                })
+            }
+        }
+
+        #[derive(Clone)]
+        struct Pool {
+            workers: Provider<Worker>,
+        }
+        impl Inject for Pool {
+            fn resolve(container: &Container) -> Result<Pool> {
+                Ok(Pool {
+                    workers: container.inject()?,
+                })
+            }
+        }
+
+        let mut builder = ContainerBuilder::new();
+        let mut next_id = 0;
+        builder
+            .register_factory::<u32, _>(move |_| {
+                next_id += 1;
+                next_id
+            })
+            .unwrap();
+        builder.register_automatic_factory::<Worker>().unwrap();
+
+        let container = builder.build();
+        let pool: Pool = container.inject().unwrap();
+
+        let ids: Vec<u32> = (0..3).map(|_| pool.workers.get().unwrap().id).collect();
+        assert_eq!(vec![1, 2, 3], ids);
+    }
+
+    #[test]
+    fn providers_fail_once_the_container_is_gone() {
+        let container = ContainerBuilder::new().build();
+        let provider: Provider<i32> = container.inject().unwrap();
+
+        drop(container);
+
+        assert!(provider.get().is_err());
+    }
+}
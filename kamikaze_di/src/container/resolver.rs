@@ -100,6 +100,31 @@ pub trait Resolver: Sealed {
     /// assert!(!container.has::<i32>());
     /// ```
     fn has<T: 'static>(&self) -> bool;
+
+    /// Resolves an optional shared dependency.
+    ///
+    /// Returns None if `Rc<T>` isn't registered. Failing to build a registered
+    /// dependency is logged, and also returns None. With the `specialization`
+    /// feature, `Option<Rc<T>>` is injected the same way, except that errors
+    /// are passed on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{Container, ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// trait Metrics { fn count(&self, name: &str); }
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register::<Rc<u32>>(Rc::new(42));
+    ///
+    /// let container = builder.build();
+    ///
+    /// assert_eq!(Some(Rc::new(42)), container.try_resolve::<u32>());
+    /// assert!(container.try_resolve::<dyn Metrics>().is_none());
+    /// ```
+    fn try_resolve<T: ?Sized + 'static>(&self) -> Option<Rc<T>>;
 }
 
 impl Resolver for Container {
@@ -110,4 +135,33 @@ impl Resolver for Container {
     fn has<T: 'static>(&self) -> bool {
         self.has::<T>()
     }
+
+    fn try_resolve<T: ?Sized + 'static>(&self) -> Option<Rc<T>> {
+        match self.get_optional::<T>() {
+            Ok(item) => item,
+            Err(error) => {
+                warn!(
+                    "optional dependency {} failed to resolve: {}",
+                    core::any::type_name::<T>(),
+                    error
+                );
+
+                None
+            }
+        }
+    }
+}
+
+impl Resolver for RequestScope {
+    fn resolve<T: Clone + 'static>(&self) -> Result<T> {
+        self.run(|container| container.resolve::<T>())
+    }
+
+    fn has<T: 'static>(&self) -> bool {
+        self.run(|container| container.has::<T>())
+    }
+
+    fn try_resolve<T: ?Sized + 'static>(&self) -> Option<Rc<T>> {
+        self.run(|container| container.try_resolve::<T>())
+    }
 }
@@ -0,0 +1,621 @@
+use core::any::{Any, TypeId};
+use core::cell::{Cell, RefCell};
+use core::ops::Deref;
+
+use super::events::ContainerEvent;
+use super::Container;
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+
+/// How long resolved dependencies live.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Scope {
+    /// A new item is created every time the dependency is resolved.
+    Transient,
+    /// One item is shared for the whole lifetime of the container.
+    Singleton,
+    /// One item is shared for the lifetime of a scope.
+    ///
+    /// See [enter_scope()](struct.Container.html#method.enter_scope) and
+    /// [enter_named_scope()](struct.Container.html#method.enter_named_scope).
+    Scoped,
+}
+
+/// Handle to an active scope.
+///
+/// Scoped dependencies resolved while the handle is alive are cached in
+/// the scope, and dropped together with it. The handle derefs to the
+/// container, so it can be used to resolve dependencies directly.
+///
+/// Scopes can also be seeded with data, like the current request's
+/// details, see [provide()](struct.ScopeGuard.html#method.provide).
+///
+/// See [enter_scope()](struct.Container.html#method.enter_scope).
+#[derive(Debug)]
+pub struct ScopeGuard<'a> {
+    container: &'a Container,
+    id: usize,
+}
+
+impl<'a> ScopeGuard<'a> {
+    pub(super) fn new(container: &'a Container, level: Option<&'static str>) -> ScopeGuard<'a> {
+        let id = container.inner.scopes.push(level);
+        container
+            .inner
+            .events
+            .record(ContainerEvent::ScopeEntered(id));
+
+        ScopeGuard { container, id }
+    }
+
+    /// Seeds the scope with an item.
+    ///
+    /// Until the scope ends, the item is resolved like a shared dependency,
+    /// taking precedence over registrations. Seeds are visible from nested
+    /// scopes too, unless they seed the same type themselves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// #[derive(Clone)]
+    /// struct UserId(u64);
+    ///
+    /// let container = ContainerBuilder::new().build();
+    ///
+    /// {
+    ///     let scope = container.enter_scope();
+    ///     scope.provide(UserId(42));
+    ///
+    ///     let nested = container.enter_scope();
+    ///     assert_eq!(42, nested.resolve::<UserId>()?.0);
+    /// }
+    ///
+    /// assert!(container.resolve::<UserId>().is_err());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn provide<T: 'static>(&self, item: T) {
+        debug!("seeding scope {}", self.id);
+
+        self.container
+            .inner
+            .scopes
+            .seed(self.id, TypeId::of::<T>(), Box::new(item));
+    }
+}
+
+impl<'a> Deref for ScopeGuard<'a> {
+    type Target = Container;
+
+    fn deref(&self) -> &Container {
+        self.container
+    }
+}
+
+impl<'a> Drop for ScopeGuard<'a> {
+    fn drop(&mut self) {
+        self.container.inner.scopes.remove(self.id);
+        self.container
+            .inner
+            .events
+            .record(ContainerEvent::ScopeExited(self.id));
+    }
+}
+
+/// A scope owned by a single request.
+///
+/// Unlike a [ScopeGuard](struct.ScopeGuard.html), a request scope doesn't
+/// borrow the container, so framework adapters can keep it around for as
+/// long as the request takes, across awaits. Many requests can be in flight
+/// on one thread, so the scope is only active while resolving through it:
+/// scoped dependencies are cached in the request, everything else comes
+/// from the container. Cached items and seeds are dropped together with
+/// the request scope.
+///
+/// Request scopes are at the `"request"` level, see
+/// [register_in_scope()](struct.ContainerBuilder.html#method.register_in_scope).
+///
+/// See [begin_request()](struct.Container.html#method.begin_request).
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Database;
+/// struct Session { user: String }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<Rc<Database>>(Rc::new(Database))?;
+/// builder.register_scoped::<Rc<Session>, _>(|container| {
+///     Rc::new(Session { user: container.resolve::<String>().unwrap() })
+/// })?;
+///
+/// let container = builder.build();
+///
+/// let ana = container.begin_request();
+/// ana.provide("ana".to_string());
+/// let mihai = container.begin_request();
+/// mihai.provide("mihai".to_string());
+///
+/// assert_eq!("ana", ana.resolve::<Rc<Session>>()?.user);
+/// assert_eq!("mihai", mihai.resolve::<Rc<Session>>()?.user);
+/// assert!(Rc::ptr_eq(
+///     &ana.resolve::<Rc<Database>>()?,
+///     &mihai.resolve::<Rc<Database>>()?
+/// ));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RequestScope {
+    container: Container,
+    frame: RefCell<Option<Frame>>,
+    id: usize,
+}
+
+impl RequestScope {
+    pub(super) fn new(container: &Container) -> RequestScope {
+        let frame = container.inner.scopes.frame(Some("request"));
+        let id = frame.id;
+        container
+            .inner
+            .events
+            .record(ContainerEvent::ScopeEntered(id));
+
+        RequestScope {
+            container: container.clone(),
+            frame: RefCell::new(Some(frame)),
+            id,
+        }
+    }
+
+    /// Runs the closure with the request's scope active.
+    ///
+    /// Everything resolved from the container inside the closure sees the
+    /// request's scoped items and seeds. Don't hold on to the container
+    /// across awaits, resolve what's needed and let the closure return.
+    /// Running again, or resolving through the request, from inside the
+    /// closure keeps using the active scope. When another request runs in
+    /// between, the request's scope is made the innermost one again until
+    /// the closure returns.
+    pub fn run<R, F: FnOnce(&Container) -> R>(&self, work: F) -> R {
+        let scopes = &self.container.inner.scopes;
+        let frame = self.frame.borrow_mut().take();
+
+        let frame = match frame {
+            Some(frame) => frame,
+            // already active, resolving through the request from inside run()
+            None if scopes.current() == Some(self.id) => return work(&self.container),
+            // active further out, another request runs inside this one
+            None => {
+                let (position, frame) = scopes
+                    .detach_at(self.id)
+                    .expect("active request scope is not attached");
+                scopes.attach(frame);
+
+                let _raised = Raised(self, position);
+
+                return work(&self.container);
+            }
+        };
+        scopes.attach(frame);
+
+        let _active = Active(self);
+
+        work(&self.container)
+    }
+
+    /// Seeds the request's scope with an item.
+    ///
+    /// See [ScopeGuard::provide()](struct.ScopeGuard.html#method.provide).
+    pub fn provide<T: 'static>(&self, item: T) {
+        debug!("seeding request scope {}", self.id);
+
+        let type_id = TypeId::of::<T>();
+        let mut frame = self.frame.borrow_mut();
+
+        match frame.as_mut() {
+            Some(frame) => {
+                frame.seeds.insert(type_id, Box::new(item));
+            }
+            // attached while run() is going on
+            None => self
+                .container
+                .inner
+                .scopes
+                .seed(self.id, type_id, Box::new(item)),
+        }
+    }
+
+    /// Returns the container the request belongs to.
+    pub fn container(&self) -> &Container {
+        &self.container
+    }
+}
+
+/// Detaches the request's frame when run() ends, even when unwinding.
+struct Active<'a>(&'a RequestScope);
+
+impl<'a> Drop for Active<'a> {
+    fn drop(&mut self) {
+        let scope = self.0;
+        let frame = scope.container.inner.scopes.detach(scope.id);
+
+        *scope.frame.borrow_mut() = frame;
+    }
+}
+
+/// Puts a request's frame back where it was when a nested run() ends.
+struct Raised<'a>(&'a RequestScope, usize);
+
+impl<'a> Drop for Raised<'a> {
+    fn drop(&mut self) {
+        let scopes = &self.0.container.inner.scopes;
+
+        if let Some(frame) = scopes.detach(self.0.id) {
+            scopes.attach_at(self.1, frame);
+        }
+    }
+}
+
+impl Drop for RequestScope {
+    fn drop(&mut self) {
+        debug!("ending request scope {}", self.id);
+
+        // scoped items may hold on to the container, drop them first
+        drop(self.frame.get_mut().take());
+        self.container
+            .inner
+            .events
+            .record(ContainerEvent::ScopeExited(self.id));
+    }
+}
+
+/// Caches of all currently active scopes, innermost last.
+#[derive(Default, Debug)]
+pub(super) struct ScopeStack {
+    frames: RefCell<Vec<Frame>>,
+    next_id: Cell<usize>,
+}
+
+#[derive(Debug)]
+struct Frame {
+    id: usize,
+    level: Option<&'static str>,
+    items: TypeIdMap<Box<dyn Any>>,
+    seeds: TypeIdMap<Box<dyn Any>>,
+}
+
+impl ScopeStack {
+    /// Id of the innermost scope.
+    pub fn current(&self) -> Option<usize> {
+        self.frames.borrow().last().map(|frame| frame.id)
+    }
+
+    /// Id of the innermost scope at a level.
+    pub fn innermost(&self, level: &str) -> Option<usize> {
+        self.frames
+            .borrow()
+            .iter()
+            .rev()
+            .find(|frame| frame.level == Some(level))
+            .map(|frame| frame.id)
+    }
+
+    pub fn get<T: Clone + 'static>(&self, id: usize, type_id: TypeId) -> Option<T> {
+        let frames = self.frames.borrow();
+        let frame = frames.iter().find(|frame| frame.id == id)?;
+
+        frame.items.get(&type_id).map(|boxed| {
+            boxed
+                .downcast_ref::<T>()
+                .expect("could not downcast scoped object")
+                .clone()
+        })
+    }
+
+    pub fn insert(&self, id: usize, type_id: TypeId, item: Box<dyn Any>) {
+        let mut frames = self.frames.borrow_mut();
+
+        if let Some(frame) = frames.iter_mut().find(|frame| frame.id == id) {
+            frame.items.insert(type_id, item);
+        }
+    }
+
+    /// Drops the cached items of a type from every scope.
+    pub fn forget(&self, type_id: TypeId) {
+        let mut frames = self.frames.borrow_mut();
+        let items: Vec<_> = frames
+            .iter_mut()
+            .filter_map(|frame| frame.items.remove(&type_id))
+            .collect();
+
+        drop(frames);
+        drop(items);
+    }
+
+    /// Finds a seeded item, searching from the innermost scope outwards.
+    pub fn get_seed<T: Clone + 'static>(&self, type_id: TypeId) -> Option<T> {
+        let frames = self.frames.borrow();
+
+        frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.seeds.get(&type_id))
+            .map(|boxed| {
+                boxed
+                    .downcast_ref::<T>()
+                    .expect("could not downcast seeded object")
+                    .clone()
+            })
+    }
+
+    pub fn has_seed(&self, type_id: TypeId) -> bool {
+        self.frames
+            .borrow()
+            .iter()
+            .any(|frame| frame.seeds.contains_key(&type_id))
+    }
+
+    fn seed(&self, id: usize, type_id: TypeId, item: Box<dyn Any>) {
+        let mut frames = self.frames.borrow_mut();
+
+        if let Some(frame) = frames.iter_mut().find(|frame| frame.id == id) {
+            frame.seeds.insert(type_id, item);
+        }
+    }
+
+    fn push(&self, level: Option<&'static str>) -> usize {
+        let frame = self.frame(level);
+        let id = frame.id;
+
+        debug!("entering scope {} at level {:?}", id, level);
+        self.attach(frame);
+
+        id
+    }
+
+    /// Creates the frame of a new scope, without entering it.
+    fn frame(&self, level: Option<&'static str>) -> Frame {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        Frame {
+            id,
+            level,
+            items: Default::default(),
+            seeds: Default::default(),
+        }
+    }
+
+    /// Makes the frame the innermost scope.
+    fn attach(&self, frame: Frame) {
+        self.frames.borrow_mut().push(frame);
+    }
+
+    /// Puts the frame back among the active scopes, outermost first.
+    fn attach_at(&self, position: usize, frame: Frame) {
+        let mut frames = self.frames.borrow_mut();
+        let position = position.min(frames.len());
+
+        frames.insert(position, frame);
+    }
+
+    /// Takes the frame out of the active scopes, keeping its items.
+    fn detach(&self, id: usize) -> Option<Frame> {
+        self.detach_at(id).map(|(_, frame)| frame)
+    }
+
+    /// Takes the frame out of the active scopes, along with where it was.
+    fn detach_at(&self, id: usize) -> Option<(usize, Frame)> {
+        let mut frames = self.frames.borrow_mut();
+        let position = frames.iter().position(|frame| frame.id == id)?;
+
+        Some((position, frames.remove(position)))
+    }
+
+    fn remove(&self, id: usize) {
+        debug!("leaving scope {}", id);
+
+        let frame = self.detach(id);
+
+        // scoped items may hold on to the container, drop them after
+        // releasing the borrow
+        drop(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_are_tracked_innermost_last() {
+        let stack = ScopeStack::default();
+        assert_eq!(None, stack.current());
+
+        let outer = stack.push(None);
+        let inner = stack.push(None);
+        assert_eq!(Some(inner), stack.current());
+
+        stack.remove(inner);
+        assert_eq!(Some(outer), stack.current());
+    }
+
+    #[test]
+    fn items_are_dropped_with_their_scope() {
+        let stack = ScopeStack::default();
+        let id = stack.push(None);
+
+        stack.insert(id, TypeId::of::<i32>(), Box::new(42));
+        assert_eq!(Some(42), stack.get::<i32>(id, TypeId::of::<i32>()));
+
+        stack.remove(id);
+        assert_eq!(None, stack.get::<i32>(id, TypeId::of::<i32>()));
+    }
+
+    #[test]
+    fn requests_in_flight_keep_their_items_apart() {
+        use crate::{ContainerBuilder, Resolver};
+        use alloc::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        let mut next = 0;
+        builder
+            .register_scoped::<Rc<u32>, _>(move |_| {
+                next += 1;
+                Rc::new(next)
+            })
+            .unwrap();
+
+        let container = builder.build();
+        let first = container.begin_request();
+        let second = container.begin_request();
+
+        assert_eq!(1, *first.resolve::<Rc<u32>>().unwrap());
+        assert_eq!(2, *second.resolve::<Rc<u32>>().unwrap());
+        assert_eq!(1, *first.resolve::<Rc<u32>>().unwrap());
+
+        // outside the requests, there is no scope
+        assert!(container.resolve::<Rc<u32>>().is_err());
+
+        let item = first.resolve::<Rc<u32>>().unwrap();
+        drop(first);
+        assert_eq!(1, Rc::strong_count(&item));
+    }
+
+    #[test]
+    fn levels_are_found_walking_outwards() {
+        let stack = ScopeStack::default();
+        assert_eq!(None, stack.innermost("session"));
+
+        let outer = stack.push(Some("session"));
+        let inner = stack.push(Some("session"));
+        let request = stack.push(Some("request"));
+        stack.push(None);
+
+        assert_eq!(Some(inner), stack.innermost("session"));
+        assert_eq!(Some(request), stack.innermost("request"));
+
+        stack.remove(inner);
+        assert_eq!(Some(outer), stack.innermost("session"));
+    }
+
+    #[test]
+    fn requests_cache_their_level_under_a_session() {
+        use crate::{ContainerBuilder, Resolver};
+        use alloc::rc::Rc;
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_in_scope::<Rc<u32>, _>("session", |_| Rc::new(1))
+            .unwrap();
+        builder
+            .register_in_scope::<Rc<u64>, _>("request", |_| Rc::new(2))
+            .unwrap();
+
+        let container = builder.build();
+        let session = container.enter_named_scope("session");
+        let request = container.begin_request();
+
+        let (user, page) = request.run(|container| {
+            (
+                container.resolve::<Rc<u32>>().unwrap(),
+                container.resolve::<Rc<u64>>().unwrap(),
+            )
+        });
+        assert!(Rc::ptr_eq(&user, &session.resolve::<Rc<u32>>().unwrap()));
+
+        let error = session.resolve::<Rc<u64>>().err().unwrap();
+        assert_eq!(
+            "Scoped type alloc::rc::Rc<u64> resolved outside of a \"request\" scope",
+            error.to_string()
+        );
+
+        drop(request);
+        assert_eq!(1, Rc::strong_count(&page));
+
+        drop(session);
+        assert_eq!(1, Rc::strong_count(&user));
+    }
+
+    #[test]
+    fn requests_can_be_used_while_active() {
+        use crate::{ContainerBuilder, Resolver};
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_scoped::<u32, _>(|container| container.resolve::<u16>().unwrap() as u32 + 1)
+            .unwrap();
+
+        let container = builder.build();
+        let request = container.begin_request();
+
+        request.run(|_| request.provide(5u16));
+        assert_eq!(5, request.resolve::<u16>().unwrap());
+
+        let resolved = request.run(|_| request.resolve::<u32>());
+        assert_eq!(6, resolved.unwrap());
+
+        // still attached only once, and detached afterwards
+        assert_eq!(None, container.inner.scopes.current());
+        assert!(container.resolve::<u16>().is_err());
+    }
+
+    #[test]
+    fn requests_can_run_inside_each_other() {
+        use crate::{ContainerBuilder, Resolver};
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_scoped::<u32, _>(|container| container.resolve::<u16>().unwrap() as u32)
+            .unwrap();
+
+        let container = builder.build();
+        let outer = container.begin_request();
+        let inner = container.begin_request();
+        outer.provide(1u16);
+        inner.provide(2u16);
+
+        let resolved = outer.run(|container| {
+            let from_inner = inner.run(|container| {
+                let from_outer = outer.run(|container| container.resolve::<u32>().unwrap());
+
+                (from_outer, container.resolve::<u32>().unwrap())
+            });
+
+            (from_inner, container.resolve::<u32>().unwrap())
+        });
+
+        assert_eq!(((1, 2), 1), resolved);
+        assert_eq!(None, container.inner.scopes.current());
+    }
+
+    #[test]
+    fn requests_survive_panics_while_active() {
+        use crate::{ContainerBuilder, Resolver};
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let container = ContainerBuilder::new().build();
+        let request = container.begin_request();
+        request.provide(42u32);
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            request.run(|_| panic!("handler failed"));
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(None, container.inner.scopes.current());
+        assert_eq!(42, request.resolve::<u32>().unwrap());
+    }
+}
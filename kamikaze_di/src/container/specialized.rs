@@ -0,0 +1,139 @@
+//! Impls that need specialization, kept in one module so builds without the
+//! `specialization` feature never parse `default fn`.
+//!
+//! Their counterparts without specialization are next to the traits.
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+use super::initialize::{Initialize, MaybeInitialize};
+use super::injector::{Inject, InjectAsRc, Injector};
+use super::validation::AutoResolvable;
+use super::{Container, Resolver};
+use crate::Result;
+
+impl<T> MaybeInitialize for T {
+    default fn maybe_initialize(&self, _: &Container) {}
+}
+
+impl<T: Initialize> MaybeInitialize for T {
+    fn maybe_initialize(&self, container: &Container) {
+        self.initialize(container)
+    }
+}
+
+impl<T> Injector<T> for Container
+where
+    T: Clone + 'static,
+{
+    default fn inject(&self) -> Result<T> {
+        debug!("injecting registered type");
+        self.get()
+    }
+}
+
+// This would be amazing
+//use std::convert::TryFrom;
+//impl<T> TryFrom<Container> for T where T: Inject {
+//    fn from(other: &Container) -> Result<T> {
+//        Injector::<T>::inject(other)
+//    }
+//}
+
+impl<T> Injector<T> for Container
+where
+    T: Inject + Clone + 'static,
+{
+    fn inject(&self) -> Result<T> {
+        debug!("injecting Inject type");
+
+        if !self.has::<T>() {
+            debug!("Inject type not known, auto-resolving");
+            let _guard = self.inner.cycle_stopper.track::<T>()?;
+            let item = T::resolve(self)?;
+
+            let resolver = Resolver::Shared(Rc::new(item));
+
+            self.insert::<T>(resolver)?;
+        }
+
+        self.get()
+    }
+}
+
+impl<T> Injector<Rc<T>> for Container
+where
+    T: InjectAsRc + 'static,
+{
+    fn inject(&self) -> Result<Rc<T>> {
+        debug!("injecting InjectAsRc type");
+
+        if !self.has::<Rc<T>>() {
+            debug!("InjectAsRc type not known, auto-resolving");
+
+            let _guard = self.inner.cycle_stopper.track::<Rc<T>>()?;
+            let item = T::resolve(self)?;
+
+            let resolver = Resolver::Shared(Rc::new(Rc::new(item)));
+
+            self.insert::<Rc<T>>(resolver)?;
+        }
+
+        self.get()
+    }
+}
+
+/// Mutable services are resolved as registered via
+/// [register_mut()](struct.ContainerBuilder.html#method.register_mut),
+/// or auto-resolved from Inject types and shared from then on.
+impl<T> Injector<Rc<RefCell<T>>> for Container
+where
+    T: Inject + 'static,
+{
+    fn inject(&self) -> Result<Rc<RefCell<T>>> {
+        debug!("injecting mutable Inject type");
+
+        if !self.has::<Rc<RefCell<T>>>() {
+            debug!("mutable Inject type not known, auto-resolving");
+
+            let _guard = self.inner.cycle_stopper.track::<Rc<RefCell<T>>>()?;
+            let item = T::resolve(self)?;
+
+            let resolver = Resolver::Shared(Rc::new(Rc::new(RefCell::new(item))));
+
+            self.insert::<Rc<RefCell<T>>>(resolver)?;
+        }
+
+        self.get()
+    }
+}
+
+/// Optional dependencies are injected if registered, see
+/// [try_resolve()](trait.Resolver.html#method.try_resolve).
+impl<T> Injector<Option<Rc<T>>> for Container
+where
+    T: ?Sized + 'static,
+{
+    fn inject(&self) -> Result<Option<Rc<T>>> {
+        debug!("injecting optional type");
+        self.get_optional()
+    }
+}
+
+impl<T> AutoResolvable for T {
+    default fn auto_resolvable() -> bool {
+        false
+    }
+}
+
+impl<T: Inject> AutoResolvable for T {
+    fn auto_resolvable() -> bool {
+        true
+    }
+}
+
+impl<T: InjectAsRc> AutoResolvable for Rc<T> {
+    fn auto_resolvable() -> bool {
+        true
+    }
+}
@@ -0,0 +1,199 @@
+use core::any::TypeId;
+use core::cell::RefCell;
+use core::fmt;
+use core::time::Duration;
+
+use crate::clock::Stopwatch;
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+use crate::Result;
+
+/// How a type was resolved, see [stats()](struct.Container.html#method.stats).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct TypeStats {
+    /// Name of the registered type.
+    pub type_name: &'static str,
+    /// Times the type was resolved.
+    pub resolutions: u64,
+    /// Resolutions handed an item that already existed, shared or scoped.
+    pub cache_hits: u64,
+    /// Times a factory or builder ran to create an item.
+    pub constructions: u64,
+    /// Time spent creating items, including their dependencies.
+    ///
+    /// Always zero without the `std` feature, or on wasm32-unknown-unknown,
+    /// there is no clock otherwise.
+    pub construction_time: Duration,
+}
+
+impl fmt::Display for TypeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} resolutions, {} cache hits, {} constructions in {:?}",
+            self.type_name,
+            self.resolutions,
+            self.cache_hits,
+            self.constructions,
+            self.construction_time
+        )
+    }
+}
+
+/// Resolution statistics of every type resolved so far.
+///
+/// Returned from [stats()](struct.Container.html#method.stats).
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct StatsReport {
+    /// Stats of each type, the most constructed first.
+    pub types: Vec<TypeStats>,
+}
+
+impl StatsReport {
+    /// Returns the stats of T, if it was resolved.
+    pub fn of<T: ?Sized + 'static>(&self) -> Option<&TypeStats> {
+        let type_name = core::any::type_name::<T>();
+
+        self.types.iter().find(|stats| stats.type_name == type_name)
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Resolution stats:")?;
+
+        for stats in &self.types {
+            write!(f, "\n  {}", stats)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects stats, if enabled via ContainerBuilder::record_stats().
+#[derive(Default, Debug)]
+pub(super) struct StatsRecorder {
+    enabled: bool,
+    stats: RefCell<TypeIdMap<TypeStats>>,
+}
+
+impl StatsRecorder {
+    pub fn new(enabled: bool) -> StatsRecorder {
+        StatsRecorder {
+            enabled,
+            stats: Default::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn resolved(&self, type_id: TypeId) {
+        self.update(type_id, |stats| stats.resolutions += 1);
+    }
+
+    pub fn hit(&self, type_id: TypeId) {
+        self.update(type_id, |stats| stats.cache_hits += 1);
+    }
+
+    /// Creates an item, keeping track of how long that took.
+    pub fn construct<T>(&self, type_id: TypeId, create: impl FnOnce() -> Result<T>) -> Result<T> {
+        if !self.enabled {
+            return create();
+        }
+
+        let begun = Stopwatch::start();
+
+        // not borrowed while creating, dependencies have stats too
+        let result = create();
+
+        let took = begun.elapsed().unwrap_or(Duration::ZERO);
+
+        self.update(type_id, |stats| {
+            stats.constructions += 1;
+            stats.construction_time += took;
+        });
+
+        result
+    }
+
+    /// Lists the stats, `type_name` looks up the name of a registered type.
+    pub fn report<F>(&self, type_name: F) -> StatsReport
+    where
+        F: Fn(TypeId) -> Option<&'static str>,
+    {
+        let mut types: Vec<_> = self
+            .stats
+            .borrow()
+            .iter()
+            .map(|(type_id, stats)| TypeStats {
+                type_name: type_name(*type_id).unwrap_or("<unknown>"),
+                ..*stats
+            })
+            .collect();
+
+        types.sort_by(|a, b| {
+            b.constructions
+                .cmp(&a.constructions)
+                .then(a.type_name.cmp(b.type_name))
+        });
+
+        StatsReport { types }
+    }
+
+    fn update(&self, type_id: TypeId, update: impl FnOnce(&mut TypeStats)) {
+        if self.enabled {
+            update(self.stats.borrow_mut().entry(type_id).or_default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_recorded_unless_enabled() {
+        let recorder = StatsRecorder::new(false);
+        recorder.resolved(TypeId::of::<i32>());
+        recorder.construct(TypeId::of::<i32>(), || Ok(1)).unwrap();
+
+        assert!(recorder.report(|_| Some("i32")).types.is_empty());
+    }
+
+    #[test]
+    fn reports_put_the_most_constructed_first() {
+        let recorder = StatsRecorder::new(true);
+        for _ in 0..2 {
+            recorder.resolved(TypeId::of::<u8>());
+            recorder.hit(TypeId::of::<u8>());
+        }
+        for _ in 0..3 {
+            recorder.resolved(TypeId::of::<i32>());
+            recorder.construct(TypeId::of::<i32>(), || Ok(1)).unwrap();
+        }
+
+        let report = recorder.report(|type_id| {
+            Some(if type_id == TypeId::of::<u8>() {
+                "u8"
+            } else {
+                "i32"
+            })
+        });
+
+        assert_eq!(
+            vec!["i32", "u8"],
+            report
+                .types
+                .iter()
+                .map(|stats| stats.type_name)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(3, report.of::<i32>().unwrap().constructions);
+        assert_eq!(2, report.of::<u8>().unwrap().cache_hits);
+        assert!(report
+            .to_string()
+            .contains("\n  u8: 2 resolutions, 2 cache hits, 0 constructions in 0ns"));
+    }
+}
@@ -0,0 +1,145 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::marker::PhantomData;
+
+use super::erased::{ErasedBuilder, ErasedFactory};
+use super::{Container, Resolver, ResolverType};
+
+/// What to resolve T with after a [swap()](struct.MutableContainer.html#method.swap).
+///
+/// Items convert into a swap, so `container.swap::<T>(item)` shares the
+/// item from then on. Use [factory()](#method.factory) or
+/// [builder()](#method.builder) for the other lifetimes.
+pub struct Swap<T> {
+    pub(super) resolver: Resolver,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> Swap<T> {
+    /// Shares the item.
+    pub fn item(item: T) -> Swap<T> {
+        Swap::with(Resolver::Shared(Rc::new(item)))
+    }
+
+    /// Creates a new item every time T is resolved.
+    pub fn factory<F>(factory: F) -> Swap<T>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+    {
+        let factory = ErasedFactory::new(factory);
+
+        Swap::with(Resolver::Factory(Rc::new(RefCell::new(factory))))
+    }
+
+    /// Creates the item the next time T is resolved, and shares it.
+    ///
+    /// Handy after reloading configuration, as the new item is only built
+    /// once something needs it, from whatever the container has by then.
+    pub fn builder<B>(builder: B) -> Swap<T>
+    where
+        B: FnOnce(&Container) -> T + 'static,
+    {
+        Swap::with(Resolver::Builder(ErasedBuilder::new(builder)))
+    }
+
+    fn with(resolver: Resolver) -> Swap<T> {
+        Swap {
+            resolver,
+            item: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> From<T> for Swap<T> {
+    fn from(item: T) -> Swap<T> {
+        Swap::item(item)
+    }
+}
+
+impl<T> core::fmt::Debug for Swap<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Swap")
+            .field("type_name", &core::any::type_name::<T>())
+            .field("resolver_type", &ResolverType::from(&self.resolver))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerBuilder, Resolver};
+
+    struct Settings {
+        retries: u32,
+    }
+
+    #[test]
+    fn holders_keep_the_swapped_out_item() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register::<Rc<Settings>>(Rc::new(Settings { retries: 1 }))
+            .unwrap();
+
+        let container = builder.build();
+        let before = container.resolve::<Rc<Settings>>().unwrap();
+
+        container
+            .swap::<Rc<Settings>>(Rc::new(Settings { retries: 3 }))
+            .unwrap();
+
+        assert_eq!(1, before.retries);
+        assert_eq!(3, container.resolve::<Rc<Settings>>().unwrap().retries);
+    }
+
+    #[test]
+    fn swapped_builders_run_on_next_resolve() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(3).unwrap();
+        builder.register::<u64>(1).unwrap();
+
+        let container = builder.build();
+        assert_eq!(1, container.resolve::<u64>().unwrap());
+
+        container
+            .swap::<u64>(Swap::builder(|container| {
+                u64::from(container.resolve::<u32>().unwrap())
+            }))
+            .unwrap();
+        container.swap::<u32>(5).unwrap();
+
+        assert_eq!(5, container.resolve::<u64>().unwrap());
+        assert_eq!(5, container.resolve::<u64>().unwrap());
+    }
+
+    #[test]
+    fn swapping_drops_scoped_items() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_scoped::<Rc<u32>, _>(|_| Rc::new(1))
+            .unwrap();
+
+        let container = builder.build();
+        let scope = container.enter_scope();
+        let before = scope.resolve::<Rc<u32>>().unwrap();
+
+        let mut next = 1;
+        scope
+            .swap::<Rc<u32>>(Swap::factory(move |_| {
+                next += 1;
+                Rc::new(next)
+            }))
+            .unwrap();
+
+        assert_eq!(1, *before);
+        assert_eq!(2, *scope.resolve::<Rc<u32>>().unwrap());
+        assert_eq!(3, *scope.resolve::<Rc<u32>>().unwrap());
+    }
+
+    #[test]
+    fn only_registered_types_can_be_swapped() {
+        let container = ContainerBuilder::new().build();
+
+        assert!(container.swap::<u32>(1).is_err());
+    }
+}
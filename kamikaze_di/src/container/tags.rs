@@ -0,0 +1,236 @@
+use alloc::rc::Rc;
+use core::any::{Any, TypeId};
+use core::fmt;
+
+use super::Container;
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::Result;
+
+/// Resolves a tagged type, boxed as Rc<dyn Any>.
+type ResolveAny = fn(&Container) -> Result<Rc<dyn Any>>;
+/// Resolves a tagged type as Rc<U>, see ContainerBuilder::tag_as().
+type ResolveAs<U> = Rc<dyn Fn(&Container) -> Result<Rc<U>>>;
+
+/// Tags and the types carrying them, in the order they were tagged.
+#[derive(Clone, Default)]
+pub(super) struct Tags {
+    tags: HashMap<String, Vec<Tagged>>,
+}
+
+#[derive(Clone)]
+struct Tagged {
+    type_id: TypeId,
+    type_name: &'static str,
+    resolve: ResolveAny,
+    /// ResolveAs<U>, if tagged via tag_as().
+    resolve_as: Option<(TypeId, Rc<dyn Any>)>,
+}
+
+impl Tags {
+    pub fn add<T: Clone + 'static>(&mut self, tag: &str) -> Result<()> {
+        self.insert(tag, Tagged::of::<T>())
+    }
+
+    pub fn add_as<T, U, F>(&mut self, tag: &str, cast: F) -> Result<()>
+    where
+        T: Clone + 'static,
+        U: ?Sized + 'static,
+        F: Fn(T) -> Rc<U> + 'static,
+    {
+        let resolve_as: ResolveAs<U> = Rc::new(move |container| Ok(cast(container.get::<T>()?)));
+
+        self.insert(
+            tag,
+            Tagged {
+                resolve_as: Some((TypeId::of::<U>(), Rc::new(resolve_as))),
+                ..Tagged::of::<T>()
+            },
+        )
+    }
+
+    /// Adds their tags, skipping types that already carry them.
+    pub fn merge(&mut self, other: Tags) {
+        for (tag, theirs) in other.tags {
+            let ours = self.tags.entry(tag).or_default();
+
+            for tagged in theirs {
+                if !ours.iter().any(|item| item.type_id == tagged.type_id) {
+                    ours.push(tagged);
+                }
+            }
+        }
+    }
+
+    pub fn resolve(&self, tag: &str, container: &Container) -> Result<Vec<Rc<dyn Any>>> {
+        self.tagged(tag)
+            .iter()
+            .map(|tagged| (tagged.resolve)(container))
+            .collect()
+    }
+
+    pub fn resolve_as<U: ?Sized + 'static>(
+        &self,
+        tag: &str,
+        container: &Container,
+    ) -> Result<Vec<Rc<U>>> {
+        self.tagged(tag)
+            .iter()
+            .map(|tagged| {
+                let resolve = tagged
+                    .resolve_as
+                    .as_ref()
+                    .filter(|(type_id, _)| *type_id == TypeId::of::<U>())
+                    .and_then(|(_, resolve)| resolve.downcast_ref::<ResolveAs<U>>())
+                    .ok_or_else(|| {
+                        format!(
+                            "{} is tagged \"{}\", but not as {}",
+                            tagged.type_name,
+                            tag,
+                            core::any::type_name::<U>()
+                        )
+                    })?;
+
+                resolve(container)
+            })
+            .collect()
+    }
+
+    /// Tags carried by a type, sorted.
+    pub fn of(&self, type_id: TypeId) -> Vec<&str> {
+        let mut tags: Vec<_> = self
+            .tags
+            .iter()
+            .filter(|(_, tagged)| tagged.iter().any(|tagged| tagged.type_id == type_id))
+            .map(|(tag, _)| tag.as_str())
+            .collect();
+
+        tags.sort_unstable();
+
+        tags
+    }
+
+    fn tagged(&self, tag: &str) -> &[Tagged] {
+        self.tags.get(tag).map_or(&[], |tagged| tagged)
+    }
+
+    fn insert(&mut self, tag: &str, tagged: Tagged) -> Result<()> {
+        let items = self.tags.entry(tag.to_string()).or_default();
+
+        if items.iter().any(|item| item.type_id == tagged.type_id) {
+            return Err(format!("{} is already tagged \"{}\"", tagged.type_name, tag).into());
+        }
+
+        items.push(tagged);
+
+        Ok(())
+    }
+}
+
+impl Tagged {
+    fn of<T: Clone + 'static>() -> Tagged {
+        Tagged {
+            type_id: TypeId::of::<T>(),
+            type_name: core::any::type_name::<T>(),
+            resolve: |container| Ok(Rc::new(container.get::<T>()?)),
+            resolve_as: None,
+        }
+    }
+}
+
+impl fmt::Debug for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut tags: Vec<_> = self
+            .tags
+            .iter()
+            .map(|(tag, tagged)| {
+                let type_names: Vec<_> = tagged.iter().map(|tagged| tagged.type_name).collect();
+                (tag, type_names)
+            })
+            .collect();
+
+        tags.sort();
+
+        f.debug_map().entries(tags).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ContainerBuilder;
+    use std::rc::Rc;
+
+    trait Subscriber {
+        fn name(&self) -> &'static str;
+    }
+
+    #[derive(Clone)]
+    struct Mailer;
+    #[derive(Clone)]
+    struct AuditLog;
+
+    impl Subscriber for Mailer {
+        fn name(&self) -> &'static str {
+            "mailer"
+        }
+    }
+
+    impl Subscriber for AuditLog {
+        fn name(&self) -> &'static str {
+            "audit log"
+        }
+    }
+
+    fn builder() -> ContainerBuilder {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Rc<Mailer>>(Rc::new(Mailer)).unwrap();
+        builder.register::<Rc<AuditLog>>(Rc::new(AuditLog)).unwrap();
+        builder.register::<u32>(42).unwrap();
+
+        builder
+            .tag_as::<Rc<Mailer>, dyn Subscriber, _>("subscriber", |mailer| mailer)
+            .unwrap();
+        builder
+            .tag_as::<Rc<AuditLog>, dyn Subscriber, _>("subscriber", |log| log)
+            .unwrap();
+
+        builder
+    }
+
+    #[test]
+    fn tagged_services_come_in_tagging_order() {
+        let container = builder().build();
+
+        let names: Vec<_> = container
+            .resolve_tagged_as::<dyn Subscriber>("subscriber")
+            .unwrap()
+            .iter()
+            .map(|subscriber| subscriber.name())
+            .collect();
+
+        assert_eq!(vec!["mailer", "audit log"], names);
+
+        let untyped = container.resolve_tagged("subscriber").unwrap();
+        assert!(untyped[0].downcast_ref::<Rc<Mailer>>().is_some());
+        assert!(container.resolve_tagged("nothing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn typed_resolution_needs_a_cast() {
+        let mut builder = builder();
+        builder.tag::<u32>("subscriber").unwrap();
+        assert!(builder.tag::<u32>("subscriber").is_err());
+
+        let container = builder.build();
+
+        assert_eq!(3, container.resolve_tagged("subscriber").unwrap().len());
+
+        let error = container
+            .resolve_tagged_as::<dyn Subscriber>("subscriber")
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("u32 is tagged \"subscriber\", but not as"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Spans and events through tracing, with the `tracing` feature.
+//!
+//! Without the feature everything here does nothing, and compiles away.
+
+#[cfg(feature = "tracing")]
+use core::cell::Cell;
+#[cfg(not(feature = "tracing"))]
+use core::marker::PhantomData;
+
+use super::ResolverType;
+use crate::Result;
+
+/// How many resolutions are in progress, one inside the other.
+#[derive(Debug, Default)]
+pub(super) struct Depth {
+    #[cfg(feature = "tracing")]
+    current: Cell<usize>,
+}
+
+/// Span of a resolution, entered until dropped.
+pub(super) struct ResolveSpan<'a> {
+    #[cfg(feature = "tracing")]
+    depth: &'a Depth,
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+    #[cfg(not(feature = "tracing"))]
+    _depth: PhantomData<&'a Depth>,
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for ResolveSpan<'_> {
+    fn drop(&mut self) {
+        self.depth.current.set(self.depth.current.get() - 1);
+    }
+}
+
+/// Enters the span of a resolution.
+#[cfg(feature = "tracing")]
+pub(super) fn resolving<'a>(type_name: &'static str, depth: &'a Depth) -> ResolveSpan<'a> {
+    let current = depth.current.get();
+    depth.current.set(current + 1);
+
+    let span = tracing::trace_span!("resolve", type_name = type_name, depth = current);
+
+    ResolveSpan {
+        depth,
+        _span: span.entered(),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(super) fn resolving<'a>(_: &'static str, _: &'a Depth) -> ResolveSpan<'a> {
+    ResolveSpan {
+        _depth: PhantomData,
+    }
+}
+
+/// Reports how the resolution of T ended, within its span.
+#[cfg(feature = "tracing")]
+pub(super) fn resolved<T>(result: &Result<T>) {
+    match result {
+        Ok(_) => tracing::trace!("resolved"),
+        Err(error) => tracing::debug!(error = %error, "resolution failed"),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(super) fn resolved<T>(_: &Result<T>) {}
+
+/// Reports that T was registered, or replaced.
+#[cfg(feature = "tracing")]
+pub(super) fn registered<T>(resolver_type: ResolverType) {
+    tracing::debug!(
+        type_name = core::any::type_name::<T>(),
+        resolver_type = ?resolver_type,
+        "registered"
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(super) fn registered<T>(_: ResolverType) {}
+
+/// Reports that the builder of T ran, and T is now shared.
+#[cfg(feature = "tracing")]
+pub(super) fn built<T>() {
+    tracing::debug!(type_name = core::any::type_name::<T>(), "builder consumed");
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(super) fn built<T>() {}
+
+#[cfg(all(test, feature = "tracing", feature = "std"))]
+mod tests {
+    use crate::{ContainerBuilder, Resolver};
+    use std::fmt::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Writes every span and event as a line of `name field=value ...`.
+    #[derive(Clone, Default)]
+    struct Lines(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            write!(self.0, " {}={:?}", field.name(), value).unwrap();
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            write!(self.0, " {}={}", field.name(), value).unwrap();
+        }
+    }
+
+    impl Subscriber for Lines {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes) -> Id {
+            let mut fields = Fields(format!("span {}", span.metadata().name()));
+            span.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields(String::from("event"));
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn container_activity_is_traced() {
+        let lines = Lines::default();
+
+        tracing::subscriber::with_default(lines.clone(), || {
+            let mut builder = ContainerBuilder::new();
+            builder.register::<i32>(42).unwrap();
+            builder
+                .register_builder::<i64, _>(|container| container.resolve::<i32>().unwrap().into())
+                .unwrap();
+
+            let container = builder.build();
+            container.resolve::<i64>().unwrap();
+            container.resolve::<u8>().unwrap_err();
+        });
+
+        let lines = lines.0.lock().unwrap();
+
+        assert_eq!(
+            vec![
+                "event message=registered type_name=i32 resolver_type=Shared",
+                "event message=registered type_name=i64 resolver_type=Builder",
+                "span resolve type_name=i64 depth=0",
+                "span resolve type_name=i32 depth=1",
+                "event message=resolved",
+                "event message=builder consumed type_name=i64",
+                "event message=registered type_name=i64 resolver_type=Shared",
+                "event message=resolved",
+                "span resolve type_name=u8 depth=0",
+            ],
+            lines[..9]
+        );
+        assert!(lines[9].starts_with("event message=resolution failed error="));
+    }
+}
@@ -0,0 +1,176 @@
+use core::any::TypeId;
+use core::cell::RefCell;
+use core::fmt;
+
+use super::scope::Scope;
+use super::ResolverType;
+use crate::collections::TypeIdMap;
+use crate::prelude::*;
+
+/// A lifetime that could be changed, based on how a dependency was used.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct LifetimeSuggestion {
+    /// Name of the registered type.
+    pub type_name: &'static str,
+    /// The lifetime the type was registered with.
+    pub declared: Scope,
+    /// The lifetime that fits what was observed.
+    pub suggested: Scope,
+    /// What was observed.
+    pub reason: &'static str,
+}
+
+impl fmt::Display for LifetimeSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is {:?}, but was {}, consider {:?}",
+            self.type_name, self.declared, self.reason, self.suggested
+        )
+    }
+}
+
+/// Lifetimes that don't match how dependencies were used so far.
+///
+/// Returned from [lifetime_report()](struct.Container.html#method.lifetime_report).
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct LifetimeReport {
+    /// Suggestions, sorted by type name.
+    pub suggestions: Vec<LifetimeSuggestion>,
+}
+
+impl LifetimeReport {
+    /// Returns true if there is nothing to suggest.
+    pub fn is_empty(&self) -> bool {
+        self.suggestions.is_empty()
+    }
+}
+
+impl fmt::Display for LifetimeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lifetime report:")?;
+
+        for suggestion in &self.suggestions {
+            write!(f, "\n  {}", suggestion)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Counts how registered dependencies are resolved.
+#[derive(Default, Debug)]
+pub(super) struct UsageTracker {
+    usages: RefCell<TypeIdMap<Usage>>,
+}
+
+#[derive(Default, Debug)]
+struct Usage {
+    resolved: u64,
+    unscoped: u64,
+    // kept to a single scope id, so tracking doesn't grow with every request
+    first_scope: Option<usize>,
+    many_scopes: bool,
+}
+
+impl UsageTracker {
+    pub fn was_resolved(&self, type_id: TypeId) -> bool {
+        self.usages.borrow().contains_key(&type_id)
+    }
+
+    pub fn record(&self, type_id: TypeId, scope: Option<usize>) {
+        let mut usages = self.usages.borrow_mut();
+        let usage = usages.entry(type_id).or_default();
+
+        usage.resolved += 1;
+
+        match (scope, usage.first_scope) {
+            (None, _) => usage.unscoped += 1,
+            (Some(scope), None) => usage.first_scope = Some(scope),
+            (Some(scope), Some(first)) => usage.many_scopes |= scope != first,
+        }
+    }
+
+    /// Compares usage to how types were registered.
+    ///
+    /// `registered` looks up the name and resolver type of a registration.
+    pub fn report<F>(&self, registered: F) -> LifetimeReport
+    where
+        F: Fn(TypeId) -> Option<(&'static str, ResolverType)>,
+    {
+        let usages = self.usages.borrow();
+
+        let mut suggestions: Vec<_> = usages
+            .iter()
+            .filter_map(|(type_id, usage)| {
+                let (type_name, resolver_type) = registered(*type_id)?;
+
+                suggest(lifetime_of(resolver_type), usage).map(|(suggested, reason)| {
+                    LifetimeSuggestion {
+                        type_name,
+                        declared: lifetime_of(resolver_type),
+                        suggested,
+                        reason,
+                    }
+                })
+            })
+            .collect();
+
+        suggestions.sort_by_key(|suggestion| suggestion.type_name);
+
+        LifetimeReport { suggestions }
+    }
+}
+
+pub(super) fn lifetime_of(resolver_type: ResolverType) -> Scope {
+    match resolver_type {
+        ResolverType::Factory => Scope::Transient,
+        ResolverType::Builder | ResolverType::Shared => Scope::Singleton,
+        ResolverType::Scoped => Scope::Scoped,
+    }
+}
+
+fn suggest(declared: Scope, usage: &Usage) -> Option<(Scope, &'static str)> {
+    let in_one_scope = usage.unscoped == 0 && !usage.many_scopes;
+
+    // scoped items are already as tight as it gets, and whether they could be
+    // shared more widely can't be told from usage
+    match declared {
+        Scope::Transient if usage.resolved == 1 => Some((Scope::Singleton, "resolved only once")),
+        Scope::Singleton if in_one_scope => {
+            Some((Scope::Scoped, "resolved only inside a single scope"))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(resolver_type: ResolverType, scopes: &[Option<usize>]) -> LifetimeReport {
+        let tracker = UsageTracker::default();
+        for scope in scopes {
+            tracker.record(TypeId::of::<i32>(), *scope);
+        }
+
+        tracker.report(|_| Some(("i32", resolver_type)))
+    }
+
+    #[test]
+    fn transients_resolved_once_could_be_singletons() {
+        let suggestions = report(ResolverType::Factory, &[None]).suggestions;
+        assert_eq!(Scope::Singleton, suggestions[0].suggested);
+
+        assert!(report(ResolverType::Factory, &[None, Some(1)]).is_empty());
+    }
+
+    #[test]
+    fn singletons_used_in_one_scope_could_be_scoped() {
+        let suggestions = report(ResolverType::Shared, &[Some(1), Some(1)]).suggestions;
+        assert_eq!(Scope::Scoped, suggestions[0].suggested);
+
+        assert!(report(ResolverType::Shared, &[Some(1), Some(2)]).is_empty());
+        assert!(report(ResolverType::Shared, &[Some(1), None]).is_empty());
+    }
+}
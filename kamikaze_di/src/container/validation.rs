@@ -0,0 +1,729 @@
+use core::any::TypeId;
+use core::fmt;
+
+use crate::collections::{HashSet, TypeIdMap, TypeIdSet};
+use crate::prelude::*;
+
+/// A dependency declared for a registration.
+///
+/// See [declare_dependencies()](struct.ContainerBuilder.html#method.declare_dependencies).
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Dependency {
+    type_id: TypeId,
+    type_name: &'static str,
+    auto_resolvable: bool,
+}
+
+impl Dependency {
+    /// Describes a dependency on T.
+    pub fn of<T: 'static>() -> Dependency {
+        Dependency {
+            type_id: TypeId::of::<T>(),
+            type_name: core::any::type_name::<T>(),
+            auto_resolvable: <T as AutoResolvable>::auto_resolvable(),
+        }
+    }
+
+    /// Name of the type depended on.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns true if the container can resolve the type without it
+    /// being registered, see [Inject](trait.Inject.html).
+    pub fn is_auto_resolvable(&self) -> bool {
+        self.auto_resolvable
+    }
+}
+
+impl fmt::Debug for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dependency")
+            .field("type_name", &self.type_name)
+            .field("auto_resolvable", &self.auto_resolvable)
+            .finish()
+    }
+}
+
+/// A list of dependencies, implemented for tuples of types.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::Dependencies;
+/// #
+/// let dependencies = <(i32, String)>::dependencies();
+///
+/// assert_eq!("i32", dependencies[0].type_name());
+/// ```
+pub trait Dependencies {
+    /// Lists the dependencies.
+    fn dependencies() -> Vec<Dependency>;
+}
+
+macro_rules! impl_dependencies {
+    ($($dependency:ident),*) => {
+        impl<$($dependency: 'static),*> Dependencies for ($($dependency,)*) {
+            fn dependencies() -> Vec<Dependency> {
+                vec![$(Dependency::of::<$dependency>()),*]
+            }
+        }
+    };
+}
+
+impl_dependencies!();
+impl_dependencies!(A);
+impl_dependencies!(A, B);
+impl_dependencies!(A, B, C);
+impl_dependencies!(A, B, C, D);
+impl_dependencies!(A, B, C, D, E);
+impl_dependencies!(A, B, C, D, E, F);
+impl_dependencies!(A, B, C, D, E, F, G);
+impl_dependencies!(A, B, C, D, E, F, G, H);
+
+/// Everything wrong with the container's wiring.
+///
+/// Returned from [validate()](struct.Container.html#method.validate).
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ValidationReport {
+    /// Pairs of (dependent, missing dependency) type names.
+    pub missing: Vec<(&'static str, &'static str)>,
+    /// Dependency cycles, each one listed as the type names in the cycle.
+    pub cycles: Vec<Vec<&'static str>>,
+    /// Pairs of (type name, problem) for items registered with invalid
+    /// values, like config sections failing their checks.
+    pub invalid: Vec<(&'static str, String)>,
+}
+
+impl ValidationReport {
+    /// Returns true if no problems were found.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.cycles.is_empty() && self.invalid.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Container validation failed:")?;
+
+        for (dependent, dependency) in &self.missing {
+            write!(
+                f,
+                "\n  {} depends on {}, which is not registered",
+                dependent, dependency
+            )?;
+        }
+
+        for cycle in &self.cycles {
+            write!(f, "\n  circular dependency: {}", cycle.join(" -> "))?;
+        }
+
+        for (type_name, problem) in &self.invalid {
+            write!(f, "\n  {} is invalid: {}", type_name, problem)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Problems with the values of a registered item, attached as metadata.
+///
+/// Reported by [validate()](struct.Container.html#method.validate) and
+/// [warm_up()](struct.Container.html#method.warm_up).
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "config"), allow(dead_code))]
+pub(crate) struct InvalidValues(pub(crate) Vec<String>);
+
+/// A declared dependency on a deprecated registration.
+///
+/// See [deprecate()](struct.ContainerBuilder.html#method.deprecate).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeprecatedUse {
+    /// Name of the type declaring the dependency.
+    pub dependent: &'static str,
+    /// Name of the deprecated type.
+    pub dependency: &'static str,
+    /// Why it's deprecated, or what to use instead.
+    pub note: &'static str,
+}
+
+/// Everything wrong with a composition root's wiring.
+///
+/// Returned from [composition_report()](struct.Container.html#method.composition_report)
+/// and [ContainerBuilder::validate()](struct.ContainerBuilder.html#method.validate),
+/// and checked by [composition_root!](macro.composition_root.html).
+///
+/// Missing dependencies, cycles and forbidden dependencies are errors,
+/// unused and deprecated registrations are warnings. With the `serde`
+/// feature, the report can be serialized, to gate deployments on it.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CompositionReport {
+    /// Missing dependencies and cycles.
+    pub validation: ValidationReport,
+    /// Registered types nothing depends on, that aren't roots either.
+    pub unused: Vec<&'static str>,
+    /// Pairs of (dependent, dependency) type names crossing forbidden module boundaries.
+    pub forbidden: Vec<(&'static str, &'static str)>,
+    /// Declared dependencies on deprecated registrations.
+    pub deprecated: Vec<DeprecatedUse>,
+}
+
+impl CompositionReport {
+    /// Returns true if no problems were found, warnings included.
+    pub fn is_empty(&self) -> bool {
+        !self.has_errors() && !self.has_warnings()
+    }
+
+    /// Returns true if there are missing, circular or forbidden dependencies.
+    pub fn has_errors(&self) -> bool {
+        !self.validation.is_empty() || !self.forbidden.is_empty()
+    }
+
+    /// Returns true if there are unused or deprecated registrations.
+    pub fn has_warnings(&self) -> bool {
+        !self.unused.is_empty() || !self.deprecated.is_empty()
+    }
+}
+
+impl fmt::Display for CompositionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Composition root check failed:")?;
+
+        for (dependent, dependency) in &self.validation.missing {
+            write!(
+                f,
+                "\n  {} depends on {}, which is not registered",
+                dependent, dependency
+            )?;
+        }
+
+        for cycle in &self.validation.cycles {
+            write!(f, "\n  circular dependency: {}", cycle.join(" -> "))?;
+        }
+
+        for unused in &self.unused {
+            write!(f, "\n  {} is registered, but nothing depends on it", unused)?;
+        }
+
+        for (dependent, dependency) in &self.forbidden {
+            write!(
+                f,
+                "\n  {} depends on {}, which is forbidden",
+                dependent, dependency
+            )?;
+        }
+
+        for deprecated in &self.deprecated {
+            write!(
+                f,
+                "\n  {} depends on {}, which is deprecated: {}",
+                deprecated.dependent, deprecated.dependency, deprecated.note
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Declared dependencies of a registered type.
+#[derive(Clone, Debug)]
+pub(super) struct Declaration {
+    pub type_name: &'static str,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Checks declared dependencies against what is registered.
+pub(super) fn validate<F>(
+    declarations: &TypeIdMap<Declaration>,
+    is_registered: F,
+) -> ValidationReport
+where
+    F: Fn(TypeId) -> bool,
+{
+    let mut report = ValidationReport::default();
+
+    let mut dependents: Vec<_> = declarations.iter().collect();
+    // keep reports stable
+    dependents.sort_by_key(|(_, declaration)| declaration.type_name);
+
+    for (_, declaration) in &dependents {
+        for dependency in &declaration.dependencies {
+            if !dependency.auto_resolvable && !is_registered(dependency.type_id) {
+                report
+                    .missing
+                    .push((declaration.type_name, dependency.type_name));
+            }
+        }
+    }
+
+    let mut finished = TypeIdSet::default();
+    for (type_id, _) in &dependents {
+        let mut path = Vec::new();
+        find_cycles(
+            **type_id,
+            declarations,
+            &mut path,
+            &mut finished,
+            &mut report.cycles,
+        );
+    }
+
+    report
+}
+
+fn find_cycles(
+    type_id: TypeId,
+    declarations: &TypeIdMap<Declaration>,
+    path: &mut Vec<TypeId>,
+    finished: &mut TypeIdSet,
+    cycles: &mut Vec<Vec<&'static str>>,
+) {
+    if finished.contains(&type_id) {
+        return;
+    }
+
+    if let Some(start) = path.iter().position(|visited| *visited == type_id) {
+        let mut cycle: Vec<_> = path[start..]
+            .iter()
+            .map(|visited| declarations[visited].type_name)
+            .collect();
+        cycle.push(declarations[&type_id].type_name);
+        cycles.push(cycle);
+
+        return;
+    }
+
+    let declaration = match declarations.get(&type_id) {
+        Some(declaration) => declaration,
+        None => return,
+    };
+
+    path.push(type_id);
+    for dependency in &declaration.dependencies {
+        find_cycles(dependency.type_id, declarations, path, finished, cycles);
+    }
+    path.pop();
+
+    finished.insert(type_id);
+}
+
+/// Finds a chain of declared dependencies leading from one type to another.
+///
+/// Returns the type names along the chain, both ends included.
+pub(super) fn find_path(
+    declarations: &TypeIdMap<Declaration>,
+    from: TypeId,
+    to: TypeId,
+) -> Option<Vec<&'static str>> {
+    let mut visited = TypeIdSet::default();
+    let mut path = Vec::new();
+
+    if walk_path(from, to, declarations, &mut visited, &mut path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn walk_path(
+    type_id: TypeId,
+    to: TypeId,
+    declarations: &TypeIdMap<Declaration>,
+    visited: &mut TypeIdSet,
+    path: &mut Vec<&'static str>,
+) -> bool {
+    if !visited.insert(type_id) {
+        return false;
+    }
+
+    let declaration = match declarations.get(&type_id) {
+        Some(declaration) => declaration,
+        None => return false,
+    };
+
+    path.push(declaration.type_name);
+    for dependency in &declaration.dependencies {
+        if dependency.type_id == to {
+            path.push(dependency.type_name);
+            return true;
+        }
+
+        if walk_path(dependency.type_id, to, declarations, visited, path) {
+            return true;
+        }
+    }
+    path.pop();
+
+    false
+}
+
+/// Orders types so declared dependencies come before their dependents.
+///
+/// Types keep their order otherwise, and cycles are broken where they're
+/// found, validation reports them.
+pub(super) fn initialization_order(
+    declarations: &TypeIdMap<Declaration>,
+    types: &[TypeId],
+) -> Vec<TypeId> {
+    let mut visited = TypeIdSet::default();
+    let mut order = Vec::with_capacity(types.len());
+
+    for type_id in types {
+        visit_dependencies_first(*type_id, declarations, &mut visited, &mut order);
+    }
+
+    let wanted: TypeIdSet = types.iter().copied().collect();
+    order.retain(|type_id| wanted.contains(type_id));
+
+    order
+}
+
+fn visit_dependencies_first(
+    type_id: TypeId,
+    declarations: &TypeIdMap<Declaration>,
+    visited: &mut TypeIdSet,
+    order: &mut Vec<TypeId>,
+) {
+    if !visited.insert(type_id) {
+        return;
+    }
+
+    if let Some(declaration) = declarations.get(&type_id) {
+        for dependency in &declaration.dependencies {
+            visit_dependencies_first(dependency.type_id, declarations, visited, order);
+        }
+    }
+
+    order.push(type_id);
+}
+
+/// Collects a type and everything it transitively depends on.
+pub(super) fn dependency_closure(
+    declarations: &TypeIdMap<Declaration>,
+    type_id: TypeId,
+) -> TypeIdSet {
+    let mut closure = TypeIdSet::default();
+    let mut queue = vec![type_id];
+
+    while let Some(type_id) = queue.pop() {
+        if !closure.insert(type_id) {
+            continue;
+        }
+
+        if let Some(declaration) = declarations.get(&type_id) {
+            queue.extend(
+                declaration
+                    .dependencies
+                    .iter()
+                    .map(|dependency| dependency.type_id),
+            );
+        }
+    }
+
+    closure
+}
+
+/// Lists registered types that are neither roots nor depended on, sorted.
+pub(super) fn unused<'a, I>(
+    declarations: &TypeIdMap<Declaration>,
+    registered: I,
+    roots: &[Dependency],
+) -> Vec<&'static str>
+where
+    I: IntoIterator<Item = (&'a TypeId, &'static str)>,
+{
+    let used: HashSet<_> = declarations
+        .values()
+        .flat_map(|declaration| declaration.dependencies.iter())
+        .chain(roots)
+        .map(|dependency| dependency.type_id)
+        .collect();
+
+    let mut unused: Vec<_> = registered
+        .into_iter()
+        .filter(|(type_id, _)| !used.contains(*type_id))
+        .map(|(_, type_name)| type_name)
+        .collect();
+
+    unused.sort();
+
+    unused
+}
+
+/// Lists declared dependencies of types in one module on types in another.
+///
+/// Modules are matched as prefixes of type names, like `my_app::web`.
+pub(super) fn module_dependencies(
+    declarations: &TypeIdMap<Declaration>,
+    from: &str,
+    to: &str,
+) -> Vec<(&'static str, &'static str)> {
+    let mut found: Vec<_> = declarations
+        .values()
+        .filter(|declaration| in_module(declaration.type_name, from))
+        .flat_map(|declaration| {
+            declaration
+                .dependencies
+                .iter()
+                .filter(|dependency| in_module(dependency.type_name, to))
+                .map(move |dependency| (declaration.type_name, dependency.type_name))
+        })
+        .collect();
+
+    // keep reports stable
+    found.sort();
+
+    found
+}
+
+/// Lists declared dependencies on deprecated types, sorted.
+pub(super) fn deprecated_uses(
+    declarations: &TypeIdMap<Declaration>,
+    deprecations: &TypeIdMap<&'static str>,
+) -> Vec<DeprecatedUse> {
+    let mut found: Vec<_> = declarations
+        .values()
+        .flat_map(|declaration| {
+            declaration
+                .dependencies
+                .iter()
+                .filter_map(move |dependency| {
+                    deprecations
+                        .get(&dependency.type_id)
+                        .map(|note| DeprecatedUse {
+                            dependent: declaration.type_name,
+                            dependency: dependency.type_name,
+                            note,
+                        })
+                })
+        })
+        .collect();
+
+    found.sort();
+
+    found
+}
+
+/// Runs every wiring check, see Container::composition_report().
+pub(super) fn composition_report<'a, F, I>(
+    declarations: &TypeIdMap<Declaration>,
+    is_registered: F,
+    registered: I,
+    deprecations: &TypeIdMap<&'static str>,
+    roots: &[Dependency],
+    forbidden: &[(&str, &str)],
+) -> CompositionReport
+where
+    F: Fn(TypeId) -> bool,
+    I: IntoIterator<Item = (&'a TypeId, &'static str)>,
+{
+    CompositionReport {
+        validation: validate(declarations, is_registered),
+        unused: unused(declarations, registered, roots),
+        forbidden: forbidden
+            .iter()
+            .flat_map(|(from, to)| module_dependencies(declarations, from, to))
+            .collect(),
+        deprecated: deprecated_uses(declarations, deprecations),
+    }
+}
+
+fn in_module(type_name: &str, module: &str) -> bool {
+    type_name.starts_with(module)
+        && (type_name.len() == module.len() || type_name[module.len()..].starts_with("::"))
+}
+
+/// Tells apart types the Injector can build on its own.
+pub(super) trait AutoResolvable {
+    fn auto_resolvable() -> bool;
+}
+
+// nothing is built on its own without specialization
+#[cfg(not(feature = "specialization"))]
+impl<T> AutoResolvable for T {
+    fn auto_resolvable() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declare<T: 'static, D: Dependencies>(declarations: &mut TypeIdMap<Declaration>) {
+        declarations.insert(
+            TypeId::of::<T>(),
+            Declaration {
+                type_name: std::any::type_name::<T>(),
+                dependencies: D::dependencies(),
+            },
+        );
+    }
+
+    #[test]
+    fn reports_missing_dependencies() {
+        let mut declarations = TypeIdMap::default();
+        declare::<i32, (i64, u8)>(&mut declarations);
+
+        let report = validate(&declarations, |type_id| type_id == TypeId::of::<u8>());
+
+        assert_eq!(vec![("i32", "i64")], report.missing);
+        assert!(report.cycles.is_empty());
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let mut declarations = TypeIdMap::default();
+        declare::<i32, (i64,)>(&mut declarations);
+        declare::<i64, (i16,)>(&mut declarations);
+        declare::<i16, (i32,)>(&mut declarations);
+        declare::<u8, (i32,)>(&mut declarations);
+
+        let report = validate(&declarations, |_| true);
+
+        assert_eq!(vec![vec!["i16", "i32", "i64", "i16"]], report.cycles);
+    }
+
+    #[test]
+    fn finds_transitive_paths() {
+        let mut declarations = TypeIdMap::default();
+        declare::<i32, (u8, i64)>(&mut declarations);
+        declare::<i64, (i16,)>(&mut declarations);
+
+        assert_eq!(
+            Some(vec!["i32", "i64", "i16"]),
+            find_path(&declarations, TypeId::of::<i32>(), TypeId::of::<i16>())
+        );
+        assert_eq!(
+            None,
+            find_path(&declarations, TypeId::of::<i16>(), TypeId::of::<i32>())
+        );
+    }
+
+    #[test]
+    fn dependencies_are_initialized_first() {
+        let mut declarations = TypeIdMap::default();
+        declare::<u8, (u32, u16)>(&mut declarations);
+        declare::<u16, (u32,)>(&mut declarations);
+        // cycles don't stop the ordering
+        declare::<i8, (i16,)>(&mut declarations);
+        declare::<i16, (i8,)>(&mut declarations);
+
+        let types = [
+            TypeId::of::<u8>(),
+            TypeId::of::<i8>(),
+            TypeId::of::<u16>(),
+            TypeId::of::<u32>(),
+            TypeId::of::<i16>(),
+        ];
+
+        assert_eq!(
+            vec![
+                TypeId::of::<u32>(),
+                TypeId::of::<u16>(),
+                TypeId::of::<u8>(),
+                TypeId::of::<i16>(),
+                TypeId::of::<i8>(),
+            ],
+            initialization_order(&declarations, &types)
+        );
+    }
+
+    #[test]
+    fn closures_include_transitive_dependencies() {
+        let mut declarations = TypeIdMap::default();
+        declare::<i64, (i32,)>(&mut declarations);
+        declare::<i32, (u8, i64)>(&mut declarations);
+        declare::<u16, (u32,)>(&mut declarations);
+
+        let closure = dependency_closure(&declarations, TypeId::of::<i64>());
+
+        let expected: TypeIdSet =
+            vec![TypeId::of::<i64>(), TypeId::of::<i32>(), TypeId::of::<u8>()]
+                .into_iter()
+                .collect();
+        assert_eq!(expected, closure);
+    }
+
+    #[test]
+    fn roots_and_dependencies_are_used() {
+        let mut declarations = TypeIdMap::default();
+        declare::<i64, (i32,)>(&mut declarations);
+
+        let registered = [
+            (TypeId::of::<i64>(), "i64"),
+            (TypeId::of::<i32>(), "i32"),
+            (TypeId::of::<u8>(), "u8"),
+        ];
+        let registered = registered.iter().map(|(type_id, name)| (type_id, *name));
+
+        let roots = <(i64,)>::dependencies();
+
+        assert_eq!(vec!["u8"], unused(&declarations, registered, &roots));
+    }
+
+    #[test]
+    fn reports_dependencies_on_deprecated_types() {
+        let mut declarations = TypeIdMap::default();
+        declare::<i64, (i32, u8)>(&mut declarations);
+
+        let mut deprecations = TypeIdMap::default();
+        deprecations.insert(TypeId::of::<i32>(), "use u8");
+
+        let found = deprecated_uses(&declarations, &deprecations);
+
+        assert_eq!(
+            vec![DeprecatedUse {
+                dependent: "i64",
+                dependency: "i32",
+                note: "use u8"
+            }],
+            found
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn reports_can_be_serialized() {
+        let mut declarations = TypeIdMap::default();
+        declare::<i64, (i32,)>(&mut declarations);
+
+        let report = composition_report(
+            &declarations,
+            |_| false,
+            Vec::new(),
+            &TypeIdMap::default(),
+            &[],
+            &[],
+        );
+
+        let serialized = toml::to_string(&report).unwrap();
+        assert!(serialized.contains(r#"missing = [["i64", "i32"]]"#));
+    }
+
+    #[test]
+    fn matches_whole_module_names() {
+        assert!(in_module("app::web::Handler", "app::web"));
+        assert!(!in_module("app::webhooks::Handler", "app::web"));
+    }
+
+    #[test]
+    #[cfg(feature = "specialization")]
+    fn auto_resolvable_types_are_not_missing() {
+        use crate::{Container, Inject, Result};
+
+        #[derive(Clone)]
+        struct X;
+        impl Inject for X {
+            fn resolve(_: &Container) -> Result<X> {
+                Ok(X)
+            }
+        }
+
+        assert!(Dependency::of::<X>().is_auto_resolvable());
+        assert!(!Dependency::of::<i32>().is_auto_resolvable());
+    }
+}
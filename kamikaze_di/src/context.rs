@@ -0,0 +1,112 @@
+//! Per-request cultural context.
+//!
+//! Seed a scope with a [RequestContext](struct.RequestContext.html) and every
+//! service resolved in that scope can depend on it, instead of passing time
+//! zones and locales around by hand.
+
+use crate::prelude::*;
+use crate::{Container, Inject, Injector, Result};
+
+/// Time zone, locale and currency of the current request.
+///
+/// The context is resolvable inside scopes seeded with it, see
+/// [enter_scope_with()](../struct.Container.html#method.enter_scope_with).
+/// Resolving it anywhere else is an error.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{Container, ContainerBuilder, Inject, Injector, RequestContext, Result};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Clone)]
+/// struct PriceFormatter {
+///     context: RequestContext,
+/// }
+///
+/// impl Inject for PriceFormatter {
+///     fn resolve(container: &Container) -> Result<Self> {
+///         Ok(PriceFormatter { context: container.inject()? })
+///     }
+/// }
+///
+/// impl PriceFormatter {
+///     fn format(&self, amount: u32) -> String {
+///         format!("{} {}", amount, self.context.currency)
+///     }
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_automatic_factory::<PriceFormatter>()?;
+///
+/// let container = builder.build();
+///
+/// let scope = container.enter_scope_with(RequestContext::new("UTC", "en-GB", "GBP"));
+/// let formatter: PriceFormatter = scope.inject()?;
+///
+/// assert_eq!("42 GBP", formatter.format(42));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RequestContext {
+    /// IANA time zone name, like "Europe/Bucharest".
+    pub time_zone: String,
+    /// BCP 47 language tag, like "ro-RO".
+    pub locale: String,
+    /// ISO 4217 currency code, like "RON".
+    pub currency: String,
+}
+
+impl RequestContext {
+    /// Constructor.
+    pub fn new(time_zone: &str, locale: &str, currency: &str) -> RequestContext {
+        RequestContext {
+            time_zone: time_zone.to_string(),
+            locale: locale.to_string(),
+            currency: currency.to_string(),
+        }
+    }
+
+    /// Resolves the context of the current scope.
+    pub fn current(container: &Container) -> Result<RequestContext> {
+        container.inject()
+    }
+}
+
+impl Default for RequestContext {
+    fn default() -> RequestContext {
+        RequestContext::new("UTC", "en-US", "USD")
+    }
+}
+
+impl Inject for RequestContext {
+    // Only reached when no scope was seeded with a context.
+    fn resolve(_: &Container) -> Result<RequestContext> {
+        Err("No RequestContext in scope, seed one with enter_scope_with()".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequestContext;
+    use crate::ContainerBuilder;
+
+    #[test]
+    fn context_is_only_available_in_seeded_scopes() {
+        let container = ContainerBuilder::new().build();
+        assert!(RequestContext::current(&container).is_err());
+
+        {
+            let _scope = container.enter_scope_with(RequestContext::default());
+            assert_eq!(
+                "UTC",
+                RequestContext::current(&container).unwrap().time_zone
+            );
+        }
+
+        assert!(RequestContext::current(&container).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Database connection pools through r2d2, needs the `r2d2` feature.
+//!
+//! [register_r2d2_pool()](../struct.ContainerBuilder.html#method.register_r2d2_pool)
+//! registers the pool, shared by everything in the container, and a
+//! [ScopedConnection](struct.ScopedConnection.html) for each scope, so
+//! everything handling the same request uses the same connection. Diesel's
+//! pools are r2d2 pools, so they're registered the same way, with diesel's
+//! `ConnectionManager`.
+
+use std::cell::{RefCell, RefMut};
+use std::fmt;
+use std::rc::Rc;
+
+use r2d2::{Builder, ManageConnection, Pool, PooledConnection};
+
+use crate::{ContainerBuilder, Result};
+
+/// The connection of a scope, checked out of the pool the first time it's used.
+///
+/// Resolve it as `Rc<ScopedConnection<M>>` within a scope, like the one
+/// [per_request()](../actix/fn.per_request.html) enters. The connection
+/// goes back to the pool when the scope ends.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::db_pool::ScopedConnection;
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use std::rc::Rc;
+/// #
+/// # #[derive(Debug)]
+/// # struct Postgres;
+/// # impl r2d2::ManageConnection for Postgres {
+/// #     type Connection = Vec<String>;
+/// #     type Error = std::io::Error;
+/// #     fn connect(&self) -> Result<Vec<String>, Self::Error> { Ok(Vec::new()) }
+/// #     fn is_valid(&self, _: &mut Vec<String>) -> Result<(), Self::Error> { Ok(()) }
+/// #     fn has_broken(&self, _: &mut Vec<String>) -> bool { false }
+/// # }
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// builder.register_r2d2_pool(Postgres, r2d2::Pool::builder().max_size(4))?;
+///
+/// let container = builder.build();
+/// let scope = container.enter_scope();
+///
+/// let connection = scope.resolve::<Rc<ScopedConnection<Postgres>>>()?;
+/// connection.get()?.push("BEGIN".to_string());
+///
+/// // the same connection, for the rest of the scope
+/// let same = scope.resolve::<Rc<ScopedConnection<Postgres>>>()?;
+/// assert_eq!(vec!["BEGIN"], *same.get()?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScopedConnection<M: ManageConnection> {
+    pool: Pool<M>,
+    connection: RefCell<Option<PooledConnection<M>>>,
+}
+
+impl<M: ManageConnection> ScopedConnection<M> {
+    fn new(pool: Pool<M>) -> ScopedConnection<M> {
+        ScopedConnection {
+            pool,
+            connection: RefCell::new(None),
+        }
+    }
+
+    /// Borrows the connection, checking it out of the pool if needed.
+    ///
+    /// Fails if the pool has no connection to give before its timeout, or
+    /// if the connection is already borrowed.
+    pub fn get(&self) -> Result<RefMut<'_, M::Connection>> {
+        let mut connection = self.connection.try_borrow_mut().map_err(|_| {
+            format!(
+                "The connection of {} is already in use",
+                std::any::type_name::<M>()
+            )
+        })?;
+
+        if connection.is_none() {
+            let checked_out = self.pool.get().map_err(|error| {
+                format!(
+                    "Could not get a connection from the {} pool: {}",
+                    std::any::type_name::<M>(),
+                    error
+                )
+            })?;
+
+            *connection = Some(checked_out);
+        }
+
+        Ok(RefMut::map(connection, |connection| {
+            &mut **connection
+                .as_mut()
+                .expect("the connection was just checked out")
+        }))
+    }
+
+    /// Whether the connection was checked out of the pool yet.
+    pub fn is_checked_out(&self) -> bool {
+        self.connection
+            .try_borrow()
+            .map_or(true, |connection| connection.is_some())
+    }
+}
+
+impl<M: ManageConnection> fmt::Debug for ScopedConnection<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScopedConnection")
+            .field("manager", &std::any::type_name::<M>())
+            .field("checked_out", &self.is_checked_out())
+            .finish()
+    }
+}
+
+impl ContainerBuilder {
+    /// Registers an r2d2 pool as `Rc<Pool<M>>`, and a connection per scope.
+    ///
+    /// The pool is set up via `options`, and connects as connections are
+    /// needed, not right away. Connections are resolved as
+    /// `Rc<ScopedConnection<M>>`, see
+    /// [ScopedConnection](db_pool/struct.ScopedConnection.html).
+    ///
+    /// # Panics
+    ///
+    /// Like r2d2, panics if the options ask for more idle connections than
+    /// the pool's size.
+    #[track_caller]
+    pub fn register_r2d2_pool<M: ManageConnection>(
+        &mut self,
+        manager: M,
+        options: Builder<M>,
+    ) -> Result<()> {
+        debug!("registering r2d2 pool");
+
+        let pool = options.build_unchecked(manager);
+        let scoped = pool.clone();
+
+        self.register::<Rc<Pool<M>>>(Rc::new(pool))?;
+        self.register_scoped::<Rc<ScopedConnection<M>>, _>(move |_| {
+            Rc::new(ScopedConnection::new(scoped.clone()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[derive(Debug, Default)]
+    struct Counted {
+        connects: Arc<AtomicU32>,
+    }
+
+    impl ManageConnection for Counted {
+        type Connection = u32;
+        type Error = std::io::Error;
+
+        fn connect(&self) -> std::result::Result<u32, Self::Error> {
+            Ok(self.connects.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+
+        fn is_valid(&self, _: &mut u32) -> std::result::Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn has_broken(&self, _: &mut u32) -> bool {
+            false
+        }
+    }
+
+    fn options() -> Builder<Counted> {
+        Pool::builder()
+            .max_size(1)
+            .min_idle(Some(0))
+            .connection_timeout(Duration::from_millis(50))
+    }
+
+    #[test]
+    fn scopes_share_a_connection_until_they_end() {
+        let manager = Counted::default();
+        let connects = manager.connects.clone();
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_r2d2_pool(manager, options()).unwrap();
+
+        let container = builder.build();
+        let pool = container.resolve::<Rc<Pool<Counted>>>().unwrap();
+
+        {
+            let scope = container.enter_scope();
+            let connection = scope.resolve::<Rc<ScopedConnection<Counted>>>().unwrap();
+            assert!(!connection.is_checked_out());
+
+            *connection.get().unwrap() += 10;
+
+            let same = scope.resolve::<Rc<ScopedConnection<Counted>>>().unwrap();
+            assert_eq!(11, *same.get().unwrap());
+            assert_eq!(0, pool.state().idle_connections);
+        }
+
+        // given back, and reused by the next scope
+        assert_eq!(1, pool.state().idle_connections);
+
+        let scope = container.enter_scope();
+        let connection = scope.resolve::<Rc<ScopedConnection<Counted>>>().unwrap();
+        assert_eq!(11, *connection.get().unwrap());
+        assert_eq!(1, connects.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn exhausted_pools_are_errors() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_r2d2_pool(Counted::default(), options())
+            .unwrap();
+
+        let container = builder.build();
+
+        let first = container.enter_scope();
+        let connection = first.resolve::<Rc<ScopedConnection<Counted>>>().unwrap();
+        let _in_use = connection.get().unwrap();
+
+        assert!(connection
+            .get()
+            .err()
+            .unwrap()
+            .to_string()
+            .ends_with("is already in use"));
+
+        let nested = container.enter_scope();
+        let other = nested.resolve::<Rc<ScopedConnection<Counted>>>().unwrap();
+        assert!(other
+            .get()
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Could not get a connection from the"));
+    }
+}
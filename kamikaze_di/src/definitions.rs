@@ -0,0 +1,351 @@
+//! Wiring from a definition file, needs the `config` feature.
+//!
+//! Services are described in TOML, each with the name of the factory that
+//! creates it and the arguments the factory gets. Factories are written in
+//! code and added to a [FactoryRegistry](struct.FactoryRegistry.html) by
+//! name, which decides the type they register, so the file only picks
+//! between them and their arguments. Changing the wiring then only means
+//! changing the file.
+//!
+//! ```toml
+//! [services.mailer]
+//! factory = "smtp_mailer"
+//! lifetime = "shared" # the default, or "factory" for a new one every time
+//!
+//! [services.mailer.arguments]
+//! host = "smtp.example.com"
+//! port = 25
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::{Container, ContainerBuilder, Result};
+
+/// Factories services in a definition file are created with, by name.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::definitions::FactoryRegistry;
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// trait Mailer { fn host(&self) -> String; }
+///
+/// struct Smtp { host: String, port: u16 }
+/// impl Mailer for Smtp { fn host(&self) -> String { format!("{}:{}", self.host, self.port) } }
+///
+/// struct Outbox;
+/// impl Mailer for Outbox { fn host(&self) -> String { "outbox".to_string() } }
+///
+/// let mut registry = FactoryRegistry::new();
+/// registry.add::<Rc<dyn Mailer>, _>("smtp_mailer", |arguments, _| {
+///     Ok(Rc::new(Smtp { host: arguments.get("host")?, port: arguments.get("port")? }))
+/// })?;
+/// registry.add::<Rc<dyn Mailer>, _>("outbox_mailer", |_, _| Ok(Rc::new(Outbox)))?;
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.load_definitions(r#"
+///     [services.mailer]
+///     factory = "smtp_mailer"
+///     arguments = { host = "smtp.example.com", port = 25 }
+/// "#, &registry)?;
+///
+/// let container = builder.build();
+///
+/// assert_eq!("smtp.example.com:25", container.resolve::<Rc<dyn Mailer>>()?.host());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct FactoryRegistry {
+    factories: BTreeMap<String, Box<FactoryRegistration>>,
+}
+
+type FactoryRegistration = dyn Fn(&mut ContainerBuilder, Arguments, Lifetime) -> Result<()>;
+
+impl FactoryRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> FactoryRegistry {
+        Default::default()
+    }
+
+    /// Adds a factory of T under a name.
+    ///
+    /// The factory gets the arguments of the service it creates, and the
+    /// container, for its dependencies. Returns an error if the name is taken.
+    pub fn add<T, F>(&mut self, name: &str, factory: F) -> Result<()>
+    where
+        F: Fn(&Arguments, &Container) -> Result<T> + 'static,
+        T: 'static,
+    {
+        if self.factories.contains_key(name) {
+            return Err(format!("Registry already has a factory named \"{}\"", name).into());
+        }
+
+        let factory = Rc::new(factory);
+        let registration = move |builder: &mut ContainerBuilder, arguments, lifetime| {
+            let factory = factory.clone();
+
+            match lifetime {
+                Lifetime::Shared => builder
+                    .register_try_builder::<T, _>(move |container| factory(&arguments, container)),
+                Lifetime::Factory => builder
+                    .register_try_factory::<T, _>(move |container| factory(&arguments, container)),
+            }
+        };
+
+        self.factories
+            .insert(name.to_string(), Box::new(registration));
+
+        Ok(())
+    }
+
+    /// Returns true if there is a factory with the name.
+    pub fn has(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    fn register(
+        &self,
+        builder: &mut ContainerBuilder,
+        name: String,
+        definition: Definition,
+    ) -> Result<()> {
+        let registration = self.factories.get(&definition.factory).ok_or_else(|| {
+            format!(
+                "Service {} uses an unknown factory \"{}\", known factories: {}",
+                name,
+                definition.factory,
+                self.factories
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+
+        let arguments = Arguments {
+            service: name.clone(),
+            values: definition.arguments,
+        };
+
+        registration(builder, arguments, definition.lifetime)
+            .map_err(|error| format!("Could not register service {}: {}", name, error).into())
+    }
+}
+
+impl fmt::Debug for FactoryRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FactoryRegistry")
+            .field("factories", &self.factories.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Arguments of a service, from its definition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arguments {
+    service: String,
+    values: Table,
+}
+
+impl Arguments {
+    /// Name of the service the arguments are for.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Deserializes an argument.
+    ///
+    /// Returns an error if the service has no such argument, or if it isn't a T.
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        let value =
+            self.values.get(name).cloned().ok_or_else(|| {
+                format!("Service {} needs the argument \"{}\"", self.service, name)
+            })?;
+
+        Value::try_into(value).map_err(|error| {
+            format!(
+                "Invalid argument \"{}\" of service {}: {}",
+                name, self.service, error
+            )
+            .into()
+        })
+    }
+
+    /// Returns true if the service has the argument.
+    pub fn has(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Definitions {
+    #[serde(default)]
+    services: BTreeMap<String, Definition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Definition {
+    factory: String,
+    #[serde(default)]
+    lifetime: Lifetime,
+    #[serde(default)]
+    arguments: Table,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Lifetime {
+    #[default]
+    Shared,
+    Factory,
+}
+
+impl ContainerBuilder {
+    /// Creates a builder with the services defined in a TOML file.
+    ///
+    /// See [load_definitions()](struct.ContainerBuilder.html#method.load_definitions).
+    pub fn from_file<P: AsRef<Path>>(
+        path: P,
+        registry: &FactoryRegistry,
+    ) -> Result<ContainerBuilder> {
+        let path = path.as_ref();
+        let definitions = std::fs::read_to_string(path)
+            .map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+
+        let mut builder = ContainerBuilder::new();
+        builder.load_definitions(&definitions, registry)?;
+
+        Ok(builder)
+    }
+
+    /// Registers the services defined in a TOML document.
+    ///
+    /// Every service is created by a factory from the registry, see
+    /// [FactoryRegistry](definitions/struct.FactoryRegistry.html). Nothing is
+    /// registered if the document is invalid, but services registered before
+    /// a failing one are kept.
+    pub fn load_definitions(
+        &mut self,
+        definitions: &str,
+        registry: &FactoryRegistry,
+    ) -> Result<()> {
+        let definitions: Definitions = toml::from_str(definitions)
+            .map_err(|error| format!("Invalid service definitions: {}", error))?;
+
+        for (name, definition) in definitions.services {
+            registry.register(self, name, definition)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+
+    fn registry() -> FactoryRegistry {
+        let mut registry = FactoryRegistry::new();
+        registry
+            .add::<u16, _>("port", |arguments, _| arguments.get("port"))
+            .unwrap();
+        registry
+            .add::<String, _>("address", |arguments, container| {
+                let host: String = arguments.get("host")?;
+                Ok(format!("{}:{}", host, container.resolve::<u16>()?))
+            })
+            .unwrap();
+
+        registry
+    }
+
+    #[test]
+    fn services_are_created_by_their_factories() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .load_definitions(
+                r#"
+                [services.port]
+                factory = "port"
+                arguments = { port = 8080 }
+
+                [services.address]
+                factory = "address"
+                lifetime = "factory"
+                arguments = { host = "localhost" }
+                "#,
+                &registry(),
+            )
+            .unwrap();
+
+        let container = builder.build();
+
+        assert_eq!("localhost:8080", container.resolve::<String>().unwrap());
+    }
+
+    #[test]
+    fn invalid_definitions_are_reported() {
+        let registry = registry();
+        let error = |definitions: &str| {
+            ContainerBuilder::new()
+                .load_definitions(definitions, &registry)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            "Service mailer uses an unknown factory \"smtp\", known factories: address, port",
+            error("[services.mailer]\nfactory = \"smtp\"")
+        );
+        assert!(
+            error("[services.port]\nfactory = \"port\"\nlifetime = \"scoped\"")
+                .starts_with("Invalid service definitions")
+        );
+        assert!(error("[services.port]\nfactory = \"port\"\nshared = true")
+            .starts_with("Invalid service definitions"));
+        assert!(
+            error("[services.port]\nfactory = \"port\"\n[services.other]\nfactory = \"port\"")
+                .starts_with("Could not register service port")
+        );
+    }
+
+    #[test]
+    fn arguments_are_checked_when_resolving() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .load_definitions(
+                "[services.port]\nfactory = \"port\"\narguments = { port = \"http\" }",
+                &registry(),
+            )
+            .unwrap();
+
+        let error = builder.build().resolve::<u16>().err().unwrap().to_string();
+
+        assert!(error.contains("Invalid argument \"port\" of service port"));
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        let error = ContainerBuilder::from_file("no/such/services.toml", &registry()).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Could not read no/such/services.toml"));
+    }
+}
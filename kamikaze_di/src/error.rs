@@ -1,47 +1,215 @@
+use core::fmt;
+
+use crate::container::validation::{CompositionReport, ValidationReport};
+use crate::container::ResolverType;
+use crate::prelude::*;
+
 /// Error type.
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
 pub struct Error {
-    message: String,
+    // boxed, errors are returned everywhere and should stay small
+    kind: Box<ErrorKind>,
+    path: Vec<&'static str>,
+}
+
+/// The different kinds of errors the container can produce.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ErrorKind {
+    /// Generic error, described by its message.
+    Message(String),
+    /// The container's internal state is not what it should be.
+    InvariantViolation(InvariantViolation),
+    /// Container validation found problems with the wiring.
+    Validation(ValidationReport),
+    /// A composition root's checks found problems with the wiring.
+    Composition(CompositionReport),
+    /// A type was needed to resolve itself, directly or through others.
+    CycleDetected(CycleDetected),
+}
+
+/// A resolver was missing or of the wrong kind when resolving a type.
+///
+/// This points to a bug in the container, not to a wiring mistake.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct InvariantViolation {
+    /// Name of the type that was being resolved.
+    pub type_name: &'static str,
+    /// The resolver kind the container expected to find.
+    pub expected: ResolverType,
+    /// The resolver kind that was actually registered, if any.
+    pub found: Option<ResolverType>,
+}
+
+/// Resolving a type needed the type itself.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CycleDetected {
+    /// Types being resolved, from the first to need the type to the type
+    /// itself, resolved again.
+    pub path: Vec<&'static str>,
+}
+
+impl Error {
+    /// Returns what kind of error this is.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Returns the types that were being resolved when the error happened,
+    /// outermost first, ending with the type that failed.
+    ///
+    /// Empty if the error didn't happen while resolving.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// #
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_try_factory::<i64, _>(|container| {
+    ///     Ok(container.resolve::<i32>()?.into())
+    /// }).unwrap();
+    ///
+    /// let error = builder.build().resolve::<i64>().unwrap_err();
+    ///
+    /// assert_eq!(&["i64", "i32"], error.path());
+    /// assert_eq!(
+    ///     "while resolving i64 -> i32: Type not registered: i32",
+    ///     error.to_string()
+    /// );
+    /// ```
+    pub fn path(&self) -> &[&'static str] {
+        &self.path
+    }
+
+    /// Adds the resolution path, unless the error already has one.
+    pub(crate) fn resolving(mut self, path: impl FnOnce() -> Vec<&'static str>) -> Error {
+        // cycles name their path already
+        if self.path.is_empty() && !matches!(*self.kind, ErrorKind::CycleDetected(_)) {
+            self.path = path();
+        }
+
+        self
+    }
+}
+
+impl InvariantViolation {
+    pub(crate) fn new<T>(
+        expected: ResolverType,
+        found: Option<ResolverType>,
+    ) -> InvariantViolation {
+        InvariantViolation {
+            type_name: core::any::type_name::<T>(),
+            expected,
+            found,
+        }
+    }
+}
+
+impl Default for ErrorKind {
+    fn default() -> ErrorKind {
+        ErrorKind::Message(String::new())
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error {
+            kind: Box::new(kind),
+            path: Vec::new(),
+        }
+    }
+}
+
+impl From<InvariantViolation> for Error {
+    fn from(violation: InvariantViolation) -> Error {
+        ErrorKind::InvariantViolation(violation).into()
+    }
+}
+
+impl From<CycleDetected> for Error {
+    fn from(cycle: CycleDetected) -> Error {
+        ErrorKind::CycleDetected(cycle).into()
+    }
 }
 
 impl From<String> for Error {
     fn from(message: String) -> Error {
-        Error { message }
+        ErrorKind::Message(message).into()
     }
 }
 
 impl From<&str> for Error {
     fn from(message: &str) -> Error {
-        let message = message.to_string();
-        Error { message }
+        message.to_string().into()
     }
 }
 
 impl From<Error> for String {
     fn from(error: Error) -> String {
-        error.message
+        match *error.kind {
+            ErrorKind::Message(message) if error.path().len() < 2 => message,
+            _ => error.to_string(),
+        }
     }
 }
 
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        &self.message
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        None
     }
+}
 
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the type that failed names itself
+        if self.path().len() > 1 {
+            write!(f, "while resolving {}: ", self.path().join(" -> "))?;
+        }
+
+        self.kind.fmt(f)
     }
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        self.message.fmt(f)
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Message(message) => message.fmt(f),
+            ErrorKind::InvariantViolation(violation) => violation.fmt(f),
+            ErrorKind::Validation(report) => report.fmt(f),
+            ErrorKind::Composition(report) => report.fmt(f),
+            ErrorKind::CycleDetected(cycle) => cycle.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invariant violated for {}: expected {:?} resolver, found ",
+            self.type_name, self.expected
+        )?;
+
+        match self.found {
+            Some(found) => write!(f, "{:?} resolver", found),
+            None => write!(f, "nothing"),
+        }
+    }
+}
+
+impl fmt::Display for CycleDetected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Circular dependency detected: {}",
+            self.path.join(" -> ")
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Error;
+    use super::*;
 
     #[test]
     fn test_send() {
@@ -54,4 +222,27 @@ mod tests {
         fn assert_sync<T: Sync>() {}
         assert_sync::<Error>();
     }
+
+    #[test]
+    fn invariant_violations_name_the_type() {
+        let error: Error =
+            InvariantViolation::new::<i32>(ResolverType::Factory, Some(ResolverType::Shared))
+                .into();
+
+        assert_eq!(
+            "Invariant violated for i32: expected Factory resolver, found Shared resolver",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn the_innermost_path_is_kept() {
+        let error = Error::from("u32 broke")
+            .resolving(|| vec!["i32", "u32"])
+            .resolving(|| vec!["i32"]);
+
+        assert_eq!(&["i32", "u32"], error.path());
+        assert_eq!("while resolving i32 -> u32: u32 broke", error.to_string());
+        assert_eq!("while resolving i32 -> u32: u32 broke", String::from(error));
+    }
 }
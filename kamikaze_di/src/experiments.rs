@@ -0,0 +1,175 @@
+//! Injectable A/B experiment assignment.
+//!
+//! Services depend on `Rc<dyn Experiments>` and ask it which variant a unit,
+//! like a user or a session, is assigned to. The implementation is chosen
+//! when wiring the container via [ExperimentsModule](struct.ExperimentsModule.html),
+//! and is resolved once per scope.
+
+use alloc::rc::Rc;
+use core::fmt;
+
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::{Container, ContainerBuilder, Module, Result};
+
+/// Assigns units to experiment variants.
+pub trait Experiments {
+    /// Returns the variant of an experiment the unit is assigned to.
+    ///
+    /// Returns None if the unit is not part of the experiment.
+    fn variant(&self, experiment: &str, unit_id: &str) -> Option<String>;
+}
+
+/// Experiments with fixed assignments, useful for tests.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::experiments::{Experiments, FixedExperiments};
+/// #
+/// let mut experiments = FixedExperiments::new();
+/// experiments.assign_all("checkout", "control");
+/// experiments.assign("checkout", "user-42", "one-click");
+///
+/// assert_eq!(Some("one-click".to_string()), experiments.variant("checkout", "user-42"));
+/// assert_eq!(Some("control".to_string()), experiments.variant("checkout", "user-7"));
+/// assert_eq!(None, experiments.variant("search", "user-42"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FixedExperiments {
+    assignments: HashMap<(String, String), String>,
+    defaults: HashMap<String, String>,
+}
+
+impl FixedExperiments {
+    /// Constructor.
+    pub fn new() -> FixedExperiments {
+        Default::default()
+    }
+
+    /// Assigns a single unit to a variant.
+    pub fn assign(&mut self, experiment: &str, unit_id: &str, variant: &str) {
+        self.assignments.insert(
+            (experiment.to_string(), unit_id.to_string()),
+            variant.to_string(),
+        );
+    }
+
+    /// Assigns every unit without an assignment of its own to a variant.
+    pub fn assign_all(&mut self, experiment: &str, variant: &str) {
+        self.defaults
+            .insert(experiment.to_string(), variant.to_string());
+    }
+}
+
+impl Experiments for FixedExperiments {
+    fn variant(&self, experiment: &str, unit_id: &str) -> Option<String> {
+        self.assignments
+            .get(&(experiment.to_string(), unit_id.to_string()))
+            .or_else(|| self.defaults.get(experiment))
+            .cloned()
+    }
+}
+
+/// Registers a scoped `Rc<dyn Experiments>`.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use kamikaze_di::experiments::{Experiments, ExperimentsModule, FixedExperiments};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut experiments = FixedExperiments::new();
+/// experiments.assign_all("checkout", "one-click");
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.add_module(ExperimentsModule::fixed(experiments))?;
+///
+/// let container = builder.build();
+/// let scope = container.enter_scope();
+///
+/// let experiments = scope.resolve::<Rc<dyn Experiments>>()?;
+/// assert_eq!(Some("one-click".to_string()), experiments.variant("checkout", "user-42"));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct ExperimentsModule {
+    factory: Rc<ExperimentsFactory>,
+}
+
+type ExperimentsFactory = dyn Fn(&Container) -> Rc<dyn Experiments>;
+
+impl ExperimentsModule {
+    /// Uses a factory, called once per scope.
+    ///
+    /// The factory can use whatever the scope was seeded with, like
+    /// the current request's details.
+    pub fn new<F>(factory: F) -> ExperimentsModule
+    where
+        F: Fn(&Container) -> Rc<dyn Experiments> + 'static,
+    {
+        ExperimentsModule {
+            factory: Rc::new(factory),
+        }
+    }
+
+    /// Uses the same fixed assignments in every scope.
+    pub fn fixed(experiments: FixedExperiments) -> ExperimentsModule {
+        let experiments: Rc<dyn Experiments> = Rc::new(experiments);
+
+        ExperimentsModule::new(move |_| experiments.clone())
+    }
+}
+
+impl Module for ExperimentsModule {
+    fn register(&self, builder: &mut ContainerBuilder) -> Result<()> {
+        let factory = self.factory.clone();
+
+        builder.register_scoped::<Rc<dyn Experiments>, _>(move |container| factory(container))
+    }
+}
+
+impl fmt::Debug for ExperimentsModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExperimentsModule").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+
+    #[test]
+    fn experiments_are_resolved_per_scope() {
+        #[derive(Clone)]
+        struct Tenant(&'static str);
+
+        let mut builder = ContainerBuilder::new();
+        builder
+            .add_module(ExperimentsModule::new(|container| {
+                let mut experiments = FixedExperiments::new();
+                let tenant = container.resolve::<Tenant>().unwrap();
+                experiments.assign_all("checkout", tenant.0);
+
+                Rc::new(experiments)
+            }))
+            .unwrap();
+
+        let container = builder.build();
+
+        for tenant in &["a", "b"] {
+            let scope = container.enter_scope_with(Tenant(tenant));
+            let experiments = scope.resolve::<Rc<dyn Experiments>>().unwrap();
+
+            assert_eq!(
+                Some(tenant.to_string()),
+                experiments.variant("checkout", "user")
+            );
+        }
+    }
+}
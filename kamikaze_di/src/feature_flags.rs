@@ -0,0 +1,290 @@
+//! Services switched on and off by feature flags.
+//!
+//! The flags come from an `Rc<dyn FeatureFlags>` registered via
+//! [register_feature_flags()](../struct.ContainerBuilder.html#method.register_feature_flags),
+//! and are checked every time a flagged service is resolved, so flipping
+//! a flag takes effect right away. Flagged services are transient for the
+//! same reason.
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::{Container, ContainerBuilder, Resolver, Result};
+
+/// Tells whether feature flags are on.
+pub trait FeatureFlags {
+    /// Returns true if the flag is on.
+    fn is_enabled(&self, flag: &str) -> bool;
+}
+
+/// Flags set in code, useful for tests and simple setups.
+///
+/// Flags can be flipped while the container is in use, flags that were
+/// never set are off.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::feature_flags::{FeatureFlags, FixedFlags};
+/// #
+/// let flags = FixedFlags::new();
+/// flags.enable("new_billing");
+///
+/// assert!(flags.is_enabled("new_billing"));
+/// assert!(!flags.is_enabled("dark_mode"));
+///
+/// flags.disable("new_billing");
+/// assert!(!flags.is_enabled("new_billing"));
+/// ```
+#[derive(Debug, Default)]
+pub struct FixedFlags {
+    flags: RefCell<HashMap<String, bool>>,
+}
+
+impl FixedFlags {
+    /// Constructor.
+    pub fn new() -> FixedFlags {
+        Default::default()
+    }
+
+    /// Turns a flag on.
+    pub fn enable(&self, flag: &str) {
+        self.set(flag, true);
+    }
+
+    /// Turns a flag off.
+    pub fn disable(&self, flag: &str) {
+        self.set(flag, false);
+    }
+
+    /// Sets a flag.
+    pub fn set(&self, flag: &str, enabled: bool) {
+        self.flags.borrow_mut().insert(flag.to_string(), enabled);
+    }
+}
+
+impl FeatureFlags for FixedFlags {
+    fn is_enabled(&self, flag: &str) -> bool {
+        self.flags.borrow().get(flag).copied().unwrap_or(false)
+    }
+}
+
+impl ContainerBuilder {
+    /// Registers where feature flags come from, as `Rc<dyn FeatureFlags>`.
+    ///
+    /// See [register_when_flag()](struct.ContainerBuilder.html#method.register_when_flag)
+    /// and [register_by_flag()](struct.ContainerBuilder.html#method.register_by_flag).
+    #[track_caller]
+    pub fn register_feature_flags(&mut self, flags: Rc<dyn FeatureFlags>) -> Result<()> {
+        debug!("registering feature flags");
+
+        self.register::<Rc<dyn FeatureFlags>>(flags)
+    }
+
+    /// Registers a factory that only works while a flag is on.
+    ///
+    /// Resolving T while the flag is off is an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::feature_flags::FixedFlags;
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// let flags = Rc::new(FixedFlags::new());
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_feature_flags(flags.clone())?;
+    /// builder.register_when_flag::<String, _>("greetings", |_| "hello".to_string())?;
+    ///
+    /// let container = builder.build();
+    /// assert!(container.resolve::<String>().is_err());
+    ///
+    /// flags.enable("greetings");
+    /// assert_eq!("hello", container.resolve::<String>()?);
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_when_flag<T, F>(&mut self, flag: &str, mut factory: F) -> Result<()>
+    where
+        F: (FnMut(&Container) -> T) + 'static,
+        T: 'static,
+    {
+        debug!("registering factory behind a feature flag");
+
+        let flag = flag.to_string();
+
+        self.register_try_factory::<T, _>(move |container| {
+            if !is_enabled(container, &flag)? {
+                return Err(format!("feature flag \"{}\" is off", flag).into());
+            }
+
+            Ok(factory(container))
+        })
+    }
+
+    /// Registers `Rc<U>`, resolved as one of two registered types depending on a flag.
+    ///
+    /// While the flag is on, `On` is resolved and cast to `Rc<U>`, `Off`
+    /// otherwise. Only the type picked gets resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kamikaze_di::feature_flags::FixedFlags;
+    /// # use kamikaze_di::{ContainerBuilder, Resolver};
+    /// # use std::rc::Rc;
+    /// #
+    /// # fn main() -> std::result::Result<(), String> {
+    /// #
+    /// trait Billing { fn invoice(&self) -> &str; }
+    ///
+    /// struct OldBilling;
+    /// impl Billing for OldBilling { fn invoice(&self) -> &str { "paper" } }
+    ///
+    /// struct NewBilling;
+    /// impl Billing for NewBilling { fn invoice(&self) -> &str { "email" } }
+    ///
+    /// let flags = Rc::new(FixedFlags::new());
+    ///
+    /// let mut builder = ContainerBuilder::new();
+    /// builder.register_feature_flags(flags.clone())?;
+    /// builder.register::<Rc<NewBilling>>(Rc::new(NewBilling))?;
+    /// builder.register::<Rc<OldBilling>>(Rc::new(OldBilling))?;
+    /// builder.register_by_flag::<dyn Billing, Rc<NewBilling>, Rc<OldBilling>, _, _>(
+    ///     "new_billing",
+    ///     |new| new,
+    ///     |old| old,
+    /// )?;
+    ///
+    /// let container = builder.build();
+    /// assert_eq!("paper", container.resolve::<Rc<dyn Billing>>()?.invoice());
+    ///
+    /// flags.enable("new_billing");
+    /// assert_eq!("email", container.resolve::<Rc<dyn Billing>>()?.invoice());
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn register_by_flag<U, On, Off, FOn, FOff>(
+        &mut self,
+        flag: &str,
+        when_on: FOn,
+        when_off: FOff,
+    ) -> Result<()>
+    where
+        U: ?Sized + 'static,
+        On: Clone + 'static,
+        Off: Clone + 'static,
+        FOn: Fn(On) -> Rc<U> + 'static,
+        FOff: Fn(Off) -> Rc<U> + 'static,
+    {
+        debug!("registering implementations switched by a feature flag");
+
+        let flag = flag.to_string();
+
+        self.register_try_factory::<Rc<U>, _>(move |container| {
+            if is_enabled(container, &flag)? {
+                Ok(when_on(container.resolve::<On>()?))
+            } else {
+                Ok(when_off(container.resolve::<Off>()?))
+            }
+        })
+    }
+}
+
+fn is_enabled(container: &Container, flag: &str) -> Result<bool> {
+    let flags = container.resolve::<Rc<dyn FeatureFlags>>()?;
+
+    Ok(flags.is_enabled(flag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Billing {
+        fn name(&self) -> &'static str;
+    }
+
+    #[derive(Clone)]
+    struct Old;
+
+    impl Billing for Old {
+        fn name(&self) -> &'static str {
+            "old"
+        }
+    }
+
+    #[test]
+    fn flags_are_checked_on_every_resolution() {
+        let flags = Rc::new(FixedFlags::new());
+        flags.enable("new_billing");
+
+        let mut builder = ContainerBuilder::new();
+        builder.register_feature_flags(flags.clone()).unwrap();
+        builder.register::<Old>(Old).unwrap();
+        builder
+            .register_by_flag::<dyn Billing, Rc<Old>, Old, _, _>(
+                "new_billing",
+                |new| new,
+                |old| Rc::new(old),
+            )
+            .unwrap();
+
+        let container = builder.build();
+
+        // only the type picked gets resolved
+        let error = container.resolve::<Rc<dyn Billing>>().err().unwrap();
+        assert!(error.to_string().ends_with(
+            "Type not registered: alloc::rc::Rc<kamikaze_di::feature_flags::tests::Old>"
+        ));
+
+        flags.disable("new_billing");
+        assert_eq!(
+            "old",
+            container.resolve::<Rc<dyn Billing>>().unwrap().name()
+        );
+    }
+
+    #[test]
+    fn flags_need_a_source() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_when_flag::<u32, _>("answers", |_| 42)
+            .unwrap();
+
+        let container = builder.build();
+
+        let error = container.resolve::<u32>().err().unwrap();
+        assert!(error.to_string().ends_with(
+            "Type not registered: alloc::rc::Rc<dyn kamikaze_di::feature_flags::FeatureFlags>"
+        ));
+    }
+
+    #[test]
+    fn disabled_flags_are_named() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register_feature_flags(Rc::new(FixedFlags::new()))
+            .unwrap();
+        builder
+            .register_when_flag::<u32, _>("answers", |_| 42)
+            .unwrap();
+
+        let container = builder.build();
+
+        let error = container.resolve::<u32>().err().unwrap();
+        assert!(error
+            .to_string()
+            .ends_with("feature flag \"answers\" is off"));
+    }
+}
@@ -0,0 +1,143 @@
+//! One container for the whole program, needs the `std` feature.
+//!
+//! Binaries that only ever build one container can store it here once, at
+//! startup, with [init()](fn.init.html), and then resolve from it anywhere
+//! with [resolve()](fn.resolve.html), instead of passing it around or
+//! keeping it in a static of their own.
+//!
+//! Containers share their items through `Rc`, so they can't be shared
+//! between threads. The global container belongs to the thread that called
+//! `init()`, and other threads have to initialize their own, like worker
+//! threads building their container on startup.
+//!
+//! # Examples
+//!
+//! ```
+//! # use kamikaze_di::{global, ContainerBuilder};
+//! #
+//! # fn main() -> std::result::Result<(), String> {
+//! #
+//! fn greeting() -> kamikaze_di::Result<String> {
+//!     global::resolve::<String>()
+//! }
+//!
+//! let mut builder = ContainerBuilder::new();
+//! builder.register::<String>("hello".to_string())?;
+//!
+//! global::init(builder.build())?;
+//!
+//! assert_eq!("hello", greeting()?);
+//! assert!(global::init(ContainerBuilder::new().build()).is_err());
+//! #
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::RefCell;
+
+use crate::{Container, Resolver, Result};
+
+thread_local! {
+    static CONTAINER: RefCell<Option<Container>> = const { RefCell::new(None) };
+}
+
+/// Makes the container this thread's global container.
+///
+/// Returns an error if the thread already has one.
+pub fn init(container: Container) -> Result<()> {
+    debug!("initializing global container");
+
+    CONTAINER.with(|global| {
+        let mut global = global.borrow_mut();
+
+        if global.is_some() {
+            return Err("The global container was already initialized".into());
+        }
+
+        *global = Some(container);
+
+        Ok(())
+    })
+}
+
+/// Returns true if this thread has a global container.
+pub fn is_initialized() -> bool {
+    CONTAINER.with(|global| global.borrow().is_some())
+}
+
+/// Returns a handle to the global container.
+///
+/// Returns an error if [init()](fn.init.html) wasn't called on this thread.
+pub fn container() -> Result<Container> {
+    CONTAINER.with(|global| global.borrow().clone().ok_or_else(not_initialized))
+}
+
+/// Resolves T from the global container.
+///
+/// Returns an error if [init()](fn.init.html) wasn't called on this thread,
+/// or if T can't be resolved.
+pub fn resolve<T: Clone + 'static>() -> Result<T> {
+    // not borrowed while resolving, factories may use the global container too
+    container()?.resolve()
+}
+
+/// Removes the global container, so another one can be initialized.
+///
+/// Returns the container, if there was one. Handy between tests, or to
+/// shut the container down before the thread ends.
+pub fn take() -> Option<Container> {
+    debug!("taking global container");
+
+    CONTAINER.with(|global| global.borrow_mut().take())
+}
+
+fn not_initialized() -> crate::Error {
+    "No global container, call global::init() first".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContainerBuilder;
+
+    // every test runs on a thread of its own, so each has its own global
+
+    #[test]
+    fn resolving_needs_a_container() {
+        assert!(!is_initialized());
+        assert_eq!(
+            "No global container, call global::init() first",
+            resolve::<u32>().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn containers_can_be_replaced_after_taking_them() {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(1).unwrap();
+        init(builder.build()).unwrap();
+
+        assert_eq!(1, resolve::<u32>().unwrap());
+        assert!(init(ContainerBuilder::new().build()).is_err());
+
+        let taken = take().unwrap();
+        assert_eq!(1, taken.resolve::<u32>().unwrap());
+        assert!(!is_initialized());
+
+        let mut builder = ContainerBuilder::new();
+        builder.register::<u32>(2).unwrap();
+        init(builder.build()).unwrap();
+
+        assert_eq!(2, resolve::<u32>().unwrap());
+    }
+
+    #[test]
+    fn other_threads_have_their_own() {
+        init(ContainerBuilder::new().build()).unwrap();
+
+        let elsewhere = std::thread::spawn(is_initialized).join().unwrap();
+
+        assert!(is_initialized());
+        assert!(!elsewhere);
+    }
+}
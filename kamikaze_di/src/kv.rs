@@ -0,0 +1,382 @@
+//! Key-value store abstraction for small persistence needs.
+//!
+//! Services depend on `Rc<dyn KvStore>` to keep things like offsets or
+//! checkpoints, the backend is chosen when wiring the container via
+//! [KvModule](struct.KvModule.html). Tests usually go with the in-memory
+//! store, the file backed one needs the `file-store` feature.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+#[cfg(feature = "file-store")]
+use core::convert::TryInto;
+#[cfg(feature = "file-store")]
+use std::path::{Path, PathBuf};
+
+use crate::prelude::*;
+use crate::{ContainerBuilder, Module, Result};
+
+/// Persistent key-value store.
+pub trait KvStore {
+    /// Returns the value stored under the key, if there is one.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Stores a value, replacing any previous one.
+    fn put(&self, key: &str, value: &[u8]) -> Result<()>;
+
+    /// Removes a value, if there is one.
+    fn delete(&self, key: &str) -> Result<()>;
+
+    /// Lists every entry, sorted by key.
+    fn entries(&self) -> Result<Vec<(String, Vec<u8>)>>;
+}
+
+/// Store that keeps everything in memory.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::kv::{KvStore, MemoryStore};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let store = MemoryStore::new();
+/// store.put("offset", b"42")?;
+///
+/// assert_eq!(Some(b"42".to_vec()), store.get("offset")?);
+///
+/// store.delete("offset")?;
+/// assert!(store.entries()?.is_empty());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: RefCell<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> MemoryStore {
+        Default::default()
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.entries.borrow().get(key).cloned())
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<()> {
+        self.entries
+            .borrow_mut()
+            .insert(key.to_string(), value.to_vec());
+
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.entries.borrow_mut().remove(key);
+
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        Ok(self
+            .entries
+            .borrow()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+/// Store that keeps everything in a single file.
+///
+/// Entries are read when the store is opened and kept in memory. Every
+/// change rewrites the whole file, through a temporary file that's flushed
+/// to disk before replacing it, so a crash never leaves it half written.
+/// Changes that can't be written are kept out of memory too. Meant for a
+/// handful of small entries, not for large data sets.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::kv::{FileStore, KvStore};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let path = std::env::temp_dir().join("kamikaze_di_file_store_example");
+/// # let _ = std::fs::remove_file(&path);
+///
+/// FileStore::open(&path)?.put("checkpoint", b"7")?;
+///
+/// let reopened = FileStore::open(&path)?;
+/// assert_eq!(Some(b"7".to_vec()), reopened.get("checkpoint")?);
+/// # std::fs::remove_file(&path).unwrap();
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "file-store")]
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    entries: RefCell<BTreeMap<String, Vec<u8>>>,
+}
+
+#[cfg(feature = "file-store")]
+impl FileStore {
+    /// Opens the store kept in the file, which is created on the first change.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FileStore> {
+        let path = path.as_ref().to_path_buf();
+
+        let entries = match std::fs::read(&path) {
+            Ok(bytes) => decode(&bytes)
+                .ok_or_else(|| format!("{} is not a valid store file", path.display()))?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(format!("Can't read {}: {}", path.display(), error).into()),
+        };
+
+        Ok(FileStore {
+            path,
+            entries: RefCell::new(entries),
+        })
+    }
+
+    /// Writes the entries, which replace the ones in memory once written.
+    fn save(&self, entries: BTreeMap<String, Vec<u8>>) -> Result<()> {
+        use std::io::Write;
+
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+
+        std::fs::File::create(&temporary)
+            .and_then(|mut file| {
+                file.write_all(&encode(&entries))?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&temporary, &self.path))
+            .map_err(|error| format!("Can't write {}: {}", self.path.display(), error))?;
+
+        *self.entries.borrow_mut() = entries;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "file-store")]
+impl KvStore for FileStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.entries.borrow().get(key).cloned())
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<()> {
+        let mut entries = self.entries.borrow().clone();
+        entries.insert(key.to_string(), value.to_vec());
+
+        self.save(entries)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut entries = self.entries.borrow().clone();
+        if entries.remove(key).is_none() {
+            return Ok(());
+        }
+
+        self.save(entries)
+    }
+
+    fn entries(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        Ok(self
+            .entries
+            .borrow()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+/// Lays entries out as length prefixed keys and values.
+#[cfg(feature = "file-store")]
+fn encode(entries: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    for (key, value) in entries {
+        for part in &[key.as_bytes(), value.as_slice()] {
+            bytes.extend_from_slice(&(part.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(part);
+        }
+    }
+
+    bytes
+}
+
+#[cfg(feature = "file-store")]
+fn decode(mut bytes: &[u8]) -> Option<BTreeMap<String, Vec<u8>>> {
+    fn part<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+        if bytes.len() < 8 {
+            return None;
+        }
+
+        let (len, rest) = bytes.split_at(8);
+        let len = u64::from_le_bytes(len.try_into().ok()?) as usize;
+
+        if rest.len() < len {
+            return None;
+        }
+
+        let (part, rest) = rest.split_at(len);
+        *bytes = rest;
+
+        Some(part)
+    }
+
+    let mut entries = BTreeMap::new();
+
+    while !bytes.is_empty() {
+        let key = String::from_utf8(part(&mut bytes)?.to_vec()).ok()?;
+        let value = part(&mut bytes)?.to_vec();
+
+        entries.insert(key, value);
+    }
+
+    Some(entries)
+}
+
+/// Registers an `Rc<dyn KvStore>` with the chosen backend.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver};
+/// # use kamikaze_di::kv::{KvModule, KvStore};
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// let mut builder = ContainerBuilder::new();
+/// builder.add_module(KvModule::memory())?;
+///
+/// let container = builder.build();
+///
+/// container.resolve::<Rc<dyn KvStore>>()?.put("offset", b"42")?;
+///
+/// let same_store = container.resolve::<Rc<dyn KvStore>>()?;
+/// assert_eq!(Some(b"42".to_vec()), same_store.get("offset")?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct KvModule {
+    backend: Backend,
+}
+
+#[derive(Clone, Debug)]
+enum Backend {
+    Memory,
+    #[cfg(feature = "file-store")]
+    File(PathBuf),
+}
+
+impl KvModule {
+    /// Uses a [MemoryStore](struct.MemoryStore.html) as backend.
+    pub fn memory() -> KvModule {
+        KvModule {
+            backend: Backend::Memory,
+        }
+    }
+
+    /// Uses a [FileStore](struct.FileStore.html) as backend.
+    ///
+    /// The file is opened when the module is added, errors reading it are
+    /// returned from [add_module()](../struct.ContainerBuilder.html#method.add_module).
+    #[cfg(feature = "file-store")]
+    pub fn file<P: AsRef<Path>>(path: P) -> KvModule {
+        KvModule {
+            backend: Backend::File(path.as_ref().to_path_buf()),
+        }
+    }
+}
+
+impl Module for KvModule {
+    fn register(&self, builder: &mut ContainerBuilder) -> Result<()> {
+        let store: Rc<dyn KvStore> = match &self.backend {
+            Backend::Memory => Rc::new(MemoryStore::new()),
+            #[cfg(feature = "file-store")]
+            Backend::File(path) => Rc::new(FileStore::open(path)?),
+        };
+
+        builder.register(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_store_lists_entries_by_key() {
+        let store = MemoryStore::new();
+        store.put("b", b"2").unwrap();
+        store.put("a", b"1").unwrap();
+        store.put("a", b"one").unwrap();
+
+        assert_eq!(
+            vec![
+                ("a".to_string(), b"one".to_vec()),
+                ("b".to_string(), b"2".to_vec())
+            ],
+            store.entries().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "file-store")]
+    fn file_store_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("kamikaze_di_kv_{}", std::process::id()));
+
+        {
+            let store = FileStore::open(&path).unwrap();
+            store.put("kept", b"1").unwrap();
+            store.put("deleted", b"2").unwrap();
+            store.delete("deleted").unwrap();
+        }
+
+        let reopened = FileStore::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            vec![("kept".to_string(), b"1".to_vec())],
+            reopened.entries().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "file-store")]
+    fn failed_writes_are_not_kept() {
+        let path = std::env::temp_dir()
+            .join(format!("kamikaze_di_kv_missing_{}", std::process::id()))
+            .join("store");
+
+        let store = FileStore::open(&path).unwrap();
+
+        assert!(store.put("lost", b"1").is_err());
+        assert_eq!(None, store.get("lost").unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "file-store")]
+    fn truncated_files_are_rejected() {
+        let mut bytes = encode(
+            &vec![("key".to_string(), b"value".to_vec())]
+                .into_iter()
+                .collect(),
+        );
+        bytes.pop();
+
+        assert_eq!(None, decode(&bytes));
+    }
+}
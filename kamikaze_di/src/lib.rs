@@ -46,7 +46,10 @@
 //! # }
 //! ```
 #![doc(html_root_url = "https://docs.rs/kamikaze_di/0.1.0")]
-#![feature(specialization)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "specialization", feature(specialization))]
+#![cfg_attr(feature = "specialization", allow(incomplete_features))]
+#![cfg_attr(feature = "unsize", feature(unsize))]
 #![deny(
     missing_docs,
     missing_debug_implementations,
@@ -58,17 +61,88 @@
     unused_qualifications
 )]
 
+extern crate alloc;
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "actix")]
+pub mod actix;
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod channel;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod context;
+#[cfg(feature = "r2d2")]
+pub mod db_pool;
+#[cfg(feature = "config")]
+pub mod definitions;
+pub mod experiments;
+pub mod feature_flags;
+#[cfg(feature = "std")]
+pub mod global;
+pub mod kv;
+#[cfg(feature = "mockall")]
+pub mod mocks;
+pub mod repository;
+pub mod testing;
+
+mod clock;
+mod collections;
+mod composition;
 mod container;
 mod error;
+mod prelude;
+mod static_container;
 
-pub use container::builder::ContainerBuilder;
+pub use container::arena::ArenaHandle;
+pub use container::assisted::Assisted;
+#[cfg(feature = "auto-register")]
+pub use container::auto::AutoRegistration;
+pub use container::bridge::ExternalResolver;
+pub use container::builder::{
+    ContainerBuilder, DefaultFallback, Definition, DuplicatePolicy, MergePolicy,
+};
+pub use container::call::{Callable, InjectAll};
+pub use container::deferred::Deferred;
+pub use container::events::ContainerEvent;
+pub use container::explain::{ResolutionTrace, ResolvedVia, TraceNode};
+pub use container::fallback::Fallback;
+pub use container::fields::InjectFields;
+pub use container::generic::GenericFactory;
+pub use container::graph::DependencyGraph;
+pub use container::handle::ContainerHandle;
+pub use container::initialize::Initialize;
 pub use container::injector::{Inject, InjectAsRc, Injector};
+pub use container::interceptor::{Interceptor, Resolution};
+pub use container::lazy::Lazy;
+pub use container::leaks::{Leak, LeakReport};
+pub use container::lifecycle::Lifecycle;
+pub use container::marked::Marked;
+pub use container::memo::MemoStats;
+pub use container::module::Module;
+pub use container::mutable::MutableContainer;
+pub use container::null::NullObject;
+pub use container::observer::{Observed, Observer};
+pub use container::overrides::{ActiveOverride, OverrideGuard, Overrides};
+pub use container::pool::PooledHandle;
+pub use container::provider::Provider;
 pub use container::resolver::Resolver;
-pub use container::Container;
-pub use error::Error;
+pub use container::scope::{RequestScope, Scope, ScopeGuard};
+pub use container::stats::{StatsReport, TypeStats};
+pub use container::swap::Swap;
+pub use container::usage::{LifetimeReport, LifetimeSuggestion};
+pub use container::validation::{
+    CompositionReport, Dependencies, Dependency, DeprecatedUse, ValidationReport,
+};
+pub use container::{Container, RegistrationInfo, ResolverType};
+pub use context::RequestContext;
+pub use error::{CycleDetected, Error, ErrorKind, InvariantViolation};
+pub use static_container::Supplies;
+
+#[cfg(feature = "auto-register")]
+#[doc(hidden)]
+pub use inventory as __inventory;
 
 /// Result type
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
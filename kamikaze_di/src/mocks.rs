@@ -0,0 +1,229 @@
+//! Containers wired with mockall mocks, needs the `mockall` feature.
+//!
+//! Traits mocked via `#[mockall::automock]` are bound to their mocks with
+//! [mocked!](../macro.mocked.html). A [TestContainer](struct.TestContainer.html)
+//! then hands out the mock of a trait to set expectations on, and registers
+//! it as `Rc<dyn Trait>` when it's built, next to whatever else the test
+//! registers.
+//!
+//! # Examples
+//!
+//! ```
+//! # use kamikaze_di::mocked;
+//! # use kamikaze_di::mocks::TestContainer;
+//! # use kamikaze_di::Resolver;
+//! # use mockall::automock;
+//! # use std::rc::Rc;
+//! #
+//! # fn main() -> std::result::Result<(), String> {
+//! #
+//! #[automock]
+//! trait Mailer {
+//!     fn send(&self, to: &str) -> bool;
+//! }
+//!
+//! mocked!(Mailer => MockMailer);
+//!
+//! let mut test_container = TestContainer::new();
+//! test_container
+//!     .mock::<dyn Mailer>()
+//!     .expect_send()
+//!     .withf(|to| to == "ana")
+//!     .times(1)
+//!     .return_const(true);
+//!
+//! let container = test_container.build()?;
+//!
+//! assert!(container.resolve::<Rc<dyn Mailer>>()?.send("ana"));
+//! #
+//! # Ok(())
+//! # }
+//! ```
+
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::collections::TypeIdMap;
+use crate::{Container, ContainerBuilder, Result};
+
+/// Traits with a mock to stand in for them, see [mocked!](../macro.mocked.html).
+pub trait Mocked {
+    /// The mock, like the `MockTrait` mockall generates for `Trait`.
+    type Mock: Default + 'static;
+
+    /// Turns the mock into what's registered.
+    fn from_mock(mock: Self::Mock) -> Rc<Self>;
+}
+
+/// Binds traits to their mocks, implementing [Mocked](mocks/trait.Mocked.html).
+///
+/// Takes pairs of `Trait => MockTrait`, see the [mocks](mocks/index.html)
+/// module.
+#[macro_export]
+macro_rules! mocked {
+    ($($trait_name:path => $mock:ty),* $(,)?) => {
+        $(
+            impl $crate::mocks::Mocked for dyn $trait_name {
+                type Mock = $mock;
+
+                fn from_mock(mock: $mock) -> ::std::rc::Rc<Self> {
+                    ::std::rc::Rc::new(mock)
+                }
+            }
+        )*
+    };
+}
+
+/// Builds a container with mocks registered for traits.
+///
+/// Real registrations go in [builder()](struct.TestContainer.html#method.builder),
+/// mocks are added by asking for them.
+#[derive(Default)]
+pub struct TestContainer {
+    builder: ContainerBuilder,
+    mocks: TypeIdMap<PendingMock>,
+}
+
+/// A mock with its expectations, and how to register it.
+struct PendingMock {
+    type_name: &'static str,
+    mock: Box<dyn Any>,
+    register: fn(Box<dyn Any>, &mut ContainerBuilder) -> Result<()>,
+}
+
+impl TestContainer {
+    /// Constructor.
+    pub fn new() -> TestContainer {
+        Default::default()
+    }
+
+    /// The builder everything besides mocks is registered with.
+    pub fn builder(&mut self) -> &mut ContainerBuilder {
+        &mut self.builder
+    }
+
+    /// Returns the mock of T, to set expectations on.
+    ///
+    /// The mock is created the first time it's asked for, and registered as
+    /// `Rc<T>` once the container is built.
+    pub fn mock<T: Mocked + ?Sized + 'static>(&mut self) -> &mut T::Mock {
+        self.mocks
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| PendingMock {
+                type_name: std::any::type_name::<T>(),
+                mock: Box::new(T::Mock::default()),
+                register: register_mock::<T>,
+            })
+            .mock
+            .downcast_mut()
+            .expect("mocks are kept by the type they stand in for")
+    }
+
+    /// Registers the mocks, and builds the container.
+    ///
+    /// Fails if a mocked trait was registered with the builder too.
+    pub fn build(mut self) -> Result<Container> {
+        for (_, pending) in self.mocks {
+            (pending.register)(pending.mock, &mut self.builder)?;
+        }
+
+        Ok(self.builder.build())
+    }
+}
+
+impl fmt::Debug for TestContainer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut mocked: Vec<_> = self.mocks.values().map(|mock| mock.type_name).collect();
+        mocked.sort_unstable();
+
+        f.debug_struct("TestContainer")
+            .field("builder", &self.builder)
+            .field("mocked", &mocked)
+            .finish()
+    }
+}
+
+fn register_mock<T: Mocked + ?Sized + 'static>(
+    mock: Box<dyn Any>,
+    builder: &mut ContainerBuilder,
+) -> Result<()> {
+    let mock = mock
+        .downcast::<T::Mock>()
+        .expect("mocks are kept by the type they stand in for");
+
+    builder.register_rc::<T>(T::from_mock(*mock))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+    use mockall::automock;
+
+    #[automock]
+    trait Clock {
+        fn now(&self) -> u64;
+    }
+
+    #[automock]
+    trait Store {
+        fn save(&self, at: u64);
+    }
+
+    mocked!(Clock => MockClock, Store => MockStore);
+
+    struct Recorder {
+        clock: Rc<dyn Clock>,
+        store: Rc<dyn Store>,
+    }
+
+    impl Recorder {
+        fn record(&self) {
+            self.store.save(self.clock.now());
+        }
+    }
+
+    #[test]
+    fn mocks_are_wired_with_real_registrations() {
+        let mut test_container = TestContainer::new();
+        test_container
+            .mock::<dyn Clock>()
+            .expect_now()
+            .return_const(7u64);
+        test_container
+            .mock::<dyn Store>()
+            .expect_save()
+            .withf(|at| *at == 7)
+            .times(1)
+            .return_const(());
+        test_container
+            .builder()
+            .register_builder::<Rc<Recorder>, _>(|container| {
+                Rc::new(Recorder {
+                    clock: container.resolve().unwrap(),
+                    store: container.resolve().unwrap(),
+                })
+            })
+            .unwrap();
+
+        let container = test_container.build().unwrap();
+
+        container.resolve::<Rc<Recorder>>().unwrap().record();
+    }
+
+    #[test]
+    fn mocks_clash_with_real_registrations() {
+        let mut test_container = TestContainer::new();
+        test_container.mock::<dyn Clock>();
+        test_container
+            .builder()
+            .register::<Rc<dyn Clock>>(Rc::new(MockClock::new()))
+            .unwrap();
+
+        let error = test_container.build().err().unwrap();
+        assert!(error.to_string().starts_with(
+            "Container already has alloc::rc::Rc<dyn kamikaze_di::mocks::tests::Clock>"
+        ));
+    }
+}
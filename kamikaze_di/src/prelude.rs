@@ -0,0 +1,6 @@
+//! What the std prelude brings in, for modules that also build without std.
+
+pub use alloc::boxed::Box;
+pub use alloc::string::{String, ToString};
+pub use alloc::vec::Vec;
+pub use alloc::{format, vec};
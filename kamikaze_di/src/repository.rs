@@ -0,0 +1,419 @@
+//! Repository and unit of work conventions.
+//!
+//! Services depend on `Rc<dyn Repository<T, Id = I>>` to load and store
+//! items, and on `Rc<dyn UnitOfWork>` to group changes. The unit of work is
+//! scoped, one per [transaction()](fn.transaction.html), so every
+//! repository resolved within the transaction shares it. Several
+//! repositories of the same item, like a primary and an archive, go through
+//! [register_keyed()](../struct.ContainerBuilder.html#method.register_keyed).
+
+use alloc::rc::Rc;
+
+use crate::prelude::*;
+use crate::{Container, ContainerBuilder, Resolver, Result};
+
+/// Which page of items to load.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct PageRequest {
+    /// Number of items to skip.
+    pub offset: usize,
+    /// Maximum number of items in the page.
+    pub limit: usize,
+}
+
+impl PageRequest {
+    /// Creates a request for the first page.
+    pub fn first(limit: usize) -> PageRequest {
+        PageRequest { offset: 0, limit }
+    }
+
+    /// Returns the request for the page after this one.
+    pub fn next(&self) -> PageRequest {
+        PageRequest {
+            offset: self.offset + self.limit,
+            limit: self.limit,
+        }
+    }
+}
+
+/// A page of items.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Page<T> {
+    /// Items in the page.
+    pub items: Vec<T>,
+    /// Number of items in the repository, in all pages.
+    pub total: usize,
+    /// The request the page was loaded for.
+    pub request: PageRequest,
+}
+
+impl<T> Page<T> {
+    /// Returns true if there are items after this page.
+    pub fn has_next(&self) -> bool {
+        self.request.offset + self.items.len() < self.total
+    }
+}
+
+/// Loads and stores items of type T.
+pub trait Repository<T> {
+    /// Identifies an item.
+    type Id;
+
+    /// Returns the item with the id, if there is one.
+    fn find(&self, id: &Self::Id) -> Result<Option<T>>;
+
+    /// Returns a page of items.
+    fn page(&self, request: PageRequest) -> Result<Page<T>>;
+
+    /// Stores an item, replacing any previous one with the same id.
+    fn save(&self, item: T) -> Result<()>;
+
+    /// Removes the item with the id, if there is one.
+    fn delete(&self, id: &Self::Id) -> Result<()>;
+}
+
+/// Groups changes made through repositories, so they are kept or dropped together.
+pub trait UnitOfWork {
+    /// Keeps the changes.
+    fn commit(&self) -> Result<()>;
+
+    /// Drops the changes.
+    fn rollback(&self);
+}
+
+/// Goes through every item in a repository, a page at a time.
+///
+/// A page is only loaded once the items of the previous one were used up,
+/// so large repositories are never loaded whole. An error loading a page
+/// is returned once, and ends the iteration.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::Result;
+/// # use kamikaze_di::repository::{stream, Page, PageRequest, Repository};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Numbers;
+///
+/// impl Repository<u32> for Numbers {
+///     type Id = u32;
+///
+///     fn find(&self, id: &u32) -> Result<Option<u32>> { Ok(Some(*id).filter(|id| *id < 5)) }
+///     fn save(&self, _: u32) -> Result<()> { Ok(()) }
+///     fn delete(&self, _: &u32) -> Result<()> { Ok(()) }
+///
+///     fn page(&self, request: PageRequest) -> Result<Page<u32>> {
+///         let items = (0..5u32).skip(request.offset).take(request.limit).collect();
+///
+///         Ok(Page { items, total: 5, request })
+///     }
+/// }
+///
+/// let all: Result<Vec<u32>> = stream(&Numbers, 2).collect();
+///
+/// assert_eq!(vec![0, 1, 2, 3, 4], all?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub fn stream<T, R>(repository: &R, page_size: usize) -> Stream<'_, T, R>
+where
+    R: Repository<T> + ?Sized,
+{
+    assert!(page_size > 0, "page size must be greater than 0");
+
+    Stream {
+        repository,
+        next: Some(PageRequest::first(page_size)),
+        items: Vec::new().into_iter(),
+    }
+}
+
+/// Iterator over every item in a repository, see [stream()](fn.stream.html).
+pub struct Stream<'a, T, R: ?Sized> {
+    repository: &'a R,
+    next: Option<PageRequest>,
+    items: vec::IntoIter<T>,
+}
+
+impl<'a, T, R> Iterator for Stream<'a, T, R>
+where
+    R: Repository<T> + ?Sized,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+
+            let request = self.next.take()?;
+
+            match self.repository.page(request) {
+                Ok(page) => {
+                    if page.has_next() && !page.items.is_empty() {
+                        self.next = Some(request.next());
+                    }
+
+                    self.items = page.items.into_iter();
+                }
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+impl<'a, T, R: ?Sized> core::fmt::Debug for Stream<'a, T, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Stream")
+            .field("next", &self.next)
+            .field("buffered", &self.items.len())
+            .finish()
+    }
+}
+
+/// Registers the unit of work, created once per transaction.
+///
+/// Repositories that take part in transactions resolve `Rc<dyn UnitOfWork>`
+/// when built, so they should be scoped as well.
+pub fn register_unit_of_work<F>(builder: &mut ContainerBuilder, factory: F) -> Result<()>
+where
+    F: (FnMut(&Container) -> Rc<dyn UnitOfWork>) + 'static,
+{
+    builder.register_scoped::<Rc<dyn UnitOfWork>, _>(factory)
+}
+
+/// Runs the work in a new scope, within a unit of work.
+///
+/// The unit of work is committed if the work succeeds, and rolled back if
+/// it fails. Either way, the scope ends together with the transaction.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Resolver, Result};
+/// # use kamikaze_di::repository::{register_unit_of_work, transaction, UnitOfWork};
+/// # use std::cell::RefCell;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// #[derive(Default)]
+/// struct Log(RefCell<Vec<&'static str>>);
+///
+/// struct Transaction(Rc<Log>);
+///
+/// impl UnitOfWork for Transaction {
+///     fn commit(&self) -> Result<()> { Ok(self.0 .0.borrow_mut().push("commit")) }
+///     fn rollback(&self) { self.0 .0.borrow_mut().push("rollback") }
+/// }
+///
+/// let log = Rc::new(Log::default());
+/// let transaction_log = log.clone();
+///
+/// let mut builder = ContainerBuilder::new();
+/// register_unit_of_work(&mut builder, move |_| Rc::new(Transaction(transaction_log.clone())))?;
+///
+/// let container = builder.build();
+///
+/// transaction(&container, |_| Ok(()))?;
+/// assert!(transaction(&container, |_| -> Result<()> { Err("failed".into()) }).is_err());
+///
+/// assert_eq!(vec!["commit", "rollback"], *log.0.borrow());
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub fn transaction<R, F>(container: &Container, work: F) -> Result<R>
+where
+    F: FnOnce(&Container) -> Result<R>,
+{
+    let scope = container.enter_scope();
+    let unit_of_work = scope.resolve::<Rc<dyn UnitOfWork>>()?;
+
+    match work(&scope) {
+        Ok(result) => {
+            unit_of_work.commit()?;
+
+            Ok(result)
+        }
+        Err(error) => {
+            debug!("rolling back: {}", error);
+            unit_of_work.rollback();
+
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct Changes {
+        committed: RefCell<BTreeMap<u32, String>>,
+        staged: RefCell<Vec<(u32, Option<String>)>>,
+    }
+
+    impl UnitOfWork for Changes {
+        fn commit(&self) -> Result<()> {
+            let mut committed = self.committed.borrow_mut();
+
+            for (id, name) in self.staged.borrow_mut().drain(..) {
+                match name {
+                    Some(name) => committed.insert(id, name),
+                    None => committed.remove(&id),
+                };
+            }
+
+            Ok(())
+        }
+
+        fn rollback(&self) {
+            self.staged.borrow_mut().clear();
+        }
+    }
+
+    struct Names(Rc<Changes>);
+
+    impl Repository<String> for Names {
+        type Id = u32;
+
+        fn find(&self, id: &u32) -> Result<Option<String>> {
+            Ok(self.0.committed.borrow().get(id).cloned())
+        }
+
+        fn page(&self, request: PageRequest) -> Result<Page<String>> {
+            let committed = self.0.committed.borrow();
+            let items = committed
+                .values()
+                .skip(request.offset)
+                .take(request.limit)
+                .cloned()
+                .collect();
+
+            Ok(Page {
+                items,
+                total: committed.len(),
+                request,
+            })
+        }
+
+        fn save(&self, item: String) -> Result<()> {
+            let id = item.len() as u32;
+            self.0.staged.borrow_mut().push((id, Some(item)));
+
+            Ok(())
+        }
+
+        fn delete(&self, id: &u32) -> Result<()> {
+            self.0.staged.borrow_mut().push((*id, None));
+
+            Ok(())
+        }
+    }
+
+    fn container(changes: &Rc<Changes>) -> Container {
+        let mut builder = ContainerBuilder::new();
+
+        let unit_of_work = changes.clone();
+        register_unit_of_work(&mut builder, move |_| unit_of_work.clone()).unwrap();
+
+        let repository = changes.clone();
+        builder
+            .register_scoped::<Rc<dyn Repository<String, Id = u32>>, _>(move |_| {
+                Rc::new(Names(repository.clone()))
+            })
+            .unwrap();
+
+        builder.build()
+    }
+
+    #[test]
+    fn transactions_commit_or_roll_back() {
+        let changes = Rc::new(Changes::default());
+        let container = container(&changes);
+
+        transaction(&container, |container| {
+            let names = container.resolve::<Rc<dyn Repository<String, Id = u32>>>()?;
+            names.save("ana".to_string())?;
+            names.save("mihai".to_string())
+        })
+        .unwrap();
+
+        let result = transaction(&container, |container| -> Result<()> {
+            let names = container.resolve::<Rc<dyn Repository<String, Id = u32>>>()?;
+            names.delete(&3)?;
+
+            Err("changed my mind".into())
+        });
+
+        assert_eq!("changed my mind", result.unwrap_err().to_string());
+        assert_eq!(
+            Some("ana".to_string()),
+            Names(changes.clone()).find(&3).unwrap()
+        );
+        assert!(changes.staged.borrow().is_empty());
+    }
+
+    #[test]
+    fn transactions_need_a_unit_of_work() {
+        let container = ContainerBuilder::new().build();
+
+        assert!(transaction(&container, |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn streams_go_through_every_page() {
+        let changes = Rc::new(Changes::default());
+        for name in &["a", "bb", "ccc", "dddd", "eeeee"] {
+            changes
+                .committed
+                .borrow_mut()
+                .insert(name.len() as u32, name.to_string());
+        }
+
+        let names = Names(changes);
+        let all: Vec<String> = stream(&names, 2).map(|name| name.unwrap()).collect();
+        assert_eq!(vec!["a", "bb", "ccc", "dddd", "eeeee"], all);
+
+        let exact: Vec<String> = stream(&names, 5).map(|name| name.unwrap()).collect();
+        assert_eq!(5, exact.len());
+    }
+
+    #[test]
+    fn stream_errors_end_the_iteration() {
+        struct Broken;
+
+        impl Repository<u32> for Broken {
+            type Id = u32;
+
+            fn find(&self, _: &u32) -> Result<Option<u32>> {
+                Ok(None)
+            }
+
+            fn page(&self, _: PageRequest) -> Result<Page<u32>> {
+                Err("database is down".into())
+            }
+
+            fn save(&self, _: u32) -> Result<()> {
+                Ok(())
+            }
+
+            fn delete(&self, _: &u32) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut items = stream(&Broken, 10);
+
+        assert!(items.next().unwrap().is_err());
+        assert!(items.next().is_none());
+    }
+}
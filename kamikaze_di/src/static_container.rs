@@ -0,0 +1,162 @@
+use crate::{Container, Resolver, Result};
+
+/// Hands out items of type T, from a container built at runtime or one
+/// generated at compile time by [container!](macro.container.html).
+///
+/// Code that only needs a few items can ask for them through this trait,
+/// and work with either kind of container.
+///
+/// # Examples
+///
+/// ```
+/// # use kamikaze_di::{ContainerBuilder, Supplies};
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// fn port<C: Supplies<u16>>(container: &C) -> kamikaze_di::Result<u16> {
+///     container.supply()
+/// }
+///
+/// let mut builder = ContainerBuilder::new();
+/// builder.register::<u16>(8080)?;
+///
+/// assert_eq!(8080, port(&builder.build())?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub trait Supplies<T> {
+    /// Returns the item.
+    fn supply(&self) -> Result<T>;
+}
+
+impl<T: Clone + 'static> Supplies<T> for Container {
+    fn supply(&self) -> Result<T> {
+        self.resolve()
+    }
+}
+
+/// Generates a container with the whole wiring known at compile time.
+///
+/// Each service becomes a field of the generated struct, built once, in
+/// the order listed, by `new()`. Initializers can use the services listed
+/// before them by name. Services are handed out through
+/// [Supplies](trait.Supplies.html) by cloning the field, without any type
+/// lookups or downcasts, so code written against `Supplies` works with
+/// either kind of container.
+///
+/// Listing a type twice fails to compile, like registering it twice fails
+/// at runtime.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate kamikaze_di;
+/// # use kamikaze_di::Supplies;
+/// # use std::rc::Rc;
+/// #
+/// # fn main() -> std::result::Result<(), String> {
+/// #
+/// struct Database {
+///     url: String,
+/// }
+///
+/// container! {
+///     #[derive(Clone)]
+///     struct App {
+///         url: String = "postgres://localhost".to_string(),
+///         database: Rc<Database> = Rc::new(Database { url: url.clone() }),
+///     }
+/// }
+///
+/// let app = App::new();
+///
+/// let database: Rc<Database> = app.supply()?;
+/// assert_eq!("postgres://localhost", database.url);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! container {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field:ident: $type:ty = $init:expr),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field: $type),*
+        }
+
+        #[allow(clippy::new_without_default)]
+        impl $name {
+            /// Builds every service, in the order they are listed.
+            #[allow(clippy::redundant_field_names)]
+            $vis fn new() -> $name {
+                $(let $field: $type = $init;)*
+
+                $name { $($field: $field),* }
+            }
+        }
+
+        $(
+            impl $crate::Supplies<$type> for $name {
+                fn supply(&self) -> $crate::Result<$type> {
+                    Ok(::core::clone::Clone::clone(&self.$field))
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Supplies;
+    use crate::ContainerBuilder;
+    use std::rc::Rc;
+
+    struct Config {
+        port: u16,
+    }
+
+    struct Server {
+        config: Rc<Config>,
+    }
+
+    container! {
+        struct Wired {
+            config: Rc<Config> = Rc::new(Config { port: 8080 }),
+            server: Rc<Server> = Rc::new(Server { config: config.clone() }),
+        }
+    }
+
+    fn port<C: Supplies<Rc<Server>>>(container: &C) -> u16 {
+        container.supply().unwrap().config.port
+    }
+
+    #[test]
+    fn generated_containers_share_their_services() {
+        let wired = Wired::new();
+
+        let config: Rc<Config> = wired.supply().unwrap();
+        let server: Rc<Server> = wired.supply().unwrap();
+
+        assert!(Rc::ptr_eq(&config, &server.config));
+        assert!(Rc::ptr_eq(&server, &wired.supply().unwrap()));
+    }
+
+    #[test]
+    fn code_works_with_either_container() {
+        let mut builder = ContainerBuilder::new();
+        builder
+            .register::<Rc<Server>>(Rc::new(Server {
+                config: Rc::new(Config { port: 80 }),
+            }))
+            .unwrap();
+
+        assert_eq!(80, port(&builder.build()));
+        assert_eq!(8080, port(&Wired::new()));
+    }
+}
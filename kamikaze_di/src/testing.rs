@@ -0,0 +1,129 @@
+//! Assertions for testing the wiring of a container.
+//!
+//! Each helper panics with a message naming the type when the container
+//! doesn't resolve it the expected way, so a wiring regression fails a
+//! plain unit test instead of the application at runtime.
+//!
+//! # Examples
+//!
+//! ```
+//! # use kamikaze_di::ContainerBuilder;
+//! # use kamikaze_di::testing::{assert_resolves, assert_singleton, assert_transient};
+//! # use std::rc::Rc;
+//! #
+//! # fn main() -> std::result::Result<(), String> {
+//! #
+//! struct Database;
+//! struct Request;
+//!
+//! let mut builder = ContainerBuilder::new();
+//! builder.register::<u16>(5432)?;
+//! builder.register_builder::<Rc<Database>, _>(|_| Rc::new(Database))?;
+//! builder.register_factory::<Rc<Request>, _>(|_| Rc::new(Request))?;
+//!
+//! let container = builder.build();
+//!
+//! assert_eq!(5432, assert_resolves::<u16>(&container));
+//! assert_singleton::<Database>(&container);
+//! assert_transient::<Request>(&container);
+//! #
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::rc::Rc;
+
+use crate::{Container, Resolver};
+
+/// Asserts that T resolves, and returns it.
+///
+/// # Panics
+///
+/// Panics with the resolution error if T can't be resolved.
+#[track_caller]
+pub fn assert_resolves<T: Clone + 'static>(container: &Container) -> T {
+    match container.resolve::<T>() {
+        Ok(item) => item,
+        Err(error) => panic!(
+            "expected {} to resolve, but it failed: {}",
+            core::any::type_name::<T>(),
+            error
+        ),
+    }
+}
+
+/// Asserts that `Rc<T>` resolves to the same item every time, and returns it.
+///
+/// # Panics
+///
+/// Panics if `Rc<T>` can't be resolved, or if resolving it twice gives two
+/// different items.
+#[track_caller]
+pub fn assert_singleton<T: ?Sized + 'static>(container: &Container) -> Rc<T> {
+    let first = assert_resolves::<Rc<T>>(container);
+    let second = assert_resolves::<Rc<T>>(container);
+
+    if !Rc::ptr_eq(&first, &second) {
+        panic!(
+            "expected {} to be shared, but it was created again",
+            core::any::type_name::<Rc<T>>()
+        );
+    }
+
+    first
+}
+
+/// Asserts that `Rc<T>` resolves to a new item every time.
+///
+/// # Panics
+///
+/// Panics if `Rc<T>` can't be resolved, or if resolving it twice gives the
+/// same item.
+#[track_caller]
+pub fn assert_transient<T: ?Sized + 'static>(container: &Container) {
+    let first = assert_resolves::<Rc<T>>(container);
+    let second = assert_resolves::<Rc<T>>(container);
+
+    if Rc::ptr_eq(&first, &second) {
+        panic!(
+            "expected {} to be created every time, but it was shared",
+            core::any::type_name::<Rc<T>>()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContainerBuilder;
+
+    fn container() -> Container {
+        let mut builder = ContainerBuilder::new();
+        builder.register::<Rc<u8>>(Rc::new(1)).unwrap();
+        builder
+            .register_factory::<Rc<u16>, _>(|_| Rc::new(2))
+            .unwrap();
+
+        builder.build()
+    }
+
+    #[test]
+    #[should_panic(expected = "expected u32 to resolve, but it failed: Type not registered: u32")]
+    fn unregistered_types_fail() {
+        assert_resolves::<u32>(&container());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected alloc::rc::Rc<u16> to be shared, but it was created again")]
+    fn factories_are_not_singletons() {
+        assert_singleton::<u16>(&container());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "expected alloc::rc::Rc<u8> to be created every time, but it was shared"
+    )]
+    fn shared_items_are_not_transient() {
+        assert_transient::<u8>(&container());
+    }
+}
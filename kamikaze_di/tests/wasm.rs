@@ -0,0 +1,86 @@
+//! Runs under node or in a browser, via `wasm-pack test --node`.
+#![cfg(target_arch = "wasm32")]
+
+use kamikaze_di::{Container, ContainerBuilder, Resolver};
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[derive(Clone)]
+struct Config {
+    api: &'static str,
+}
+
+fn container() -> Container {
+    let mut builder = ContainerBuilder::new();
+    builder
+        .register::<Config>(Config {
+            api: "https://example.com",
+        })
+        .unwrap();
+
+    builder.build()
+}
+
+#[wasm_bindgen_test]
+fn registered_items_resolve() {
+    let config = container().resolve::<Config>().unwrap();
+
+    assert_eq!("https://example.com", config.api);
+    assert!(container().resolve::<u32>().is_err());
+}
+
+#[wasm_bindgen_test]
+fn factories_run_every_time() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+
+    let mut builder = ContainerBuilder::new();
+    builder
+        .register_factory::<u32, _>(move |_| {
+            counter.set(counter.get() + 1);
+            counter.get()
+        })
+        .unwrap();
+
+    let container = builder.build();
+
+    assert_eq!(1, container.resolve::<u32>().unwrap());
+    assert_eq!(2, container.resolve::<u32>().unwrap());
+    assert_eq!(2, calls.get());
+}
+
+#[wasm_bindgen_test]
+fn builders_run_once() {
+    let mut builder = ContainerBuilder::new();
+    builder.register::<Config>(Config { api: "/api" }).unwrap();
+    builder
+        .register_builder::<Rc<String>, _>(|container| {
+            let config: Config = container.resolve().unwrap();
+
+            Rc::new(format!("{}/users", config.api))
+        })
+        .unwrap();
+
+    let container = builder.build();
+    let first = container.resolve::<Rc<String>>().unwrap();
+
+    assert_eq!("/api/users", *first);
+    assert!(Rc::ptr_eq(&first, &container.resolve().unwrap()));
+}
+
+#[wasm_bindgen_test]
+fn stats_work_without_a_clock() {
+    let mut builder = ContainerBuilder::new();
+    builder.record_stats();
+    builder.register_factory::<u32, _>(|_| 42).unwrap();
+
+    let container = builder.build();
+    container.resolve::<u32>().unwrap();
+
+    let stats = container.stats();
+    let stats = stats.of::<u32>().unwrap();
+
+    assert_eq!(1, stats.constructions);
+    assert_eq!(0, stats.construction_time.as_nanos());
+}
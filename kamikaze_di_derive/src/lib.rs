@@ -10,8 +10,9 @@ use proc_macro::TokenStream;
 use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
 use syn::{
-    parse_macro_input, parse_str, Data, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, Ident,
-    Path,
+    parse_macro_input, parse_quote, parse_str, Attribute, Data, DeriveInput, Fields, FieldsNamed,
+    FieldsUnnamed, FnArg, GenericArgument, Ident, ImplItem, ItemImpl, ItemTrait, Lit, Meta,
+    MetaList, MethodSig, NestedMeta, Pat, Path, PathArguments, ReturnType, TraitItem, Type,
 };
 
 #[proc_macro_derive(Inject)]
@@ -24,6 +25,689 @@ pub fn derive_resolve_to_rc(input: TokenStream) -> TokenStream {
     derive_code(input, "kamikaze_di::InjectAsRc")
 }
 
+/// Implements `kamikaze_di::InjectFields`.
+///
+/// `Rc<T>` fields are resolved from the container, `Option<Rc<T>>` fields
+/// are set to whatever is registered. Other fields are left alone.
+#[proc_macro_derive(InjectFields)]
+pub fn derive_inject_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match input.data {
+        Data::Struct(structure) => match structure.fields {
+            Fields::Named(fields) => fields,
+            _ => unimplemented!("InjectFields needs a struct with named fields"),
+        },
+        _ => unimplemented!("InjectFields needs a struct with named fields"),
+    };
+
+    let name = input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let assignments = fields.named.iter().filter_map(|field| {
+        let name = &field.ident;
+
+        match outer_type(&field.ty)? {
+            ("Rc", _) => Some(quote_spanned! {field.span()=>
+                self.#name = kamikaze_di::Resolver::resolve(container)?;
+            }),
+            ("Option", Some(inner)) if outer_type(inner)?.0 == "Rc" => {
+                Some(quote_spanned! {field.span()=>
+                    self.#name = kamikaze_di::Resolver::try_resolve(container);
+                })
+            }
+            _ => None,
+        }
+    });
+
+    let quote = quote! {
+        impl #impl_generics kamikaze_di::InjectFields for #name #type_generics #where_clause {
+            fn inject_fields(&mut self, container: &kamikaze_di::Container) -> kamikaze_di::Result<()> {
+                #(#assignments)*
+
+                Ok(())
+            }
+        }
+    };
+
+    TokenStream::from(quote)
+}
+
+/// Implements `kamikaze_di::Dependencies`, listing the struct's field types.
+///
+/// Meant for structs deriving `Inject` or `InjectAsRc`, whose fields are
+/// exactly what they depend on, so
+/// `builder.declare_dependencies::<Service, Service>()` lets
+/// `ContainerBuilder::try_build()` check them.
+#[proc_macro_derive(Dependencies)]
+pub fn derive_dependencies(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match input.data {
+        Data::Struct(structure) => structure.fields,
+        _ => unimplemented!("Dependencies needs a struct"),
+    };
+
+    let name = input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let dependencies = fields.iter().map(|field| {
+        let ty = &field.ty;
+
+        quote_spanned! {field.span()=>
+            kamikaze_di::Dependency::of::<#ty>(),
+        }
+    });
+
+    let quote = quote! {
+        impl #impl_generics kamikaze_di::Dependencies for #name #type_generics #where_clause {
+            fn dependencies() -> Vec<kamikaze_di::Dependency> {
+                vec![#(#dependencies)*]
+            }
+        }
+    };
+
+    TokenStream::from(quote)
+}
+
+/// Implements `kamikaze_di::config::ConfigSection`, needs the `config` feature.
+///
+/// The section is read from `#[config(section = "search.cluster")]`, or
+/// from the type name otherwise. Fields are checked with
+/// `#[validate(...)]`: `range(min = 1, max = 100)` bounds numbers, and
+/// `length(min = 1, max = 64)` bounds the `len()` of strings and
+/// collections. Every failed check is reported, not just the first one.
+#[proc_macro_derive(ConfigSection, attributes(config, validate))]
+pub fn derive_config_section(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match input.data {
+        Data::Struct(structure) => match structure.fields {
+            Fields::Named(fields) => fields,
+            _ => unimplemented!("ConfigSection needs a struct with named fields"),
+        },
+        _ => unimplemented!("ConfigSection needs a struct with named fields"),
+    };
+
+    let name = input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let section = input
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path.is_ident("config"))
+        .flat_map(|attribute| attribute_options(attribute, "config"))
+        .map(|option| match option {
+            Meta::NameValue(ref option) if option.ident == "section" => match &option.lit {
+                Lit::Str(section) => section.value(),
+                _ => unimplemented!("the config section needs to be a string"),
+            },
+            _ => unimplemented!("unknown config option, use #[config(section = \"...\")]"),
+        })
+        .next_back()
+        .map(|section| {
+            quote! {
+                fn section() -> String {
+                    #section.to_string()
+                }
+            }
+        });
+
+    let checks = fields.named.iter().flat_map(|field| {
+        let field_name = field.ident.as_ref().expect("named fields have names");
+
+        field
+            .attrs
+            .iter()
+            .filter(|attribute| attribute.path.is_ident("validate"))
+            .flat_map(|attribute| attribute_options(attribute, "validate"))
+            .flat_map(move |check| match check {
+                Meta::List(check) => config_checks(field_name, &check),
+                _ => unimplemented!(
+                    "unknown check on {}, use range(...) or length(...)",
+                    field_name
+                ),
+            })
+    });
+
+    let quote = quote! {
+        impl #impl_generics kamikaze_di::config::ConfigSection for #name #type_generics #where_clause {
+            #section
+
+            fn validate(&self) -> Vec<String> {
+                let mut problems = Vec::new();
+                #(#checks)*
+
+                problems
+            }
+        }
+    };
+
+    TokenStream::from(quote)
+}
+
+/// Options listed in an attribute, like `name(a, b = 1)`.
+fn attribute_options(attribute: &Attribute, name: &str) -> Vec<Meta> {
+    let nested = match attribute.parse_meta() {
+        Ok(Meta::List(list)) => list.nested,
+        _ => unimplemented!("{} needs a list of options, like #[{}(...)]", name, name),
+    };
+
+    nested
+        .into_iter()
+        .map(|option| match option {
+            NestedMeta::Meta(option) => option,
+            NestedMeta::Literal(_) => unimplemented!("{} options need names", name),
+        })
+        .collect()
+}
+
+/// Checks the bounds of a `range(...)` or `length(...)` check, see ConfigSection.
+fn config_checks(field: &Ident, check: &MetaList) -> Vec<impl quote::ToTokens> {
+    let (value, must) = if check.ident == "range" {
+        (quote!(self.#field), format!("{} must be", field))
+    } else if check.ident == "length" {
+        (
+            quote!(self.#field.len()),
+            format!("{} must have a length of", field),
+        )
+    } else {
+        unimplemented!("unknown check on {}, use range(...) or length(...)", field);
+    };
+
+    check
+        .nested
+        .iter()
+        .map(|bound| {
+            let bound = match bound {
+                NestedMeta::Meta(Meta::NameValue(bound)) => bound,
+                _ => unimplemented!("{} bounds look like min = 1 or max = 10", check.ident),
+            };
+            let limit = &bound.lit;
+
+            let (fails, message) = if bound.ident == "min" {
+                (quote!(<), format!("{} at least {{}}, got {{}}", must))
+            } else if bound.ident == "max" {
+                (quote!(>), format!("{} at most {{}}, got {{}}", must))
+            } else {
+                unimplemented!(
+                    "{} bounds are min and max, not {}",
+                    check.ident,
+                    bound.ident
+                );
+            };
+
+            quote_spanned! {field.span()=>
+                if #value #fails #limit {
+                    problems.push(format!(#message, #limit, #value));
+                }
+            }
+        })
+        .collect()
+}
+
+/// Name of the outermost type, and its first type argument, if any.
+///
+/// Rc<Db> gives ("Rc", Some(Db)), and so does std::rc::Rc<Db>. Only Rc,
+/// Option and Result are recognized.
+fn outer_type(ty: &Type) -> Option<(&'static str, Option<&Type>)> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.iter().last()?,
+        _ => return None,
+    };
+
+    let argument = match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => {
+            arguments.args.iter().find_map(|argument| match argument {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+        }
+        _ => None,
+    };
+
+    let name = if segment.ident == "Rc" {
+        "Rc"
+    } else if segment.ident == "Option" {
+        "Option"
+    } else if segment.ident == "Result" {
+        "Result"
+    } else {
+        return None;
+    };
+
+    Some((name, argument))
+}
+
+/// Declares a struct as the seed data of a scope.
+///
+/// Generates a `create_<name>_scope(container, data)` function, named after
+/// the struct without a `Data` suffix, that enters a scope seeded with every
+/// field, and a `current(container)` function that reads the data back from
+/// the scope. Fields are resolvable on their own while the scope lives, so
+/// every field needs its own type, like `UserId` or `TraceId`.
+#[proc_macro_attribute]
+pub fn scope_data(_attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let mut output = item.clone();
+    let input = parse_macro_input!(item as DeriveInput);
+
+    let fields = match input.data {
+        Data::Struct(structure) => match structure.fields {
+            Fields::Named(fields) => fields,
+            _ => unimplemented!("scope_data needs a struct with named fields"),
+        },
+        _ => unimplemented!("scope_data needs a struct with named fields"),
+    };
+
+    let name = input.ident;
+    let vis = input.vis;
+    let scope_name = scope_name(&name.to_string());
+    let create_scope = Ident::new(&format!("create_{}_scope", scope_name), name.span());
+    let create_doc = format!("Enters a scope seeded with a {}.", name);
+    let current_doc = format!("Reads the {} of the current scope.", name);
+
+    let field_names: Vec<_> = fields.named.iter().map(|field| &field.ident).collect();
+    let provide_fields = field_names.iter().map(|field| {
+        quote! { scope.provide(data.#field); }
+    });
+    let resolve_fields = field_names.iter().map(|field| {
+        quote! { #field: kamikaze_di::Resolver::resolve(container)?, }
+    });
+
+    let generated = quote! {
+        #[doc = #create_doc]
+        #vis fn #create_scope(
+            container: &kamikaze_di::Container,
+            data: #name,
+        ) -> kamikaze_di::ScopeGuard<'_> {
+            let scope = container.enter_scope();
+            #(#provide_fields)*
+
+            scope
+        }
+
+        impl #name {
+            #[doc = #current_doc]
+            #vis fn current(container: &kamikaze_di::Container) -> kamikaze_di::Result<#name> {
+                Ok(#name {
+                    #(#resolve_fields)*
+                })
+            }
+        }
+    };
+
+    output.extend(TokenStream::from(generated));
+
+    output
+}
+
+/// Resolves a type through one of its constructors.
+///
+/// Goes on an `impl` block, and implements `kamikaze_di::Inject` by
+/// resolving every parameter of the constructor from the container. The
+/// constructor is the method marked `#[inject]`, or `new()`. It can return
+/// `Self` or a `kamikaze_di::Result<Self>`. Use `#[inject(as_rc)]` to
+/// implement `kamikaze_di::InjectAsRc` instead.
+///
+/// Marking the constructor `#[assisted(report_id, ...)]` implements
+/// `kamikaze_di::Assisted` instead, the named parameters are given by the
+/// caller, as a tuple in the order they're declared in.
+#[proc_macro_attribute]
+pub fn inject(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let resolve_type = match attribute.to_string().as_str() {
+        "" => quote!(kamikaze_di::Inject),
+        "as_rc" => quote!(kamikaze_di::InjectAsRc),
+        other => unimplemented!("unknown inject option: {}", other),
+    };
+
+    let mut input = parse_macro_input!(item as ItemImpl);
+
+    let is_marker = |attribute: &Attribute| attribute.path.is_ident("inject");
+    let is_assisted = |attribute: &Attribute| attribute.path.is_ident("assisted");
+    let mut constructor = None;
+    let mut assisted = None;
+
+    for item in input.items.iter_mut() {
+        if let ImplItem::Method(method) = item {
+            let marked = method.attrs.iter().any(is_marker);
+            let arguments = method
+                .attrs
+                .iter()
+                .find(|attribute| is_assisted(attribute))
+                .map(assisted_arguments);
+            method
+                .attrs
+                .retain(|attribute| !is_marker(attribute) && !is_assisted(attribute));
+
+            if marked || (constructor.is_none() && method.sig.ident == "new") {
+                constructor = Some(method.sig.clone());
+                assisted = arguments;
+            }
+        }
+    }
+
+    let constructor = constructor.expect("inject needs a new() method, or one marked #[inject]");
+    let name = &constructor.ident;
+    let self_type = &input.self_ty;
+    let quoted_name = quote!(#self_type::#name).to_string();
+
+    if let Some(assisted) = assisted {
+        if attribute.to_string() == "as_rc" {
+            unimplemented!("assisted constructors can't be injected as Rc");
+        }
+
+        return inject_assisted(&input, &constructor, &assisted);
+    }
+
+    let arguments = constructor.decl.inputs.iter().map(|argument| match argument {
+        FnArg::Captured(argument) => {
+            let ty = &argument.ty;
+            let quoted_ty = quote!(#ty).to_string();
+
+            quote_spanned! {argument.ty.span()=>
+                <kamikaze_di::Container as kamikaze_di::Injector<#ty>>::inject(container)
+                    .map_err(|s| format!("could not resolve {}({}): {}", #quoted_name, #quoted_ty, s))?
+            }
+        }
+        _ => unimplemented!("inject needs a constructor without self"),
+    });
+
+    let construct = quote! { Self::#name(#(#arguments),*) };
+    let body = match &constructor.decl.output {
+        ReturnType::Type(_, ty) if is_result(ty) => construct,
+        _ => quote! { Ok(#construct) },
+    };
+
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+
+    let generated = quote! {
+        #input
+
+        impl #impl_generics #resolve_type for #self_type #where_clause {
+            fn resolve(container: &kamikaze_di::Container) -> kamikaze_di::Result<Self> {
+                #body
+            }
+        }
+    };
+
+    TokenStream::from(generated)
+}
+
+/// Names listed in `#[assisted(...)]`.
+fn assisted_arguments(attribute: &Attribute) -> Vec<Ident> {
+    let nested = match attribute.parse_meta() {
+        Ok(Meta::List(list)) => list.nested,
+        _ => unimplemented!("assisted needs a list of parameters, like #[assisted(id)]"),
+    };
+
+    nested
+        .into_iter()
+        .map(|meta| match meta {
+            NestedMeta::Meta(Meta::Word(name)) => name,
+            _ => unimplemented!("assisted needs a list of parameters, like #[assisted(id)]"),
+        })
+        .collect()
+}
+
+/// Implements `kamikaze_di::Assisted` for a constructor, see inject.
+fn inject_assisted(input: &ItemImpl, constructor: &MethodSig, assisted: &[Ident]) -> TokenStream {
+    let name = &constructor.ident;
+    let self_type = &input.self_ty;
+    let quoted_name = quote!(#self_type::#name).to_string();
+
+    let mut given = Vec::new();
+    let mut given_types = Vec::new();
+
+    let arguments: Vec<_> = constructor
+        .decl
+        .inputs
+        .iter()
+        .map(|argument| {
+            let argument = match argument {
+                FnArg::Captured(argument) => argument,
+                _ => unimplemented!("inject needs a constructor without self"),
+            };
+            let ty = &argument.ty;
+
+            match &argument.pat {
+                Pat::Ident(pattern) if assisted.contains(&pattern.ident) => {
+                    let ident = &pattern.ident;
+                    given.push(ident.clone());
+                    given_types.push(ty.clone());
+
+                    quote! { #ident }
+                }
+                _ => {
+                    let quoted_ty = quote!(#ty).to_string();
+
+                    quote_spanned! {argument.ty.span()=>
+                        <kamikaze_di::Container as kamikaze_di::Injector<#ty>>::inject(container)
+                            .map_err(|s| format!("could not resolve {}({}): {}", #quoted_name, #quoted_ty, s))?
+                    }
+                }
+            }
+        })
+        .collect();
+
+    if let Some(missing) = assisted.iter().find(|name| !given.contains(name)) {
+        unimplemented!("{} has no parameter named {}", quoted_name, missing);
+    }
+
+    let construct = quote! { Self::#name(#(#arguments),*) };
+    let body = match &constructor.decl.output {
+        ReturnType::Type(_, ty) if is_result(ty) => construct,
+        _ => quote! { Ok(#construct) },
+    };
+
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+
+    let generated = quote! {
+        #input
+
+        impl #impl_generics kamikaze_di::Assisted for #self_type #where_clause {
+            type Args = (#(#given_types,)*);
+
+            fn resolve_assisted(
+                container: &kamikaze_di::Container,
+                (#(#given,)*): Self::Args,
+            ) -> kamikaze_di::Result<Self> {
+                #body
+            }
+        }
+    };
+
+    TokenStream::from(generated)
+}
+
+/// Implements `kamikaze_di::Module` for the functions of an `impl` block.
+///
+/// Every associated function becomes a registration of the type it
+/// returns. Functions take the `&Container` their dependencies come from,
+/// or nothing, and return the type or a `kamikaze_di::Result` of it. Items
+/// are shared by default, built the first time they're needed, functions
+/// marked `#[factory]` create a new item every time instead.
+/// `#[singleton]` spells out the default.
+#[proc_macro_attribute]
+pub fn module(_attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(item as ItemImpl);
+
+    let is_factory = |attribute: &Attribute| attribute.path.is_ident("factory");
+    let is_singleton = |attribute: &Attribute| attribute.path.is_ident("singleton");
+    let mut registrations = Vec::new();
+
+    for item in input.items.iter_mut() {
+        let method = match item {
+            ImplItem::Method(method) => method,
+            _ => continue,
+        };
+
+        let factory = method.attrs.iter().any(is_factory);
+        if factory && method.attrs.iter().any(is_singleton) {
+            unimplemented!(
+                "{} can't be both a factory and a singleton",
+                method.sig.ident
+            );
+        }
+        method
+            .attrs
+            .retain(|attribute| !is_factory(attribute) && !is_singleton(attribute));
+
+        registrations.push(module_registration(&method.sig, factory));
+    }
+
+    let self_type = &input.self_ty;
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+
+    let generated = quote! {
+        #input
+
+        impl #impl_generics kamikaze_di::Module for #self_type #where_clause {
+            fn register(&self, builder: &mut kamikaze_di::ContainerBuilder) -> kamikaze_di::Result<()> {
+                #(#registrations)*
+
+                Ok(())
+            }
+        }
+    };
+
+    TokenStream::from(generated)
+}
+
+/// Registers what a function of a module provides, see module.
+fn module_registration(provider: &MethodSig, factory: bool) -> impl quote::ToTokens {
+    let name = &provider.ident;
+
+    let provide = match provider.decl.inputs.len() {
+        0 => quote! { |_| Self::#name() },
+        1 => match provider.decl.inputs.first().map(|input| input.into_value()) {
+            Some(FnArg::Captured(_)) => quote! { |container| Self::#name(container) },
+            _ => unimplemented!("module functions can't take self, {} does", name),
+        },
+        _ => unimplemented!(
+            "module functions take the container or nothing, {} takes more",
+            name
+        ),
+    };
+
+    let ty = match &provider.decl.output {
+        ReturnType::Type(_, ty) => ty,
+        ReturnType::Default => unimplemented!("{} needs to return what it provides", name),
+    };
+
+    let (provided, fallible) = match outer_type(ty) {
+        Some(("Result", Some(inner))) => (inner, true),
+        _ => (&**ty, false),
+    };
+
+    let register = match (factory, fallible) {
+        (false, false) => quote!(register_builder),
+        (false, true) => quote!(register_try_builder),
+        (true, false) => quote!(register_factory),
+        (true, true) => quote!(register_try_factory),
+    };
+
+    quote_spanned! {provider.span()=>
+        builder.#register::<#provided, _>(#provide)?;
+    }
+}
+
+/// Generates a null object for a trait.
+///
+/// Generates a `Null<Trait>` unit struct implementing the trait with
+/// methods that do nothing, and implements `kamikaze_di::NullObject` for
+/// `dyn Trait`, so it can be registered via `register_null()`. Methods
+/// return `Default::default()`, or `Ok(Default::default())` for `Result`s.
+/// Provided methods keep their default implementation.
+#[proc_macro_attribute]
+pub fn null_object(_attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let mut output = item.clone();
+    let input = parse_macro_input!(item as ItemTrait);
+
+    if !input.generics.params.is_empty() {
+        unimplemented!("null_object needs a trait without generics");
+    }
+
+    let name = input.ident;
+    let vis = input.vis;
+    let null_name = Ident::new(&format!("Null{}", name), name.span());
+    let struct_doc = format!("Null object for {}, does nothing.", name);
+
+    let methods = input.items.into_iter().filter_map(|item| match item {
+        TraitItem::Method(method) => {
+            if method.default.is_some() {
+                return None;
+            }
+
+            let mut sig = method.sig;
+            for input in sig.decl.inputs.iter_mut() {
+                if let FnArg::Captured(argument) = input {
+                    argument.pat = parse_quote!(_);
+                }
+            }
+
+            let body = match &sig.decl.output {
+                ReturnType::Default => quote! {},
+                ReturnType::Type(_, ty) if is_result(ty) => quote! { Ok(Default::default()) },
+                ReturnType::Type(..) => quote! { Default::default() },
+            };
+
+            Some(quote! { #sig { #body } })
+        }
+        _ => unimplemented!("null_object only supports traits with just methods"),
+    });
+
+    let generated = quote! {
+        #[doc = #struct_doc]
+        #[derive(Clone, Copy, Debug, Default)]
+        #vis struct #null_name;
+
+        impl #name for #null_name {
+            #(#methods)*
+        }
+
+        impl kamikaze_di::NullObject for dyn #name {
+            fn null() -> std::rc::Rc<dyn #name> {
+                std::rc::Rc::new(#null_name)
+            }
+        }
+    };
+
+    output.extend(TokenStream::from(generated));
+
+    output
+}
+
+fn is_result(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .iter()
+            .last()
+            .is_some_and(|segment| segment.ident == "Result"),
+        _ => false,
+    }
+}
+
+/// RequestData becomes request, HttpSession becomes http_session.
+fn scope_name(struct_name: &str) -> String {
+    let name = match struct_name.trim_end_matches("Data") {
+        "" => struct_name,
+        trimmed => trimmed,
+    };
+
+    let mut scope_name = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            scope_name.push('_');
+        }
+
+        scope_name.extend(c.to_lowercase());
+    }
+
+    scope_name
+}
+
 fn derive_code(input: TokenStream, trait_path: &str) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
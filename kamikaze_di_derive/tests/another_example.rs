@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 #[macro_use]
 extern crate kamikaze_di_derive;
 extern crate kamikaze_di;
@@ -0,0 +1,85 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, Result};
+use std::rc::Rc;
+
+struct Db {
+    url: String,
+}
+
+struct ReportGenerator {
+    db: Rc<Db>,
+    report_id: u64,
+    title: String,
+}
+
+#[inject]
+impl ReportGenerator {
+    #[assisted(report_id, title)]
+    fn new(report_id: u64, db: Rc<Db>, title: String) -> Self {
+        ReportGenerator {
+            db,
+            report_id,
+            title,
+        }
+    }
+}
+
+struct Export {
+    format: &'static str,
+}
+
+#[inject]
+impl Export {
+    #[inject]
+    #[assisted(format)]
+    fn with_format(_db: Rc<Db>, format: &'static str) -> Result<Export> {
+        if format.is_empty() {
+            return Err("no format given".into());
+        }
+
+        Ok(Export { format })
+    }
+}
+
+fn container() -> kamikaze_di::Container {
+    let mut builder = ContainerBuilder::new();
+    builder
+        .register::<Rc<Db>>(Rc::new(Db {
+            url: "db.local".to_string(),
+        }))
+        .unwrap();
+
+    builder.build()
+}
+
+#[test]
+fn test_assisted_constructor() {
+    let generator = container()
+        .resolve_assisted::<ReportGenerator>((42, "Sales".to_string()))
+        .unwrap();
+
+    assert_eq!("db.local", generator.db.url);
+    assert_eq!(42, generator.report_id);
+    assert_eq!("Sales", generator.title);
+}
+
+#[test]
+fn test_assisted_constructor_errors() {
+    let export = container().resolve_assisted::<Export>(("csv",)).unwrap();
+    assert_eq!("csv", export.format);
+
+    let error = container().resolve_assisted::<Export>(("",)).err().unwrap();
+    assert_eq!("no format given", error.to_string());
+
+    let error = ContainerBuilder::new()
+        .build()
+        .resolve_assisted::<ReportGenerator>((1, String::new()))
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .contains("could not resolve ReportGenerator :: new(Rc < Db >)"));
+}
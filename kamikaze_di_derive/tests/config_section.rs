@@ -0,0 +1,114 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::config::{ConfigModule, ConfigSection, ConfigSource};
+use kamikaze_di::{ContainerBuilder, ErrorKind, Resolver};
+use serde::Deserialize;
+use std::rc::Rc;
+
+#[derive(Deserialize, ConfigSection)]
+struct HttpConfig {
+    #[validate(range(min = 1, max = 65535))]
+    port: u32,
+    #[validate(length(min = 1))]
+    host: String,
+}
+
+#[derive(Deserialize, ConfigSection)]
+#[config(section = "search.cluster")]
+struct Cluster {
+    #[validate(length(min = 1, max = 3))]
+    nodes: Vec<String>,
+    #[validate(range(min = 0.5))]
+    timeout: f64,
+}
+
+#[test]
+fn sections_are_named_by_attributes() {
+    assert_eq!("http", HttpConfig::section());
+    assert_eq!("search.cluster", Cluster::section());
+}
+
+#[test]
+fn valid_sections_are_registered() {
+    let source = ConfigSource::from_toml(
+        r#"
+        [http]
+        port = 8080
+        host = "localhost"
+    "#,
+    )
+    .unwrap();
+
+    let mut builder = ContainerBuilder::new();
+    builder
+        .add_module(ConfigModule::new(source).section::<HttpConfig>())
+        .unwrap();
+
+    let container = builder.build();
+    container.validate().unwrap();
+    container.warm_up().unwrap();
+
+    let http = container.resolve::<Rc<HttpConfig>>().unwrap();
+    assert_eq!(8080, http.port);
+    assert_eq!("localhost", http.host);
+}
+
+#[test]
+fn failures_are_reported_together() {
+    let source = ConfigSource::from_toml(
+        r#"
+        [http]
+        port = 0
+        host = ""
+
+        [search.cluster]
+        nodes = ["a", "b", "c", "d"]
+        timeout = 0.1
+    "#,
+    )
+    .unwrap();
+
+    let mut builder = ContainerBuilder::new();
+    builder
+        .add_module(
+            ConfigModule::new(source)
+                .section::<HttpConfig>()
+                .section::<Cluster>(),
+        )
+        .unwrap();
+
+    let container = builder.build();
+
+    let report = match container.validate().unwrap_err().kind() {
+        ErrorKind::Validation(report) => report.clone(),
+        _ => unreachable!(),
+    };
+    assert_eq!(
+        vec![
+            (
+                "alloc::rc::Rc<config_section::HttpConfig>",
+                "port must be at least 1, got 0".to_string()
+            ),
+            (
+                "alloc::rc::Rc<config_section::HttpConfig>",
+                "host must have a length of at least 1, got 0".to_string()
+            ),
+            (
+                "alloc::rc::Rc<config_section::Cluster>",
+                "nodes must have a length of at most 3, got 4".to_string()
+            ),
+            (
+                "alloc::rc::Rc<config_section::Cluster>",
+                "timeout must be at least 0.5, got 0.1".to_string()
+            ),
+        ],
+        report.invalid
+    );
+
+    let error = container.warm_up().unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("alloc::rc::Rc<config_section::Cluster>: timeout must be at least 0.5, got 0.1"));
+}
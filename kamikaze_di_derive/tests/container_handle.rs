@@ -0,0 +1,30 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, ContainerHandle, Injector, Resolver};
+
+#[derive(Inject, Clone)]
+struct JobRunner {
+    container: ContainerHandle,
+}
+
+impl JobRunner {
+    fn run(&self) -> u32 {
+        self.container.resolve::<u32>().unwrap() + 1
+    }
+}
+
+#[test]
+fn test_container_handle_field() {
+    let mut builder = ContainerBuilder::new();
+    builder.register::<u32>(41).unwrap();
+    builder.register_automatic::<JobRunner>().unwrap();
+
+    let container = builder.build();
+    container.validate().unwrap();
+
+    let runner: JobRunner = container.inject().unwrap();
+
+    assert_eq!(42, runner.run());
+}
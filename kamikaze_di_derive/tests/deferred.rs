@@ -0,0 +1,28 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use std::rc::Rc;
+
+use kamikaze_di::{ContainerBuilder, Deferred, Injector};
+
+#[derive(InjectAsRc)]
+struct Parent {
+    child: Deferred<Rc<Child>>,
+}
+
+#[derive(InjectAsRc)]
+struct Child {
+    parent: Rc<Parent>,
+}
+
+#[test]
+fn deferred_fields_close_cycles() {
+    let container = ContainerBuilder::new().build();
+
+    let parent: Rc<Parent> = container.inject().unwrap();
+    let child: Rc<Child> = container.inject().unwrap();
+
+    assert!(Rc::ptr_eq(&child, &parent.child));
+    assert!(Rc::ptr_eq(&parent, &child.parent));
+}
@@ -0,0 +1,40 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, ErrorKind, Resolver};
+use std::rc::Rc;
+
+struct Config;
+
+#[derive(Dependencies)]
+struct Mailer {
+    config: Rc<Config>,
+    sender: String,
+}
+
+#[test]
+fn derived_dependencies_are_the_field_types() {
+    let mut builder = ContainerBuilder::new();
+    builder.register::<Rc<Config>>(Rc::new(Config)).unwrap();
+    builder
+        .register_builder_with_deps::<Rc<Mailer>, Mailer, _>(|container| {
+            Rc::new(Mailer {
+                config: container.resolve().unwrap(),
+                sender: container.resolve().unwrap(),
+            })
+        })
+        .unwrap();
+
+    let error = builder.try_build().err().unwrap();
+
+    match error.kind() {
+        ErrorKind::Validation(report) => {
+            assert_eq!(1, report.missing.len());
+            assert_eq!("alloc::string::String", report.missing[0].1);
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,88 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, Injector, Result};
+use std::rc::Rc;
+
+struct Db {
+    url: String,
+}
+
+#[derive(Clone)]
+struct UserRepository {
+    db: Rc<Db>,
+    page_size: usize,
+}
+
+#[inject]
+impl UserRepository {
+    fn new(db: Rc<Db>, page_size: usize) -> Self {
+        UserRepository { db, page_size }
+    }
+}
+
+struct Mailer {
+    from: String,
+}
+
+#[inject(as_rc)]
+impl Mailer {
+    #[inject]
+    fn from_config(from: String) -> Result<Mailer> {
+        if from.is_empty() {
+            return Err("no sender configured".into());
+        }
+
+        Ok(Mailer { from })
+    }
+}
+
+#[test]
+fn test_inject_constructor() {
+    let mut builder = ContainerBuilder::new();
+    builder
+        .register::<Rc<Db>>(Rc::new(Db {
+            url: "db.local".to_string(),
+        }))
+        .unwrap();
+    builder.register::<usize>(20).unwrap();
+
+    let container = builder.build();
+
+    let repository: UserRepository = container.inject().unwrap();
+
+    assert_eq!("db.local", repository.db.url);
+    assert_eq!(20, repository.page_size);
+}
+
+#[test]
+fn test_inject_constructor_as_rc() {
+    let mut builder = ContainerBuilder::new();
+    builder
+        .register::<String>("noreply@example.com".to_string())
+        .unwrap();
+
+    let container = builder.build();
+
+    let mailer: Rc<Mailer> = container.inject().unwrap();
+    assert_eq!("noreply@example.com", mailer.from);
+}
+
+#[test]
+fn test_inject_constructor_errors() {
+    let mut builder = ContainerBuilder::new();
+    builder.register::<String>(String::new()).unwrap();
+
+    let container = builder.build();
+
+    let result: Result<Rc<Mailer>> = container.inject();
+    assert_eq!("no sender configured", result.err().unwrap().to_string());
+
+    let result: Result<UserRepository> = ContainerBuilder::new().build().inject();
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("could not resolve UserRepository :: new"));
+}
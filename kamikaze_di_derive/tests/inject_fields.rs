@@ -0,0 +1,51 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, InjectFields};
+use std::rc::Rc;
+
+struct Db(&'static str);
+
+trait Metrics {}
+
+#[derive(InjectFields)]
+struct Job {
+    id: u32,
+    db: Rc<Db>,
+    metrics: Option<Rc<dyn Metrics>>,
+}
+
+#[test]
+fn test_inject_fields() {
+    let mut builder = ContainerBuilder::new();
+    builder.register::<Rc<Db>>(Rc::new(Db("prod"))).unwrap();
+
+    let container = builder.build();
+
+    // like a deserialized job, with placeholders for its dependencies
+    let mut job = Job {
+        id: 7,
+        db: Rc::new(Db("placeholder")),
+        metrics: None,
+    };
+    container.inject_into(&mut job).unwrap();
+
+    assert_eq!(7, job.id);
+    assert_eq!("prod", job.db.0);
+    assert!(job.metrics.is_none());
+}
+
+#[test]
+fn test_inject_fields_missing() {
+    let container = ContainerBuilder::new().build();
+
+    let mut job = Job {
+        id: 7,
+        db: Rc::new(Db("placeholder")),
+        metrics: None,
+    };
+
+    let error = job.inject_fields(&container).unwrap_err();
+    assert!(error.to_string().contains("Db"));
+}
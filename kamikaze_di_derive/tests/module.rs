@@ -0,0 +1,75 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{Container, ContainerBuilder, Resolver};
+use std::cell::Cell;
+use std::rc::Rc;
+
+struct Config {
+    url: &'static str,
+}
+
+struct Database {
+    url: &'static str,
+}
+
+struct RequestId(u32);
+
+struct AppModule;
+
+#[module]
+impl AppModule {
+    fn provide_config() -> Rc<Config> {
+        Rc::new(Config {
+            url: "postgres://db",
+        })
+    }
+
+    #[singleton]
+    fn provide_database(container: &Container) -> kamikaze_di::Result<Rc<Database>> {
+        let config: Rc<Config> = container.resolve()?;
+
+        Ok(Rc::new(Database { url: config.url }))
+    }
+
+    #[factory]
+    fn provide_request_id(container: &Container) -> Rc<RequestId> {
+        let counter: Rc<Cell<u32>> = container.resolve().unwrap();
+        counter.set(counter.get() + 1);
+
+        Rc::new(RequestId(counter.get()))
+    }
+
+    #[factory]
+    fn provide_port() -> kamikaze_di::Result<u16> {
+        Err("no port configured".into())
+    }
+}
+
+#[test]
+fn test_module() {
+    let mut builder = ContainerBuilder::new();
+    builder
+        .register::<Rc<Cell<u32>>>(Rc::new(Cell::new(0)))
+        .unwrap();
+    builder.add_module(AppModule).unwrap();
+
+    let container = builder.build();
+
+    let database = container.resolve::<Rc<Database>>().unwrap();
+    assert_eq!("postgres://db", database.url);
+    assert!(Rc::ptr_eq(
+        &database,
+        &container.resolve::<Rc<Database>>().unwrap()
+    ));
+
+    assert_eq!(1, container.resolve::<Rc<RequestId>>().unwrap().0);
+    assert_eq!(2, container.resolve::<Rc<RequestId>>().unwrap().0);
+
+    let error = container.resolve::<u16>().err().unwrap();
+    assert!(error.to_string().ends_with("no port configured"));
+
+    // the functions are still there
+    assert_eq!("postgres://db", AppModule::provide_config().url);
+}
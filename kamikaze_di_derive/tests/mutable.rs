@@ -0,0 +1,29 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, Injector};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+struct Cache {
+    hits: u32,
+}
+
+#[derive(Inject, Clone)]
+struct Handler {
+    cache: Rc<RefCell<Cache>>,
+}
+
+#[test]
+fn mutable_fields_share_the_registered_service() {
+    let mut builder = ContainerBuilder::new();
+    builder.register_mut::<Cache>(Cache { hits: 0 }).unwrap();
+
+    let container = builder.build();
+
+    let handler: Handler = container.inject().unwrap();
+    handler.cache.borrow_mut().hits += 1;
+
+    assert_eq!(1, container.resolve_mut::<Cache>().unwrap().borrow().hits);
+}
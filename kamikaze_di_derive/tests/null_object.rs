@@ -0,0 +1,58 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, Resolver};
+use std::rc::Rc;
+
+#[null_object]
+trait Analytics {
+    fn track(&self, event: &str, count: u32);
+    fn enabled(&self) -> bool;
+    fn flush(&self) -> kamikaze_di::Result<usize>;
+
+    fn name(&self) -> &'static str {
+        "default"
+    }
+}
+
+struct Segment;
+
+impl Analytics for Segment {
+    fn track(&self, _: &str, _: u32) {}
+
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn flush(&self) -> kamikaze_di::Result<usize> {
+        Ok(3)
+    }
+}
+
+#[test]
+fn test_null_object() {
+    let mut builder = ContainerBuilder::new();
+    builder.register_null::<dyn Analytics>().unwrap();
+
+    let analytics = builder.build().resolve::<Rc<dyn Analytics>>().unwrap();
+
+    analytics.track("signup", 1);
+    assert!(!analytics.enabled());
+    assert_eq!(0, analytics.flush().unwrap());
+    assert_eq!("default", analytics.name());
+}
+
+#[test]
+fn test_null_object_replaced() {
+    let mut builder = ContainerBuilder::new();
+    builder.register_null::<dyn Analytics>().unwrap();
+    builder
+        .register::<Rc<dyn Analytics>>(Rc::new(Segment))
+        .unwrap();
+
+    let analytics = builder.build().resolve::<Rc<dyn Analytics>>().unwrap();
+
+    assert!(analytics.enabled());
+    assert_eq!(3, analytics.flush().unwrap());
+}
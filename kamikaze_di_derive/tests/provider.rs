@@ -0,0 +1,34 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, Injector, Provider};
+
+#[derive(Inject, Clone)]
+struct Connection {
+    id: u32,
+}
+
+#[derive(Inject, Clone)]
+struct Pool {
+    connections: Provider<Connection>,
+}
+
+#[test]
+fn provider_fields_create_items_on_demand() {
+    let mut builder = ContainerBuilder::new();
+    let mut next_id = 0;
+    builder
+        .register_factory::<u32, _>(move |_| {
+            next_id += 1;
+            next_id
+        })
+        .unwrap();
+    builder.register_automatic_factory::<Connection>().unwrap();
+
+    let container = builder.build();
+    let pool: Pool = container.inject().unwrap();
+
+    assert_eq!(1, pool.connections.get().unwrap().id);
+    assert_eq!(2, pool.connections.get().unwrap().id);
+}
@@ -0,0 +1,39 @@
+#[macro_use]
+extern crate kamikaze_di_derive;
+extern crate kamikaze_di;
+
+use kamikaze_di::{ContainerBuilder, Resolver};
+
+#[derive(Clone, Debug, PartialEq)]
+struct UserId(u64);
+
+#[derive(Clone, Debug, PartialEq)]
+struct TraceId(String);
+
+#[scope_data]
+struct RequestData {
+    user: UserId,
+    trace: TraceId,
+}
+
+#[test]
+fn test_scope_data() {
+    let container = ContainerBuilder::new().build();
+
+    {
+        let scope = create_request_scope(
+            &container,
+            RequestData {
+                user: UserId(42),
+                trace: TraceId("abc".to_string()),
+            },
+        );
+
+        assert_eq!(UserId(42), scope.resolve::<UserId>().unwrap());
+
+        let data = RequestData::current(&scope).unwrap();
+        assert_eq!(TraceId("abc".to_string()), data.trace);
+    }
+
+    assert!(RequestData::current(&container).is_err());
+}
@@ -7,7 +7,7 @@ use std::rc::Rc;
 
 #[derive(Inject, Clone)]
 struct X {
-    u: usize,
+    _u: usize,
 }
 
 #[derive(Inject, Clone)]